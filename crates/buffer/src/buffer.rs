@@ -1,4 +1,6 @@
-use std::path::PathBuf;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::io::{Write, BufRead, BufReader};
 
 use std::fs::{File};
@@ -6,18 +8,120 @@ use std::fs;
 
 use utils::{Range, Position};
 use crate::errors::BufferError;
+use crate::filetype;
+
+/// One recorded edit: the lines starting at `start` went from `old` to `new`.
+#[derive(Clone, Debug)]
+struct Change {
+    start: usize,
+    old: Vec<String>,
+    new: Vec<String>
+}
+
+/// How many edits `edits_since` can replay before a consumer that fell
+/// further behind has to start over.
+const EDIT_LOG_LIMIT: usize = 1024;
+
+static NEXT_BUFFER_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A line-level summary of an edit, for consumers that keep state derived
+/// from the text: lines `line .. line + old_count` were replaced by the
+/// `new_count` lines now at `line`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LineEdit {
+    pub line: usize,
+    pub old_count: usize,
+    pub new_count: usize
+}
+
+/// A byte-level summary of the same edit, in the text the lines make when
+/// joined by newlines: the bytes `start_byte .. old_end_byte` were replaced
+/// by those now at `start_byte .. new_end_byte`. `start`, `old_end` and
+/// `new_end` are the same places as a line and byte column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextEdit {
+    pub start_byte: usize,
+    pub old_end_byte: usize,
+    pub new_end_byte: usize,
+    pub start: Position,
+    pub old_end: Position,
+    pub new_end: Position
+}
+
+impl TextEdit {
+    /// The edit replacing `old` by `new` at `start`, which is `start_byte`
+    /// into the text.
+    fn replacing(start_byte: usize, start: Position, old: &str, new: &str) -> Self {
+        Self {
+            start_byte,
+            old_end_byte: start_byte + old.len(),
+            new_end_byte: start_byte + new.len(),
+            start,
+            old_end: end_of(start, old),
+            new_end: end_of(start, new)
+        }
+    }
+}
+
+/// Where `text` ends if it starts at `start`.
+fn end_of(start: Position, text: &str) -> Position {
+    match text.rfind('\n') {
+        Some(last) => Position::new(start.line + text.matches('\n').count(), text.len() - last - 1),
+        None => Position::new(start.line, start.column + text.len())
+    }
+}
 
 #[derive(Debug)]    
 pub struct Buffer {
     pub lines: Vec<String>,
-    file_path: Option<PathBuf>
+    file_path: Option<PathBuf>,
+    filetype: Option<String>,
+    marks: HashMap<char, Position>,
+    line_marks: Vec<Option<usize>>,
+    modified: bool,
+    undo_stack: Vec<Vec<Change>>,
+    redo_stack: Vec<Vec<Change>>,
+    pending_changes: Vec<Change>,
+    undo_group_depth: usize,
+    id: u64,
+    version: u64,
+    edit_log: VecDeque<(LineEdit, TextEdit)>,
+    /// The byte offset of the start of each line, as far as `byte_offset`
+    /// has needed them. An edit only drops the offsets after the line it
+    /// starts on, so editing in one place doesn't count the lines above it
+    /// again.
+    line_starts: Vec<usize>
+}
+
+impl Default for Buffer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Buffer {
     pub fn new() -> Self {
+        Self::with_lines(vec![String::new()], None)
+    }
+
+    fn with_lines(lines: Vec<String>, file_path: Option<PathBuf>) -> Self {
+        let filetype = filetype::detect(file_path.as_deref(), &lines).map(|filetype| filetype.to_string());
+
         Self {
-            lines: vec![String::new()],
-            file_path: None
+            lines,
+            file_path,
+            filetype,
+            marks: HashMap::new(),
+            line_marks: vec![],
+            modified: false,
+            undo_stack: vec![],
+            redo_stack: vec![],
+            pending_changes: vec![],
+            undo_group_depth: 0,
+            id: NEXT_BUFFER_ID.fetch_add(1, Ordering::Relaxed),
+            version: 0,
+            edit_log: VecDeque::new(),
+            line_starts: vec![0]
         }
     }
 
@@ -30,13 +134,14 @@ impl Buffer {
             lines
         };
 
-        Self {
-            lines,
-            file_path: None
-        }
+        Self::with_lines(lines, None)
     }
 
-    pub fn from_file(path: &PathBuf) -> Self {
+    pub fn from_file(path: &PathBuf) -> Result<Self, BufferError> {
+        if path.is_dir() {
+            return Err(BufferError::IsDirectory);
+        }
+
         if !path.exists() {
             if let Some(parent) = path.parent() {
                 let _ = fs::create_dir_all(parent);
@@ -44,46 +149,52 @@ impl Buffer {
 
             let _ = File::create(path);
 
-            return Self {
-                lines: vec![String::new()],
-                file_path: Some(path.to_path_buf())
-            };
+            return Ok(Self::with_lines(vec![String::new()], Some(path.to_path_buf())));
         }
 
-        let file = File::open(path).expect("Error opening file");
-        let metadata = file.metadata().expect("No metadata");
+        let file = File::open(path)?;
+        let metadata = file.metadata()?;
 
         if metadata.is_file() && metadata.len() == 0 {
-            return Self {
-                lines: vec![String::new()],
-                file_path: Some(path.to_path_buf())
-            };
+            return Ok(Self::with_lines(vec![String::new()], Some(path.to_path_buf())));
         }
 
         let buf = BufReader::new(file);
-        let lines: Vec<String> = buf.lines().map(|line| line.expect("Error parsing line")).collect();
+        let lines = buf.lines().collect::<Result<Vec<String>, _>>()?;
 
-        Self {
-            lines,
-            file_path: Some(path.to_path_buf())
-        }
+        Ok(Self::with_lines(lines, Some(path.to_path_buf())))
     }
 
-    pub fn save_to_file(&self) -> Result<(), BufferError> {
-        if let Some(path) = &self.file_path {
-            let mut file = File::create(path).map_err(BufferError::IoError)?;
-            let content = self.lines.join("\n");
-            file.write_all(content.as_bytes()).map_err(BufferError::IoError)?;
+    /// Writes the buffer to its file, returning how many bytes were written.
+    pub fn save_to_file(&mut self) -> Result<usize, BufferError> {
+        if let Some(path) = self.file_path.clone() {
+            let bytes = self.write_lines(&path, 0, self.len() - 1)?;
+            self.modified = false;
 
-            Ok(())
+            Ok(bytes)
         } else {
             Err(BufferError::FileNotSet)
         }
     }
 
+    /// Writes lines `start` to `end` to `path`, returning how many bytes
+    /// were written.
+    pub fn write_lines(&self, path: &Path, start: usize, end: usize) -> Result<usize, BufferError> {
+        let lines = self.get_lines(start, end)?;
+
+        let mut file = File::create(path).map_err(BufferError::IoError)?;
+        let content = lines.join("\n");
+        file.write_all(content.as_bytes()).map_err(BufferError::IoError)?;
+
+        Ok(content.len())
+    }
+
     pub fn insert(&mut self, pos: Position, text: &str) -> Result<(), BufferError> {
         self.validate_position(pos)?;
 
+        let old = vec![self.lines[pos.line].clone()];
+        let start_byte = self.byte_offset(pos.line) + pos.column;
+
         if text == "\n" {
             let current = &self.lines[pos.line].clone();
             let before = current[..pos.column].to_string();
@@ -91,6 +202,8 @@ impl Buffer {
 
             self.lines[pos.line] = before;
             self.lines.insert(pos.line + 1, after);
+            self.lines_inserted(pos.line + 1, 1);
+            self.record(pos.line, old, 2, TextEdit::replacing(start_byte, pos, "", "\n"));
             return Ok(());
         }
 
@@ -99,6 +212,11 @@ impl Buffer {
             return Ok(());
         }
 
+        let edit = match lines.len() {
+            1 => TextEdit::replacing(start_byte, pos, "", text),
+            _ => TextEdit::replacing(start_byte, pos, "", &lines.join("\n"))
+        };
+
         if lines.len() == 1 {
             self.lines[pos.line].insert_str(pos.column, text);
         } else {
@@ -108,27 +226,83 @@ impl Buffer {
 
             self.lines[pos.line] = before + lines[0];
 
-            for i in 1 .. lines.len() {
-                self.lines.insert(pos.line + i, lines[i].to_string());
+            for (i, line) in lines.iter().enumerate().skip(1) {
+                self.lines.insert(pos.line + i, line.to_string());
             }
 
             let last_line_idx = pos.line + lines.len() - 1;
             self.lines[last_line_idx].push_str(&after);
+            self.lines_inserted(pos.line + 1, lines.len() - 1);
         }
 
+        self.record(pos.line, old, lines.len(), edit);
+
         Ok(())
     }
 
+    pub fn insert_lines(&mut self, at: usize, lines: Vec<String>) -> Result<(), BufferError> {
+        if at > self.len() {
+            return Err(BufferError::InvalidPosition { line: at, column: 0 });
+        }
+
+        let count = lines.len();
+        self.lines.splice(at .. at, lines);
+        self.lines_inserted(at, count);
+        let edit = self.lines_edit(at, &[], count);
+        self.record(at, vec![], count, edit);
+
+        Ok(())
+    }
+
+    pub fn delete_lines(&mut self, start: usize, end: usize) -> Result<Vec<String>, BufferError> {
+        if start > end || end >= self.len() {
+            return Err(BufferError::InvalidRange);
+        }
+
+        let deleted: Vec<String> = self.lines.drain(start ..= end).collect();
+        self.lines_removed(start, end);
+
+        let count = match self.lines.is_empty() {
+            true => {
+                self.lines.push(String::new());
+                1
+            },
+            false => 0
+        };
+
+        let edit = self.lines_edit(start, &deleted, count);
+        self.record(start, deleted.clone(), count, edit);
+
+        Ok(deleted)
+    }
+
+    pub fn set_line(&mut self, line: usize, text: String) -> Result<String, BufferError> {
+        if line >= self.len() {
+            return Err(BufferError::InvalidPosition { line, column: 0 });
+        }
+
+        let old = std::mem::replace(&mut self.lines[line], text);
+        let start_byte = self.byte_offset(line);
+        let edit = TextEdit::replacing(start_byte, Position::new(line, 0), &old, &self.lines[line]);
+        self.record(line, vec![old.clone()], 1, edit);
+
+        Ok(old)
+    }
+
     pub fn delete(&mut self, range: Range) -> Result<String, BufferError> {
         self.validate_position(range.start)?;
         self.validate_position(range.end)?;
 
         if range.start.line == range.end.line  {
             let line = &mut self.lines[range.start.line];
+            let old = vec![line.clone()];
             let deleted = line[range.start.column .. range.end.column + 1].to_string();
 
             line.drain(range.start.column .. range.end.column + 1);
 
+            let edit = TextEdit::replacing(self.byte_offset(range.start.line) + range.start.column, range.start, &deleted, "");
+            self.record(range.start.line, old, 1, edit);
+
             Ok(deleted)
         } else {
             let mut deleted = String::new();
@@ -137,8 +311,8 @@ impl Buffer {
             deleted.push_str(&first_line[range.start.column ..]);
             deleted.push('\n');
 
-            for i in range.start.line + 1 .. range.end.line {
-                deleted.push_str(&self.lines[i]);
+            for line in &self.lines[range.start.line + 1 .. range.end.line] {
+                deleted.push_str(line);
                 deleted.push('\n');
             }
 
@@ -148,9 +322,14 @@ impl Buffer {
             let before = self.lines[range.start.line][.. range.start.column].to_string();
             let after = self.lines[range.end.line][range.end.column ..].to_string();
     
-            self.lines.drain(range.start.line .. range.end.line);
+            let old: Vec<String> = self.lines.drain(range.start.line ..= range.end.line).collect();
             self.lines.insert(range.start.line, before + &after);
 
+            self.lines_removed(range.start.line + 1, range.end.line);
+
+            let edit = TextEdit::replacing(self.byte_offset(range.start.line) + range.start.column, range.start, &deleted, "");
+            self.record(range.start.line, old, 1, edit);
+
             Ok(deleted)
         }
     }
@@ -171,8 +350,8 @@ impl Buffer {
             result.push_str(&first_line[range.start.column ..]);
             result.push('\n');
 
-            for i in range.start.line + 1 .. range.end.line {
-                result.push_str(&self.lines[i]);
+            for line in &self.lines[range.start.line + 1 .. range.end.line] {
+                result.push_str(line);
                 result.push('\n');
             }
 
@@ -192,8 +371,8 @@ impl Buffer {
             return result;
         }
 
-        for i in 0 .. self.len() - 1 {
-            result.push_str(&self.lines[i]);
+        for line in &self.lines[.. self.len() - 1] {
+            result.push_str(line);
             result.push('\n')
         }
 
@@ -203,21 +382,289 @@ impl Buffer {
     }
 
     pub fn get_line(&self, line: usize) -> Result<String, BufferError> {
-        self.lines.get(line).map(|s| s.to_string()).ok_or(BufferError::InvalidPosition { line: line, column: 0})
+        self.lines.get(line).map(|s| s.to_string()).ok_or(BufferError::InvalidPosition { line, column: 0})
+    }
+
+    pub fn get_lines(&self, start: usize, end: usize) -> Result<Vec<String>, BufferError> {
+        if start > end || end >= self.len() {
+            return Err(BufferError::InvalidRange);
+        }
+
+        Ok(self.lines[start ..= end].to_vec())
+    }
+
+    /// Borrows the lines in `start..end`, clamped to the buffer, so a caller
+    /// that only shows part of it doesn't copy or walk the rest.
+    pub fn line_slice(&self, start: usize, end: usize) -> &[String] {
+        let end = end.min(self.len());
+        &self.lines[start.min(end) .. end]
     }
 
     pub fn get_path(&self) -> Option<PathBuf> {
-        if let Some(path) = &self.file_path {
-            Some(path.to_path_buf())
+        self.file_path.as_ref().map(|path| path.to_path_buf())
+    }
+
+    /// Sets the file the buffer is saved to, detecting the filetype again
+    /// from the new name.
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.file_path = Some(path);
+        self.detect_filetype();
+    }
+
+    pub fn filetype(&self) -> Option<&str> {
+        self.filetype.as_deref()
+    }
+
+    /// Overrides the detected filetype, as `:set filetype=` does.
+    pub fn set_filetype(&mut self, filetype: Option<String>) {
+        self.filetype = filetype;
+    }
+
+    pub fn detect_filetype(&mut self) {
+        self.filetype = filetype::detect(self.file_path.as_deref(), &self.lines).map(|filetype| filetype.to_string());
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    pub fn set_mark(&mut self, mark: char, pos: Position) {
+        self.marks.insert(mark, pos);
+    }
+
+    pub fn get_mark(&self, mark: char) -> Option<Position> {
+        self.marks.get(&mark).copied()
+    }
+
+    /// Remembers `lines` so their positions can be followed through later
+    /// edits with `line_mark`. Marks whose line is deleted become `None`.
+    pub fn set_line_marks(&mut self, lines: Vec<usize>) {
+        self.line_marks = lines.into_iter().map(Some).collect();
+    }
+
+    pub fn line_mark(&self, index: usize) -> Option<usize> {
+        self.line_marks.get(index).copied().flatten()
+    }
+
+    pub fn clear_line_marks(&mut self) {
+        self.line_marks.clear();
+    }
+
+    fn lines_inserted(&mut self, at: usize, count: usize) {
+        for pos in self.marks.values_mut() {
+            if pos.line >= at {
+                pos.line += count;
+            }
+        }
+
+        for line in self.line_marks.iter_mut().flatten() {
+            if *line >= at {
+                *line += count;
+            }
+        }
+    }
+
+    fn lines_removed(&mut self, start: usize, end: usize) {
+        let count = end - start + 1;
+
+        self.marks.retain(|_, pos| pos.line < start || pos.line > end);
+
+        for pos in self.marks.values_mut() {
+            if pos.line > end {
+                pos.line -= count;
+            }
+        }
+
+        for mark in self.line_marks.iter_mut() {
+            match mark {
+                Some(line) if *line > end => *line -= count,
+                Some(line) if *line >= start => *mark = None,
+                _ => {}
+            }
+        }
+    }
+
+    /// Records that the `old` lines at `start` were replaced by the `count`
+    /// lines now at `start`, which `edit` describes byte by byte.
+    fn record(&mut self, start: usize, old: Vec<String>, count: usize, edit: TextEdit) {
+        self.log_edit(LineEdit { line: start, old_count: old.len(), new_count: count }, edit);
+
+        let change = Change {
+            start,
+            old,
+            new: self.lines[start .. start + count].to_vec()
+        };
+
+        self.modified = true;
+        self.redo_stack.clear();
+
+        if self.undo_group_depth > 0 {
+            self.pending_changes.push(change);
         } else {
-            None
+            self.undo_stack.push(vec![change]);
         }
     }
 
+    /// Starts collecting changes into a single undo step. Groups nest; the
+    /// step is committed when the outermost group ends.
+    pub fn begin_undo_group(&mut self) {
+        self.undo_group_depth += 1;
+    }
+
+    pub fn end_undo_group(&mut self) {
+        if self.undo_group_depth == 0 {
+            return;
+        }
+
+        self.undo_group_depth -= 1;
+
+        if self.undo_group_depth == 0 {
+            self.commit_pending_changes();
+        }
+    }
+
+    fn commit_pending_changes(&mut self) {
+        if !self.pending_changes.is_empty() {
+            let changes = std::mem::take(&mut self.pending_changes);
+            self.undo_stack.push(changes);
+        }
+    }
+
+    /// Reverts the last undo step, returning the first line it touched.
+    pub fn undo(&mut self) -> Option<usize> {
+        self.commit_pending_changes();
+
+        let changes = self.undo_stack.pop()?;
+
+        for change in changes.iter().rev() {
+            self.replace_lines(change.start, change.new.len(), change.old.clone());
+        }
+
+        let first = changes.iter().map(|change| change.start).min();
+        self.redo_stack.push(changes);
+
+        first
+    }
+
+    /// Re-applies the last undone step, returning the first line it touched.
+    pub fn redo(&mut self) -> Option<usize> {
+        let changes = self.redo_stack.pop()?;
+
+        for change in changes.iter() {
+            self.replace_lines(change.start, change.old.len(), change.new.clone());
+        }
+
+        let first = changes.iter().map(|change| change.start).min();
+        self.undo_stack.push(changes);
+
+        first
+    }
+
+    fn replace_lines(&mut self, start: usize, count: usize, lines: Vec<String>) {
+        let new_count = lines.len();
+        let old: Vec<String> = self.lines.splice(start .. start + count, lines).collect();
+
+        if count > new_count {
+            self.lines_removed(start + new_count, start + count - 1);
+        } else if new_count > count {
+            self.lines_inserted(start + count, new_count - count);
+        }
+
+        let emptied = self.lines.is_empty();
+
+        if emptied {
+            self.lines.push(String::new());
+        }
+
+        let new_count = new_count + usize::from(emptied);
+        let edit = self.lines_edit(start, &old, new_count);
+        self.log_edit(LineEdit { line: start, old_count: count, new_count }, edit);
+        self.modified = true;
+    }
+
+    /// The byte offset of the start of `line` in the text, counting on from
+    /// the last line whose offset is known.
+    fn byte_offset(&mut self, line: usize) -> usize {
+        while self.line_starts.len() <= line {
+            let last = self.line_starts.len() - 1;
+            self.line_starts.push(self.line_starts[last] + self.lines[last].len() + 1);
+        }
+
+        self.line_starts[line]
+    }
+
+    /// Describes the `old` lines at `start` having been replaced by the
+    /// `count` lines now there. Every line but the last ends in a newline,
+    /// so at the end of the buffer the newline before the lines goes with
+    /// them instead.
+    fn lines_edit(&mut self, start: usize, old: &[String], count: usize) -> TextEdit {
+        let start_byte = self.byte_offset(start);
+        let new = &self.lines[start .. start + count];
+
+        if start + count < self.lines.len() {
+            let text = |lines: &[String]| lines.iter().map(|line| format!("{}\n", line)).collect::<String>();
+            return TextEdit::replacing(start_byte, Position::new(start, 0), &text(old), &text(new));
+        }
+
+        if start == 0 {
+            return TextEdit::replacing(0, Position::new(0, 0), &old.join("\n"), &new.join("\n"));
+        }
+
+        let text = |lines: &[String]| lines.iter().map(|line| format!("\n{}", line)).collect::<String>();
+        let previous = Position::new(start - 1, self.lines[start - 1].len());
+        TextEdit::replacing(start_byte - 1, previous, &text(old), &text(new))
+    }
+
+    fn log_edit(&mut self, lines: LineEdit, text: TextEdit) {
+        self.line_starts.truncate(lines.line + 1);
+        self.version += 1;
+        self.edit_log.push_back((lines, text));
+
+        if self.edit_log.len() > EDIT_LOG_LIMIT {
+            self.edit_log.pop_front();
+        }
+    }
+
+    /// Identifies this buffer among all buffers created by the process, so a
+    /// consumer can tell when a buffer was replaced, e.g. by `:edit`.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Counts edits made to the buffer; see `edits_since`.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// The edits made after `version`, oldest first, or `None` if they are no
+    /// longer all in the log.
+    pub fn edits_since(&self, version: u64) -> Option<impl Iterator<Item = &LineEdit>> {
+        Some(self.logged_since(version)?.map(|(lines, _)| lines))
+    }
+
+    /// The same edits as `edits_since`, described byte by byte.
+    pub fn text_edits_since(&self, version: u64) -> Option<impl Iterator<Item = &TextEdit>> {
+        Some(self.logged_since(version)?.map(|(_, text)| text))
+    }
+
+    fn logged_since(&self, version: u64) -> Option<impl Iterator<Item = &(LineEdit, TextEdit)>> {
+        let behind = usize::try_from(self.version.checked_sub(version)?).ok()?;
+
+        if behind > self.edit_log.len() {
+            return None;
+        }
+
+        Some(self.edit_log.iter().skip(self.edit_log.len() - behind))
+    }
+
     pub fn len(&self) -> usize {
         self.lines.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len() == 1 && self.lines[0].is_empty()
+    }
+
     pub fn validate_position(&self, pos: Position) -> Result<(), BufferError> {
         if pos.line >= self.len() {
             return Err(BufferError::InvalidPosition { 
@@ -244,7 +691,7 @@ impl Buffer {
 
         match self.validate_position(range.end) {
             Ok(_) => Ok(()),
-            Err(_) => return Err(BufferError::InvalidRange)
+            Err(_) => Err(BufferError::InvalidRange)
         } 
     }
 }
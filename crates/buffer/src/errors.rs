@@ -13,4 +13,7 @@ pub enum BufferError {
 
     #[error("File not set")]
     FileNotSet,
+
+    #[error("Is a directory")]
+    IsDirectory,
 }
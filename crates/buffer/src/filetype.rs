@@ -0,0 +1,272 @@
+use std::path::Path;
+
+/// What the editor needs to know about a filetype beyond its name: how to
+/// comment a line out, and how it is indented.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FiletypeInfo {
+    pub name: &'static str,
+    /// Text put before and after a commented line, the second empty for
+    /// languages with line comments.
+    pub comment: Option<(&'static str, &'static str)>,
+    /// Indent width and whether to indent with spaces, if the language has
+    /// a convention; buffers of the filetype start out with it.
+    pub indent: Option<(usize, bool)>,
+    /// Line endings after which the next line is indented a level deeper.
+    pub indent_after: &'static [&'static str]
+}
+
+const BRACES: &[&str] = &["{", "(", "["];
+
+pub const FILETYPES: &[FiletypeInfo] = &[
+    FiletypeInfo { name: "c", comment: Some(("//", "")), indent: None, indent_after: BRACES },
+    FiletypeInfo { name: "cpp", comment: Some(("//", "")), indent: None, indent_after: BRACES },
+    FiletypeInfo { name: "css", comment: Some(("/*", "*/")), indent: Some((2, true)), indent_after: BRACES },
+    FiletypeInfo { name: "diff", comment: None, indent: None, indent_after: &[] },
+    FiletypeInfo { name: "dockerfile", comment: Some(("#", "")), indent: None, indent_after: &[] },
+    FiletypeInfo { name: "gitcommit", comment: Some(("#", "")), indent: None, indent_after: &[] },
+    FiletypeInfo { name: "gitconfig", comment: Some(("#", "")), indent: Some((8, false)), indent_after: &[] },
+    FiletypeInfo { name: "go", comment: Some(("//", "")), indent: Some((8, false)), indent_after: BRACES },
+    FiletypeInfo { name: "html", comment: Some(("<!--", "-->")), indent: Some((2, true)), indent_after: &[">"] },
+    FiletypeInfo { name: "javascript", comment: Some(("//", "")), indent: Some((2, true)), indent_after: BRACES },
+    FiletypeInfo { name: "json", comment: None, indent: Some((2, true)), indent_after: BRACES },
+    FiletypeInfo { name: "lua", comment: Some(("--", "")), indent: None, indent_after: &["do", "then", "(", "{"] },
+    FiletypeInfo { name: "make", comment: Some(("#", "")), indent: Some((8, false)), indent_after: &[":"] },
+    FiletypeInfo { name: "markdown", comment: Some(("<!--", "-->")), indent: None, indent_after: &[] },
+    FiletypeInfo { name: "python", comment: Some(("#", "")), indent: Some((4, true)), indent_after: &[":", "(", "[", "{"] },
+    FiletypeInfo { name: "ruby", comment: Some(("#", "")), indent: Some((2, true)), indent_after: &["do", "{", "("] },
+    FiletypeInfo { name: "rust", comment: Some(("//", "")), indent: Some((4, true)), indent_after: BRACES },
+    FiletypeInfo { name: "sh", comment: Some(("#", "")), indent: None, indent_after: &["then", "do", "{", "("] },
+    FiletypeInfo { name: "toml", comment: Some(("#", "")), indent: None, indent_after: &["[", "{"] },
+    FiletypeInfo { name: "typescript", comment: Some(("//", "")), indent: Some((2, true)), indent_after: BRACES },
+    FiletypeInfo { name: "xml", comment: Some(("<!--", "-->")), indent: Some((2, true)), indent_after: &[">"] },
+    FiletypeInfo { name: "yaml", comment: Some(("#", "")), indent: Some((2, true)), indent_after: &[":"] }
+];
+
+pub fn info(filetype: &str) -> Option<&'static FiletypeInfo> {
+    FILETYPES.iter().find(|info| info.name == filetype)
+}
+
+/// Whole file names that say what a file is.
+const NAMES: &[(&str, &str)] = &[
+    ("Cargo.lock", "toml"),
+    ("Pipfile", "toml"),
+    ("Makefile", "make"),
+    ("makefile", "make"),
+    ("GNUmakefile", "make"),
+    ("Dockerfile", "dockerfile"),
+    ("Containerfile", "dockerfile"),
+    ("COMMIT_EDITMSG", "gitcommit"),
+    (".gitconfig", "gitconfig"),
+    (".bashrc", "sh"),
+    (".bash_profile", "sh"),
+    (".zshrc", "sh"),
+    (".profile", "sh")
+];
+
+/// Patterns matched against the path after exact names and before
+/// extensions, so they can overrule what an extension says. `*` matches
+/// within a path component, a leading `**` any number of them.
+const GLOBS: &[(&str, &str)] = &[
+    ("**/.git/config", "gitconfig"),
+    ("**/.cargo/config", "toml"),
+    ("Dockerfile.*", "dockerfile"),
+    ("*.Dockerfile", "dockerfile"),
+    ("**/.github/workflows/*.yml", "yaml"),
+    ("*.mk", "make"),
+    ("*.d.ts", "typescript")
+];
+
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("rs", "rust"),
+    ("toml", "toml"),
+    ("md", "markdown"),
+    ("markdown", "markdown"),
+    ("json", "json"),
+    ("jsonc", "json"),
+    ("sh", "sh"),
+    ("bash", "sh"),
+    ("zsh", "sh"),
+    ("py", "python"),
+    ("pyi", "python"),
+    ("c", "c"),
+    ("h", "c"),
+    ("cc", "cpp"),
+    ("cpp", "cpp"),
+    ("cxx", "cpp"),
+    ("hpp", "cpp"),
+    ("go", "go"),
+    ("js", "javascript"),
+    ("mjs", "javascript"),
+    ("cjs", "javascript"),
+    ("ts", "typescript"),
+    ("tsx", "typescript"),
+    ("html", "html"),
+    ("htm", "html"),
+    ("css", "css"),
+    ("xml", "xml"),
+    ("svg", "xml"),
+    ("yml", "yaml"),
+    ("yaml", "yaml"),
+    ("lua", "lua"),
+    ("rb", "ruby"),
+    ("diff", "diff"),
+    ("patch", "diff")
+];
+
+/// Interpreters named on a `#!` line.
+const INTERPRETERS: &[(&str, &str)] = &[
+    ("python", "python"),
+    ("sh", "sh"),
+    ("bash", "sh"),
+    ("zsh", "sh"),
+    ("dash", "sh"),
+    ("ksh", "sh"),
+    ("node", "javascript"),
+    ("deno", "typescript"),
+    ("lua", "lua"),
+    ("ruby", "ruby"),
+    ("make", "make")
+];
+
+/// How many lines at each end of a file are searched for a modeline.
+const MODELINES: usize = 5;
+
+/// Works out the filetype of a file from, in order of precedence: a vim or
+/// emacs modeline, its name, a glob on its path, its extension, a `#!`
+/// line naming the interpreter, and what its first lines look like.
+pub fn detect(path: Option<&Path>, lines: &[String]) -> Option<&'static str> {
+    modeline(lines)
+        .or_else(|| path.and_then(by_path))
+        .or_else(|| shebang(lines.first()?))
+        .or_else(|| by_content(lines))
+}
+
+fn by_path(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_str()?;
+    let full = path.to_string_lossy();
+
+    let lookup = |table: &[(&str, &'static str)], key: &str| {
+        table.iter().find(|(candidate, _)| *candidate == key).map(|(_, filetype)| *filetype)
+    };
+
+    lookup(NAMES, name)
+        .or_else(|| GLOBS.iter().find(|(glob, _)| glob_matches(glob, &full)).map(|(_, filetype)| *filetype))
+        .or_else(|| lookup(EXTENSIONS, path.extension()?.to_str()?))
+}
+
+/// Whether `glob` matches the end of `path`: a pattern without `**` only
+/// has to match as many trailing components as it has.
+fn glob_matches(glob: &str, path: &str) -> bool {
+    let path: Vec<&str> = path.split(['/', '\\']).collect();
+    let glob: Vec<&str> = glob.split('/').collect();
+
+    match glob.first() {
+        Some(&"**") => (0 ..= path.len()).any(|start| components_match(&glob[1 ..], &path[start ..])),
+        _ => path.len() >= glob.len() && components_match(&glob, &path[path.len() - glob.len() ..])
+    }
+}
+
+fn components_match(glob: &[&str], path: &[&str]) -> bool {
+    glob.len() == path.len() && glob.iter().zip(path).all(|(glob, component)| wildcard_matches(glob.as_bytes(), component.as_bytes()))
+}
+
+/// `*` and `?` matching within one path component.
+fn wildcard_matches(glob: &[u8], text: &[u8]) -> bool {
+    match (glob.first(), text.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => wildcard_matches(&glob[1 ..], text) || (!text.is_empty() && wildcard_matches(glob, &text[1 ..])),
+        (Some(b'?'), Some(_)) => wildcard_matches(&glob[1 ..], &text[1 ..]),
+        (Some(g), Some(t)) if g == t => wildcard_matches(&glob[1 ..], &text[1 ..]),
+        _ => false
+    }
+}
+
+fn shebang(first_line: &str) -> Option<&'static str> {
+    let mut words = first_line.strip_prefix("#!")?.split_whitespace();
+    let mut interpreter = words.next()?.rsplit('/').next()?;
+
+    if interpreter == "env" {
+        interpreter = words.find(|word| !word.starts_with('-'))?;
+    }
+
+    // `python3.12` and the like name the same language as `python`.
+    let interpreter = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+    INTERPRETERS
+        .iter()
+        .find(|(name, _)| *name == interpreter)
+        .map(|(_, filetype)| *filetype)
+}
+
+/// A filetype set by a modeline in the first or last few lines, such as
+/// `vim: set ft=python:`, `vim: filetype=sh` or `-*- mode: ruby -*-`.
+fn modeline(lines: &[String]) -> Option<&'static str> {
+    let head = lines.iter().take(MODELINES);
+    let tail = lines.iter().skip(MODELINES.max(lines.len().saturating_sub(MODELINES)));
+
+    head.chain(tail).find_map(|line| vim_modeline(line).or_else(|| emacs_modeline(line)))
+}
+
+fn vim_modeline(line: &str) -> Option<&'static str> {
+    let (_, options) = ["vim:", "vi:", "ex:"]
+        .iter()
+        .filter_map(|marker| line.find(marker).map(|index| (index, &line[index + marker.len() ..])))
+        .find(|(index, _)| *index == 0 || line[.. *index].ends_with(char::is_whitespace))?;
+
+    let options = options.trim_start();
+    let options = options.strip_prefix("set ").or_else(|| options.strip_prefix("se ")).unwrap_or(options);
+
+    options
+        .split([' ', ':'])
+        .filter_map(|option| option.strip_prefix("filetype=").or_else(|| option.strip_prefix("ft=")))
+        .find_map(known)
+}
+
+fn emacs_modeline(line: &str) -> Option<&'static str> {
+    let start = line.find("-*-")? + 3;
+    let end = start + line[start ..].find("-*-")?;
+    let variables = line[start .. end].trim();
+
+    let mode = match variables.contains(':') {
+        true => variables
+            .split(';')
+            .find_map(|variable| variable.trim().strip_prefix("mode:"))?
+            .trim(),
+        false => variables
+    };
+
+    let mode = mode.to_ascii_lowercase();
+
+    match mode.as_str() {
+        "shell-script" => Some("sh"),
+        "c++" => Some("cpp"),
+        "js" => Some("javascript"),
+        mode => known(mode)
+    }
+}
+
+fn known(name: &str) -> Option<&'static str> {
+    info(name).map(|info| info.name)
+}
+
+/// Guesses from the first non-blank line when nothing else says.
+fn by_content(lines: &[String]) -> Option<&'static str> {
+    let mut non_blank = lines.iter().map(|line| line.trim()).filter(|line| !line.is_empty());
+    let first = non_blank.next()?;
+    let last = non_blank.next_back().unwrap_or(first);
+    let lower = first.to_ascii_lowercase();
+
+    if lower.starts_with("<?xml") {
+        Some("xml")
+    } else if lower.starts_with("<!doctype html") || lower.starts_with("<html") {
+        Some("html")
+    } else if first.starts_with("diff --git ") || (first.starts_with("--- ") && lines.iter().any(|line| line.starts_with("+++ "))) {
+        Some("diff")
+    } else if first == "---" || first.starts_with("%YAML") {
+        Some("yaml")
+    } else if (first.starts_with('{') || first == "[") && last.ends_with(['}', ']']) {
+        Some("json")
+    } else {
+        None
+    }
+}
@@ -1,4 +1,5 @@
 pub mod buffer;
 pub mod errors;
+pub mod filetype;
 
 pub use buffer::Buffer;
@@ -1,5 +1,5 @@
 use utils::{Position, Range};
-use buffer::buffer::Buffer;
+use buffer::buffer::{Buffer, LineEdit, TextEdit};
 
 #[test]
 fn empty_create() {
@@ -87,3 +87,167 @@ fn get_buffer() {
     let buffer2 = Buffer::from_text("Hai\nTest\nNya\nTest");
     assert_eq!(buffer2.get_buffer(), "Hai\nTest\nNya\nTest");
 }
+
+#[test]
+fn delete_multiline_joins() {
+    let mut buffer = Buffer::from_text("Hello nya :3\nTesting\nWoah");
+    buffer.delete(Range::new(
+        Position::new(0, 5),
+        Position::new(1, 4)
+    )).unwrap();
+    assert_eq!(buffer.get_buffer(), "Helloing\nWoah");
+}
+
+#[test]
+fn insert_and_delete_lines() {
+    let mut buffer = Buffer::from_text("one\ntwo\nthree");
+    buffer.insert_lines(1, vec!["mrrp".to_string(), "nya".to_string()]).unwrap();
+    assert_eq!(buffer.get_buffer(), "one\nmrrp\nnya\ntwo\nthree");
+
+    let deleted = buffer.delete_lines(0, 2).unwrap();
+    assert_eq!(deleted, vec!["one", "mrrp", "nya"]);
+    assert_eq!(buffer.get_buffer(), "two\nthree");
+    assert!(buffer.is_modified());
+}
+
+#[test]
+fn delete_all_lines_leaves_empty_line() {
+    let mut buffer = Buffer::from_text("one\ntwo");
+    buffer.delete_lines(0, 1).unwrap();
+    assert_eq!(buffer.len(), 1);
+    assert_eq!(buffer.get_line(0).unwrap(), "");
+}
+
+#[test]
+fn marks_follow_line_changes() {
+    let mut buffer = Buffer::from_text("one\ntwo\nthree\nfour");
+    buffer.set_mark('a', Position::new(3, 0));
+    buffer.set_mark('b', Position::new(1, 0));
+
+    buffer.delete_lines(1, 1).unwrap();
+    assert_eq!(buffer.get_mark('a'), Some(Position::new(2, 0)));
+    assert_eq!(buffer.get_mark('b'), None);
+
+    buffer.insert(Position::new(0, 3), "\n").unwrap();
+    assert_eq!(buffer.get_mark('a'), Some(Position::new(3, 0)));
+}
+
+#[test]
+fn undo_and_redo() {
+    let mut buffer = Buffer::from_text("one\ntwo");
+    buffer.insert(Position::new(0, 3), "!").unwrap();
+    buffer.delete_lines(1, 1).unwrap();
+    assert_eq!(buffer.get_buffer(), "one!");
+
+    assert_eq!(buffer.undo(), Some(1));
+    assert_eq!(buffer.get_buffer(), "one!\ntwo");
+    assert_eq!(buffer.undo(), Some(0));
+    assert_eq!(buffer.get_buffer(), "one\ntwo");
+    assert_eq!(buffer.undo(), None);
+
+    assert_eq!(buffer.redo(), Some(0));
+    assert_eq!(buffer.get_buffer(), "one!\ntwo");
+}
+
+#[test]
+fn undo_group() {
+    let mut buffer = Buffer::from_text("one\ntwo\nthree");
+    buffer.begin_undo_group();
+    buffer.set_line(0, "uno".to_string()).unwrap();
+    buffer.insert(Position::new(1, 3), "\n").unwrap();
+    buffer.delete_lines(3, 3).unwrap();
+    buffer.end_undo_group();
+    assert_eq!(buffer.get_buffer(), "uno\ntwo\n");
+
+    buffer.undo();
+    assert_eq!(buffer.get_buffer(), "one\ntwo\nthree");
+}
+
+#[test]
+fn line_marks_survive_deletions() {
+    let mut buffer = Buffer::from_text("a\nb\nc\nd");
+    buffer.set_line_marks(vec![0, 2, 3]);
+
+    buffer.delete_lines(0, 0).unwrap();
+    assert_eq!(buffer.line_mark(0), None);
+    assert_eq!(buffer.line_mark(1), Some(1));
+
+    buffer.insert_lines(0, vec!["x".to_string()]).unwrap();
+    assert_eq!(buffer.line_mark(2), Some(3));
+}
+
+#[test]
+fn line_slice_clamps() {
+    let buffer = Buffer::from_text("a\nb\nc");
+    assert_eq!(buffer.line_slice(1, 10), ["b", "c"]);
+    assert!(buffer.line_slice(5, 10).is_empty());
+}
+
+#[test]
+fn edit_log() {
+    let mut buffer = Buffer::from_text("one\ntwo\nthree");
+    let start = buffer.version();
+
+    buffer.insert(Position::new(0, 3), "\nmrrp").unwrap();
+    buffer.delete_lines(2, 3).unwrap();
+    buffer.undo();
+
+    let edits: Vec<LineEdit> = buffer.edits_since(start).unwrap().copied().collect();
+    assert_eq!(edits, vec![
+        LineEdit { line: 0, old_count: 1, new_count: 2 },
+        LineEdit { line: 2, old_count: 2, new_count: 0 },
+        LineEdit { line: 2, old_count: 0, new_count: 2 }
+    ]);
+
+    assert_eq!(buffer.edits_since(buffer.version()).unwrap().count(), 0);
+    assert!(buffer.edits_since(buffer.version() + 1).is_none());
+    assert_ne!(buffer.id(), Buffer::new().id());
+}
+
+#[test]
+fn text_edits_are_byte_accurate() {
+    let mut buffer = Buffer::from_text("one\ntwo\nthree");
+
+    let edits: Vec<fn(&mut Buffer)> = vec![
+        |buffer| buffer.insert(Position::new(1, 1), "x").unwrap(),
+        |buffer| buffer.insert(Position::new(0, 3), "\n").unwrap(),
+        |buffer| buffer.insert(Position::new(2, 0), "a\nbc").unwrap(),
+        |buffer| { buffer.delete(Range::new(Position::new(0, 1), Position::new(0, 2))).unwrap(); },
+        |buffer| { buffer.delete(Range::new(Position::new(2, 1), Position::new(4, 2))).unwrap(); },
+        |buffer| { buffer.set_line(0, "first".to_string()).unwrap(); },
+        |buffer| { let end = buffer.len(); buffer.insert_lines(end, vec!["last".to_string()]).unwrap(); },
+        |buffer| { buffer.insert_lines(0, vec!["top".to_string()]).unwrap(); },
+        |buffer| { let last = buffer.len() - 1; buffer.delete_lines(last, last).unwrap(); },
+        |buffer| { buffer.undo(); },
+        |buffer| { buffer.redo(); },
+        // Edits below one that moved them still know where their lines are.
+        |buffer| { let last = buffer.len() - 1; buffer.insert(Position::new(last, 1), "y").unwrap(); },
+        |buffer| buffer.insert(Position::new(0, 0), "x\nx").unwrap(),
+        |buffer| { let last = buffer.len() - 1; buffer.insert(Position::new(last, 1), "z").unwrap(); },
+        |buffer| { let last = buffer.len() - 1; buffer.delete_lines(0, last).unwrap(); },
+        |buffer| { buffer.undo(); }
+    ];
+
+    let offset = |text: &str, pos: Position| text.split('\n').take(pos.line).map(|line| line.len() + 1).sum::<usize>() + pos.column;
+
+    for edit in edits {
+        let before = buffer.lines.join("\n");
+        let version = buffer.version();
+        edit(&mut buffer);
+        let after = buffer.lines.join("\n");
+
+        let logged: Vec<TextEdit> = buffer.text_edits_since(version).unwrap().copied().collect();
+        let [text_edit] = logged[..] else {
+            panic!("{:?}", logged);
+        };
+
+        assert_eq!(before[.. text_edit.start_byte], after[.. text_edit.start_byte]);
+        assert_eq!(before[text_edit.old_end_byte ..], after[text_edit.new_end_byte ..], "{:?}", text_edit);
+        assert_eq!(offset(&before, text_edit.start), text_edit.start_byte);
+        assert_eq!(offset(&before, text_edit.old_end), text_edit.old_end_byte);
+        assert_eq!(offset(&after, text_edit.new_end), text_edit.new_end_byte);
+    }
+
+    let edit = buffer.text_edits_since(buffer.version() - 1).unwrap().next().copied();
+    assert_eq!(edit.map(|edit| (edit.start_byte, edit.old_end_byte, edit.new_end_byte)), Some((0, 0, buffer.lines.join("\n").len())));
+}
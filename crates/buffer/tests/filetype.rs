@@ -0,0 +1,61 @@
+use std::path::{Path, PathBuf};
+
+use buffer::buffer::Buffer;
+use buffer::filetype::detect;
+
+fn lines(text: &str) -> Vec<String> {
+    text.lines().map(String::from).collect()
+}
+
+#[test]
+fn detect_by_name_glob_and_extension() {
+    assert_eq!(detect(Some(Path::new("src/main.rs")), &[]), Some("rust"));
+    assert_eq!(detect(Some(Path::new("Makefile")), &[]), Some("make"));
+    assert_eq!(detect(Some(Path::new("Dockerfile.dev")), &[]), Some("dockerfile"));
+    assert_eq!(detect(Some(Path::new("repo/.git/config")), &[]), Some("gitconfig"));
+    assert_eq!(detect(Some(Path::new(".github/workflows/ci.yml")), &[]), Some("yaml"));
+    assert_eq!(detect(Some(Path::new("types.d.ts")), &[]), Some("typescript"));
+    assert_eq!(detect(Some(Path::new("notes.txt")), &[]), None);
+}
+
+#[test]
+fn detect_by_shebang() {
+    assert_eq!(detect(None, &lines("#!/bin/bash\necho hi")), Some("sh"));
+    assert_eq!(detect(None, &lines("#!/usr/bin/env python3.12")), Some("python"));
+    assert_eq!(detect(None, &lines("#!/usr/bin/env -S node --harmony")), Some("javascript"));
+    assert_eq!(detect(Some(Path::new("script")), &lines("#!/usr/bin/perl")), None);
+}
+
+#[test]
+fn modelines_win_over_the_path() {
+    assert_eq!(detect(Some(Path::new("build.txt")), &lines("# vim: set ft=python:")), Some("python"));
+    assert_eq!(detect(Some(Path::new("a.rs")), &lines("x\ny\n// vim: filetype=sh")), Some("sh"));
+    assert_eq!(detect(None, &lines("# -*- mode: ruby -*-")), Some("ruby"));
+    assert_eq!(detect(None, &lines("# -*- coding: utf-8; mode: shell-script -*-")), Some("sh"));
+    assert_eq!(detect(None, &lines("novim: ft=python")), None);
+}
+
+#[test]
+fn detect_by_content() {
+    assert_eq!(detect(None, &lines("<?xml version=\"1.0\"?>\n<a/>")), Some("xml"));
+    assert_eq!(detect(None, &lines("<!DOCTYPE html>\n<html>")), Some("html"));
+    assert_eq!(detect(None, &lines("diff --git a/x b/x")), Some("diff"));
+    assert_eq!(detect(None, &lines("---\nkey: value")), Some("yaml"));
+    assert_eq!(detect(None, &lines("\n{\n  \"a\": 1\n}")), Some("json"));
+    assert_eq!(detect(None, &lines("hello")), None);
+}
+
+#[test]
+fn buffer_filetype_follows_its_path() {
+    let mut buffer = Buffer::from_text("fn main() {}");
+    assert_eq!(buffer.filetype(), None);
+
+    buffer.set_path(PathBuf::from("main.rs"));
+    assert_eq!(buffer.filetype(), Some("rust"));
+
+    buffer.set_filetype(Some("c".to_string()));
+    assert_eq!(buffer.filetype(), Some("c"));
+
+    buffer.set_path(PathBuf::from("main.py"));
+    assert_eq!(buffer.filetype(), Some("python"));
+}
@@ -0,0 +1,173 @@
+use uuid::Uuid;
+
+use utils::Position;
+
+use crate::editor::Editor;
+use crate::errors::EditorError;
+
+impl Editor {
+    pub fn buffer_number(&self, id: &Uuid) -> usize {
+        self.buffer_numbers.get(id).copied().unwrap_or(0)
+    }
+
+    /// The buffer's path as it was given, for `:ls` and the picker.
+    pub fn get_buffer_path_name(&self, id: &Uuid) -> String {
+        self.buffers.get(id)
+            .and_then(|buffer| buffer.get_path())
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| "Untitled".to_string())
+    }
+
+    /// `:bn` and `Tab`.
+    pub fn next_buffer(&mut self) {
+        self.cycle_buffer(true);
+    }
+
+    /// `:bp` and `S-Tab`.
+    pub fn previous_buffer(&mut self) {
+        self.cycle_buffer(false);
+    }
+
+    fn cycle_buffer(&mut self, forward: bool) {
+        let len = self.buffer_order.len();
+
+        if len == 0 {
+            return;
+        }
+
+        let current = self.get_current_buffer_index();
+
+        let index = match forward {
+            true => (current + 1) % len,
+            false => (current + len - 1) % len
+        };
+
+        self.switch_to_buffer(self.buffer_order[index]);
+    }
+
+    /// `<C-^>`: back to the buffer shown before this one.
+    pub fn switch_to_alternate_buffer(&mut self) -> Result<(), EditorError> {
+        let id = self.alternate_buffer
+            .filter(|id| self.buffers.contains_key(id))
+            .ok_or(EditorError::NoAlternateBuffer)?;
+
+        self.switch_to_buffer(id);
+
+        Ok(())
+    }
+
+    /// The buffer `:b` means by `name`: a buffer number, `%` or `#`, or
+    /// part of a path. A full path or file name wins over a partial one,
+    /// and a partial name must match one buffer only.
+    pub fn find_buffer(&self, name: &str) -> Result<Uuid, EditorError> {
+        let name = name.trim();
+        let not_found = || EditorError::NoSuchBuffer(name.to_string());
+
+        match name {
+            "%" => return self.current_buffer.ok_or_else(not_found),
+            "#" => return self.alternate_buffer.filter(|id| self.buffers.contains_key(id)).ok_or(EditorError::NoAlternateBuffer),
+            _ => {}
+        }
+
+        if let Ok(number) = name.parse::<usize>() {
+            return self.buffer_order
+                .iter()
+                .find(|id| self.buffer_number(id) == number)
+                .copied()
+                .ok_or_else(not_found);
+        }
+
+        let exact: Vec<Uuid> = self.buffer_order
+            .iter()
+            .filter(|id| self.get_buffer_path_name(id) == name || self.get_buffer_display_name(id) == name)
+            .copied()
+            .collect();
+
+        let matches = match exact.is_empty() {
+            true => self.buffer_order
+                .iter()
+                .filter(|id| self.get_buffer_path_name(id).contains(name))
+                .copied()
+                .collect(),
+            false => exact
+        };
+
+        match matches.as_slice() {
+            [id] => Ok(*id),
+            [] => Err(not_found()),
+            _ => Err(EditorError::AmbiguousBuffer(name.to_string()))
+        }
+    }
+
+    /// `:bd`: closes buffer `id`, refusing if it has unsaved changes unless
+    /// `force`. Windows showing it move to the alternate buffer or a
+    /// neighbour, and closing the last buffer leaves an empty one.
+    pub fn delete_buffer(&mut self, id: Uuid, force: bool) -> Result<(), EditorError> {
+        let buffer = self.buffers.get(&id).ok_or(EditorError::NoBuffer)?;
+
+        if buffer.is_modified() && !force {
+            return Err(EditorError::BufferModified(self.buffer_number(&id)));
+        }
+
+        let index = self.buffer_order.iter().position(|other| *other == id).unwrap_or(0);
+        let alternate = self.alternate_buffer.filter(|alternate| *alternate != id && self.buffers.contains_key(alternate));
+
+        self.buffers.remove(&id);
+        self.buffer_order.retain(|other| *other != id);
+        self.buffer_cursor_pos.remove(&id);
+        self.buffer_numbers.remove(&id);
+        self.options.remove_buffer(id);
+
+        let replacement = match alternate.or_else(|| self.buffer_order.get(index.min(self.buffer_order.len().saturating_sub(1))).copied()) {
+            Some(replacement) => replacement,
+            None => self.create_empty_buffer()
+        };
+
+        if self.alternate_buffer == Some(id) {
+            self.alternate_buffer = None;
+        }
+
+        let pos = self.buffer_cursor_pos.get(&replacement).copied().unwrap_or(Position::new(0, 0));
+        let windows = self.windows
+            .values_mut()
+            .chain(self.tab_pages.iter_mut().flat_map(|tab| tab.windows.values_mut()));
+
+        for window in windows.filter(|window| window.buffer == Some(id)) {
+            window.buffer = Some(replacement);
+            window.cursor.pos = pos;
+            window.viewport.top = 0;
+            window.viewport.left = 0;
+        }
+
+        if self.current_buffer == Some(id) {
+            self.current_buffer = None;
+            self.switch_to_buffer(replacement);
+
+            if self.alternate_buffer == Some(replacement) {
+                self.alternate_buffer = None;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `:ls`: a line per buffer with its number, `%` for the current
+    /// buffer, `#` for the alternate one, `+` if modified, its name and the
+    /// line the cursor was on.
+    pub fn list_buffers(&self) -> Vec<String> {
+        self.buffer_order
+            .iter()
+            .map(|id| {
+                let current = if self.current_buffer == Some(*id) { '%' } else if self.alternate_buffer == Some(*id) { '#' } else { ' ' };
+                let modified = if self.buffers.get(id).is_some_and(|buffer| buffer.is_modified()) { '+' } else { ' ' };
+
+                let line = match self.current_buffer == Some(*id) {
+                    true => self.cursor.pos.line,
+                    false => self.buffer_cursor_pos.get(id).map(|pos| pos.line).unwrap_or(0)
+                };
+
+                format!("{:>3} {}{} \"{}\" line {}", self.buffer_number(id), current, modified, self.get_buffer_path_name(id), line + 1)
+            })
+            .collect()
+    }
+}
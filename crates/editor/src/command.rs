@@ -0,0 +1,341 @@
+use buffer::buffer::Buffer;
+use regex::Regex;
+
+use crate::errors::EditorError;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AddressKind {
+    Number(usize),
+    Current,
+    Last,
+    Mark(char),
+    SearchForward(String),
+    SearchBackward(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Address {
+    pub kind: AddressKind,
+    pub offset: isize
+}
+
+impl Address {
+    pub fn new(kind: AddressKind) -> Self {
+        Self {
+            kind,
+            offset: 0
+        }
+    }
+
+    /// Resolves the address to a 1-based line number. `0` is returned for the
+    /// address `0`, which commands such as `:m` and `:t` use to mean "above the
+    /// first line".
+    pub fn resolve(&self, buffer: &Buffer, current: usize) -> Result<usize, EditorError> {
+        let line = match &self.kind {
+            AddressKind::Number(n) => *n,
+            AddressKind::Current => current + 1,
+            AddressKind::Last => buffer.len(),
+            AddressKind::Mark(c) => buffer.get_mark(*c).ok_or(EditorError::MarkNotSet(*c))?.line + 1,
+            AddressKind::SearchForward(pattern) => search_lines(buffer, current, pattern, true)? + 1,
+            AddressKind::SearchBackward(pattern) => search_lines(buffer, current, pattern, false)? + 1,
+        };
+
+        let line = line as isize + self.offset;
+
+        if line < 0 || line as usize > buffer.len() {
+            return Err(EditorError::InvalidRange);
+        }
+
+        Ok(line as usize)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CommandRange {
+    Whole,
+    Single(Address),
+    Pair(Address, Address),
+    /// `a;b`, where `b` is resolved with the cursor moved to `a`.
+    Anchored(Address, Address),
+}
+
+/// An inclusive, 0-based range of buffer lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LineRange {
+    pub start: usize,
+    pub end: usize
+}
+
+impl LineRange {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self {
+            start,
+            end
+        }
+    }
+}
+
+impl CommandRange {
+    /// Resolves the range to 1-based `(start, end)` line numbers.
+    pub fn resolve(&self, buffer: &Buffer, current: usize) -> Result<(usize, usize), EditorError> {
+        let (start, end) = match self {
+            CommandRange::Whole => (1, buffer.len()),
+            CommandRange::Single(address) => {
+                let line = address.resolve(buffer, current)?;
+                (line, line)
+            },
+            CommandRange::Pair(start, end) => {
+                (start.resolve(buffer, current)?, end.resolve(buffer, current)?)
+            },
+            CommandRange::Anchored(start, end) => {
+                let start = start.resolve(buffer, current)?;
+                (start, end.resolve(buffer, start.saturating_sub(1))?)
+            }
+        };
+
+        if start > end {
+            return Err(EditorError::BackwardsRange);
+        }
+
+        Ok((start, end))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExCommand {
+    pub range: Option<CommandRange>,
+    pub name: String,
+    pub bang: bool,
+    /// Everything after the name; trailing whitespace is kept since it is
+    /// significant for commands like `:normal`.
+    pub args: String
+}
+
+pub fn parse_command(input: &str) -> Result<ExCommand, EditorError> {
+    let mut parser = Parser::new(input.trim_start_matches(|c: char| c == ':' || c.is_whitespace()));
+
+    let range = parser.parse_range()?;
+    parser.skip_whitespace();
+
+    let name = parser.parse_name();
+
+    let bang = if name.chars().all(|c| c.is_ascii_alphabetic()) && !name.is_empty() {
+        parser.eat('!')
+    } else {
+        false
+    };
+
+    let args = parser.rest().trim_start().to_string();
+
+    Ok(ExCommand {
+        range,
+        name,
+        bang,
+        args
+    })
+}
+
+/// Parses a single address at the start of `input`, returning it with the
+/// unparsed remainder. Used by commands that take a destination (`:m`, `:t`).
+pub fn parse_address(input: &str) -> Result<(Option<Address>, &str), EditorError> {
+    let mut parser = Parser::new(input.trim_start());
+    let address = parser.parse_address()?;
+
+    Ok((address, parser.rest()))
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            pos: 0
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos ..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos ..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.bump();
+        }
+    }
+
+    fn parse_number(&mut self) -> Option<usize> {
+        let start = self.pos;
+
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.bump();
+        }
+
+        self.input[start .. self.pos].parse().ok()
+    }
+
+    fn parse_pattern(&mut self, delimiter: char) -> String {
+        let mut pattern = String::new();
+
+        while let Some(c) = self.bump() {
+            if c == delimiter {
+                break;
+            }
+
+            if c == '\\' && self.peek() == Some(delimiter) {
+                pattern.push(delimiter);
+                self.bump();
+            } else {
+                pattern.push(c);
+            }
+        }
+
+        pattern
+    }
+
+    fn parse_range(&mut self) -> Result<Option<CommandRange>, EditorError> {
+        if self.eat('%') {
+            return Ok(Some(CommandRange::Whole));
+        }
+
+        let start = match self.parse_address()? {
+            Some(address) => address,
+            None if matches!(self.peek(), Some(',') | Some(';')) => Address::new(AddressKind::Current),
+            None => return Ok(None)
+        };
+
+        self.skip_whitespace();
+
+        let anchored = match self.peek() {
+            Some(',') => false,
+            Some(';') => true,
+            _ => return Ok(Some(CommandRange::Single(start)))
+        };
+
+        self.bump();
+        self.skip_whitespace();
+
+        let end = self.parse_address()?.unwrap_or(Address::new(AddressKind::Current));
+
+        if anchored {
+            Ok(Some(CommandRange::Anchored(start, end)))
+        } else {
+            Ok(Some(CommandRange::Pair(start, end)))
+        }
+    }
+
+    fn parse_address(&mut self) -> Result<Option<Address>, EditorError> {
+        let kind = match self.peek() {
+            Some(c) if c.is_ascii_digit() => AddressKind::Number(self.parse_number().unwrap_or(0)),
+            Some('.') => {
+                self.bump();
+                AddressKind::Current
+            },
+            Some('$') => {
+                self.bump();
+                AddressKind::Last
+            },
+            Some('\'') => {
+                self.bump();
+
+                match self.bump() {
+                    Some(c) if c.is_ascii_alphabetic() || c == '<' || c == '>' => AddressKind::Mark(c),
+                    Some(c) => return Err(EditorError::InvalidAddress(format!("'{}", c))),
+                    None => return Err(EditorError::InvalidAddress("'".to_string()))
+                }
+            },
+            Some('/') => {
+                self.bump();
+                AddressKind::SearchForward(self.parse_pattern('/'))
+            },
+            Some('?') => {
+                self.bump();
+                AddressKind::SearchBackward(self.parse_pattern('?'))
+            },
+            Some('+') | Some('-') => AddressKind::Current,
+            _ => return Ok(None)
+        };
+
+        let mut address = Address::new(kind);
+
+        while let Some(sign) = self.peek().filter(|c| *c == '+' || *c == '-') {
+            self.bump();
+
+            let amount = self.parse_number().unwrap_or(1) as isize;
+
+            if sign == '+' {
+                address.offset += amount;
+            } else {
+                address.offset -= amount;
+            }
+        }
+
+        Ok(Some(address))
+    }
+
+    fn parse_name(&mut self) -> String {
+        let start = self.pos;
+
+        match self.peek() {
+            // As in Vim, `:ka` is `:k a`: the mark name needn't be spaced off.
+            Some('k') if self.input[self.pos + 1 ..].starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                self.bump();
+            },
+            Some(c) if c.is_ascii_alphabetic() => {
+                while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+                    self.bump();
+                }
+            },
+            Some('>') | Some('<') => {
+                self.bump();
+            },
+            _ => {}
+        }
+
+        self.input[start .. self.pos].to_string()
+    }
+}
+
+/// Finds the next line containing `pattern`, starting after `current` and
+/// wrapping around the end of the buffer.
+fn search_lines(buffer: &Buffer, current: usize, pattern: &str, forward: bool) -> Result<usize, EditorError> {
+    let regex = Regex::new(pattern).map_err(|_| EditorError::InvalidPattern(pattern.to_string()))?;
+    let len = buffer.len();
+
+    for step in 1 ..= len {
+        let line = if forward {
+            (current + step) % len
+        } else {
+            (current + len * 2 - step) % len
+        };
+
+        if regex.is_match(&buffer.lines[line]) {
+            return Ok(line);
+        }
+    }
+
+    Err(EditorError::PatternNotFound(pattern.to_string()))
+}
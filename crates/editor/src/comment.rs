@@ -0,0 +1,69 @@
+use buffer::filetype;
+
+use crate::editor::Editor;
+use crate::errors::EditorError;
+
+fn indent_len(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn is_commented(line: &str, (open, close): (&str, &str)) -> bool {
+    let text = line.trim();
+    text.starts_with(open) && text.ends_with(close) && text.len() >= open.len() + close.len()
+}
+
+fn uncomment(line: &str, (open, close): (&str, &str)) -> String {
+    let indent = indent_len(line);
+    let text = line[indent ..].trim_end();
+    let text = &text[open.len() .. text.len() - close.len()];
+    let text = text.strip_prefix(' ').unwrap_or(text);
+    let text = if close.is_empty() { text } else { text.strip_suffix(' ').unwrap_or(text) };
+
+    format!("{}{}", &line[.. indent], text)
+}
+
+fn comment(line: &str, indent: usize, (open, close): (&str, &str)) -> String {
+    let close = if close.is_empty() { String::new() } else { format!(" {}", close) };
+    format!("{}{} {}{}", &line[.. indent], open, &line[indent ..], close)
+}
+
+impl Editor {
+    /// `gcc` and visual `gc`: comments out lines `start ..= end` with the
+    /// filetype's comment markers, or uncomments them if every non-blank
+    /// line already is. Markers go at the smallest indent among the lines
+    /// so the block stays aligned.
+    pub fn toggle_comment(&mut self, start: usize, end: usize) -> Result<(), EditorError> {
+        let buffer = self.get_current_buffer_mut().ok_or(EditorError::NoBuffer)?;
+        let markers = buffer.filetype()
+            .and_then(filetype::info)
+            .and_then(|info| info.comment)
+            .ok_or(EditorError::NoCommentString)?;
+
+        let end = end.min(buffer.len() - 1);
+        let lines = buffer.line_slice(start, end + 1).to_vec();
+        let non_blank: Vec<&String> = lines.iter().filter(|line| !line.trim().is_empty()).collect();
+
+        let remove = !non_blank.is_empty() && non_blank.iter().all(|line| is_commented(line, markers));
+        let indent = non_blank.iter().map(|line| indent_len(line)).min().unwrap_or(0);
+
+        buffer.begin_undo_group();
+
+        for (line, text) in (start ..= end).zip(&lines) {
+            if text.trim().is_empty() {
+                continue;
+            }
+
+            let new = match remove {
+                true => uncomment(text, markers),
+                false => comment(text, indent, markers)
+            };
+
+            let _ = buffer.set_line(line, new);
+        }
+
+        buffer.end_undo_group();
+        self.clamp_cursor();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+use std::borrow::Cow;
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// One grapheme of a line as it is drawn: its byte range in the line, the
+/// screen column it starts at counted from the start of the line, and how
+/// many columns it takes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cell {
+    pub start: usize,
+    pub end: usize,
+    pub column: usize,
+    pub width: usize
+}
+
+impl Cell {
+    /// What to draw for this cell: tabs become spaces up to the next tab
+    /// stop and control characters are shown as `^X`.
+    pub fn display<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let grapheme = &text[self.start .. self.end];
+
+        match control_char(grapheme) {
+            Some('\t') => Cow::Owned(" ".repeat(self.width)),
+            Some(c) => Cow::Owned(format!("^{}", caret_notation(c))),
+            None => Cow::Borrowed(grapheme)
+        }
+    }
+}
+
+fn control_char(grapheme: &str) -> Option<char> {
+    let mut chars = grapheme.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_control() => Some(c),
+        _ => None
+    }
+}
+
+fn caret_notation(c: char) -> char {
+    ((c as u8) ^ 0x40) as char
+}
+
+/// Splits `text` into cells, with tab stops every `tab_stop` columns.
+pub fn cells(text: &str, tab_stop: usize) -> Vec<Cell> {
+    let tab_stop = tab_stop.max(1);
+    let mut column = 0;
+
+    text.grapheme_indices(true)
+        .map(|(start, grapheme)| {
+            let width = match control_char(grapheme) {
+                Some('\t') => tab_stop - column % tab_stop,
+                Some(_) => 2,
+                None => grapheme.width()
+            };
+
+            let cell = Cell {
+                start,
+                end: start + grapheme.len(),
+                column,
+                width
+            };
+
+            column += width;
+            cell
+        })
+        .collect()
+}
+
+/// The screen column byte `column` of `text` is drawn at.
+pub fn display_column(text: &str, column: usize, tab_stop: usize) -> usize {
+    cells(text, tab_stop)
+        .iter()
+        .find(|cell| cell.end > column)
+        .map(|cell| cell.column)
+        .unwrap_or_else(|| display_width(text, tab_stop))
+}
+
+pub fn display_width(text: &str, tab_stop: usize) -> usize {
+    cells(text, tab_stop).last().map(|cell| cell.column + cell.width).unwrap_or(0)
+}
+
+/// The byte column of the grapheme covering screen column `column`, or the
+/// end of the line if it is shorter.
+pub fn column_at(text: &str, column: usize, tab_stop: usize) -> usize {
+    cells(text, tab_stop)
+        .iter()
+        .find(|cell| cell.column + cell.width > column)
+        .map(|cell| cell.start)
+        .unwrap_or(text.len())
+}
+
+/// The byte column of the grapheme after the one at `column`.
+pub fn next_boundary(text: &str, column: usize) -> usize {
+    text.grapheme_indices(true)
+        .map(|(i, grapheme)| i + grapheme.len())
+        .find(|&end| end > column)
+        .unwrap_or(text.len())
+}
+
+/// The byte column of the grapheme before `column`.
+pub fn prev_boundary(text: &str, column: usize) -> usize {
+    text.grapheme_indices(true)
+        .map(|(i, _)| i)
+        .take_while(|&start| start < column)
+        .last()
+        .unwrap_or(0)
+}
@@ -1,21 +1,41 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::ops::Range as TextRange;
+use std::path::{Path, PathBuf};
 
 use buffer::buffer::Buffer;
+use buffer::filetype;
 use utils::{Position, Range};
 
 use crate::cursor::Cursor;
+use crate::display::{column_at, display_column, next_boundary, prev_boundary};
 use crate::errors::EditorError;
+use crate::ex::CommandRegistry;
+use crate::explorer::Explorer;
+use crate::keymap::KeyInput;
+use crate::make::MakeJob;
+use crate::message::Messages;
+use crate::options::{Options, WindowOptions};
+use crate::picker::Picker;
+use crate::prompt::{Prompt, PromptEvent};
+use crate::quickfix::QuickfixList;
+use crate::search::SearchState;
+use crate::substitute::{ParsedPreview, Substitution, SubstituteConfirm};
+use crate::syntax::Syntax;
+use crate::tabpage::TabPage;
+use crate::theme::{ColorDepth, Theme};
+use crate::viewport::Viewport;
+use crate::window::{Window, WindowId, WindowLayout, WindowRect};
 
 use uuid::Uuid;
-use crossterm::event::{KeyCode};
+use crossterm::event::KeyEvent;
 
 #[derive(Debug)]
 pub enum EditorMode {
     Normal,
     Insert,
     Visual,
-    Command
+    Command,
+    Search
 }
 
 impl std::fmt::Display for EditorMode {
@@ -25,6 +45,7 @@ impl std::fmt::Display for EditorMode {
             EditorMode::Insert => write!(f, "INSERT"),
             EditorMode::Visual => write!(f, "VISUAL"),
             EditorMode::Command => write!(f, "COMMAND"),
+            EditorMode::Search => write!(f, "SEARCH"),
         }
     }
 }
@@ -34,56 +55,220 @@ pub struct Editor {
     pub buffers: HashMap<Uuid, Buffer>, 
     pub buffer_order: Vec<Uuid>,
     pub buffer_cursor_pos: HashMap<Uuid, Position>,
+    /// The number `:ls` shows and `:b N` takes; a buffer keeps its number
+    /// while it is open.
+    pub buffer_numbers: HashMap<Uuid, usize>,
+    pub(crate) next_buffer_number: usize,
     pub current_buffer: Option<Uuid>,
+    /// The buffer `<C-^>` goes back to: the one shown before the current
+    /// one.
+    pub alternate_buffer: Option<Uuid>,
     pub cursor: Cursor, 
-    pub mode: EditorMode
+    /// Where the visual selection started; the cursor is its other end.
+    pub visual_anchor: Option<Position>,
+    /// Selections replaced by expanding, for shrinking back to.
+    pub selection_history: Vec<TextRange<Position>>,
+    pub viewport: Viewport,
+    pub options: Options,
+    /// The options of the window, which `:setlocal` changes alone.
+    pub window_options: WindowOptions,
+    /// Every window by id; the current one's entry is stale while it has
+    /// the focus.
+    pub windows: HashMap<WindowId, Window>,
+    pub window_layout: WindowLayout,
+    pub current_window: WindowId,
+    pub(crate) next_window: WindowId,
+    /// The screen area the windows share, set by the UI.
+    pub window_area: WindowRect,
+    /// Every tab page; the current one's entry is stale while it is shown.
+    pub tab_pages: Vec<TabPage>,
+    pub current_tab: usize,
+    pub mode: EditorMode,
+    pub commands: CommandRegistry,
+    pub command_line: Prompt,
+    pub registers: HashMap<char, Vec<String>>,
+    pub in_global: bool,
+    pub last_substitute: Option<Substitution>,
+    pub last_pattern: Option<String>,
+    pub search: SearchState,
+    pub syntax: Syntax,
+    /// The colour scheme, already fitted to `color_depth`.
+    pub theme: Theme,
+    pub color_depth: ColorDepth,
+    pub substitute_confirm: Option<SubstituteConfirm>,
+    /// The last substitution parsed for the preview, kept so redrawing
+    /// doesn't compile its pattern again.
+    pub(crate) parsed_preview: Option<ParsedPreview>,
+    /// The list `:make` and `:grep` fill and `:cnext` goes through.
+    pub quickfix: QuickfixList,
+    /// Each window's location list, which the `:l` commands use, kept
+    /// apart from the windows so their state stays cheap to copy.
+    pub location_lists: HashMap<WindowId, QuickfixList>,
+    /// A `:make` still running.
+    pub make: Option<MakeJob>,
+    /// The fuzzy picker popup, which takes all keys while it is open.
+    pub picker: Option<Picker>,
+    /// The file tree beside the windows, if it is open.
+    pub explorer: Option<Explorer>,
+    /// Keys typed but not yet acted on, and the keymaps.
+    pub input: KeyInput,
+    pub messages: Messages,
+    pub should_quit: bool
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Editor {
     pub fn new() -> Self {
+        let window = Window {
+            buffer: None,
+            cursor: Cursor::new(Position::new(0, 0)),
+            viewport: Viewport::new(),
+            options: WindowOptions::default()
+        };
+
+        let tab = TabPage {
+            layout: WindowLayout::Window(0),
+            windows: HashMap::from([(0, window)]),
+            current_window: 0
+        };
+
         Self {
             buffers: HashMap::new(),
             buffer_order: vec![],
             buffer_cursor_pos: HashMap::new(),
+            buffer_numbers: HashMap::new(),
+            next_buffer_number: 1,
             current_buffer: None,
+            alternate_buffer: None,
             cursor: Cursor::new(Position::new(0, 0)),
-            mode: EditorMode::Normal
+            visual_anchor: None,
+            selection_history: vec![],
+            viewport: Viewport::new(),
+            options: Options::new(),
+            window_options: WindowOptions::default(),
+            windows: tab.windows.clone(),
+            window_layout: tab.layout.clone(),
+            current_window: 0,
+            next_window: 1,
+            window_area: WindowRect::new(0, 0, 80, 24),
+            tab_pages: vec![tab],
+            current_tab: 0,
+            mode: EditorMode::Normal,
+            commands: CommandRegistry::default(),
+            command_line: Prompt::new(),
+            registers: HashMap::new(),
+            in_global: false,
+            last_substitute: None,
+            last_pattern: None,
+            search: SearchState::default(),
+            syntax: Syntax::new(),
+            theme: Theme::default(),
+            color_depth: ColorDepth::TrueColor,
+            substitute_confirm: None,
+            parsed_preview: None,
+            quickfix: QuickfixList::default(),
+            location_lists: HashMap::new(),
+            make: None,
+            picker: None,
+            explorer: None,
+            input: KeyInput::new(),
+            messages: Messages::new(),
+            should_quit: false
         }
     }
 
-    pub fn create_empty_buffer(&mut self) {
-        let id = Uuid::new_v4();
-        let buffer = Buffer::new();
+    pub fn create_empty_buffer(&mut self) -> Uuid {
+        let pos = self.cursor.pos;
+        self.add_buffer(Buffer::new(), pos)
+    }
 
-        self.buffers.insert(id, buffer);
-        self.buffer_order.push(id);
-        self.buffer_cursor_pos.insert(id, self.cursor.pos);
+    pub fn create_buffer_from_file(&mut self, path: PathBuf) -> Result<Uuid, EditorError> {
+        let buffer = Buffer::from_file(&path).map_err(|err| EditorError::ReadFailed(path.display().to_string(), err))?;
 
-        if self.current_buffer.is_none() {
-            self.current_buffer = Some(id);
-        }
+        Ok(self.add_buffer(buffer, Position::new(0, 0)))
     }
 
-    pub fn create_buffer_from_file(&mut self, path: PathBuf) {
+    /// Adds `buffer` to the buffer list with the next buffer number, making
+    /// it current if there is no current buffer.
+    fn add_buffer(&mut self, buffer: Buffer, cursor: Position) -> Uuid {
         let id = Uuid::new_v4();
-        let buffer = Buffer::from_file(&path);
 
+        self.options.sync_buffer(id, buffer.filetype());
         self.buffers.insert(id, buffer);
         self.buffer_order.push(id);
-        self.buffer_cursor_pos.insert(id, self.cursor.pos);
+        self.buffer_cursor_pos.insert(id, cursor);
+        self.buffer_numbers.insert(id, self.next_buffer_number);
+        self.next_buffer_number += 1;
 
         if self.current_buffer.is_none() {
             self.current_buffer = Some(id);
         }
+
+        id
+    }
+
+    /// The open buffer for the file at `path`, if there is one, however
+    /// either path names it: `src/x.rs`, `./src/x.rs` and the absolute path
+    /// are the same file.
+    pub fn buffer_for_path(&self, path: &Path) -> Option<Uuid> {
+        self.buffer_order
+            .iter()
+            .find(|id| self.buffers.get(id).and_then(|buffer| buffer.get_path()).is_some_and(|other| same_file(&other, path)))
+            .copied()
+    }
+
+    /// Switches to the buffer for `path`, reusing an open one if there is one.
+    /// A directory is opened in the file explorer instead.
+    pub fn open_file(&mut self, path: PathBuf) -> Result<(), EditorError> {
+        if path.is_dir() {
+            return self.open_explorer(&path);
+        }
+
+        let id = match self.buffer_for_path(&path) {
+            Some(id) => id,
+            None => self.create_buffer_from_file(path)?
+        };
+
+        self.switch_to_buffer(id);
+
+        Ok(())
     }
 
+    /// Writes the current buffer to its file and reports how much was
+    /// written.
     pub fn save_buffer(&mut self) -> Result<(), EditorError> {
-        let buffer = self.get_current_buffer_mut().unwrap();
+        let buffer = self.get_current_buffer_mut().ok_or(EditorError::NoBuffer)?;
+        let path = buffer.get_path().ok_or(EditorError::NoFileName)?;
+
+        let bytes = buffer.save_to_file().map_err(|err| EditorError::WriteFailed(path.display().to_string(), err))?;
+        let lines = buffer.len();
 
-        match buffer.save_to_file() {
-            Ok(_) => Ok(()),
-            Err(_) => Err(EditorError::SaveError) 
+        self.report_written(&path, lines, bytes);
+
+        Ok(())
+    }
+
+    /// Shows what a write did, as `"foo.rs" 120L, 3400B written`.
+    pub(crate) fn report_written(&mut self, path: &Path, lines: usize, bytes: usize) {
+        self.messages.info(format!("\"{}\" {}L, {}B written", path.display(), lines, bytes));
+    }
+
+    pub fn set_register(&mut self, register: char, lines: Vec<String>) {
+        if register.is_ascii_uppercase() {
+            self.registers
+                .entry(register.to_ascii_lowercase())
+                .or_default()
+                .extend(lines.iter().cloned());
+        } else if register != '"' {
+            self.registers.insert(register, lines.clone());
         }
+
+        self.registers.insert('"', lines);
     }
 
     pub fn get_current_buffer(&self) -> Option<&Buffer> {
@@ -116,84 +301,170 @@ impl Editor {
         }
     }
 
-    pub fn next_buffer(&mut self) {
-        if let Some(key) = self.buffer_cursor_pos.get_mut(&self.current_buffer.unwrap()) {
-            *key = self.cursor.pos;
+    pub fn switch_to_buffer(&mut self, id: Uuid) {
+        if let Some(current) = self.current_buffer {
+            self.buffer_cursor_pos.insert(current, self.cursor.pos);
+
+            if current != id {
+                self.alternate_buffer = Some(current);
+            }
         }
 
-        if !self.buffer_order.is_empty() {
-            let current_idx = self.get_current_buffer_index();
-            let next_idx = (current_idx + 1) % self.buffer_order.len();
+        self.current_buffer = Some(id);
+        self.cursor.pos = self.buffer_cursor_pos.get(&id).copied().unwrap_or(Position::new(0, 0));
+        self.clamp_cursor();
+
+        self.viewport.top = 0;
+        self.viewport.left = 0;
+        self.scroll_to_cursor();
+    }
 
-            self.current_buffer = Some(self.buffer_order[next_idx]);
-            self.cursor.pos = *self.buffer_cursor_pos.get(&self.current_buffer.unwrap()).unwrap();
+    /// Switches to the colour scheme `name`, keeping the current one if it
+    /// cannot be loaded.
+    pub fn set_theme(&mut self, name: &str) -> Result<(), EditorError> {
+        self.theme = Theme::load(name)?.fit(self.color_depth);
+
+        Ok(())
+    }
+
+    /// Refits the colour scheme for a terminal that shows `depth` colours.
+    pub fn set_color_depth(&mut self, depth: ColorDepth) {
+        self.color_depth = depth;
+        let name = self.theme.name.clone();
+
+        if self.set_theme(&name).is_err() {
+            self.theme = Theme::default().fit(depth);
         }
     }
 
     pub fn change_mode(&mut self, mode: EditorMode) {
+        let was_insert = matches!(self.mode, EditorMode::Insert);
+        let is_insert = matches!(mode, EditorMode::Insert);
+
+        if let Some(buffer) = self.get_current_buffer_mut() {
+            if is_insert && !was_insert {
+                buffer.begin_undo_group();
+            } else if was_insert && !is_insert {
+                buffer.end_undo_group();
+            }
+        }
+
+        match mode {
+            EditorMode::Visual if !matches!(self.mode, EditorMode::Visual) => self.visual_anchor = Some(self.cursor.pos),
+            EditorMode::Visual => {},
+            _ => {
+                self.visual_anchor = None;
+                self.selection_history.clear();
+            }
+        }
+
         self.mode = mode;
     }
 
-    pub fn handle_normal_mode_input(&mut self, key: KeyCode) {
-        match key {
-            KeyCode::Char('i') => self.change_mode(EditorMode::Insert),
-            KeyCode::Char('v') => self.change_mode(EditorMode::Visual),
-            KeyCode::Char(':') => self.change_mode(EditorMode::Command),
-            KeyCode::Char('h') => self.move_cursor_left(),
-            KeyCode::Char('j') => self.move_cursor_down(),
-            KeyCode::Char('k') => self.move_cursor_up(),
-            KeyCode::Char('l') => self.move_cursor_right(),
-            KeyCode::Char('W') => self.jump_to_next_word_after_space(),
-            //KeyCode::Char('w') => self.jump_to_next_different_char(),
-            KeyCode::Left => self.move_cursor_left(),
-            KeyCode::Down => self.move_cursor_down(),
-            KeyCode::Up => self.move_cursor_up(),
-            KeyCode::Right => self.move_cursor_right(),
-            KeyCode::Tab => self.next_buffer(),
-            _ => {} 
+    pub fn undo(&mut self) -> bool {
+        let Some(line) = self.get_current_buffer_mut().and_then(|buffer| buffer.undo()) else {
+            return false;
+        };
+
+        self.cursor.pos = Position::new(line, 0);
+        self.clamp_cursor();
+
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let Some(line) = self.get_current_buffer_mut().and_then(|buffer| buffer.redo()) else {
+            return false;
+        };
+
+        self.cursor.pos = Position::new(line, 0);
+        self.clamp_cursor();
+
+        true
+    }
+
+    /// Routes a key to the handler for the current mode. This is the single
+    /// entry point for input, used both by the terminal loop and `:normal`.
+    /// Keys typed in normal, insert and visual mode go through the keymaps
+    /// first; the command line and search prompt read them as they are.
+    pub fn handle_key(&mut self, key: KeyEvent) {
+        self.sync_buffer_options();
+
+        if self.picker.is_some() {
+            self.handle_picker_key(key);
+        } else if self.substitute_confirm.is_some() {
+            self.handle_confirm_input(key.code);
+        } else if self.explorer_focused() && matches!(self.mode, EditorMode::Normal) {
+            self.handle_explorer_key(key);
+        } else {
+            self.feed_key(key);
         }
+
+        self.scroll_to_cursor();
     }
 
-    pub fn handle_insert_mode_input(&mut self, key: KeyCode) {
-        match key {
-            KeyCode::Esc => self.change_mode(EditorMode::Normal),
-            KeyCode::Backspace => self.backspace(),
-            KeyCode::Enter => self.newline(),
-            KeyCode::Tab => self.indent(),
-            KeyCode::Char(c) => {
-                let pos = self.cursor.pos;
-                if let Some(buffer) = self.get_current_buffer_mut() {
-                    match buffer.insert(pos, &c.to_string()) {
-                        Ok(_) => {
-                            self.move_cursor_right();
-
-                            if c == '(' || c == '{' || c == '[' {
-                                self.auto_paren(c); 
-                            }
-                        },
-                        Err(_) => {}
-                    }
-                }
-            },
-            KeyCode::Left => self.move_cursor_left(),
-            KeyCode::Down => self.move_cursor_down(),
-            KeyCode::Up => self.move_cursor_up(),
-            KeyCode::Right => self.move_cursor_right(),
-            _ => {}
+    /// Takes in what a background search, `:make` or git has come up with,
+    /// returning whether there is anything new to draw.
+    pub fn poll_background(&mut self) -> bool {
+        let picker = self.poll_picker();
+        let make = self.poll_make();
+        let explorer = self.poll_explorer();
+
+        picker || make || explorer
+    }
+
+    /// Whether a picker is still being filled in, a `:make` is running or
+    /// the explorer is waiting on git.
+    pub fn is_busy(&self) -> bool {
+        self.make.is_some()
+            || self.picker.as_ref().is_some_and(|picker| picker.is_loading())
+            || self.explorer.as_ref().is_some_and(|explorer| explorer.is_loading())
+    }
+
+    /// Runs `action`, showing its error in the message area if it fails.
+    pub(crate) fn report(&mut self, action: impl FnOnce(&mut Self) -> Result<(), EditorError>) {
+        if let Err(err) = action(self) {
+            self.messages.error(err);
         }
     }
 
-    pub fn handle_visual_mode_input(&mut self, key: KeyCode) {
-        match key {
-            KeyCode::Esc => self.change_mode(EditorMode::Normal),
-            _ => {}
+    /// Types `c` at the cursor in insert mode, closing brackets when
+    /// `autopairs` is set.
+    pub fn insert_char(&mut self, c: char) {
+        let pos = self.cursor.pos;
+
+        if let Some(buffer) = self.get_current_buffer_mut()
+            && buffer.insert(pos, &c.to_string()).is_ok()
+        {
+            self.move_cursor_right();
+
+            if matches!(c, '(' | '{' | '[') && self.buffer_options().auto_pairs {
+                self.auto_paren(c); 
+            }
         }
     }
 
-    pub fn handle_command_mode_input(&mut self, key: KeyCode) {
-        match key {
-            KeyCode::Esc => self.change_mode(EditorMode::Normal),
-            _ => {}
+    pub fn handle_command_mode_input(&mut self, key: KeyEvent) {
+        match self.command_line.handle_key(key) {
+            PromptEvent::Cancel => {
+                self.command_line.clear();
+                self.change_mode(EditorMode::Normal);
+            },
+            PromptEvent::Submit(command) => {
+                self.command_line.push_history(&command);
+                self.command_line.clear();
+                self.change_mode(EditorMode::Normal);
+
+                if let Err(err) = self.execute_command(&command) {
+                    self.messages.error(err);
+                }
+            },
+            PromptEvent::Complete(forward) => {
+                let mut prompt = std::mem::take(&mut self.command_line);
+                prompt.complete(forward, |line| self.command_completions(line));
+                self.command_line = prompt;
+            },
+            PromptEvent::Changed | PromptEvent::Unchanged => {}
         }
     }
 
@@ -206,69 +477,149 @@ impl Editor {
 
         if let Some(buffer) = self.get_current_buffer_mut() {
             if pos.column == 0 {
-                if let Ok(current_line) = buffer.get_line(pos.line) {
-                    let current_line_content = current_line.clone();
-
-                    if current_line_content.is_empty() {
-                        buffer.lines.remove(pos.line);
-
-                        if let Ok(prev_line) = buffer.get_line(pos.line - 1) {
-                            self.move_cursor_to(Position::new(pos.line - 1, prev_line.len()));
-                        }
-                    } else {
-                        if let Ok(prev_line) = buffer.get_line(pos.line - 1) {
-                            let prev_line_len = prev_line.len();
-                            let joined = prev_line.clone() + &current_line_content;
-
-                            buffer.lines[pos.line - 1] = joined;
-                            buffer.lines.remove(pos.line);
+                if let Ok(prev_line) = buffer.get_line(pos.line - 1) {
+                    let prev_line_len = prev_line.len();
 
-                            self.move_cursor_to(Position::new(pos.line - 1, prev_line_len));
-                        }
+                    if buffer.delete(Range::new(Position::new(pos.line - 1, prev_line_len), pos)).is_ok() {
+                        self.move_cursor_to(Position::new(pos.line - 1, prev_line_len));
                     }
                 }
             } else {
+                let start = prev_boundary(&buffer.lines[pos.line], pos.column);
+
                 let _ = buffer.delete(Range::new(
-                    Position::new(pos.line, pos.column - 1),
+                    Position::new(pos.line, start),
                     Position::new(pos.line, pos.column - 1)
                 ));
 
-                self.move_cursor_left();
+                self.cursor.pos.column = start;
             }
         }
     }
 
+    /// Splits the line at the cursor. In a buffer with a filetype the new
+    /// line keeps the indent of the old one, a level deeper after one of
+    /// the filetype's openers; a closer right after the cursor is pushed
+    /// onto a line of its own back at the original indent.
     pub fn newline(&mut self) {
         let pos = self.cursor.pos;
+        let unit = self.indent_unit();
+        let auto_indent = self.buffer_options().auto_indent;
 
-        if let Some(buffer) = self.get_current_buffer_mut() {
-            match buffer.insert(pos, "\n") {
-                Ok(_) => self.move_cursor_to(Position::new(pos.line + 1, 0)),
-                Err(_) => {}
+        let Some(buffer) = self.get_current_buffer_mut() else {
+            return;
+        };
+
+        let Some(info) = buffer.filetype().and_then(filetype::info).filter(|_| auto_indent) else {
+            if buffer.insert(pos, "\n").is_ok() {
+                self.move_cursor_to(Position::new(pos.line + 1, 0));
             }
+            return;
+        };
+
+        let line = &buffer.lines[pos.line];
+        let (before, after) = line.split_at(pos.column);
+        let indent: String = before.chars().take_while(|c| c.is_whitespace()).collect();
+        let opens = opens_block(before.trim_end(), info.indent_after);
+        let closes = opens && after.trim_start().starts_with([')', ']', '}']);
+        // Whitespace left before the cursor would only trail the old line.
+        let cut = before.trim_end().len();
+
+        let inner = if opens { format!("{}{}", indent, unit) } else { indent.clone() };
+
+        buffer.begin_undo_group();
+
+        if cut < pos.column {
+            let _ = buffer.delete(Range::new(Position::new(pos.line, cut), Position::new(pos.line, pos.column - 1)));
+        }
+
+        let _ = buffer.insert(Position::new(pos.line, cut), &format!("\n{}", inner));
+
+        if closes {
+            let _ = buffer.insert(Position::new(pos.line + 1, inner.len()), &format!("\n{}", indent));
+        }
+
+        buffer.end_undo_group();
+        self.move_cursor_to(Position::new(pos.line + 1, inner.len()));
+    }
+
+    /// One level of indent as text.
+    fn indent_unit(&self) -> String {
+        let options = self.buffer_options();
+
+        match options.expand_tab {
+            true => " ".repeat(options.shift_width.max(1)),
+            false => "\t".to_string()
         }
     }
 
+    /// Indents to the next multiple of `shift_width`: with `expand_tab` in
+    /// spaces, otherwise in as many tabs as fit and spaces for the rest, the
+    /// way `>` does. In the indentation at the start of a line, the
+    /// whitespace before the cursor is redone so spaces become tabs.
     pub fn indent(&mut self) {
         let pos = self.cursor.pos;
+        let options = self.buffer_options();
+        let (tab_stop, shift_width, expand_tab) = (options.tab_stop.max(1), options.shift_width.max(1), options.expand_tab);
 
         if let Some(buffer) = self.get_current_buffer_mut() {
-            let count = 4 - (pos.column % 4);
-            let indent = " ".repeat(count);
+            let text = &buffer.lines[pos.line];
+            let column = display_column(text, pos.column, tab_stop);
+            let target = column + shift_width - column % shift_width;
+
+            let leading = text[.. pos.column].trim_start_matches([' ', '\t']).is_empty();
+            let start = if leading && !expand_tab { 0 } else { pos.column };
+            let mut from = if start == 0 { 0 } else { column };
+
+            let indent = if expand_tab {
+                " ".repeat(target - column)
+            } else {
+                let mut indent = String::new();
+
+                while (from / tab_stop + 1) * tab_stop <= target {
+                    indent.push('\t');
+                    from = (from / tab_stop + 1) * tab_stop;
+                }
+
+                indent + &" ".repeat(target - from)
+            };
+
+            buffer.begin_undo_group();
 
-            match buffer.insert(pos, &indent) {
-                Ok(_) => {self.move_cursor_to(Position::new(pos.line, pos.column + count))}
-                Err(_) => {}
+            if start < pos.column {
+                let _ = buffer.delete(Range::new(Position::new(pos.line, start), Position::new(pos.line, pos.column - 1)));
+            }
+
+            let inserted = buffer.insert(Position::new(pos.line, start), &indent).is_ok();
+            buffer.end_undo_group();
+
+            if inserted {
+                self.move_cursor_to(Position::new(pos.line, start + indent.len()));
             }
         }
     }
 
-    pub fn move_cursor_to(&mut self, pos: Position) {
+    /// Pulls the cursor back inside the current buffer after lines were
+    /// removed or shortened underneath it.
+    pub fn clamp_cursor(&mut self) {
         if let Some(buffer) = self.get_current_buffer() {
-            match buffer.validate_position(pos) {
-                Ok(_) => self.cursor.pos = pos,
-                Err(_) => {}
+            let line = self.cursor.pos.line.min(buffer.len() - 1);
+            let text = &buffer.lines[line];
+            let mut column = self.cursor.pos.column.min(text.len());
+
+            while !text.is_char_boundary(column) {
+                column -= 1;
             }
+
+            self.cursor.pos = Position::new(line, column);
+        }
+    }
+
+    pub fn move_cursor_to(&mut self, pos: Position) {
+        if let Some(buffer) = self.get_current_buffer()
+            && buffer.validate_position(pos).is_ok()
+        {
+            self.cursor.pos = pos;
         }
     }
     
@@ -308,51 +659,66 @@ impl Editor {
                     self.cursor.pos.column = line.len();
                 }
             } else if self.cursor.pos.column > 0 {
-                self.cursor.pos.column -= 1;
+                self.cursor.pos.column = prev_boundary(&buffer.lines[self.cursor.pos.line], self.cursor.pos.column);
             }
         }
     }
 
     pub fn move_cursor_down(&mut self) {        
-        if let Some(buffer) = self.get_current_buffer() {
-            if self.cursor.pos.line < buffer.len() {
-                if let Ok(line) = buffer.get_line(self.cursor.pos.line + 1) {
-                    if self.cursor.pos.column > line.len() {
-                        self.cursor.pos.column = line.len();
-                    }
-
-                    self.cursor.pos.line += 1;
-                }
-            }
+        if self.cursor.pos.line + 1 < self.get_current_buffer().map(|buffer| buffer.len()).unwrap_or(0) {
+            self.move_to_line(self.cursor.pos.line + 1);
         }
     }
 
     pub fn move_cursor_up(&mut self) {
-        if let Some(buffer) = self.get_current_buffer() {
-            if self.cursor.pos.line > 0 {
-                if let Ok(line) = buffer.get_line(self.cursor.pos.line - 1) {
-                    if self.cursor.pos.column > line.len() {
-                        self.cursor.pos.column = line.len();
-                    }
-                }
+        if self.cursor.pos.line > 0 {
+            self.move_to_line(self.cursor.pos.line - 1);
+        }
+    }
 
-                self.cursor.pos.line -= 1;
-            }
+    /// Moves to `line`, keeping the cursor in the same screen column.
+    fn move_to_line(&mut self, line: usize) {
+        let tab_stop = self.buffer_options().tab_stop;
+        let pos = self.cursor.pos;
+
+        if let Some(buffer) = self.get_current_buffer()
+            && let Some(target) = buffer.lines.get(line)
+        {
+            let column = display_column(&buffer.lines[pos.line], pos.column, tab_stop);
+            self.cursor.pos = Position::new(line, column_at(target, column, tab_stop));
         }
     }
 
     pub fn move_cursor_right(&mut self) {
-        if let Some(buffer) = self.get_current_buffer() {
-            if let Ok(line) = buffer.get_line(self.cursor.pos.line) {
-                if self.cursor.pos.column == line.len() && self.cursor.pos.line < buffer.len() {
-                    if buffer.get_line(self.cursor.pos.line + 1).is_ok() {
-                        self.cursor.pos.line += 1;
-                        self.cursor.pos.column = 0;
-                    }
-                } else if self.cursor.pos.column < line.len() {
-                    self.cursor.pos.column += 1;
-                } 
-            }
+        if let Some(buffer) = self.get_current_buffer()
+            && let Ok(line) = buffer.get_line(self.cursor.pos.line)
+        {
+            if self.cursor.pos.column == line.len() {
+                if buffer.get_line(self.cursor.pos.line + 1).is_ok() {
+                    self.cursor.pos.line += 1;
+                    self.cursor.pos.column = 0;
+                }
+            } else if self.cursor.pos.column < line.len() {
+                self.cursor.pos.column = next_boundary(&line, self.cursor.pos.column);
+            } 
         }
     }
 } 
+
+/// Whether `a` and `b` name the same file, however they were written.
+pub(crate) fn same_file(a: &Path, b: &Path) -> bool {
+    a == b || a.canonicalize().is_ok_and(|a| b.canonicalize().is_ok_and(|b| a == b))
+}
+
+/// Whether `text` ends with one of a filetype's block openers. Word openers
+/// like `do` only count as a whole word.
+fn opens_block(text: &str, openers: &[&str]) -> bool {
+    openers.iter().any(|opener| {
+        let Some(rest) = text.strip_suffix(opener) else {
+            return false;
+        };
+
+        !opener.starts_with(|c: char| c.is_alphanumeric())
+            || !rest.ends_with(|c: char| c.is_alphanumeric() || c == '_')
+    })
+}
@@ -1,7 +1,155 @@
+use buffer::errors::BufferError;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum EditorError {
-    #[error("Save error")]
-    SaveError
+    #[error("Can't open file for writing: {0}: {1}")]
+    WriteFailed(String, #[source] BufferError),
+
+    #[error("Can't open file: {0}: {1}")]
+    ReadFailed(String, #[source] BufferError),
+
+    #[error("Not an editor command: {0}")]
+    UnknownCommand(String),
+
+    #[error("Invalid range")]
+    InvalidRange,
+
+    #[error("Backwards range given")]
+    BackwardsRange,
+
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+
+    #[error("Pattern not found: {0}")]
+    PatternNotFound(String),
+
+    #[error("Invalid pattern: {0}")]
+    InvalidPattern(String),
+
+    #[error("No previous substitute regular expression")]
+    NoPreviousSubstitute,
+
+    #[error("No previous regular expression")]
+    NoPreviousPattern,
+
+    #[error("No string under cursor")]
+    NoWordUnderCursor,
+
+    #[error("Cannot do :global recursive")]
+    GlobalRecursive,
+
+    #[error("Already at oldest change")]
+    OldestChange,
+
+    #[error("Already at newest change")]
+    NewestChange,
+
+    #[error("Mark not set: {0}")]
+    MarkNotSet(char),
+
+    #[error("No range allowed")]
+    NoRangeAllowed,
+
+    #[error("No ! allowed")]
+    NoBangAllowed,
+
+    #[error("Argument required")]
+    ArgumentRequired,
+
+    #[error("Trailing characters: {0}")]
+    TrailingCharacters(String),
+
+    #[error("File exists (add ! to override): {0}")]
+    FileExists(String),
+
+    #[error("Use ! to write partial buffer")]
+    PartialWrite,
+
+    #[error("No file name")]
+    NoFileName,
+
+    #[error("No buffer open")]
+    NoBuffer,
+
+    #[error("No write since last change (add ! to override)")]
+    UnsavedChanges,
+
+    #[error("No syntax tree for this buffer")]
+    NoSyntaxTree,
+
+    #[error("No text object here")]
+    NoTextObject,
+
+    #[error("Unknown option: {0}")]
+    UnknownOption(String),
+
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+
+    #[error("Invalid value for {0}: {1}")]
+    InvalidOptionValue(String, String),
+
+    #[error("Error in {0}: {1}")]
+    InvalidConfig(String, String),
+
+    #[error("No comment string for this filetype")]
+    NoCommentString,
+
+    #[error("Cannot find color scheme: {0}")]
+    UnknownTheme(String),
+
+    #[error("Invalid color scheme {0}: {1}")]
+    InvalidTheme(String, String),
+
+    #[error("No such mapping")]
+    NoSuchMapping,
+
+    #[error("Recursive mapping")]
+    RecursiveMapping,
+
+    #[error("Cannot close last window")]
+    LastWindow,
+
+    #[error("Cannot close last tab page")]
+    LastTabPage,
+
+    #[error("No alternate file")]
+    NoAlternateBuffer,
+
+    #[error("No matching buffer for {0}")]
+    NoSuchBuffer(String),
+
+    #[error("More than one match for {0}")]
+    AmbiguousBuffer(String),
+
+    #[error("No write since last change for buffer {0} (add ! to override)")]
+    BufferModified(usize),
+
+    #[error("No Errors")]
+    NoErrors,
+
+    #[error("No more items")]
+    NoMoreItems,
+
+    #[error("No location list")]
+    NoLocationList,
+
+    #[error("A make is already running")]
+    MakeRunning,
+
+    #[error("Can't run {0}: {1}")]
+    MakeFailed(String, String),
+
+    #[error("Nothing selected")]
+    NothingSelected,
+
+    #[error("Not a directory: {0}")]
+    NotADirectory(String),
+
+    #[error("Already exists: {0}")]
+    AlreadyExists(String),
+
+    #[error("Can't {0}: {1}")]
+    FileOperationFailed(String, String),
 }
@@ -0,0 +1,1129 @@
+use std::path::{Path, PathBuf};
+
+use buffer::buffer::Buffer;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use regex::Regex;
+use utils::Position;
+
+use crate::command::{parse_address, parse_command, LineRange};
+use crate::display::display_width;
+use crate::editor::{same_file, Editor, EditorMode};
+use crate::errors::EditorError;
+use crate::keymap::MapMode;
+use crate::keys::parse_keys;
+use crate::quickfix::ListKind;
+use crate::substitute::{is_pattern_delimiter, split_delimited, Substitution};
+use crate::window::SplitDirection;
+
+pub type CommandHandler = fn(&mut Editor, &CommandContext) -> Result<(), EditorError>;
+
+/// The range a command operates on when the user doesn't give one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DefaultRange {
+    /// The command doesn't accept a range at all.
+    None,
+    CurrentLine,
+    Whole
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    /// Shortest prefix of `name` that is accepted, e.g. `1` for `d[elete]`.
+    pub abbrev: usize,
+    pub range: DefaultRange,
+    pub bang: bool,
+    pub handler: CommandHandler
+}
+
+impl CommandSpec {
+    pub fn matches(&self, name: &str) -> bool {
+        name.len() >= self.abbrev && self.name.starts_with(name)
+    }
+}
+
+#[derive(Debug)]
+pub struct CommandContext<'a> {
+    pub range: LineRange,
+    pub has_range: bool,
+    pub bang: bool,
+    pub args: &'a str,
+    /// `args` without trailing whitespace trimmed.
+    pub raw_args: &'a str
+}
+
+#[derive(Debug)]
+pub struct CommandRegistry {
+    commands: Vec<CommandSpec>
+}
+
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+
+        registry.register("quit", 1, DefaultRange::None, true, cmd_quit);
+        registry.register("write", 1, DefaultRange::Whole, true, cmd_write);
+        registry.register("wq", 2, DefaultRange::Whole, true, cmd_write_quit);
+        registry.register("xit", 1, DefaultRange::Whole, true, cmd_exit);
+        registry.register("saveas", 3, DefaultRange::None, true, cmd_saveas);
+        registry.register("edit", 1, DefaultRange::None, true, cmd_edit);
+        registry.register("split", 2, DefaultRange::None, false, cmd_split);
+        registry.register("vsplit", 2, DefaultRange::None, false, cmd_vsplit);
+        registry.register("close", 3, DefaultRange::None, true, cmd_close);
+        registry.register("only", 2, DefaultRange::None, true, cmd_only);
+        registry.register("resize", 3, DefaultRange::None, false, cmd_resize);
+        registry.register("vertical", 4, DefaultRange::None, false, cmd_vertical);
+        registry.register("buffer", 1, DefaultRange::None, true, cmd_buffer);
+        registry.register("bnext", 2, DefaultRange::None, true, cmd_bnext);
+        registry.register("bprevious", 2, DefaultRange::None, true, cmd_bprevious);
+        registry.register("bdelete", 2, DefaultRange::None, true, cmd_bdelete);
+        registry.register("ls", 2, DefaultRange::None, false, cmd_ls);
+        registry.register("buffers", 7, DefaultRange::None, false, cmd_ls);
+        registry.register("find", 3, DefaultRange::None, false, cmd_find);
+        registry.register("grep", 2, DefaultRange::None, false, cmd_grep);
+        registry.register("lgrep", 3, DefaultRange::None, false, cmd_lgrep);
+        registry.register("greplace", 5, DefaultRange::None, false, cmd_greplace);
+        registry.register("explore", 3, DefaultRange::None, false, cmd_explore);
+        registry.register("reveal", 3, DefaultRange::None, false, cmd_reveal);
+        registry.register("make", 3, DefaultRange::None, true, cmd_make);
+        registry.register("lmake", 4, DefaultRange::None, true, cmd_lmake);
+        registry.register("copen", 4, DefaultRange::None, false, cmd_copen);
+        registry.register("lopen", 3, DefaultRange::None, false, cmd_lopen);
+        registry.register("cnext", 2, DefaultRange::None, false, cmd_cnext);
+        registry.register("lnext", 3, DefaultRange::None, false, cmd_lnext);
+        registry.register("cprevious", 2, DefaultRange::None, false, cmd_cprevious);
+        registry.register("lprevious", 2, DefaultRange::None, false, cmd_lprevious);
+        registry.register("cc", 2, DefaultRange::None, false, cmd_cc);
+        registry.register("ll", 2, DefaultRange::None, false, cmd_ll);
+        registry.register("tabnext", 4, DefaultRange::None, false, cmd_tabnext);
+        registry.register("tabnew", 6, DefaultRange::None, false, cmd_tabnew);
+        registry.register("tabedit", 4, DefaultRange::None, false, cmd_tabnew);
+        registry.register("tabprevious", 4, DefaultRange::None, false, cmd_tabprevious);
+        registry.register("tabclose", 4, DefaultRange::None, true, cmd_tabclose);
+        registry.register("tabmove", 4, DefaultRange::None, false, cmd_tabmove);
+        registry.register("delete", 1, DefaultRange::CurrentLine, false, cmd_delete);
+        registry.register("yank", 1, DefaultRange::CurrentLine, false, cmd_yank);
+        registry.register("move", 1, DefaultRange::CurrentLine, false, cmd_move);
+        registry.register("copy", 2, DefaultRange::CurrentLine, false, cmd_copy);
+        registry.register("t", 1, DefaultRange::CurrentLine, false, cmd_copy);
+        registry.register(">", 1, DefaultRange::CurrentLine, false, cmd_shift_right);
+        registry.register("<", 1, DefaultRange::CurrentLine, false, cmd_shift_left);
+        registry.register("mark", 2, DefaultRange::CurrentLine, false, cmd_mark);
+        registry.register("k", 1, DefaultRange::CurrentLine, false, cmd_mark);
+        registry.register("substitute", 1, DefaultRange::CurrentLine, false, cmd_substitute);
+        registry.register("global", 1, DefaultRange::Whole, true, cmd_global);
+        registry.register("vglobal", 1, DefaultRange::Whole, false, cmd_vglobal);
+        registry.register("normal", 4, DefaultRange::CurrentLine, true, cmd_normal);
+        registry.register("nohlsearch", 3, DefaultRange::None, false, cmd_nohlsearch);
+        registry.register("undo", 1, DefaultRange::None, false, cmd_undo);
+        registry.register("redo", 3, DefaultRange::None, false, cmd_redo);
+        registry.register("set", 2, DefaultRange::None, false, cmd_set);
+        registry.register("setlocal", 4, DefaultRange::None, false, cmd_setlocal);
+        registry.register("colorscheme", 4, DefaultRange::None, false, cmd_colorscheme);
+        registry.register("messages", 3, DefaultRange::None, false, cmd_messages);
+        registry.register("map", 3, DefaultRange::None, false, cmd_map);
+        registry.register("nmap", 2, DefaultRange::None, false, cmd_nmap);
+        registry.register("imap", 2, DefaultRange::None, false, cmd_imap);
+        registry.register("vmap", 2, DefaultRange::None, false, cmd_vmap);
+        registry.register("noremap", 2, DefaultRange::None, false, cmd_noremap);
+        registry.register("nnoremap", 2, DefaultRange::None, false, cmd_nnoremap);
+        registry.register("inoremap", 3, DefaultRange::None, false, cmd_inoremap);
+        registry.register("vnoremap", 2, DefaultRange::None, false, cmd_vnoremap);
+        registry.register("unmap", 3, DefaultRange::None, false, cmd_unmap);
+        registry.register("nunmap", 3, DefaultRange::None, false, cmd_nunmap);
+        registry.register("iunmap", 2, DefaultRange::None, false, cmd_iunmap);
+        registry.register("vunmap", 2, DefaultRange::None, false, cmd_vunmap);
+
+        registry
+    }
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        Self {
+            commands: vec![]
+        }
+    }
+
+    pub fn register(&mut self, name: &'static str, abbrev: usize, range: DefaultRange, bang: bool, handler: CommandHandler) {
+        self.commands.push(CommandSpec {
+            name,
+            abbrev,
+            range,
+            bang,
+            handler
+        });
+    }
+
+    /// Looks a command up by its full name or an accepted abbreviation. An
+    /// exact match always wins over an abbreviation of a longer command.
+    pub fn lookup(&self, name: &str) -> Option<&CommandSpec> {
+        self.commands.iter()
+            .find(|spec| spec.name == name)
+            .or_else(|| self.commands.iter().find(|spec| spec.matches(name)))
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.commands.iter().map(|spec| spec.name)
+    }
+}
+
+impl Editor {
+    /// Runs one ex command line. Everything it changes in the current buffer
+    /// is undone as a single step.
+    pub fn execute_command(&mut self, input: &str) -> Result<(), EditorError> {
+        self.sync_buffer_options();
+        let id = self.current_buffer;
+
+        if let Some(buffer) = id.and_then(|id| self.buffers.get_mut(&id)) {
+            buffer.begin_undo_group();
+        }
+
+        let result = self.run_command(input);
+
+        if let Some(buffer) = id.and_then(|id| self.buffers.get_mut(&id)) {
+            buffer.end_undo_group();
+        }
+
+        result
+    }
+
+    fn run_command(&mut self, input: &str) -> Result<(), EditorError> {
+        let command = parse_command(input)?;
+        let current = self.cursor.pos.line;
+
+        let resolved = match (&command.range, self.get_current_buffer()) {
+            (Some(range), Some(buffer)) => Some(range.resolve(buffer, current)?),
+            (Some(_), None) => return Err(EditorError::NoBuffer),
+            (None, _) => None
+        };
+
+        if command.name.is_empty() {
+            if !command.args.trim_end().is_empty() {
+                return Err(EditorError::TrailingCharacters(command.args));
+            }
+
+            if let Some((_, end)) = resolved {
+                self.move_cursor_to(Position::new(end.max(1) - 1, 0));
+            }
+
+            return Ok(());
+        }
+
+        let spec = *self.commands
+            .lookup(&command.name)
+            .ok_or_else(|| EditorError::UnknownCommand(command.name.clone()))?;
+
+        if resolved.is_some() && spec.range == DefaultRange::None {
+            return Err(EditorError::NoRangeAllowed);
+        }
+
+        if command.bang && !spec.bang {
+            return Err(EditorError::NoBangAllowed);
+        }
+
+        let last = self.get_current_buffer().map(|buffer| buffer.len() - 1).unwrap_or(0);
+
+        let range = match resolved {
+            Some((start, end)) => LineRange::new(start.max(1) - 1, end.max(1) - 1),
+            None if spec.range == DefaultRange::Whole => LineRange::new(0, last),
+            None => LineRange::new(current, current)
+        };
+
+        let context = CommandContext {
+            range,
+            has_range: resolved.is_some(),
+            bang: command.bang,
+            args: command.args.trim_end(),
+            raw_args: &command.args
+        };
+
+        (spec.handler)(self, &context)
+    }
+}
+
+fn current_buffer_mut(editor: &mut Editor) -> Result<&mut Buffer, EditorError> {
+    editor.get_current_buffer_mut().ok_or(EditorError::NoBuffer)
+}
+
+fn parse_register(args: &str) -> Result<char, EditorError> {
+    let mut chars = args.chars();
+
+    match (chars.next(), chars.next()) {
+        (None, _) => Ok('"'),
+        (Some(c), None) if c.is_ascii_alphabetic() || c == '"' => Ok(c),
+        _ => Err(EditorError::TrailingCharacters(args.to_string()))
+    }
+}
+
+/// Resolves the destination address of `:m` and `:t` to a 1-based line,
+/// where `0` means above the first line.
+fn parse_destination(editor: &Editor, args: &str) -> Result<usize, EditorError> {
+    let buffer = editor.get_current_buffer().ok_or(EditorError::NoBuffer)?;
+    let (address, rest) = parse_address(args)?;
+
+    if !rest.trim().is_empty() {
+        return Err(EditorError::TrailingCharacters(rest.trim().to_string()));
+    }
+
+    address.ok_or(EditorError::ArgumentRequired)?.resolve(buffer, editor.cursor.pos.line)
+}
+
+/// Closes the current window, or with only one left quits.
+fn cmd_quit(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    if editor.window_count() > 1 || editor.tab_count() > 1 {
+        return editor.close_window(editor.current_window);
+    }
+
+    if !ctx.bang && editor.buffers.values().any(|buffer| buffer.is_modified()) {
+        return Err(EditorError::UnsavedChanges);
+    }
+
+    editor.should_quit = true;
+
+    Ok(())
+}
+
+fn cmd_write(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    let buffer = current_buffer_mut(editor)?;
+    let partial = ctx.range.start != 0 || ctx.range.end != buffer.len() - 1;
+
+    let path = if ctx.args.is_empty() {
+        buffer.get_path().ok_or(EditorError::NoFileName)?
+    } else {
+        PathBuf::from(ctx.args)
+    };
+
+    let own_file = buffer.get_path().is_some_and(|own| same_file(&own, &path));
+
+    // Writing somewhere other than the buffer's own file mustn't clobber
+    // what is there without `!`, nor part of the buffer its own file.
+    if !own_file && path.exists() && !ctx.bang {
+        return Err(EditorError::FileExists(path.display().to_string()));
+    }
+
+    if own_file && partial && !ctx.bang {
+        return Err(EditorError::PartialWrite);
+    }
+
+    if !partial {
+        // Only a whole buffer written out names an unnamed one.
+        if buffer.get_path().is_none() {
+            buffer.set_path(path.clone());
+        }
+
+        if own_file || buffer.get_path() == Some(path.clone()) {
+            return editor.save_buffer();
+        }
+    }
+
+    let bytes = buffer
+        .write_lines(&path, ctx.range.start, ctx.range.end)
+        .map_err(|err| EditorError::WriteFailed(path.display().to_string(), err))?;
+
+    editor.report_written(&path, ctx.range.end - ctx.range.start + 1, bytes);
+
+    Ok(())
+}
+
+fn cmd_write_quit(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    cmd_write(editor, ctx)?;
+    cmd_quit(editor, ctx)
+}
+
+fn cmd_exit(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    if editor.get_current_buffer().is_some_and(|buffer| buffer.is_modified()) {
+        cmd_write(editor, ctx)?;
+    }
+
+    cmd_quit(editor, ctx)
+}
+
+fn cmd_saveas(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    if ctx.args.is_empty() {
+        return Err(EditorError::ArgumentRequired);
+    }
+
+    let path = PathBuf::from(ctx.args);
+
+    if path.exists() && !ctx.bang {
+        return Err(EditorError::FileExists(ctx.args.to_string()));
+    }
+
+    current_buffer_mut(editor)?.set_path(path);
+    editor.save_buffer()
+}
+
+fn cmd_edit(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    if !ctx.args.is_empty() {
+        return editor.open_file(PathBuf::from(ctx.args));
+    }
+
+    let buffer = current_buffer_mut(editor)?;
+    let path = buffer.get_path().ok_or(EditorError::NoFileName)?;
+
+    if buffer.is_modified() && !ctx.bang {
+        return Err(EditorError::UnsavedChanges);
+    }
+
+    *buffer = Buffer::from_file(&path).map_err(|err| EditorError::ReadFailed(path.display().to_string(), err))?;
+    editor.clamp_cursor();
+
+    Ok(())
+}
+
+/// `:split [file]` and `:vsplit [file]` split the current window, the new
+/// one showing `file` if given.
+fn split_command(editor: &mut Editor, ctx: &CommandContext, direction: SplitDirection) -> Result<(), EditorError> {
+    editor.split_window(direction);
+
+    if !ctx.args.is_empty()
+        && let Err(err) = editor.open_file(PathBuf::from(ctx.args))
+    {
+        editor.close_window(editor.current_window)?;
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+fn cmd_split(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    split_command(editor, ctx, SplitDirection::Horizontal)
+}
+
+fn cmd_vsplit(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    split_command(editor, ctx, SplitDirection::Vertical)
+}
+
+fn cmd_close(editor: &mut Editor, _ctx: &CommandContext) -> Result<(), EditorError> {
+    editor.close_window(editor.current_window)
+}
+
+fn cmd_only(editor: &mut Editor, _ctx: &CommandContext) -> Result<(), EditorError> {
+    editor.only_window();
+
+    Ok(())
+}
+
+/// `:resize N` sets the window's height, `:resize +N` and `-N` change it,
+/// and `:resize` alone makes it as high as it can be.
+fn resize_command(editor: &mut Editor, args: &str, direction: SplitDirection) -> Result<(), EditorError> {
+    let invalid = || EditorError::InvalidArgument(args.to_string());
+
+    if args.is_empty() {
+        editor.set_window_size(direction, usize::MAX / 2);
+        return Ok(());
+    }
+
+    if let Some(delta) = args.strip_prefix(['+', '-']) {
+        let delta: isize = delta.parse().map_err(|_| invalid())?;
+
+        match args.starts_with('-') {
+            true => editor.resize_window(direction, -delta),
+            false => editor.resize_window(direction, delta)
+        }
+
+        return Ok(());
+    }
+
+    editor.set_window_size(direction, args.parse().map_err(|_| invalid())?);
+
+    Ok(())
+}
+
+fn cmd_resize(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    resize_command(editor, ctx.args, SplitDirection::Horizontal)
+}
+
+/// `:vertical resize` changes the width instead, and `:vertical split`
+/// splits side by side.
+fn cmd_vertical(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    let (name, args) = ctx.args.split_once(char::is_whitespace).unwrap_or((ctx.args, ""));
+    let args = args.trim();
+
+    match editor.commands.lookup(name).map(|spec| spec.name) {
+        Some("resize") => resize_command(editor, args, SplitDirection::Vertical),
+        Some("split" | "vsplit") => {
+            let context = CommandContext { args, raw_args: args, ..*ctx };
+            split_command(editor, &context, SplitDirection::Vertical)
+        },
+        None if name.is_empty() => Err(EditorError::ArgumentRequired),
+        _ => Err(EditorError::InvalidArgument(ctx.args.to_string()))
+    }
+}
+
+/// `:b N` and `:b name` switch to a buffer by number or by part of its
+/// name.
+fn cmd_buffer(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    if ctx.args.is_empty() {
+        return Ok(());
+    }
+
+    let id = editor.find_buffer(ctx.args)?;
+    editor.switch_to_buffer(id);
+
+    Ok(())
+}
+
+fn cmd_bnext(editor: &mut Editor, _ctx: &CommandContext) -> Result<(), EditorError> {
+    editor.next_buffer();
+
+    Ok(())
+}
+
+fn cmd_bprevious(editor: &mut Editor, _ctx: &CommandContext) -> Result<(), EditorError> {
+    editor.previous_buffer();
+
+    Ok(())
+}
+
+/// `:bd[!] [N|name]` closes the current buffer or the one named.
+fn cmd_bdelete(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    let id = match ctx.args {
+        "" => editor.current_buffer.ok_or(EditorError::NoBuffer)?,
+        name => editor.find_buffer(name)?
+    };
+
+    editor.delete_buffer(id, ctx.bang)
+}
+
+fn cmd_ls(editor: &mut Editor, _ctx: &CommandContext) -> Result<(), EditorError> {
+    let lines = editor.list_buffers();
+    editor.messages.show(lines.join("\n"));
+
+    Ok(())
+}
+
+fn cmd_find(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    editor.open_file_finder(ctx.args);
+
+    Ok(())
+}
+
+fn cmd_grep(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    editor.grep(ctx.args, ListKind::Quickfix)
+}
+
+fn cmd_lgrep(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    editor.grep(ctx.args, ListKind::Location)
+}
+
+fn cmd_greplace(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    editor.project_replace(ctx.args)
+}
+
+fn cmd_explore(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    let dir = ctx.args.trim();
+    editor.open_explorer(Path::new(if dir.is_empty() { "." } else { dir }))
+}
+
+fn cmd_reveal(editor: &mut Editor, _ctx: &CommandContext) -> Result<(), EditorError> {
+    editor.reveal_file()
+}
+
+fn cmd_make(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    editor.make(ctx.args, ListKind::Quickfix, !ctx.bang)
+}
+
+fn cmd_lmake(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    editor.make(ctx.args, ListKind::Location, !ctx.bang)
+}
+
+fn cmd_copen(editor: &mut Editor, _ctx: &CommandContext) -> Result<(), EditorError> {
+    editor.open_list(ListKind::Quickfix)
+}
+
+fn cmd_lopen(editor: &mut Editor, _ctx: &CommandContext) -> Result<(), EditorError> {
+    editor.open_list(ListKind::Location)
+}
+
+fn cmd_cnext(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    editor.jump_by_entries(ListKind::Quickfix, parse_count(ctx.args)?)
+}
+
+fn cmd_lnext(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    editor.jump_by_entries(ListKind::Location, parse_count(ctx.args)?)
+}
+
+fn cmd_cprevious(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    editor.jump_by_entries(ListKind::Quickfix, -parse_count(ctx.args)?)
+}
+
+fn cmd_lprevious(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    editor.jump_by_entries(ListKind::Location, -parse_count(ctx.args)?)
+}
+
+fn cmd_cc(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    jump_to_entry(editor, ListKind::Quickfix, ctx.args)
+}
+
+fn cmd_ll(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    jump_to_entry(editor, ListKind::Location, ctx.args)
+}
+
+/// How many entries `:cnext N` and the like move, 1 if not given.
+fn parse_count(args: &str) -> Result<isize, EditorError> {
+    match args {
+        "" => Ok(1),
+        _ => args.parse().ok().filter(|count| *count > 0).ok_or_else(|| EditorError::InvalidArgument(args.to_string()))
+    }
+}
+
+/// `:cc N` and `:ll N`, counting from 1; without N the current entry.
+fn jump_to_entry(editor: &mut Editor, kind: ListKind, args: &str) -> Result<(), EditorError> {
+    let index = match args {
+        "" => match kind {
+            ListKind::Quickfix => editor.quickfix.current,
+            ListKind::Location => editor.location_lists.get(&editor.current_window).map(|list| list.current).unwrap_or(0)
+        },
+        _ => args.parse::<usize>()
+            .ok()
+            .filter(|number| *number > 0)
+            .ok_or_else(|| EditorError::InvalidArgument(args.to_string()))? - 1
+    };
+
+    editor.jump_to_entry(kind, index)
+}
+
+/// A tab page number as the tab commands take it, counting from 1.
+fn parse_tab_number(args: &str) -> Result<Option<usize>, EditorError> {
+    match args {
+        "" => Ok(None),
+        args => args.parse().map(Some).map_err(|_| EditorError::InvalidArgument(args.to_string()))
+    }
+}
+
+/// `:tabnew [file]` opens a tab page showing `file`, or a new empty
+/// buffer.
+fn cmd_tabnew(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    editor.new_tab();
+
+    if ctx.args.is_empty() {
+        let id = editor.create_empty_buffer();
+        editor.switch_to_buffer(id);
+        return Ok(());
+    }
+
+    if let Err(err) = editor.open_file(PathBuf::from(ctx.args)) {
+        editor.close_tab(editor.current_tab)?;
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+/// `:tabnext` moves to the next tab page, `:tabnext N` to tab page `N`.
+fn cmd_tabnext(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    match parse_tab_number(ctx.args)? {
+        Some(number) if number == 0 || number > editor.tab_count() => Err(EditorError::InvalidArgument(ctx.args.to_string())),
+        Some(number) => {
+            editor.switch_tab(number - 1);
+            Ok(())
+        },
+        None => {
+            editor.cycle_tab(true, 1);
+            Ok(())
+        }
+    }
+}
+
+/// `:tabprevious [N]` moves `N` tab pages back.
+fn cmd_tabprevious(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    let count = parse_tab_number(ctx.args)?.unwrap_or(1);
+    editor.cycle_tab(false, count);
+
+    Ok(())
+}
+
+/// `:tabclose [N]` closes the current tab page or tab page `N`.
+fn cmd_tabclose(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    let index = match parse_tab_number(ctx.args)? {
+        Some(0) => return Err(EditorError::InvalidArgument(ctx.args.to_string())),
+        Some(number) => number - 1,
+        None => editor.current_tab
+    };
+
+    editor.close_tab(index)
+}
+
+/// `:tabmove N` puts the current tab page after tab page `N`, `0` making
+/// it the first; `+N` and `-N` move it by `N`, and with no argument it goes
+/// last.
+fn cmd_tabmove(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    let current = editor.current_tab;
+    let invalid = || EditorError::InvalidArgument(ctx.args.to_string());
+
+    let index = if let Some(count) = ctx.args.strip_prefix('+') {
+        current + count.parse::<usize>().map_err(|_| invalid())?
+    } else if let Some(count) = ctx.args.strip_prefix('-') {
+        current.saturating_sub(count.parse().map_err(|_| invalid())?)
+    } else {
+        match parse_tab_number(ctx.args)? {
+            Some(number) if number <= current => number,
+            Some(number) => number - 1,
+            None => usize::MAX
+        }
+    };
+
+    editor.move_tab(index);
+
+    Ok(())
+}
+
+fn cmd_delete(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    let register = parse_register(ctx.args)?;
+    let deleted = current_buffer_mut(editor)?
+        .delete_lines(ctx.range.start, ctx.range.end)
+        .map_err(|_| EditorError::InvalidRange)?;
+
+    editor.set_register(register, deleted);
+    editor.cursor.pos = Position::new(ctx.range.start, 0);
+    editor.clamp_cursor();
+
+    Ok(())
+}
+
+fn cmd_yank(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    let register = parse_register(ctx.args)?;
+    let lines = current_buffer_mut(editor)?
+        .get_lines(ctx.range.start, ctx.range.end)
+        .map_err(|_| EditorError::InvalidRange)?;
+
+    editor.set_register(register, lines);
+
+    Ok(())
+}
+
+fn cmd_move(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    let destination = parse_destination(editor, ctx.args)?;
+    let LineRange { start, end } = ctx.range;
+
+    if destination > start && destination <= end {
+        return Err(EditorError::InvalidRange);
+    }
+
+    let buffer = current_buffer_mut(editor)?;
+    let lines = buffer.delete_lines(start, end).map_err(|_| EditorError::InvalidRange)?;
+    let count = lines.len();
+
+    let at = if destination > end {
+        destination - count
+    } else {
+        destination
+    };
+
+    buffer.insert_lines(at, lines).map_err(|_| EditorError::InvalidRange)?;
+
+    editor.cursor.pos = Position::new(at + count - 1, 0);
+    editor.clamp_cursor();
+
+    Ok(())
+}
+
+fn cmd_copy(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    let destination = parse_destination(editor, ctx.args)?;
+
+    let buffer = current_buffer_mut(editor)?;
+    let lines = buffer.get_lines(ctx.range.start, ctx.range.end).map_err(|_| EditorError::InvalidRange)?;
+    let count = lines.len();
+
+    buffer.insert_lines(destination, lines).map_err(|_| EditorError::InvalidRange)?;
+
+    editor.cursor.pos = Position::new(destination + count - 1, 0);
+    editor.clamp_cursor();
+
+    Ok(())
+}
+
+/// Parses the arguments of `:>` and `:<`: any further `>`/`<` characters add
+/// another level of indent, and an optional count selects that many lines
+/// starting at the end of the range.
+fn parse_shift(ctx: &CommandContext, symbol: char) -> Result<(LineRange, usize), EditorError> {
+    let rest = ctx.args.trim_start_matches(symbol);
+    let levels = 1 + ctx.args.len() - rest.len();
+    let rest = rest.trim();
+
+    if rest.is_empty() {
+        return Ok((ctx.range, levels));
+    }
+
+    let count: usize = rest.parse().map_err(|_| EditorError::TrailingCharacters(rest.to_string()))?;
+
+    if count == 0 {
+        return Err(EditorError::InvalidRange);
+    }
+
+    Ok((LineRange::new(ctx.range.end, ctx.range.end + count - 1), levels))
+}
+
+fn shift_lines(editor: &mut Editor, range: LineRange, levels: usize, right: bool) -> Result<(), EditorError> {
+    let options = editor.buffer_options();
+    let (tab_stop, shift_width, expand_tab) = (options.tab_stop.max(1), options.shift_width, options.expand_tab);
+    let buffer = current_buffer_mut(editor)?;
+    let end = range.end.min(buffer.len() - 1);
+
+    for line in range.start ..= end {
+        let text = buffer.get_line(line).map_err(|_| EditorError::InvalidRange)?;
+
+        if text.is_empty() {
+            continue;
+        }
+
+        let content = text.trim_start_matches([' ', '\t']);
+        let leading = &text[.. text.len() - content.len()];
+        let current = display_width(leading, tab_stop);
+
+        let indent = if right {
+            current + shift_width * levels
+        } else {
+            current.saturating_sub(shift_width * levels)
+        };
+
+        let whitespace = if expand_tab {
+            " ".repeat(indent)
+        } else {
+            "\t".repeat(indent / tab_stop) + &" ".repeat(indent % tab_stop)
+        };
+
+        if whitespace != leading {
+            let _ = buffer.set_line(line, whitespace + content);
+        }
+    }
+
+    editor.cursor.pos = Position::new(end, 0);
+    editor.clamp_cursor();
+
+    Ok(())
+}
+
+fn cmd_shift_right(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    let (range, levels) = parse_shift(ctx, '>')?;
+    shift_lines(editor, range, levels, true)
+}
+
+fn cmd_shift_left(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    let (range, levels) = parse_shift(ctx, '<')?;
+    shift_lines(editor, range, levels, false)
+}
+
+fn cmd_mark(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    let mark = match ctx.args.chars().collect::<Vec<char>>().as_slice() {
+        [c] if c.is_ascii_alphabetic() => *c,
+        [] => return Err(EditorError::ArgumentRequired),
+        _ => return Err(EditorError::TrailingCharacters(ctx.args.to_string()))
+    };
+
+    current_buffer_mut(editor)?.set_mark(mark, Position::new(ctx.range.end, 0));
+
+    Ok(())
+}
+
+fn cmd_substitute(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    let (substitution, count) = Substitution::parse(
+        ctx.args,
+        editor.last_substitute.as_ref(),
+        editor.last_pattern.as_deref()
+    )?;
+    let last = current_buffer_mut(editor)?.len() - 1;
+
+    let range = match count {
+        Some(count) => LineRange::new(ctx.range.end, (ctx.range.end + count - 1).min(last)),
+        None => ctx.range
+    };
+
+    editor.substitute(range, substitution)
+}
+
+fn cmd_global(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    run_global(editor, ctx, ctx.bang)
+}
+
+fn cmd_vglobal(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    run_global(editor, ctx, true)
+}
+
+/// Runs the command after `/pattern/` on every line in the range that matches
+/// (or, when `invert` is set, doesn't match). Lines are marked up front so
+/// the command can delete or move lines without losing track of the rest.
+fn run_global(editor: &mut Editor, ctx: &CommandContext, invert: bool) -> Result<(), EditorError> {
+    if editor.in_global {
+        return Err(EditorError::GlobalRecursive);
+    }
+
+    let mut chars = ctx.raw_args.chars();
+
+    let delimiter = match chars.next() {
+        None => return Err(EditorError::ArgumentRequired),
+        Some(c) if !is_pattern_delimiter(c) => {
+            return Err(EditorError::TrailingCharacters(ctx.args.to_string()));
+        },
+        Some(c) => c
+    };
+
+    let (pattern, command) = split_delimited(chars.as_str(), delimiter);
+    let command = command.unwrap_or("").trim_start().to_string();
+
+    let pattern = if pattern.is_empty() {
+        editor.last_pattern.clone().ok_or(EditorError::NoPreviousPattern)?
+    } else {
+        pattern
+    };
+
+    let regex = Regex::new(&pattern).map_err(|_| EditorError::InvalidPattern(pattern.clone()))?;
+    editor.last_pattern = Some(pattern.clone());
+
+    let id = editor.current_buffer.ok_or(EditorError::NoBuffer)?;
+    let buffer = current_buffer_mut(editor)?;
+
+    let lines: Vec<usize> = (ctx.range.start ..= ctx.range.end)
+        .filter(|line| regex.is_match(&buffer.lines[*line]) != invert)
+        .collect();
+
+    if lines.is_empty() {
+        return Err(EditorError::PatternNotFound(pattern));
+    }
+
+    let count = lines.len();
+    buffer.set_line_marks(lines);
+    editor.in_global = true;
+
+    let mut result = Ok(());
+
+    for index in 0 .. count {
+        if editor.current_buffer != Some(id) || editor.should_quit {
+            break;
+        }
+
+        let Some(line) = editor.buffers.get(&id).and_then(|buffer| buffer.line_mark(index)) else {
+            continue;
+        };
+
+        editor.cursor.pos = Position::new(line, 0);
+
+        if let Err(err) = editor.execute_command(&command) {
+            result = Err(err);
+            break;
+        }
+    }
+
+    editor.in_global = false;
+
+    if let Some(buffer) = editor.buffers.get_mut(&id) {
+        buffer.clear_line_marks();
+    }
+
+    editor.clamp_cursor();
+
+    result
+}
+
+fn cmd_undo(editor: &mut Editor, _ctx: &CommandContext) -> Result<(), EditorError> {
+    if editor.undo() {
+        Ok(())
+    } else {
+        Err(EditorError::OldestChange)
+    }
+}
+
+fn cmd_redo(editor: &mut Editor, _ctx: &CommandContext) -> Result<(), EditorError> {
+    if editor.redo() {
+        Ok(())
+    } else {
+        Err(EditorError::NewestChange)
+    }
+}
+
+/// Feeds `keys` through the normal-mode handlers, once per line in the range
+/// with the cursor at the start of the line, or once at the cursor without a
+/// range. An unfinished command is cancelled as if `<Esc>` was typed.
+fn cmd_normal(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    if ctx.args.is_empty() {
+        return Err(EditorError::ArgumentRequired);
+    }
+
+    let keys = parse_keys(ctx.raw_args);
+    let id = editor.current_buffer.ok_or(EditorError::NoBuffer)?;
+
+    let lines: Vec<Option<usize>> = if ctx.has_range {
+        (ctx.range.start ..= ctx.range.end).map(Some).collect()
+    } else {
+        vec![None]
+    };
+
+    let marked = ctx.has_range && !editor.in_global;
+
+    // Keys still to come from a mapping that ran this command wait until it
+    // is done.
+    let outer = std::mem::take(&mut editor.input.pending);
+
+    if marked {
+        current_buffer_mut(editor)?.set_line_marks(lines.iter().flatten().copied().collect());
+    }
+
+    for (index, line) in lines.iter().enumerate() {
+        if editor.current_buffer != Some(id) || editor.should_quit {
+            break;
+        }
+
+        if line.is_some() {
+            let line = if marked {
+                editor.buffers.get(&id).and_then(|buffer| buffer.line_mark(index))
+            } else {
+                *line
+            };
+
+            let Some(line) = line else {
+                continue;
+            };
+
+            editor.cursor.pos = Position::new(line, 0);
+            editor.clamp_cursor();
+        }
+
+        for key in &keys {
+            editor.handle_key(*key);
+        }
+
+        editor.flush_keys();
+        editor.input.clear();
+
+        while !matches!(editor.mode, EditorMode::Normal) || editor.substitute_confirm.is_some() {
+            editor.handle_key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+        }
+    }
+
+    editor.input.pending = outer;
+
+    if marked && let Some(buffer) = editor.buffers.get_mut(&id) {
+        buffer.clear_line_marks();
+    }
+
+    editor.clamp_cursor();
+
+    Ok(())
+}
+
+fn cmd_nohlsearch(editor: &mut Editor, _ctx: &CommandContext) -> Result<(), EditorError> {
+    editor.search.highlight = false;
+
+    Ok(())
+}
+
+fn cmd_set(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    editor.set_options(ctx.raw_args, false)
+}
+
+fn cmd_setlocal(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    editor.set_options(ctx.raw_args, true)
+}
+
+/// `:colorscheme name` switches theme; without a name it shows the current
+/// one.
+fn cmd_colorscheme(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    if ctx.args.is_empty() {
+        editor.messages.show(&editor.options.global.color_scheme);
+        return Ok(());
+    }
+
+    editor.set_options(&format!("colorscheme={}", ctx.raw_args.trim()), false)
+}
+
+/// `:messages` shows the messages shown so far; `:messages clear` forgets
+/// them.
+fn cmd_messages(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    match ctx.args.trim() {
+        "" => {},
+        "clear" => {
+            editor.messages.clear_history();
+            return Ok(());
+        },
+        args => return Err(EditorError::InvalidArgument(args.to_string()))
+    }
+
+    let lines: Vec<&str> = editor.messages.history().map(|message| message.text.as_str()).collect();
+
+    if !lines.is_empty() {
+        editor.messages.show(lines.join("\n"));
+    }
+
+    Ok(())
+}
+
+const MAP_MODES: &[MapMode] = &[MapMode::Normal, MapMode::Visual];
+
+/// `:map lhs rhs` and its variants map `lhs` in `modes`; with only `lhs`
+/// they list the mappings starting with it, and with nothing all of them.
+fn map_command(editor: &mut Editor, ctx: &CommandContext, modes: &[MapMode], noremap: bool) -> Result<(), EditorError> {
+    let args = ctx.raw_args.trim_start();
+    let (lhs, rhs) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    let rhs = rhs.trim_start();
+
+    if rhs.is_empty() {
+        let lines = editor.list_mappings(modes, lhs.trim_end());
+
+        match lines.is_empty() {
+            true => editor.messages.info("No mapping found"),
+            false => editor.messages.show(lines.join("\n"))
+        }
+
+        return Ok(());
+    }
+
+    editor.map_keys(modes, lhs, rhs, noremap)
+}
+
+fn unmap_command(editor: &mut Editor, ctx: &CommandContext, modes: &[MapMode]) -> Result<(), EditorError> {
+    if ctx.args.is_empty() {
+        return Err(EditorError::ArgumentRequired);
+    }
+
+    editor.unmap_keys(modes, ctx.args.trim_start())
+}
+
+fn cmd_map(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    map_command(editor, ctx, MAP_MODES, false)
+}
+
+fn cmd_nmap(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    map_command(editor, ctx, &[MapMode::Normal], false)
+}
+
+fn cmd_imap(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    map_command(editor, ctx, &[MapMode::Insert], false)
+}
+
+fn cmd_vmap(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    map_command(editor, ctx, &[MapMode::Visual], false)
+}
+
+fn cmd_noremap(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    map_command(editor, ctx, MAP_MODES, true)
+}
+
+fn cmd_nnoremap(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    map_command(editor, ctx, &[MapMode::Normal], true)
+}
+
+fn cmd_inoremap(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    map_command(editor, ctx, &[MapMode::Insert], true)
+}
+
+fn cmd_vnoremap(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    map_command(editor, ctx, &[MapMode::Visual], true)
+}
+
+fn cmd_unmap(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    unmap_command(editor, ctx, MAP_MODES)
+}
+
+fn cmd_nunmap(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    unmap_command(editor, ctx, &[MapMode::Normal])
+}
+
+fn cmd_iunmap(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    unmap_command(editor, ctx, &[MapMode::Insert])
+}
+
+fn cmd_vunmap(editor: &mut Editor, ctx: &CommandContext) -> Result<(), EditorError> {
+    unmap_command(editor, ctx, &[MapMode::Visual])
+}
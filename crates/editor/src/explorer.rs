@@ -0,0 +1,683 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use uuid::Uuid;
+
+use crate::editor::{Editor, EditorMode};
+use crate::errors::EditorError;
+use crate::prompt::{Prompt, PromptEvent};
+use crate::window::SplitDirection;
+
+/// How a file stands with git, from least to most pressing, the way a
+/// folder shows the most pressing of what is in it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GitStatus {
+    Ignored,
+    Untracked,
+    Added,
+    Modified,
+    Deleted,
+    Conflicted
+}
+
+impl GitStatus {
+    /// The status for an `XY` code from `git status --porcelain`.
+    pub fn parse(code: &str) -> Option<Self> {
+        let mut letters = code.chars();
+        let (x, y) = (letters.next()?, letters.next()?);
+
+        Some(match (x, y) {
+            ('?', '?') => GitStatus::Untracked,
+            ('!', '!') => GitStatus::Ignored,
+            ('U', _) | (_, 'U') | ('A', 'A') | ('D', 'D') => GitStatus::Conflicted,
+            ('D', _) | (_, 'D') => GitStatus::Deleted,
+            ('M' | 'T', _) | (_, 'M' | 'T') => GitStatus::Modified,
+            ('A' | 'R' | 'C', _) => GitStatus::Added,
+            _ => return None
+        })
+    }
+
+    /// The letter shown next to a file with this status.
+    pub fn marker(self) -> char {
+        match self {
+            GitStatus::Ignored => '!',
+            GitStatus::Untracked => '?',
+            GitStatus::Added => 'A',
+            GitStatus::Modified => 'M',
+            GitStatus::Deleted => 'D',
+            GitStatus::Conflicted => 'U'
+        }
+    }
+}
+
+/// Reads `git status --porcelain=v1 -z` output for the repository at
+/// `top`, giving each path its status and each folder the most pressing
+/// status of what is in it. Ignored files don't count towards folders.
+pub fn parse_git_status(output: &str, top: &Path) -> HashMap<PathBuf, GitStatus> {
+    let mut statuses: HashMap<PathBuf, GitStatus> = HashMap::new();
+    let mut fields = output.split('\0');
+
+    while let Some(field) = fields.next() {
+        let (Some(code), Some(path)) = (field.get(.. 2), field.get(3 ..)) else {
+            continue;
+        };
+
+        // A rename or copy is followed by the path it was made from.
+        if code.starts_with(['R', 'C']) {
+            fields.next();
+        }
+
+        let Some(status) = GitStatus::parse(code) else {
+            continue;
+        };
+
+        let path = top.join(path.trim_end_matches('/'));
+        let folders = path.ancestors().skip(1).take_while(|folder| *folder != top);
+
+        if status != GitStatus::Ignored {
+            for folder in folders {
+                let entry = statuses.entry(folder.to_path_buf()).or_insert(status);
+                *entry = (*entry).max(status);
+            }
+        }
+
+        let entry = statuses.entry(path).or_insert(status);
+        *entry = (*entry).max(status);
+    }
+
+    statuses
+}
+
+/// Asks git on another thread how the files under `root` stand. Outside a
+/// repository, or without git, nothing has a status.
+fn git_status(root: &Path) -> Receiver<HashMap<PathBuf, GitStatus>> {
+    let (sender, receiver) = mpsc::channel();
+    let root = root.to_path_buf();
+
+    thread::spawn(move || {
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .arg("-C")
+                .arg(&root)
+                .args(args)
+                .stdin(Stdio::null())
+                .stderr(Stdio::null())
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+        };
+
+        let statuses = git(&["rev-parse", "--show-toplevel"])
+            .zip(git(&["status", "--porcelain=v1", "-z", "--ignored=matching"]))
+            .map(|(top, output)| parse_git_status(&output, Path::new(top.trim_end())))
+            .unwrap_or_default();
+
+        let _ = sender.send(statuses);
+    });
+
+    receiver
+}
+
+/// `path` from the working directory if it is under it, so a file opened
+/// from the explorer is named the way it would be on the command line.
+fn relative(path: &Path) -> PathBuf {
+    env::current_dir()
+        .and_then(|dir| dir.canonicalize())
+        .ok()
+        .and_then(|dir| path.strip_prefix(dir).ok().map(Path::to_path_buf))
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_else(|| path.display().to_string())
+}
+
+/// A file or folder shown in the explorer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExplorerEntry {
+    pub path: PathBuf,
+    /// How many folders down from the root it is.
+    pub depth: usize,
+    pub is_dir: bool
+}
+
+impl ExplorerEntry {
+    pub fn name(&self) -> String {
+        file_name(&self.path)
+    }
+}
+
+/// What can be done to the files in the explorer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileAction {
+    Create,
+    Rename,
+    Move,
+    Delete
+}
+
+/// A file action waiting for the name to use, or for `y` to a delete.
+#[derive(Debug)]
+pub struct ExplorerPrompt {
+    pub action: FileAction,
+    /// The entry acted on.
+    pub path: PathBuf,
+    pub prompt: Prompt
+}
+
+impl ExplorerPrompt {
+    /// What the status line asks.
+    pub fn question(&self, root: &Path) -> String {
+        let path = self.path.strip_prefix(root).unwrap_or(&self.path).display();
+
+        match self.action {
+            FileAction::Create => "New file (end with / for a folder): ".to_string(),
+            FileAction::Rename => format!("Rename {} to: ", path),
+            FileAction::Move => format!("Move {} to: ", path),
+            FileAction::Delete => format!("Delete {}? (y/n)", path)
+        }
+    }
+}
+
+/// The tree of files beside the windows: the folders under `root`, with
+/// the open ones showing what is in them.
+#[derive(Debug)]
+pub struct Explorer {
+    pub root: PathBuf,
+    /// The entries as shown, each folder's followed by what is in it if
+    /// it is open.
+    pub entries: Vec<ExplorerEntry>,
+    pub expanded: HashSet<PathBuf>,
+    pub selected: usize,
+    /// Whether keys go to the explorer rather than the current window.
+    pub focused: bool,
+    pub git: HashMap<PathBuf, GitStatus>,
+    git_source: Option<Receiver<HashMap<PathBuf, GitStatus>>>,
+    pub prompt: Option<ExplorerPrompt>,
+    /// `<C-w>` was typed, so the next key goes back to the windows.
+    window_key: bool
+}
+
+impl Explorer {
+    pub fn new(root: &Path) -> io::Result<Self> {
+        let root = root.canonicalize()?;
+
+        if !root.is_dir() {
+            return Err(io::ErrorKind::NotADirectory.into());
+        }
+
+        let mut explorer = Self {
+            root,
+            entries: vec![],
+            expanded: HashSet::new(),
+            selected: 0,
+            focused: true,
+            git: HashMap::new(),
+            git_source: None,
+            prompt: None,
+            window_key: false
+        };
+
+        explorer.refresh();
+        Ok(explorer)
+    }
+
+    /// Reads the tree and git's view of it again, keeping the same entry
+    /// selected if it is still there.
+    pub fn refresh(&mut self) {
+        self.rebuild();
+        self.git_source = Some(git_status(&self.root));
+    }
+
+    /// Lists the entries again from the files, for when a folder is opened
+    /// or closed or the files have changed.
+    fn rebuild(&mut self) {
+        let selected = self.selected_entry().map(|entry| entry.path.clone());
+
+        self.expanded.retain(|path| path.is_dir());
+        self.entries.clear();
+        self.read_dir(&self.root.clone(), 0);
+
+        if !selected.is_some_and(|path| self.select_path(&path)) {
+            self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+        }
+    }
+
+    fn read_dir(&mut self, dir: &Path, depth: usize) {
+        let Ok(read) = fs::read_dir(dir) else {
+            return;
+        };
+
+        let mut children: Vec<(bool, PathBuf)> = read
+            .filter_map(Result::ok)
+            .filter(|child| child.file_name() != ".git")
+            .map(|child| (child.path().is_dir(), child.path()))
+            .collect();
+
+        // Folders first, then by name whatever the case.
+        children.sort_by_cached_key(|(is_dir, path)| (!*is_dir, file_name(path).to_lowercase()));
+
+        for (is_dir, path) in children {
+            let expanded = is_dir && self.expanded.contains(&path);
+            self.entries.push(ExplorerEntry { path: path.clone(), depth, is_dir });
+
+            if expanded {
+                self.read_dir(&path, depth + 1);
+            }
+        }
+    }
+
+    /// Takes in git's answer once it comes, returning whether it has.
+    pub fn poll(&mut self) -> bool {
+        let Some(source) = &self.git_source else {
+            return false;
+        };
+
+        match source.try_recv() {
+            Ok(statuses) => self.git = statuses,
+            Err(TryRecvError::Empty) => return false,
+            Err(TryRecvError::Disconnected) => {}
+        }
+
+        self.git_source = None;
+        true
+    }
+
+    /// Whether git hasn't answered yet.
+    pub fn is_loading(&self) -> bool {
+        self.git_source.is_some()
+    }
+
+    /// How `path` stands with git. What is in an untracked or ignored
+    /// folder is untracked or ignored too.
+    pub fn status(&self, path: &Path) -> Option<GitStatus> {
+        self.git.get(path).copied().or_else(|| {
+            path.ancestors()
+                .skip(1)
+                .take_while(|folder| folder.starts_with(&self.root))
+                .find_map(|folder| self.git.get(folder).filter(|status| **status <= GitStatus::Untracked))
+                .copied()
+        })
+    }
+
+    pub fn selected_entry(&self) -> Option<&ExplorerEntry> {
+        self.entries.get(self.selected)
+    }
+
+    /// Selects the entry for `path`, returning whether it is shown.
+    pub fn select_path(&mut self, path: &Path) -> bool {
+        match self.entries.iter().position(|entry| entry.path == path) {
+            Some(index) => {
+                self.selected = index;
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Opens the folders down to `path` and selects it, returning whether
+    /// it is under the root.
+    pub fn reveal(&mut self, path: &Path) -> bool {
+        if !path.starts_with(&self.root) {
+            return false;
+        }
+
+        let folders = path.ancestors().skip(1).take_while(|folder| *folder != self.root);
+        self.expanded.extend(folders.map(Path::to_path_buf));
+        self.rebuild();
+
+        self.select_path(path)
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        let last = self.entries.len().saturating_sub(1) as isize;
+        self.selected = (self.selected as isize + delta).clamp(0, last) as usize;
+    }
+
+    /// Opens the selected folder if it is closed and closes it if it is
+    /// open.
+    pub fn toggle(&mut self) {
+        let Some(entry) = self.selected_entry().filter(|entry| entry.is_dir) else {
+            return;
+        };
+
+        let path = entry.path.clone();
+
+        if !self.expanded.remove(&path) {
+            self.expanded.insert(path);
+        }
+
+        self.rebuild();
+    }
+
+    /// `h`: closes the selected folder, or goes up to the folder it is in.
+    pub fn collapse(&mut self) {
+        let Some(entry) = self.selected_entry() else {
+            return;
+        };
+
+        if entry.is_dir && self.expanded.contains(&entry.path) {
+            self.toggle();
+        } else if let Some(parent) = entry.path.parent().map(Path::to_path_buf) {
+            self.select_path(&parent);
+        }
+    }
+
+    /// The folder a new file goes in: the selected one, or the one the
+    /// selected file is in.
+    fn target_dir(&self) -> PathBuf {
+        match self.selected_entry() {
+            Some(entry) if entry.is_dir => entry.path.clone(),
+            Some(entry) => entry.path.parent().map(Path::to_path_buf).unwrap_or_else(|| self.root.clone()),
+            None => self.root.clone()
+        }
+    }
+}
+
+impl Editor {
+    pub fn explorer_focused(&self) -> bool {
+        self.explorer.as_ref().is_some_and(|explorer| explorer.focused)
+    }
+
+    /// Opens the explorer on `dir`, with keys going to it.
+    pub fn open_explorer(&mut self, dir: &Path) -> Result<(), EditorError> {
+        let explorer = Explorer::new(dir).map_err(|err| match err.kind() {
+            io::ErrorKind::NotADirectory => EditorError::NotADirectory(dir.display().to_string()),
+            _ => EditorError::FileOperationFailed(format!("open {}", dir.display()), err.to_string())
+        })?;
+
+        self.explorer = Some(explorer);
+
+        Ok(())
+    }
+
+    /// `<leader>e` and `:explore`: opens the explorer on the working
+    /// directory, moves to it if it is open, or closes it if it is where
+    /// keys are going.
+    pub fn toggle_explorer(&mut self) -> Result<(), EditorError> {
+        match self.explorer.as_mut() {
+            Some(explorer) if explorer.focused => self.explorer = None,
+            Some(explorer) => explorer.focused = true,
+            None => self.open_explorer(Path::new("."))?
+        }
+
+        Ok(())
+    }
+
+    /// `<leader>E` and `:reveal`: shows the current buffer's file in the
+    /// explorer, opening it first if need be. A file outside the explorer's
+    /// folder opens it on the working directory, or the file's own folder.
+    pub fn reveal_file(&mut self) -> Result<(), EditorError> {
+        let path = self.get_current_buffer().and_then(|buffer| buffer.get_path()).ok_or(EditorError::NoFileName)?;
+        let path = path.canonicalize().map_err(|err| EditorError::FileOperationFailed(format!("find {}", path.display()), err.to_string()))?;
+
+        if !self.explorer.as_ref().is_some_and(|explorer| path.starts_with(&explorer.root)) {
+            let root = env::current_dir()
+                .and_then(|dir| dir.canonicalize())
+                .ok()
+                .filter(|dir| path.starts_with(dir))
+                .or_else(|| path.parent().map(Path::to_path_buf))
+                .unwrap_or_else(|| PathBuf::from("."));
+
+            self.open_explorer(&root)?;
+        }
+
+        if let Some(explorer) = self.explorer.as_mut() {
+            explorer.reveal(&path);
+            explorer.focused = true;
+        }
+
+        Ok(())
+    }
+
+    /// Takes in git's view of the explorer's files once it comes.
+    pub fn poll_explorer(&mut self) -> bool {
+        self.explorer.as_mut().is_some_and(Explorer::poll)
+    }
+
+    pub(crate) fn handle_explorer_key(&mut self, key: KeyEvent) {
+        let Some(explorer) = self.explorer.as_mut() else {
+            return;
+        };
+
+        if explorer.prompt.is_some() {
+            self.handle_explorer_prompt(key);
+            return;
+        }
+
+        if std::mem::take(&mut explorer.window_key) {
+            explorer.focused = false;
+            return;
+        }
+
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => explorer.move_selection(1),
+            KeyCode::Char('k') | KeyCode::Up => explorer.move_selection(-1),
+            KeyCode::Char('g') | KeyCode::Home => explorer.selected = 0,
+            KeyCode::Char('G') | KeyCode::End => explorer.move_selection(isize::MAX),
+            KeyCode::Char('h') | KeyCode::Left => explorer.collapse(),
+            KeyCode::Char('l') | KeyCode::Right | KeyCode::Enter => self.report(|editor| editor.open_explorer_entry(None)),
+            KeyCode::Char('s') => self.report(|editor| editor.open_explorer_entry(Some(SplitDirection::Vertical))),
+            KeyCode::Char('i') => self.report(|editor| editor.open_explorer_entry(Some(SplitDirection::Horizontal))),
+            KeyCode::Char('a') => self.start_file_action(FileAction::Create),
+            KeyCode::Char('r') => self.start_file_action(FileAction::Rename),
+            KeyCode::Char('m') => self.start_file_action(FileAction::Move),
+            KeyCode::Char('d') => self.start_file_action(FileAction::Delete),
+            KeyCode::Char('R') => explorer.refresh(),
+            KeyCode::Char('w') if control => explorer.window_key = true,
+            KeyCode::Char(':') => self.change_mode(EditorMode::Command),
+            KeyCode::Char('q') => self.explorer = None,
+            KeyCode::Esc => explorer.focused = false,
+            _ => {}
+        }
+    }
+
+    /// Opens the selected file in the current window, or a new one split
+    /// off it, and goes to it. A folder is opened or closed instead.
+    pub fn open_explorer_entry(&mut self, split: Option<SplitDirection>) -> Result<(), EditorError> {
+        let Some(explorer) = self.explorer.as_mut() else {
+            return Ok(());
+        };
+
+        let Some(entry) = explorer.selected_entry().cloned() else {
+            return Ok(());
+        };
+
+        if entry.is_dir {
+            explorer.toggle();
+            return Ok(());
+        }
+
+        explorer.focused = false;
+
+        if let Some(direction) = split {
+            self.split_window(direction);
+        }
+
+        self.open_file(relative(&entry.path))
+    }
+
+    /// A click on entry `index`: opens the file, or opens or closes the
+    /// folder.
+    pub fn click_explorer(&mut self, index: usize) {
+        if let Some(explorer) = self.explorer.as_mut() {
+            explorer.selected = index;
+            explorer.focused = true;
+        }
+
+        self.report(|editor| editor.open_explorer_entry(None));
+    }
+
+    /// Asks for what a file action needs: a name, or for a delete a `y`.
+    fn start_file_action(&mut self, action: FileAction) {
+        let Some(explorer) = self.explorer.as_mut() else {
+            return;
+        };
+
+        let path = match (action, explorer.selected_entry()) {
+            (FileAction::Create, _) => explorer.target_dir(),
+            (_, Some(entry)) => entry.path.clone(),
+            (_, None) => return
+        };
+
+        let text = match action {
+            FileAction::Create => path.strip_prefix(&explorer.root).map(|dir| dir.join("")).unwrap_or_default().display().to_string(),
+            FileAction::Rename => file_name(&path),
+            FileAction::Move => path.strip_prefix(&explorer.root).unwrap_or(&path).display().to_string(),
+            FileAction::Delete => String::new()
+        };
+
+        let mut prompt = Prompt::new();
+        prompt.set_text(&text);
+
+        explorer.prompt = Some(ExplorerPrompt { action, path, prompt });
+    }
+
+    fn handle_explorer_prompt(&mut self, key: KeyEvent) {
+        let Some(explorer) = self.explorer.as_mut() else {
+            return;
+        };
+
+        let Some(pending) = explorer.prompt.as_mut() else {
+            return;
+        };
+
+        if pending.action == FileAction::Delete {
+            let path = pending.path.clone();
+            explorer.prompt = None;
+
+            if key.code == KeyCode::Char('y') {
+                self.report(|editor| editor.delete_path(&path));
+            }
+
+            return;
+        }
+
+        match pending.prompt.handle_key(key) {
+            PromptEvent::Cancel => explorer.prompt = None,
+            PromptEvent::Submit(text) => {
+                let (action, path) = (pending.action, pending.path.clone());
+                explorer.prompt = None;
+
+                self.report(|editor| editor.run_file_action(action, &path, text.trim()));
+            },
+            _ => {}
+        }
+    }
+
+    /// Creates `name` under the explorer's root, a folder if it ends in
+    /// `/`, or renames or moves `path` to `name`. Nothing is overwritten.
+    pub fn run_file_action(&mut self, action: FileAction, path: &Path, name: &str) -> Result<(), EditorError> {
+        let Some(explorer) = self.explorer.as_ref() else {
+            return Ok(());
+        };
+
+        if name.is_empty() || action == FileAction::Delete {
+            return Ok(());
+        }
+
+        let root = explorer.root.clone();
+        let target = match action {
+            FileAction::Rename => path.parent().unwrap_or(&root).join(name),
+            _ => root.join(name)
+        };
+
+        let shown = |path: &Path| path.strip_prefix(&root).unwrap_or(path).display().to_string();
+
+        if target.exists() {
+            return Err(EditorError::AlreadyExists(shown(&target)));
+        }
+
+        let (verb, result) = match action {
+            FileAction::Create => ("create", create_path(&target, name.ends_with('/'))),
+            _ => ("move", self.move_path(path, &target))
+        };
+
+        result.map_err(|err| EditorError::FileOperationFailed(format!("{} {}", verb, shown(&target)), err.to_string()))?;
+
+        let message = match action {
+            FileAction::Create => format!("Created {}", shown(&target)),
+            _ => format!("Moved {} to {}", shown(path), shown(&target))
+        };
+
+        if let Some(explorer) = self.explorer.as_mut() {
+            explorer.refresh();
+            explorer.reveal(&target);
+        }
+
+        self.messages.info(message);
+
+        Ok(())
+    }
+
+    /// Renames `from` to `to`, making the folders `to` needs, and keeps
+    /// the buffers open on it or on what is in it pointing at their files.
+    fn move_path(&mut self, from: &Path, to: &Path) -> io::Result<()> {
+        let moved: Vec<(Uuid, PathBuf)> = self.buffers
+            .iter()
+            .filter_map(|(id, buffer)| {
+                let path = buffer.get_path()?.canonicalize().ok()?;
+                path.strip_prefix(from).ok().map(|rest| (*id, rest.to_path_buf()))
+            })
+            .collect();
+
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::rename(from, to)?;
+
+        for (id, rest) in moved {
+            let path = if rest.as_os_str().is_empty() { to.to_path_buf() } else { to.join(rest) };
+
+            if let Some(buffer) = self.buffers.get_mut(&id) {
+                buffer.set_path(relative(&path));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Deletes the file or folder at `path`. Buffers open on it are left
+    /// as they are, to be written again or closed.
+    pub fn delete_path(&mut self, path: &Path) -> Result<(), EditorError> {
+        let Some(explorer) = self.explorer.as_mut() else {
+            return Ok(());
+        };
+
+        let shown = path.strip_prefix(&explorer.root).unwrap_or(path).display().to_string();
+        let result = match path.is_dir() {
+            true => fs::remove_dir_all(path),
+            false => fs::remove_file(path)
+        };
+
+        result.map_err(|err| EditorError::FileOperationFailed(format!("delete {}", shown), err.to_string()))?;
+
+        explorer.refresh();
+        self.messages.info(format!("Deleted {}", shown));
+
+        Ok(())
+    }
+}
+
+fn create_path(path: &Path, dir: bool) -> io::Result<()> {
+    if dir {
+        return fs::create_dir_all(path);
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::File::create_new(path).map(|_| ())
+}
@@ -0,0 +1,220 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use ignore::{DirEntry, ParallelVisitor, ParallelVisitorBuilder, WalkBuilder, WalkState};
+
+use crate::editor::Editor;
+use crate::picker::{Picker, PickerItem, PickerTarget, Preview};
+use crate::replace::preview_replacement;
+
+/// How many paths a walker thread collects before sending them on.
+const BATCH: usize = 512;
+const PREVIEW_LINES: usize = 200;
+/// How many lines the preview shows above a match.
+const PREVIEW_CONTEXT: usize = 5;
+
+/// Goes through the files under `root` on other threads, skipping hidden
+/// files and whatever `.gitignore`, `.ignore` and the global git excludes
+/// leave out. `visit` turns each file into picker items, which are sent on
+/// in batches. The walk stops once the receiver is dropped.
+pub fn walk<F>(root: &Path, visit: F) -> Receiver<Vec<PickerItem>>
+where
+    F: Fn(&Path, &mut Vec<PickerItem>) + Clone + Send + 'static
+{
+    let (sender, receiver) = mpsc::channel();
+    let walker = WalkBuilder::new(root).build_parallel();
+
+    thread::spawn(move || {
+        let mut builder = FileVisitorBuilder { sender, visit };
+        walker.visit(&mut builder);
+    });
+
+    receiver
+}
+
+/// Lists the files under `root`, labelled with their paths from there.
+pub fn walk_files(root: &Path) -> Receiver<Vec<PickerItem>> {
+    let root = root.to_path_buf();
+
+    walk(&root.clone(), move |path, batch| {
+        let path = path.strip_prefix(&root).unwrap_or(path).to_path_buf();
+
+        batch.push(PickerItem {
+            label: path.display().to_string(),
+            target: PickerTarget::File(path)
+        });
+    })
+}
+
+struct FileVisitorBuilder<F> {
+    sender: Sender<Vec<PickerItem>>,
+    visit: F
+}
+
+impl<'s, F> ParallelVisitorBuilder<'s> for FileVisitorBuilder<F>
+where
+    F: Fn(&Path, &mut Vec<PickerItem>) + Clone + Send + 's
+{
+    fn build(&mut self) -> Box<dyn ParallelVisitor + 's> {
+        Box::new(FileVisitor {
+            sender: self.sender.clone(),
+            batch: Vec::with_capacity(BATCH),
+            visit: self.visit.clone()
+        })
+    }
+}
+
+/// One walker thread's share of the items, sent whenever a batch fills up
+/// and once more when the thread is done.
+struct FileVisitor<F> {
+    sender: Sender<Vec<PickerItem>>,
+    batch: Vec<PickerItem>,
+    visit: F
+}
+
+impl<F> FileVisitor<F> {
+    fn flush(&mut self) -> WalkState {
+        let batch = std::mem::replace(&mut self.batch, Vec::with_capacity(BATCH));
+
+        match self.sender.send(batch) {
+            Ok(()) => WalkState::Continue,
+            Err(_) => WalkState::Quit
+        }
+    }
+}
+
+impl<F: Fn(&Path, &mut Vec<PickerItem>) + Send> ParallelVisitor for FileVisitor<F> {
+    fn visit(&mut self, entry: Result<DirEntry, ignore::Error>) -> WalkState {
+        let Ok(entry) = entry else {
+            return WalkState::Continue;
+        };
+
+        if !entry.file_type().is_some_and(|kind| kind.is_file()) {
+            return WalkState::Continue;
+        }
+
+        let path = entry.path();
+        (self.visit)(path.strip_prefix(".").unwrap_or(path), &mut self.batch);
+
+        match self.batch.len() >= BATCH {
+            true => self.flush(),
+            false => WalkState::Continue
+        }
+    }
+}
+
+impl<F> Drop for FileVisitor<F> {
+    fn drop(&mut self) {
+        if !self.batch.is_empty() {
+            self.flush();
+        }
+    }
+}
+
+/// Lines of the file at `path` from line `start` on, or a note saying why
+/// there are none to show.
+pub fn read_preview(path: &Path, start: usize) -> Vec<String> {
+    let Ok(file) = File::open(path) else {
+        return vec!["<cannot read file>".to_string()];
+    };
+
+    let mut reader = BufReader::new(file);
+
+    match reader.fill_buf() {
+        Ok(bytes) if bytes.contains(&0) => return vec!["<binary file>".to_string()],
+        Ok(_) => {},
+        Err(_) => return vec!["<cannot read file>".to_string()]
+    }
+
+    reader
+        .split(b'\n')
+        .skip(start)
+        .take(PREVIEW_LINES)
+        .map_while(Result::ok)
+        .map(|line| String::from_utf8_lossy(&line).trim_end_matches('\r').replace('\t', "    "))
+        .collect()
+}
+
+impl Editor {
+    /// `<leader>f` and `:find`: a picker over the files under the working
+    /// directory, which fills in as they are found.
+    pub fn open_file_finder(&mut self, pattern: &str) {
+        let mut picker = Picker::with_source("Files", walk_files(Path::new(".")));
+
+        if !pattern.is_empty() {
+            picker.prompt.set_text(pattern);
+            picker.filter();
+        }
+
+        self.picker = Some(picker);
+        self.update_picker_preview();
+    }
+
+    /// Takes in the items a background search has found, returning whether
+    /// the picker changed.
+    pub fn poll_picker(&mut self) -> bool {
+        let changed = self.picker.as_mut().is_some_and(Picker::poll);
+
+        if changed {
+            self.update_picker_preview();
+        }
+
+        self.fill_list_from_picker();
+
+        changed
+    }
+
+    /// Loads the preview for the highlighted item if it is a file or a
+    /// place in one other than the one already shown.
+    pub(crate) fn update_picker_preview(&mut self) {
+        let Some(picker) = self.picker.as_mut() else {
+            return;
+        };
+
+        let target = picker.selected_item().map(|item| item.target.clone());
+
+        // A list entry is previewed as the place it is.
+        let place = match &target {
+            Some(PickerTarget::ListEntry(kind, index)) => self.list_entry(*kind, *index)
+                .map(|entry| PickerTarget::Location(entry.path.clone(), entry.pos)),
+            _ => target.clone()
+        };
+
+        let Some(picker) = self.picker.as_mut() else {
+            return;
+        };
+
+        if picker.preview.as_ref().map(|preview| &preview.target) == target.as_ref() {
+            return;
+        }
+
+        // What `:greplace` would make of the line.
+        let replaced = match (&place, &picker.replace) {
+            (Some(PickerTarget::Location(path, pos)), Some(substitution)) => preview_replacement(substitution, path, *pos),
+            _ => None
+        };
+
+        picker.preview = match (place, target) {
+            (Some(PickerTarget::File(path)), Some(target)) => Some(Preview {
+                lines: read_preview(&path, 0),
+                line: None,
+                replaced: None,
+                target
+            }),
+            (Some(PickerTarget::Location(path, pos)), Some(target)) => {
+                let start = pos.line.saturating_sub(PREVIEW_CONTEXT);
+
+                Some(Preview {
+                    lines: read_preview(&path, start),
+                    line: Some(pos.line - start),
+                    replaced,
+                    target
+                })
+            },
+            _ => None
+        };
+    }
+}
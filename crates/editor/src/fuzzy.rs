@@ -0,0 +1,223 @@
+/// Score for each matched character.
+const MATCH: i64 = 16;
+/// Extra for a character right after the previous matched one.
+const CONSECUTIVE: i64 = 8;
+/// Extra for a character at the start of a path component.
+const PATH_START: i64 = 12;
+/// Extra for a character at the start of a word inside a name.
+const WORD_START: i64 = 8;
+/// Extra for an uppercase letter after a lowercase one, as in `camelCase`.
+const CAMEL: i64 = 6;
+const GAP_START: i64 = 3;
+const GAP_EXTEND: i64 = 1;
+/// Marks a place in the score table the pattern can't be matched at.
+const NONE: i64 = i64::MIN / 2;
+
+/// How well a pattern matched some text, and the characters it matched.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    /// Character indices of the matched characters in the text.
+    pub positions: Vec<usize>
+}
+
+/// Matches the characters of `pattern` in order anywhere in `text`, the
+/// way fuzzy finders do. The case is ignored unless `pattern` has an
+/// uppercase letter. Of the ways the pattern could match, the one with the
+/// most characters in runs and at the start of words and path components
+/// scores best.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<FuzzyMatch> {
+    FuzzyMatcher::new(pattern).find(text)
+}
+
+/// One pattern to match against many texts, keeping its working space
+/// between them so a picker with a hundred thousand items doesn't allocate
+/// for each one.
+#[derive(Clone, Debug, Default)]
+pub struct FuzzyMatcher {
+    pattern: Vec<char>,
+    case_sensitive: bool,
+    original: Vec<char>,
+    text: Vec<char>,
+    /// The first and last places each pattern character could be matched.
+    earliest: Vec<usize>,
+    latest: Vec<usize>,
+    /// `scores[i * columns + j]`: the best score with pattern character `i`
+    /// matched at text character `j`; `from` has the position of character
+    /// `i - 1` in that match.
+    scores: Vec<i64>,
+    from: Vec<usize>
+}
+
+impl FuzzyMatcher {
+    pub fn new(pattern: &str) -> Self {
+        let case_sensitive = pattern.chars().any(char::is_uppercase);
+
+        Self {
+            pattern: pattern.chars().filter(|c| !c.is_whitespace()).map(|c| fold(c, case_sensitive)).collect(),
+            case_sensitive,
+            ..Self::default()
+        }
+    }
+
+    /// The score of the best match of the pattern in `text`, if it matches.
+    pub fn score(&mut self, text: &str) -> Option<i64> {
+        self.run(text, false).map(|(_, score)| score)
+    }
+
+    /// The best match of the pattern in `text` with the characters matched.
+    pub fn find(&mut self, text: &str) -> Option<FuzzyMatch> {
+        let (mut column, score) = self.run(text, true)?;
+        let (rows, columns) = (self.pattern.len(), self.text.len());
+        let mut positions = vec![column; rows];
+
+        for i in (1 .. rows).rev() {
+            column = self.from[i * columns + column];
+            positions[i - 1] = column;
+        }
+
+        Some(FuzzyMatch { score, positions })
+    }
+
+    /// Fills in the score table for `text`, and with `backtrack` the table
+    /// to find the matched characters from, returning where the last
+    /// pattern character is matched in the best match and its score.
+    fn run(&mut self, text: &str, backtrack: bool) -> Option<(usize, i64)> {
+        let case_sensitive = self.case_sensitive;
+        let pattern = &self.pattern;
+
+        if pattern.is_empty() {
+            return Some((0, 0));
+        }
+
+        // A quick pass to throw out text that doesn't have every character,
+        // which is most of it when there are many items to match.
+        let mut rest = text.chars().map(|c| fold(c, case_sensitive));
+
+        if !pattern.iter().all(|c| rest.any(|t| t == *c)) {
+            return None;
+        }
+
+        self.original.clear();
+        self.original.extend(text.chars());
+        self.text.clear();
+        self.text.extend(self.original.iter().map(|c| fold(*c, case_sensitive)));
+
+        let (rows, columns) = (pattern.len(), self.text.len());
+
+        // Pattern character `i` can only be matched between where matching
+        // as early as possible and as late as possible puts it.
+        let (earliest, latest) = (&mut self.earliest, &mut self.latest);
+        let mut j = 0;
+
+        earliest.clear();
+        latest.clear();
+        latest.resize(rows, 0);
+
+        for p in pattern {
+            j += self.text[j ..].iter().position(|t| t == p)?;
+            earliest.push(j);
+            j += 1;
+        }
+
+        let mut end = columns;
+
+        for (i, p) in pattern.iter().enumerate().rev() {
+            end = self.text[.. end].iter().rposition(|t| t == p)?;
+            latest[i] = end;
+        }
+
+        self.scores.clear();
+        self.scores.resize(rows * columns, NONE);
+
+        if backtrack {
+            self.from.clear();
+            self.from.resize(rows * columns, 0);
+        }
+
+        for (i, &p) in pattern.iter().enumerate() {
+            let row = i * columns;
+            let previous = row.wrapping_sub(columns);
+            let start = if i == 0 { self.earliest[0] } else { self.earliest[i - 1] + 1 };
+
+            // The best match of the previous characters ending before
+            // `j - 1`, with the gap up to `j` taken off.
+            let mut gapped: Option<(i64, usize)> = None;
+
+            for j in start ..= self.latest[i] {
+                if i > 0 && j >= 2 && self.scores[previous + j - 2] != NONE {
+                    let candidate = self.scores[previous + j - 2] - GAP_START;
+
+                    gapped = match gapped {
+                        Some((best, at)) if best - GAP_EXTEND >= candidate => Some((best - GAP_EXTEND, at)),
+                        _ => Some((candidate, j - 2))
+                    };
+                } else if let Some((best, at)) = gapped {
+                    gapped = Some((best - GAP_EXTEND, at));
+                }
+
+                if self.text[j] != p {
+                    continue;
+                }
+
+                let base = MATCH + boundary_bonus(&self.original, j);
+
+                if i == 0 {
+                    // Text skipped before the first match costs a little,
+                    // so earlier matches win ties.
+                    self.scores[j] = base - (j as i64).min(GAP_START);
+                    continue;
+                }
+
+                let diagonal = j.checked_sub(1)
+                    .filter(|k| self.scores[previous + k] != NONE)
+                    .map(|k| (self.scores[previous + k] + CONSECUTIVE + boundary_bonus(&self.original, k).min(CONSECUTIVE), k));
+
+                let best = match (diagonal, gapped) {
+                    (Some(diagonal), Some(gapped)) if gapped.0 > diagonal.0 => Some(gapped),
+                    (Some(diagonal), _) => Some(diagonal),
+                    (None, gapped) => gapped
+                };
+
+                if let Some((score, k)) = best {
+                    self.scores[row + j] = score + base;
+
+                    if backtrack {
+                        self.from[row + j] = k;
+                    }
+                }
+            }
+        }
+
+        let last = (rows - 1) * columns;
+
+        self.scores[last ..]
+            .iter()
+            .enumerate()
+            .filter(|(_, score)| **score != NONE)
+            .map(|(j, score)| (j, *score))
+            .max_by_key(|(j, score)| (*score, std::cmp::Reverse(*j)))
+    }
+}
+
+fn fold(c: char, case_sensitive: bool) -> char {
+    if case_sensitive { c } else { c.to_ascii_lowercase() }
+}
+
+/// The extra score for matching at `index`: the start of the text, a path
+/// component or a word, or a camel case hump.
+fn boundary_bonus(text: &[char], index: usize) -> i64 {
+    let Some(&previous) = index.checked_sub(1).and_then(|previous| text.get(previous)) else {
+        return PATH_START;
+    };
+
+    let current = text[index];
+
+    match previous {
+        '/' | '\\' => PATH_START,
+        '_' | '-' | '.' | ' ' | ':' => WORD_START,
+        _ if previous.is_lowercase() && current.is_uppercase() => CAMEL,
+        _ if !previous.is_alphanumeric() && current.is_alphanumeric() => WORD_START,
+        _ => 0
+    }
+}
@@ -0,0 +1,132 @@
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+
+use regex::Regex;
+
+use utils::Position;
+
+use crate::editor::Editor;
+use crate::errors::EditorError;
+use crate::finder::walk;
+use crate::picker::{Picker, PickerItem, PickerTarget};
+use crate::quickfix::ListKind;
+
+/// How many characters of a matching line a result shows.
+const MAX_TEXT: usize = 200;
+
+/// Searches the files under `root` for `regex` on other threads, the way
+/// `walk` goes through them, with an item for each line that matches. The
+/// search stops once the receiver is dropped.
+pub fn grep(regex: Regex, root: &Path) -> Receiver<Vec<PickerItem>> {
+    walk(root, move |path, batch| search_file(&regex, path, false, batch))
+}
+
+/// Like `grep`, with an item for every match rather than every line.
+pub fn grep_matches(regex: Regex, root: &Path) -> Receiver<Vec<PickerItem>> {
+    walk(root, move |path, batch| search_file(&regex, path, true, batch))
+}
+
+/// Adds an item for each line of `path` that matches `regex`, or with
+/// `every` for each match, labelled `file:line:column: text`. Files that
+/// look binary are skipped, and with `every` so are ones that aren't UTF-8.
+fn search_file(regex: &Regex, path: &Path, every: bool, batch: &mut Vec<PickerItem>) {
+    let Ok(file) = File::open(path) else {
+        return;
+    };
+
+    let mut reader = BufReader::new(file);
+
+    if reader.fill_buf().is_ok_and(|bytes| bytes.contains(&0)) {
+        return;
+    }
+
+    let mut found = vec![];
+
+    for (number, line) in reader.split(b'\n').enumerate() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        // A file that isn't UTF-8 can't be opened into a buffer to make a
+        // replacement in, so with `every` it is left out.
+        let line = match std::str::from_utf8(&line) {
+            Ok(line) => Cow::Borrowed(line),
+            Err(_) if every => return,
+            Err(_) => String::from_utf8_lossy(&line)
+        };
+
+        let text = line.trim_end_matches('\r');
+
+        let limit = if every { usize::MAX } else { 1 };
+        let mut matches = regex.find_iter(text).take(limit).peekable();
+
+        if matches.peek().is_none() {
+            continue;
+        }
+
+        let shown: String = text.trim().chars().take(MAX_TEXT).collect();
+
+        for place in matches {
+            found.push(PickerItem {
+                label: format!("{}:{}:{}: {}", path.display(), number + 1, place.start() + 1, shown),
+                target: PickerTarget::Location(path.to_path_buf(), Position::new(number, place.start()))
+            });
+        }
+    }
+
+    batch.extend(found);
+}
+
+/// Splits `:grep` arguments into the pattern and the path to search. A
+/// pattern with spaces in it can be quoted.
+fn split_args(args: &str) -> Result<(&str, &str), EditorError> {
+    let args = args.trim();
+
+    let (pattern, rest) = match args.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let end = args[1 ..].find(quote).ok_or_else(|| EditorError::InvalidArgument(args.to_string()))?;
+            (&args[1 .. end + 1], &args[end + 2 ..])
+        },
+        Some(_) => args.split_once(char::is_whitespace).unwrap_or((args, "")),
+        None => return Err(EditorError::ArgumentRequired)
+    };
+
+    Ok((pattern, rest.trim()))
+}
+
+impl Editor {
+    /// `:grep pattern [path]`: searches the files under `path`, or the
+    /// working directory, showing the matching lines in a picker as they
+    /// are found. Closing the picker or `<C-c>` stops the search; once it
+    /// is done or stopped the matches go into the list of `kind`.
+    pub fn grep(&mut self, args: &str, kind: ListKind) -> Result<(), EditorError> {
+        let (pattern, path) = split_args(args)?;
+        let regex = self.build_regex(pattern).ok_or(EditorError::ArgumentRequired)?;
+        let root = if path.is_empty() { "." } else { path };
+
+        if !Path::new(root).exists() {
+            return Err(EditorError::InvalidArgument(root.to_string()));
+        }
+
+        let mut picker = Picker::with_source(&format!("Grep {}", pattern), grep(regex, Path::new(root)));
+        picker.list = Some(kind);
+
+        self.picker = Some(picker);
+        self.update_picker_preview();
+
+        Ok(())
+    }
+
+    /// Opens `path` with the cursor at `pos`.
+    pub fn open_location(&mut self, path: PathBuf, pos: Position) -> Result<(), EditorError> {
+        self.open_file(path)?;
+        self.cursor.pos = pos;
+        self.clamp_cursor();
+        self.scroll_to_cursor();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,788 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use highlight::TextObject;
+
+use crate::editor::{Editor, EditorMode};
+use crate::errors::EditorError;
+use crate::keys::{format_keys, normalize_key, parse_keys};
+use crate::options::GlobalOptions;
+use crate::viewport::ScrollAnchor;
+use crate::window::{SplitDirection, WindowDirection};
+
+/// How many mappings one key may expand into before it is taken to be a
+/// mapping that maps to itself.
+const MAX_EXPANSIONS: usize = 1000;
+
+/// The modes keys can be mapped in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MapMode {
+    Normal,
+    Insert,
+    Visual
+}
+
+impl MapMode {
+    pub const ALL: [MapMode; 3] = [MapMode::Normal, MapMode::Insert, MapMode::Visual];
+
+    pub fn letter(self) -> char {
+        match self {
+            MapMode::Normal => 'n',
+            MapMode::Insert => 'i',
+            MapMode::Visual => 'v'
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "normal" | "n" => Some(MapMode::Normal),
+            "insert" | "i" => Some(MapMode::Insert),
+            "visual" | "v" => Some(MapMode::Visual),
+            _ => None
+        }
+    }
+
+    fn of(mode: &EditorMode) -> Option<Self> {
+        match mode {
+            EditorMode::Normal => Some(MapMode::Normal),
+            EditorMode::Insert => Some(MapMode::Insert),
+            EditorMode::Visual => Some(MapMode::Visual),
+            EditorMode::Command | EditorMode::Search => None
+        }
+    }
+}
+
+/// Everything a key can be bound to by name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    NormalMode,
+    InsertMode,
+    VisualMode,
+    CommandMode,
+    MoveLeft,
+    MoveDown,
+    MoveUp,
+    MoveRight,
+    NextBigWord,
+    DisplayLineDown,
+    DisplayLineUp,
+    DisplayLineStart,
+    DisplayLineEnd,
+    ScreenTop,
+    ScreenMiddle,
+    ScreenBottom,
+    ScrollCursorTop,
+    ScrollCursorCenter,
+    ScrollCursorBottom,
+    ScrollDown,
+    ScrollUp,
+    HalfPageDown,
+    HalfPageUp,
+    PageDown,
+    PageUp,
+    SearchForward,
+    SearchBackward,
+    SearchNext,
+    SearchPrevious,
+    SearchWordForward,
+    SearchWordBackward,
+    SetMark,
+    Undo,
+    Redo,
+    NextBuffer,
+    PreviousBuffer,
+    AlternateBuffer,
+    BufferPicker,
+    FileFinder,
+    Explorer,
+    RevealFile,
+    WindowLeft,
+    WindowDown,
+    WindowUp,
+    WindowRight,
+    NextWindow,
+    PreviousWindow,
+    SplitWindow,
+    VsplitWindow,
+    CloseWindow,
+    QuitWindow,
+    OnlyWindow,
+    EqualizeWindows,
+    IncreaseHeight,
+    DecreaseHeight,
+    IncreaseWidth,
+    DecreaseWidth,
+    MaximizeHeight,
+    MaximizeWidth,
+    NextTab,
+    PreviousTab,
+    ToggleComment,
+    TextObject(TextObject, bool),
+    ExpandSelection,
+    ShrinkSelection,
+    Backspace,
+    Newline,
+    Indent
+}
+
+pub const COMMANDS: &[(&str, Command)] = &[
+    ("normal_mode", Command::NormalMode),
+    ("insert_mode", Command::InsertMode),
+    ("visual_mode", Command::VisualMode),
+    ("command_mode", Command::CommandMode),
+    ("move_left", Command::MoveLeft),
+    ("move_down", Command::MoveDown),
+    ("move_up", Command::MoveUp),
+    ("move_right", Command::MoveRight),
+    ("next_big_word", Command::NextBigWord),
+    ("display_line_down", Command::DisplayLineDown),
+    ("display_line_up", Command::DisplayLineUp),
+    ("display_line_start", Command::DisplayLineStart),
+    ("display_line_end", Command::DisplayLineEnd),
+    ("screen_top", Command::ScreenTop),
+    ("screen_middle", Command::ScreenMiddle),
+    ("screen_bottom", Command::ScreenBottom),
+    ("scroll_cursor_top", Command::ScrollCursorTop),
+    ("scroll_cursor_center", Command::ScrollCursorCenter),
+    ("scroll_cursor_bottom", Command::ScrollCursorBottom),
+    ("scroll_down", Command::ScrollDown),
+    ("scroll_up", Command::ScrollUp),
+    ("half_page_down", Command::HalfPageDown),
+    ("half_page_up", Command::HalfPageUp),
+    ("page_down", Command::PageDown),
+    ("page_up", Command::PageUp),
+    ("search_forward", Command::SearchForward),
+    ("search_backward", Command::SearchBackward),
+    ("search_next", Command::SearchNext),
+    ("search_previous", Command::SearchPrevious),
+    ("search_word_forward", Command::SearchWordForward),
+    ("search_word_backward", Command::SearchWordBackward),
+    ("set_mark", Command::SetMark),
+    ("undo", Command::Undo),
+    ("redo", Command::Redo),
+    ("next_buffer", Command::NextBuffer),
+    ("previous_buffer", Command::PreviousBuffer),
+    ("alternate_buffer", Command::AlternateBuffer),
+    ("buffer_picker", Command::BufferPicker),
+    ("file_finder", Command::FileFinder),
+    ("explorer", Command::Explorer),
+    ("reveal_file", Command::RevealFile),
+    ("window_left", Command::WindowLeft),
+    ("window_down", Command::WindowDown),
+    ("window_up", Command::WindowUp),
+    ("window_right", Command::WindowRight),
+    ("next_window", Command::NextWindow),
+    ("previous_window", Command::PreviousWindow),
+    ("split_window", Command::SplitWindow),
+    ("vsplit_window", Command::VsplitWindow),
+    ("close_window", Command::CloseWindow),
+    ("quit_window", Command::QuitWindow),
+    ("only_window", Command::OnlyWindow),
+    ("equalize_windows", Command::EqualizeWindows),
+    ("increase_height", Command::IncreaseHeight),
+    ("decrease_height", Command::DecreaseHeight),
+    ("increase_width", Command::IncreaseWidth),
+    ("decrease_width", Command::DecreaseWidth),
+    ("maximize_height", Command::MaximizeHeight),
+    ("maximize_width", Command::MaximizeWidth),
+    ("next_tab", Command::NextTab),
+    ("previous_tab", Command::PreviousTab),
+    ("toggle_comment", Command::ToggleComment),
+    ("around_function", Command::TextObject(TextObject::Function, false)),
+    ("inner_function", Command::TextObject(TextObject::Function, true)),
+    ("around_class", Command::TextObject(TextObject::Class, false)),
+    ("inner_class", Command::TextObject(TextObject::Class, true)),
+    ("around_argument", Command::TextObject(TextObject::Argument, false)),
+    ("inner_argument", Command::TextObject(TextObject::Argument, true)),
+    ("around_comment", Command::TextObject(TextObject::Comment, false)),
+    ("inner_comment", Command::TextObject(TextObject::Comment, true)),
+    ("expand_selection", Command::ExpandSelection),
+    ("shrink_selection", Command::ShrinkSelection),
+    ("backspace", Command::Backspace),
+    ("newline", Command::Newline),
+    ("indent", Command::Indent)
+];
+
+impl Command {
+    pub fn from_name(name: &str) -> Option<Self> {
+        COMMANDS.iter().find(|(candidate, _)| *candidate == name).map(|(_, command)| *command)
+    }
+
+    pub fn name(self) -> &'static str {
+        COMMANDS.iter().find(|(_, command)| *command == self).map(|(name, _)| *name).unwrap_or("")
+    }
+
+    /// Whether the key after the command's own keys is its argument, as
+    /// the mark name is for `m`.
+    fn takes_char(self) -> bool {
+        matches!(self, Command::SetMark)
+    }
+}
+
+/// The built-in bindings, by the modes they apply in. `<leader>` stands
+/// for the `leader` option.
+const DEFAULTS: &[(&str, &str, Command)] = &[
+    ("iv", "<Esc>", Command::NormalMode),
+    ("n", "i", Command::InsertMode),
+    ("n", "v", Command::VisualMode),
+    ("n", ":", Command::CommandMode),
+    ("nv", "h", Command::MoveLeft),
+    ("nv", "j", Command::MoveDown),
+    ("nv", "k", Command::MoveUp),
+    ("nv", "l", Command::MoveRight),
+    ("niv", "<Left>", Command::MoveLeft),
+    ("niv", "<Down>", Command::MoveDown),
+    ("niv", "<Up>", Command::MoveUp),
+    ("niv", "<Right>", Command::MoveRight),
+    ("n", "W", Command::NextBigWord),
+    ("nv", "gj", Command::DisplayLineDown),
+    ("nv", "g<Down>", Command::DisplayLineDown),
+    ("nv", "gk", Command::DisplayLineUp),
+    ("nv", "g<Up>", Command::DisplayLineUp),
+    ("nv", "g0", Command::DisplayLineStart),
+    ("nv", "g<Home>", Command::DisplayLineStart),
+    ("nv", "g$", Command::DisplayLineEnd),
+    ("nv", "g<End>", Command::DisplayLineEnd),
+    ("n", "H", Command::ScreenTop),
+    ("n", "M", Command::ScreenMiddle),
+    ("n", "L", Command::ScreenBottom),
+    ("n", "zt", Command::ScrollCursorTop),
+    ("n", "zz", Command::ScrollCursorCenter),
+    ("n", "zb", Command::ScrollCursorBottom),
+    ("n", "<C-e>", Command::ScrollDown),
+    ("n", "<C-y>", Command::ScrollUp),
+    ("n", "<C-d>", Command::HalfPageDown),
+    ("n", "<C-u>", Command::HalfPageUp),
+    ("n", "<C-f>", Command::PageDown),
+    ("n", "<PageDown>", Command::PageDown),
+    ("n", "<C-b>", Command::PageUp),
+    ("n", "<PageUp>", Command::PageUp),
+    ("n", "/", Command::SearchForward),
+    ("n", "?", Command::SearchBackward),
+    ("n", "n", Command::SearchNext),
+    ("n", "N", Command::SearchPrevious),
+    ("n", "*", Command::SearchWordForward),
+    ("n", "#", Command::SearchWordBackward),
+    ("n", "m", Command::SetMark),
+    ("n", "u", Command::Undo),
+    ("n", "<C-r>", Command::Redo),
+    ("n", "<Tab>", Command::NextBuffer),
+    ("n", "<S-Tab>", Command::PreviousBuffer),
+    ("n", "<C-^>", Command::AlternateBuffer),
+    ("n", "<C-6>", Command::AlternateBuffer),
+    ("n", "<leader>b", Command::BufferPicker),
+    ("n", "<leader>f", Command::FileFinder),
+    ("n", "<leader>e", Command::Explorer),
+    ("n", "<leader>E", Command::RevealFile),
+    ("n", "<C-w>h", Command::WindowLeft),
+    ("n", "<C-w><C-h>", Command::WindowLeft),
+    ("n", "<C-w><Left>", Command::WindowLeft),
+    ("n", "<C-w>j", Command::WindowDown),
+    ("n", "<C-w><C-j>", Command::WindowDown),
+    ("n", "<C-w><Down>", Command::WindowDown),
+    ("n", "<C-w>k", Command::WindowUp),
+    ("n", "<C-w><C-k>", Command::WindowUp),
+    ("n", "<C-w><Up>", Command::WindowUp),
+    ("n", "<C-w>l", Command::WindowRight),
+    ("n", "<C-w><C-l>", Command::WindowRight),
+    ("n", "<C-w><Right>", Command::WindowRight),
+    ("n", "<C-w>w", Command::NextWindow),
+    ("n", "<C-w><C-w>", Command::NextWindow),
+    ("n", "<C-w>W", Command::PreviousWindow),
+    ("n", "<C-w>s", Command::SplitWindow),
+    ("n", "<C-w>S", Command::SplitWindow),
+    ("n", "<C-w><C-s>", Command::SplitWindow),
+    ("n", "<C-w>v", Command::VsplitWindow),
+    ("n", "<C-w><C-v>", Command::VsplitWindow),
+    ("n", "<C-w>c", Command::CloseWindow),
+    ("n", "<C-w>q", Command::QuitWindow),
+    ("n", "<C-w><C-q>", Command::QuitWindow),
+    ("n", "<C-w>o", Command::OnlyWindow),
+    ("n", "<C-w><C-o>", Command::OnlyWindow),
+    ("n", "<C-w>=", Command::EqualizeWindows),
+    ("n", "<C-w>+", Command::IncreaseHeight),
+    ("n", "<C-w>-", Command::DecreaseHeight),
+    ("n", "<C-w>>", Command::IncreaseWidth),
+    ("n", "<C-w><lt>", Command::DecreaseWidth),
+    ("n", "<C-w>_", Command::MaximizeHeight),
+    ("n", "<C-w><Bar>", Command::MaximizeWidth),
+    ("n", "gt", Command::NextTab),
+    ("n", "<C-PageDown>", Command::NextTab),
+    ("n", "gT", Command::PreviousTab),
+    ("n", "<C-PageUp>", Command::PreviousTab),
+    ("n", "gcc", Command::ToggleComment),
+    ("v", "gc", Command::ToggleComment),
+    ("v", "af", Command::TextObject(TextObject::Function, false)),
+    ("v", "if", Command::TextObject(TextObject::Function, true)),
+    ("v", "at", Command::TextObject(TextObject::Class, false)),
+    ("v", "it", Command::TextObject(TextObject::Class, true)),
+    ("v", "aa", Command::TextObject(TextObject::Argument, false)),
+    ("v", "ia", Command::TextObject(TextObject::Argument, true)),
+    ("v", "ac", Command::TextObject(TextObject::Comment, false)),
+    ("v", "ic", Command::TextObject(TextObject::Comment, true)),
+    ("v", "+", Command::ExpandSelection),
+    ("v", "-", Command::ShrinkSelection),
+    ("i", "<BS>", Command::Backspace),
+    ("i", "<CR>", Command::Newline),
+    ("i", "<Tab>", Command::Indent)
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Binding {
+    Command(Command),
+    /// Keys typed in place of the mapped ones; with `noremap` they only
+    /// reach the built-in bindings, not other mappings.
+    Keys { keys: Vec<KeyEvent>, noremap: bool }
+}
+
+impl Binding {
+    pub fn describe(&self) -> String {
+        match self {
+            Binding::Command(command) => command.name().to_string(),
+            Binding::Keys { keys, noremap: true } => format!("* {}", format_keys(keys)),
+            Binding::Keys { keys, noremap: false } => format_keys(keys)
+        }
+    }
+}
+
+/// Key sequences bound in each mode.
+#[derive(Clone, Debug, Default)]
+pub struct Keymap {
+    maps: HashMap<MapMode, Vec<(Vec<KeyEvent>, Binding)>>
+}
+
+impl Keymap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The built-in bindings, with `leader` for `<leader>`.
+    pub fn defaults(leader: &str) -> Self {
+        let mut keymap = Keymap::new();
+
+        for (modes, keys, command) in DEFAULTS {
+            let keys = parse_keys(&replace_ignore_case(keys, "<leader>", leader));
+
+            for mode in modes.chars().filter_map(|letter| MapMode::from_name(&letter.to_string())) {
+                keymap.insert(mode, keys.clone(), Binding::Command(*command));
+            }
+        }
+
+        keymap
+    }
+
+    pub fn insert(&mut self, mode: MapMode, keys: Vec<KeyEvent>, binding: Binding) {
+        let keys: Vec<KeyEvent> = keys.into_iter().map(normalize_key).collect();
+        let map = self.maps.entry(mode).or_default();
+
+        match map.iter_mut().find(|(existing, _)| *existing == keys) {
+            Some((_, existing)) => *existing = binding,
+            None => map.push((keys, binding))
+        }
+    }
+
+    pub fn remove(&mut self, mode: MapMode, keys: &[KeyEvent]) -> bool {
+        let Some(map) = self.maps.get_mut(&mode) else {
+            return false;
+        };
+
+        let len = map.len();
+        map.retain(|(existing, _)| existing != keys);
+        map.len() != len
+    }
+
+    pub fn get(&self, mode: MapMode, keys: &[KeyEvent]) -> Option<&Binding> {
+        self.entries(mode).find(|(existing, _)| existing.as_slice() == keys).map(|(_, binding)| binding)
+    }
+
+    pub fn entries(&self, mode: MapMode) -> impl Iterator<Item = &(Vec<KeyEvent>, Binding)> {
+        self.maps.get(&mode).into_iter().flatten()
+    }
+
+    /// Whether some binding is longer than `keys` and starts with them, so
+    /// more keys could still make a different match.
+    fn extends(&self, mode: MapMode, keys: &[KeyEvent]) -> bool {
+        self.entries(mode).any(|(existing, _)| existing.len() > keys.len() && existing.starts_with(keys))
+    }
+
+    /// The longest binding that `keys` starts with.
+    fn longest_prefix(&self, mode: MapMode, keys: &[KeyEvent]) -> Option<(usize, &Binding)> {
+        self.entries(mode)
+            .filter(|(existing, _)| keys.starts_with(existing))
+            .max_by_key(|(existing, _)| existing.len())
+            .map(|(existing, binding)| (existing.len(), binding))
+    }
+}
+
+/// Keys typed but not yet acted on, with the keymaps they are matched
+/// against.
+#[derive(Debug)]
+pub struct KeyInput {
+    pub defaults: Keymap,
+    pub user: Keymap,
+    /// Each pending key, and whether user mappings may apply to it; keys
+    /// from a `noremap` mapping may not.
+    pub(crate) pending: Vec<(KeyEvent, bool)>,
+    last_key: Option<Instant>,
+    /// A command waiting for its argument key.
+    awaiting: Option<Command>
+}
+
+impl Default for KeyInput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KeyInput {
+    pub fn new() -> Self {
+        Self {
+            defaults: Keymap::defaults(&GlobalOptions::default().leader),
+            user: Keymap::new(),
+            pending: vec![],
+            last_key: None,
+            awaiting: None
+        }
+    }
+
+    pub fn pending_keys(&self) -> Vec<KeyEvent> {
+        self.pending.iter().map(|(key, _)| *key).collect()
+    }
+
+    /// Drops keys waiting for more, as `<Esc>` in vim cancels a command.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.awaiting = None;
+        self.last_key = None;
+    }
+}
+
+impl Editor {
+    /// Takes a key through the keymaps for the current mode, running
+    /// whatever it completes. Keys that could still become a longer
+    /// mapping wait for the next key or for `timeoutlen` to pass.
+    pub fn feed_key(&mut self, key: KeyEvent) {
+        let key = normalize_key(key);
+
+        if let Some(command) = self.input.awaiting.take() {
+            self.run_command_with_char(command, key);
+            return;
+        }
+
+        self.input.pending.push((key, true));
+        self.input.last_key = Some(Instant::now());
+        self.resolve_keys(false);
+    }
+
+    /// How much longer pending keys wait for more before they are taken as
+    /// they are, if any are pending and `timeout` is set.
+    pub fn key_timeout(&self) -> Option<Duration> {
+        let options = &self.options.global;
+
+        if self.input.pending.is_empty() || !options.timeout {
+            return None;
+        }
+
+        let waited = self.input.last_key.map(|last| last.elapsed()).unwrap_or_default();
+        Some(Duration::from_millis(options.timeout_len as u64).saturating_sub(waited))
+    }
+
+    /// Acts on pending keys without waiting for more, as when the timeout
+    /// passes.
+    pub fn flush_keys(&mut self) {
+        self.sync_buffer_options();
+        self.resolve_keys(true);
+        self.scroll_to_cursor();
+    }
+
+    fn resolve_keys(&mut self, timed_out: bool) {
+        let mut expansions = 0;
+
+        while !self.input.pending.is_empty() {
+            if self.picker.is_some() {
+                let (key, _) = self.input.pending.remove(0);
+                self.handle_picker_key(key);
+                continue;
+            }
+
+            if self.substitute_confirm.is_some() {
+                let (key, _) = self.input.pending.remove(0);
+                self.handle_confirm_input(key.code);
+                continue;
+            }
+
+            if self.explorer_focused() && matches!(self.mode, EditorMode::Normal) {
+                let (key, _) = self.input.pending.remove(0);
+                self.handle_explorer_key(key);
+                continue;
+            }
+
+            if let Some(command) = self.input.awaiting.take() {
+                let (key, _) = self.input.pending.remove(0);
+                self.run_command_with_char(command, key);
+                continue;
+            }
+
+            // Mappings can switch to the command line, which reads keys
+            // as they are.
+            let Some(mode) = MapMode::of(&self.mode) else {
+                let (key, _) = self.input.pending.remove(0);
+                self.handle_line_input(key);
+                continue;
+            };
+
+            let keys = self.input.pending_keys();
+            let remappable = self.input.pending.iter().take_while(|(_, remap)| *remap).count();
+
+            let ambiguous = self.input.defaults.extends(mode, &keys)
+                || (remappable == keys.len() && self.input.user.extends(mode, &keys));
+
+            if ambiguous && !timed_out {
+                return;
+            }
+
+            let user = self.input.user.longest_prefix(mode, &keys[.. remappable]);
+            let default = self.input.defaults.longest_prefix(mode, &keys);
+
+            let (len, binding) = match (user, default) {
+                (Some((user_len, binding)), Some((default_len, _))) if user_len >= default_len => (user_len, binding.clone()),
+                (_, Some((len, binding))) => (len, binding.clone()),
+                (Some((len, binding)), None) => (len, binding.clone()),
+                (None, None) => {
+                    let (key, _) = self.input.pending.remove(0);
+                    self.unmapped_key(mode, key);
+                    continue;
+                }
+            };
+
+            self.input.pending.drain(.. len);
+
+            match binding {
+                Binding::Command(command) if command.takes_char() => self.input.awaiting = Some(command),
+                Binding::Command(command) => self.run_key_command(command),
+                Binding::Keys { keys, noremap } => {
+                    expansions += 1;
+
+                    if expansions > MAX_EXPANSIONS {
+                        self.input.clear();
+                        self.messages.error(EditorError::RecursiveMapping);
+                        return;
+                    }
+
+                    let expanded = keys.into_iter().map(|key| (key, !noremap));
+                    self.input.pending.splice(0 .. 0, expanded);
+                }
+            }
+        }
+
+        self.input.last_key = None;
+    }
+
+    /// A key no binding starts with: text in insert mode, ignored
+    /// elsewhere.
+    fn unmapped_key(&mut self, mode: MapMode, key: KeyEvent) {
+        if mode == MapMode::Insert
+            && let KeyCode::Char(c) = key.code
+            && !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+        {
+            self.insert_char(c);
+        }
+    }
+
+    fn handle_line_input(&mut self, key: KeyEvent) {
+        match self.mode {
+            EditorMode::Command => self.handle_command_mode_input(key),
+            EditorMode::Search => self.handle_search_mode_input(key),
+            _ => {}
+        }
+    }
+
+    pub fn run_key_command(&mut self, command: Command) {
+        match command {
+            Command::NormalMode => self.change_mode(EditorMode::Normal),
+            Command::InsertMode => self.change_mode(EditorMode::Insert),
+            Command::VisualMode => self.change_mode(EditorMode::Visual),
+            Command::CommandMode => {
+                self.command_line.clear();
+                self.change_mode(EditorMode::Command);
+            },
+            Command::MoveLeft => self.move_cursor_left(),
+            Command::MoveDown => self.move_cursor_down(),
+            Command::MoveUp => self.move_cursor_up(),
+            Command::MoveRight => self.move_cursor_right(),
+            Command::NextBigWord => self.jump_to_next_word_after_space(),
+            Command::DisplayLineDown => self.move_display_line(true),
+            Command::DisplayLineUp => self.move_display_line(false),
+            Command::DisplayLineStart => self.move_display_line_edge(false),
+            Command::DisplayLineEnd => self.move_display_line_edge(true),
+            Command::ScreenTop => self.move_to_screen_line(ScrollAnchor::Top),
+            Command::ScreenMiddle => self.move_to_screen_line(ScrollAnchor::Center),
+            Command::ScreenBottom => self.move_to_screen_line(ScrollAnchor::Bottom),
+            Command::ScrollCursorTop => self.scroll_cursor_to(ScrollAnchor::Top),
+            Command::ScrollCursorCenter => self.scroll_cursor_to(ScrollAnchor::Center),
+            Command::ScrollCursorBottom => self.scroll_cursor_to(ScrollAnchor::Bottom),
+            Command::ScrollDown => self.scroll_lines(1),
+            Command::ScrollUp => self.scroll_lines(-1),
+            Command::HalfPageDown => self.scroll_half_page(true),
+            Command::HalfPageUp => self.scroll_half_page(false),
+            Command::PageDown => self.scroll_page(true),
+            Command::PageUp => self.scroll_page(false),
+            Command::SearchForward => self.start_search(true),
+            Command::SearchBackward => self.start_search(false),
+            Command::SearchNext => self.report(|editor| editor.search_next(true)),
+            Command::SearchPrevious => self.report(|editor| editor.search_next(false)),
+            Command::SearchWordForward => self.report(|editor| editor.search_word_under_cursor(true)),
+            Command::SearchWordBackward => self.report(|editor| editor.search_word_under_cursor(false)),
+            Command::SetMark => self.input.awaiting = Some(Command::SetMark),
+            Command::Undo => {
+                if !self.undo() {
+                    self.messages.error(EditorError::OldestChange);
+                }
+            },
+            Command::Redo => {
+                if !self.redo() {
+                    self.messages.error(EditorError::NewestChange);
+                }
+            },
+            Command::NextBuffer => self.next_buffer(),
+            Command::PreviousBuffer => self.previous_buffer(),
+            Command::AlternateBuffer => self.report(|editor| editor.switch_to_alternate_buffer()),
+            Command::BufferPicker => self.open_buffer_picker(),
+            Command::FileFinder => self.open_file_finder(""),
+            Command::Explorer => self.report(|editor| editor.toggle_explorer()),
+            Command::RevealFile => self.report(|editor| editor.reveal_file()),
+            Command::WindowLeft => self.move_to_window(WindowDirection::Left),
+            Command::WindowDown => self.move_to_window(WindowDirection::Down),
+            Command::WindowUp => self.move_to_window(WindowDirection::Up),
+            Command::WindowRight => self.move_to_window(WindowDirection::Right),
+            Command::NextWindow => self.cycle_window(true),
+            Command::PreviousWindow => self.cycle_window(false),
+            Command::SplitWindow => self.split_window(SplitDirection::Horizontal),
+            Command::VsplitWindow => self.split_window(SplitDirection::Vertical),
+            Command::CloseWindow => self.report(|editor| editor.close_window(editor.current_window)),
+            Command::QuitWindow => self.report(|editor| editor.execute_command("quit")),
+            Command::OnlyWindow => self.only_window(),
+            Command::EqualizeWindows => self.equalize_windows(),
+            Command::IncreaseHeight => self.resize_window(SplitDirection::Horizontal, 1),
+            Command::DecreaseHeight => self.resize_window(SplitDirection::Horizontal, -1),
+            Command::IncreaseWidth => self.resize_window(SplitDirection::Vertical, 1),
+            Command::DecreaseWidth => self.resize_window(SplitDirection::Vertical, -1),
+            Command::MaximizeHeight => self.set_window_size(SplitDirection::Horizontal, usize::MAX / 2),
+            Command::MaximizeWidth => self.set_window_size(SplitDirection::Vertical, usize::MAX / 2),
+            Command::NextTab => self.cycle_tab(true, 1),
+            Command::PreviousTab => self.cycle_tab(false, 1),
+            Command::ToggleComment => self.toggle_comment_command(),
+            Command::TextObject(object, inner) => self.report(|editor| editor.select_text_object(object, inner)),
+            Command::ExpandSelection => self.report(|editor| editor.expand_selection()),
+            Command::ShrinkSelection => self.shrink_selection(),
+            Command::Backspace => self.backspace(),
+            Command::Newline => self.newline(),
+            Command::Indent => self.indent()
+        }
+    }
+
+    fn run_command_with_char(&mut self, command: Command, key: KeyEvent) {
+        if let (Command::SetMark, KeyCode::Char(c)) = (command, key.code)
+            && c.is_ascii_alphabetic()
+        {
+            let pos = self.cursor.pos;
+
+            if let Some(buffer) = self.get_current_buffer_mut() {
+                buffer.set_mark(c, pos);
+            }
+        }
+    }
+
+    /// `gcc` on the cursor line, or `gc` on the visual selection.
+    fn toggle_comment_command(&mut self) {
+        if !matches!(self.mode, EditorMode::Visual) {
+            let line = self.cursor.pos.line;
+            self.report(|editor| editor.toggle_comment(line, line));
+            return;
+        }
+
+        if let Some(selection) = self.selection() {
+            self.report(|editor| editor.toggle_comment(selection.start.line, selection.end.line));
+            self.cursor.pos = selection.start;
+        }
+
+        self.change_mode(EditorMode::Normal);
+    }
+
+    /// The keys of one side of a mapping, with `<leader>` replaced by the
+    /// `leader` option.
+    pub fn parse_mapped_keys(&self, text: &str) -> Vec<KeyEvent> {
+        let leader = &self.options.global.leader;
+        let text = replace_ignore_case(text, "<leader>", leader);
+
+        parse_keys(&text).into_iter().map(normalize_key).collect()
+    }
+
+    /// Maps `lhs` to `rhs` in each of `modes`.
+    pub fn map_keys(&mut self, modes: &[MapMode], lhs: &str, rhs: &str, noremap: bool) -> Result<(), EditorError> {
+        let keys = self.parse_mapped_keys(lhs);
+
+        if keys.is_empty() || rhs.is_empty() {
+            return Err(EditorError::ArgumentRequired);
+        }
+
+        let binding = match Command::from_name(rhs) {
+            Some(command) => Binding::Command(command),
+            None => Binding::Keys { keys: self.parse_mapped_keys(rhs), noremap }
+        };
+
+        for mode in modes {
+            self.input.user.insert(*mode, keys.clone(), binding.clone());
+        }
+
+        Ok(())
+    }
+
+    pub fn unmap_keys(&mut self, modes: &[MapMode], lhs: &str) -> Result<(), EditorError> {
+        let keys = self.parse_mapped_keys(lhs);
+        let mut found = false;
+
+        for mode in modes {
+            found |= self.input.user.remove(*mode, &keys);
+        }
+
+        match found {
+            true => Ok(()),
+            false => Err(EditorError::NoSuchMapping)
+        }
+    }
+
+    /// The user's mappings in `modes` whose keys start with `prefix`, one
+    /// line each as `:map` lists them.
+    pub fn list_mappings(&self, modes: &[MapMode], prefix: &str) -> Vec<String> {
+        let prefix = self.parse_mapped_keys(prefix);
+        let mut lines = vec![];
+
+        for mode in modes {
+            for (keys, binding) in self.input.user.entries(*mode) {
+                if keys.starts_with(&prefix) {
+                    lines.push(format!("{}  {}  {}", mode.letter(), format_keys(keys), binding.describe()));
+                }
+            }
+        }
+
+        lines.sort();
+        lines
+    }
+}
+
+fn replace_ignore_case(text: &str, from: &str, to: &str) -> String {
+    let lower = text.to_ascii_lowercase();
+    let mut result = String::new();
+    let mut last = 0;
+
+    for (index, _) in lower.match_indices(from) {
+        result.push_str(&text[last .. index]);
+        result.push_str(to);
+        last = index + from.len();
+    }
+
+    result.push_str(&text[last ..]);
+    result
+}
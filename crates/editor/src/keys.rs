@@ -0,0 +1,156 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Parses a key sequence written in vim notation, e.g. `ihello<Esc>` or
+/// `<C-w>j`. Text outside of `<...>` is taken literally, and an unrecognised
+/// `<...>` is kept as the literal characters.
+pub fn parse_keys(input: &str) -> Vec<KeyEvent> {
+    let mut keys = vec![];
+    let mut rest = input;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '<'
+            && let Some(end) = rest.find('>')
+            && let Some(key) = parse_special(&rest[1 .. end])
+        {
+            keys.push(key);
+            rest = &rest[end + 1 ..];
+            continue;
+        }
+
+        keys.push(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        rest = &rest[c.len_utf8() ..];
+    }
+
+    keys
+}
+
+/// A key as keymaps store it: a press with no extra state, Shift folded
+/// into the character it changes, and Ctrl with a lowercase letter.
+pub fn normalize_key(key: KeyEvent) -> KeyEvent {
+    let mut modifiers = key.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+
+    let code = match key.code {
+        KeyCode::Char(c) => {
+            modifiers.remove(KeyModifiers::SHIFT);
+
+            match modifiers.contains(KeyModifiers::CONTROL) {
+                true => KeyCode::Char(c.to_ascii_lowercase()),
+                false => KeyCode::Char(c)
+            }
+        },
+        KeyCode::BackTab => {
+            modifiers.insert(KeyModifiers::SHIFT);
+            KeyCode::Tab
+        },
+        code => code
+    };
+
+    KeyEvent::new(code, modifiers)
+}
+
+/// Writes keys back in the notation `parse_keys` reads.
+pub fn format_keys(keys: &[KeyEvent]) -> String {
+    keys.iter().map(|key| format_key(*key)).collect()
+}
+
+fn format_key(key: KeyEvent) -> String {
+    let name = match key.code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char('<') => "lt".to_string(),
+        KeyCode::Char('|') => "Bar".to_string(),
+        KeyCode::Char('\\') => "Bslash".to_string(),
+        KeyCode::Char(c) if key.modifiers.is_empty() => return c.to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::Enter => "CR".to_string(),
+        KeyCode::Tab => "Tab".to_string(),
+        KeyCode::Backspace => "BS".to_string(),
+        KeyCode::Delete => "Del".to_string(),
+        KeyCode::Up => "Up".to_string(),
+        KeyCode::Down => "Down".to_string(),
+        KeyCode::Left => "Left".to_string(),
+        KeyCode::Right => "Right".to_string(),
+        KeyCode::Home => "Home".to_string(),
+        KeyCode::End => "End".to_string(),
+        KeyCode::PageUp => "PageUp".to_string(),
+        KeyCode::PageDown => "PageDown".to_string(),
+        KeyCode::Insert => "Insert".to_string(),
+        KeyCode::F(number) => format!("F{}", number),
+        code => format!("{:?}", code)
+    };
+
+    let mut prefix = String::new();
+
+    for (modifier, letter) in [(KeyModifiers::CONTROL, "C-"), (KeyModifiers::ALT, "A-"), (KeyModifiers::SHIFT, "S-")] {
+        if key.modifiers.contains(modifier) {
+            prefix.push_str(letter);
+        }
+    }
+
+    format!("<{}{}>", prefix, name)
+}
+
+fn parse_special(name: &str) -> Option<KeyEvent> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut name = name;
+
+    while name.len() > 2 && name.as_bytes()[1] == b'-' {
+        match name.as_bytes()[0].to_ascii_uppercase() {
+            b'C' => modifiers |= KeyModifiers::CONTROL,
+            b'A' | b'M' => modifiers |= KeyModifiers::ALT,
+            b'S' => modifiers |= KeyModifiers::SHIFT,
+            _ => return None
+        }
+
+        name = &name[2 ..];
+    }
+
+    let mut chars = name.chars();
+
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) if modifiers != KeyModifiers::NONE => {
+            if modifiers.contains(KeyModifiers::CONTROL) {
+                KeyCode::Char(c.to_ascii_lowercase())
+            } else {
+                KeyCode::Char(c)
+            }
+        },
+        _ => named_key(name)?
+    };
+
+    Some(KeyEvent::new(code, modifiers))
+}
+
+fn named_key(name: &str) -> Option<KeyCode> {
+    let code = match name.to_ascii_lowercase().as_str() {
+        "esc" => KeyCode::Esc,
+        "cr" | "enter" | "return" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "bs" | "backspace" => KeyCode::Backspace,
+        "del" | "delete" => KeyCode::Delete,
+        "space" => KeyCode::Char(' '),
+        "lt" => KeyCode::Char('<'),
+        "bar" => KeyCode::Char('|'),
+        "bslash" => KeyCode::Char('\\'),
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "insert" => KeyCode::Insert,
+        other => {
+            let number = other.strip_prefix('f')?.parse::<u8>().ok()?;
+
+            if (1 ..= 12).contains(&number) {
+                KeyCode::F(number)
+            } else {
+                return None;
+            }
+        }
+    };
+
+    Some(code)
+}
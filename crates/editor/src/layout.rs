@@ -0,0 +1,318 @@
+use std::ops::Range;
+
+use buffer::buffer::Buffer;
+use utils::Position;
+
+use crate::display::{cells, display_column, display_width, Cell};
+use crate::editor::Editor;
+
+/// Characters after which `line_break` may wrap a line, as in vim's
+/// default `breakat`.
+const BREAK_AT: &str = " \t!@*-+;:,./?";
+
+fn is_break_at(grapheme: &str) -> bool {
+    let mut chars = grapheme.chars();
+    matches!((chars.next(), chars.next()), (Some(c), None) if BREAK_AT.contains(c))
+}
+
+/// How buffer lines are split into screen rows. With `wrap` off every line
+/// is a single row that the viewport scrolls sideways; with it on, lines are
+/// broken at `width` (or at the last `BREAK_AT` character before it when
+/// `line_break` is set) and continuation rows start with `show_break`.
+///
+/// Columns here are screen columns as laid out by `display::cells`, so tabs
+/// and wide characters take their real width.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextLayout {
+    pub width: usize,
+    pub tab_stop: usize,
+    pub wrap: bool,
+    pub show_break: String,
+    pub line_break: bool
+}
+
+impl TextLayout {
+    fn break_width(&self) -> usize {
+        display_width(&self.show_break, self.tab_stop)
+    }
+
+    pub fn cells(&self, text: &str) -> Vec<Cell> {
+        cells(text, self.tab_stop)
+    }
+
+    /// The byte ranges of `text` shown on each of its screen rows. There is
+    /// always at least one row, even for an empty line. A grapheme wider
+    /// than the space left on a row moves to the next one.
+    pub fn rows(&self, text: &str) -> Vec<Range<usize>> {
+        if !self.wrap || self.width == 0 {
+            let whole = 0 .. text.len();
+            return vec![whole];
+        }
+
+        let cells = self.cells(text);
+        let mut rows = vec![];
+        let mut start = 0;
+
+        while start < cells.len() {
+            let available = if rows.is_empty() {
+                self.width
+            } else {
+                self.width.saturating_sub(self.break_width()).max(1)
+            };
+
+            let origin = cells[start].column;
+            let mut end = start + 1;
+
+            while end < cells.len() && cells[end].column + cells[end].width - origin <= available {
+                end += 1;
+            }
+
+            if end < cells.len() && self.line_break
+                && let Some(at) = (start .. end).rev().find(|&i| is_break_at(&text[cells[i].start .. cells[i].end]))
+            {
+                end = at + 1;
+            }
+
+            rows.push(cells[start].start .. cells.get(end).map(|cell| cell.start).unwrap_or(text.len()));
+            start = end;
+        }
+
+        if rows.is_empty() {
+            rows.push(0 .. 0);
+        }
+
+        rows
+    }
+
+    pub fn row_count(&self, text: &str) -> usize {
+        self.rows(text).len()
+    }
+
+    /// The row within the line and screen column of byte `column`. A column
+    /// where one row ends and the next starts belongs to the next row.
+    pub fn to_visual(&self, text: &str, column: usize) -> (usize, usize) {
+        let column = column.min(text.len());
+        let rows = self.rows(text);
+        let row = rows.iter().rposition(|r| r.start <= column).unwrap_or(0);
+        let offset = if row > 0 { self.break_width() } else { 0 };
+        let origin = display_column(text, rows[row].start, self.tab_stop);
+        let visual = offset + display_column(text, column, self.tab_stop) - origin;
+
+        if self.wrap && self.width > 0 {
+            (row, visual.min(self.width - 1))
+        } else {
+            (row, visual)
+        }
+    }
+
+    /// The byte column shown at screen column `column` of `row`. Past the end
+    /// of a row this is its last character, or the end of the line on the
+    /// last row.
+    pub fn to_column(&self, text: &str, row: usize, column: usize) -> usize {
+        let rows = self.rows(text);
+        let last = rows.len() - 1;
+        let row = row.min(last);
+        let range = rows[row].clone();
+        let offset = if row > 0 { self.break_width() } else { 0 };
+        let origin = display_column(text, range.start, self.tab_stop);
+        let target = origin.saturating_add(column.saturating_sub(offset));
+
+        let cells: Vec<Cell> = self.cells(text)
+            .into_iter()
+            .filter(|cell| cell.start >= range.start && cell.end <= range.end)
+            .collect();
+
+        match cells.iter().find(|cell| cell.column + cell.width > target) {
+            Some(cell) => cell.start,
+            None if row == last => range.end,
+            None => cells.last().map(|cell| cell.start).unwrap_or(range.start)
+        }
+    }
+
+    /// Screen row (counted from the line at `top`) and column of `pos`, or
+    /// `None` if it is above `top`.
+    pub fn screen_position(&self, buffer: &Buffer, top: usize, pos: Position) -> Option<(usize, usize)> {
+        if pos.line < top {
+            return None;
+        }
+
+        let rows_above: usize = buffer.line_slice(top, pos.line)
+            .iter()
+            .map(|text| self.row_count(text))
+            .sum();
+
+        let text = buffer.line_slice(pos.line, pos.line + 1).first()?;
+        let (row, column) = self.to_visual(text, pos.column);
+
+        Some((rows_above + row, column))
+    }
+
+    /// The buffer position drawn at screen `row` and `column`, counting rows
+    /// from the line at `top`. Rows past the end of the buffer land on its
+    /// last line.
+    pub fn position_at(&self, buffer: &Buffer, top: usize, row: usize, column: usize) -> Position {
+        let mut remaining = row;
+        let last = buffer.len() - 1;
+
+        for (line, text) in buffer.line_slice(top, buffer.len()).iter().enumerate() {
+            let count = self.row_count(text);
+
+            if remaining < count {
+                return Position::new(top + line, self.to_column(text, remaining, column));
+            }
+
+            remaining -= count;
+        }
+
+        let text = &buffer.lines[last];
+        Position::new(last, self.to_column(text, usize::MAX, column))
+    }
+}
+
+impl Editor {
+    pub fn text_layout(&self) -> TextLayout {
+        let window = &self.window_options;
+
+        TextLayout {
+            width: self.viewport.width,
+            tab_stop: self.buffer_options().tab_stop,
+            wrap: window.wrap,
+            show_break: window.show_break.clone(),
+            line_break: window.line_break
+        }
+    }
+
+    /// The last line whose first row is on screen.
+    pub fn visible_bottom(&self) -> usize {
+        let Some(buffer) = self.get_current_buffer() else {
+            return self.viewport.bottom();
+        };
+
+        if !self.window_options.wrap {
+            return self.viewport.bottom();
+        }
+
+        let layout = self.text_layout();
+        let mut rows = 0;
+        let mut line = self.viewport.top;
+
+        while line + 1 < buffer.len() {
+            rows += layout.row_count(&buffer.lines[line]);
+
+            if rows >= self.viewport.height {
+                break;
+            }
+
+            line += 1;
+        }
+
+        line
+    }
+
+    /// Scrolls down until the cursor's row, and the rows of the `below`
+    /// lines after it, fit on screen. Long wrapped lines can need this after
+    /// the line-based scroll in `scroll_to_cursor`.
+    pub(crate) fn scroll_wrapped_to_cursor(&mut self, below: usize) {
+        let layout = self.text_layout();
+        let height = self.viewport.height;
+        let pos = self.cursor.pos;
+
+        let Some(buffer) = self.get_current_buffer() else {
+            return;
+        };
+
+        let cursor_row = layout.to_visual(&buffer.lines[pos.line], pos.column).0;
+        let after: usize = buffer.line_slice(pos.line + 1, pos.line + 1 + below)
+            .iter()
+            .map(|text| layout.row_count(text))
+            .sum();
+
+        let mut top = self.viewport.top.min(pos.line);
+
+        while top < pos.line {
+            let above: usize = buffer.line_slice(top, pos.line)
+                .iter()
+                .map(|text| layout.row_count(text))
+                .sum();
+
+            if above + cursor_row + 1 + after <= height {
+                break;
+            }
+
+            top += 1;
+        }
+
+        self.viewport.top = top;
+        self.viewport.left = 0;
+    }
+
+    /// Where the cursor is drawn, relative to the top-left of the text area.
+    pub fn cursor_screen_position(&self) -> Option<(usize, usize)> {
+        let buffer = self.get_current_buffer()?;
+        let (row, column) = self.text_layout().screen_position(buffer, self.viewport.top, self.cursor.pos)?;
+        let column = column.checked_sub(self.viewport.left)?;
+
+        (row < self.viewport.height && column < self.viewport.width.max(1)).then_some((row, column))
+    }
+
+    /// Moves the cursor to what is drawn at `row` and `column` of the text
+    /// area, e.g. for a mouse click.
+    pub fn click(&mut self, row: usize, column: usize) {
+        let Some(buffer) = self.get_current_buffer() else {
+            return;
+        };
+
+        self.cursor.pos = self.text_layout().position_at(buffer, self.viewport.top, row, column + self.viewport.left);
+        self.clamp_cursor();
+    }
+
+    /// `gj` and `gk`: moves by one screen row, keeping the screen column, so
+    /// a long wrapped line can be walked through row by row.
+    pub fn move_display_line(&mut self, down: bool) {
+        let layout = self.text_layout();
+        let pos = self.cursor.pos;
+
+        let Some(buffer) = self.get_current_buffer() else {
+            return;
+        };
+
+        let text = &buffer.lines[pos.line];
+        let (row, column) = layout.to_visual(text, pos.column);
+        let rows = layout.row_count(text);
+
+        self.cursor.pos = if down && row + 1 < rows {
+            Position::new(pos.line, layout.to_column(text, row + 1, column))
+        } else if down && pos.line + 1 < buffer.len() {
+            Position::new(pos.line + 1, layout.to_column(&buffer.lines[pos.line + 1], 0, column))
+        } else if !down && row > 0 {
+            Position::new(pos.line, layout.to_column(text, row - 1, column))
+        } else if !down && pos.line > 0 {
+            let above = &buffer.lines[pos.line - 1];
+            Position::new(pos.line - 1, layout.to_column(above, layout.row_count(above) - 1, column))
+        } else {
+            pos
+        };
+    }
+
+    /// `g0` and `g$`: moves to the first or last character of the screen row.
+    pub fn move_display_line_edge(&mut self, end: bool) {
+        let layout = self.text_layout();
+        let pos = self.cursor.pos;
+        let left = self.viewport.left;
+        let width = self.viewport.width.max(1);
+
+        let Some(buffer) = self.get_current_buffer() else {
+            return;
+        };
+
+        let text = &buffer.lines[pos.line];
+        let row = layout.to_visual(text, pos.column).0;
+
+        self.cursor.pos.column = match (self.window_options.wrap, end) {
+            (true, false) => layout.rows(text)[row].start,
+            (true, true) => layout.to_column(text, row, usize::MAX),
+            (false, false) => layout.to_column(text, 0, left),
+            (false, true) => layout.to_column(text, 0, left + width - 1)
+        };
+    }
+}
@@ -1,6 +1,32 @@
 pub mod editor;
+pub mod buffers;
+pub mod command;
+pub mod comment;
 pub mod cursor;
+pub mod display;
 pub mod errors;
+pub mod ex;
+pub mod explorer;
+pub mod finder;
+pub mod fuzzy;
+pub mod grep;
+pub mod keymap;
+pub mod keys;
+pub mod layout;
+pub mod make;
+pub mod message;
+pub mod options;
+pub mod picker;
+pub mod prompt;
+pub mod quickfix;
+pub mod replace;
+pub mod search;
+pub mod substitute;
+pub mod syntax;
+pub mod tabpage;
+pub mod theme;
+pub mod viewport;
+pub mod window;
 
 pub use cursor::Cursor;
 pub use errors::EditorError;
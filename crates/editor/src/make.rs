@@ -0,0 +1,305 @@
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread::{self, JoinHandle};
+
+use regex::Regex;
+use serde_json::Value;
+
+use utils::Position;
+
+use crate::editor::Editor;
+use crate::errors::EditorError;
+use crate::quickfix::{ListKind, QuickfixEntry, QuickfixList, Severity};
+use crate::window::WindowId;
+
+/// The cargo commands that report diagnostics as JSON when asked to.
+const CARGO_COMMANDS: &[&str] = &["build", "b", "check", "c", "clippy", "test", "t", "bench", "run", "r", "rustc", "doc", "d"];
+
+/// What the threads reading a `:make` send back.
+#[derive(Debug)]
+enum MakeOutput {
+    Line(String),
+    /// The command finished, with its exit code if it had one.
+    Done(Option<i32>)
+}
+
+/// A `:make` running in the background, its errors going into a list as
+/// they come.
+#[derive(Debug)]
+pub struct MakeJob {
+    pub command: String,
+    kind: ListKind,
+    /// The window whose location list `:lmake` fills.
+    window: WindowId,
+    /// Whether to go to the first error once the command is done.
+    jump: bool,
+    formats: Vec<Regex>,
+    output: Receiver<MakeOutput>
+}
+
+/// Turns an `errorformat` into a regex for each of its comma separated
+/// patterns. `%f`, `%l`, `%c`, `%t` and `%m` stand for the file, line,
+/// column, kind and message, `%%` for a `%` and `\,` for a comma.
+pub fn compile_error_format(format: &str) -> Vec<Regex> {
+    let mut patterns = vec![String::new()];
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&',') => {
+                chars.next();
+                patterns.last_mut().unwrap().push(',');
+            },
+            ',' => patterns.push(String::new()),
+            c => patterns.last_mut().unwrap().push(c)
+        }
+    }
+
+    patterns
+        .iter()
+        .filter(|pattern| !pattern.is_empty())
+        .filter_map(|pattern| {
+            let mut regex = String::from("^");
+            let mut chars = pattern.chars();
+
+            while let Some(c) = chars.next() {
+                if c != '%' {
+                    regex.push_str(&regex::escape(&c.to_string()));
+                    continue;
+                }
+
+                match chars.next() {
+                    Some('f') => regex.push_str(r"(?P<f>.+?)"),
+                    Some('l') => regex.push_str(r"(?P<l>\d+)"),
+                    Some('c') => regex.push_str(r"(?P<c>\d+)"),
+                    Some('t') => regex.push_str(r"(?P<t>[A-Za-z]+)"),
+                    Some('m') => regex.push_str(r"(?P<m>.*)"),
+                    Some(other) => regex.push_str(&regex::escape(&other.to_string())),
+                    None => regex.push('%')
+                }
+            }
+
+            regex.push('$');
+            Regex::new(&regex).ok()
+        })
+        .collect()
+}
+
+/// The entries one line of `:make` output gives: cargo's JSON messages
+/// are read as such, other lines matched against `formats`.
+pub fn parse_output_line(line: &str, formats: &[Regex]) -> Vec<QuickfixEntry> {
+    if line.starts_with('{')
+        && let Ok(value) = serde_json::from_str::<Value>(line)
+    {
+        return parse_cargo_message(&value);
+    }
+
+    let Some(captures) = formats.iter().find_map(|format| format.captures(line)) else {
+        return vec![];
+    };
+
+    let Some(path) = captures.name("f") else {
+        return vec![];
+    };
+
+    let number = |name: &str| captures.name(name).and_then(|found| found.as_str().parse::<usize>().ok()).unwrap_or(1);
+
+    vec![QuickfixEntry {
+        path: PathBuf::from(path.as_str()),
+        pos: Position::new(number("l").saturating_sub(1), number("c").saturating_sub(1)),
+        message: captures.name("m").map(|found| found.as_str().trim().to_string()).unwrap_or_default(),
+        severity: captures.name("t").map(|found| Severity::parse(found.as_str())).unwrap_or(Severity::Error)
+    }]
+}
+
+/// The entries for a `compiler-message` from `cargo --message-format=json`:
+/// the diagnostic at its primary span, then its notes and help that point
+/// somewhere.
+fn parse_cargo_message(value: &Value) -> Vec<QuickfixEntry> {
+    if value["reason"] != "compiler-message" {
+        return vec![];
+    }
+
+    let message = &value["message"];
+    let children = message["children"].as_array().into_iter().flatten();
+
+    std::iter::once(message)
+        .chain(children)
+        .filter_map(diagnostic_entry)
+        .collect()
+}
+
+fn diagnostic_entry(diagnostic: &Value) -> Option<QuickfixEntry> {
+    let spans = diagnostic["spans"].as_array()?;
+    let span = spans.iter().find(|span| span["is_primary"] == true).or(spans.first())?;
+    let number = |name: &str| span[name].as_u64().map(|number| number as usize).unwrap_or(1);
+
+    Some(QuickfixEntry {
+        path: PathBuf::from(span["file_name"].as_str()?),
+        pos: Position::new(number("line_start").saturating_sub(1), number("column_start").saturating_sub(1)),
+        message: diagnostic["message"].as_str()?.to_string(),
+        severity: Severity::parse(diagnostic["level"].as_str().unwrap_or_default())
+    })
+}
+
+/// `command` asking cargo for JSON diagnostics if it is a cargo command
+/// that compiles and doesn't already choose a message format.
+pub fn cargo_json_command(command: &str) -> String {
+    let mut words = command.split_whitespace();
+
+    if words.next() != Some("cargo") || command.contains("--message-format") {
+        return command.to_string();
+    }
+
+    match words.next() {
+        Some(subcommand) if CARGO_COMMANDS.contains(&subcommand) => {
+            let rest: Vec<&str> = words.collect();
+            format!("cargo {} --message-format=json {}", subcommand, rest.join(" ")).trim_end().to_string()
+        },
+        _ => command.to_string()
+    }
+}
+
+fn shell(command: &str) -> Command {
+    let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let mut shell = Command::new(shell);
+    shell.args([flag, command]);
+    shell
+}
+
+/// Starts `command` in the shell, with threads sending back its output a
+/// line at a time and then its exit code.
+fn spawn(command: &str) -> io::Result<Receiver<MakeOutput>> {
+    let mut child = shell(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let (sender, receiver) = mpsc::channel();
+    let readers = [
+        child.stdout.take().map(|stdout| read_lines(stdout, sender.clone())),
+        child.stderr.take().map(|stderr| read_lines(stderr, sender.clone()))
+    ];
+
+    thread::spawn(move || {
+        for reader in readers.into_iter().flatten() {
+            let _ = reader.join();
+        }
+
+        let status = child.wait().ok().and_then(|status| status.code());
+        let _ = sender.send(MakeOutput::Done(status));
+    });
+
+    Ok(receiver)
+}
+
+fn read_lines(from: impl Read + Send + 'static, sender: Sender<MakeOutput>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        for line in BufReader::new(from).split(b'\n').map_while(Result::ok) {
+            let line = String::from_utf8_lossy(&line).trim_end_matches('\r').to_string();
+
+            if sender.send(MakeOutput::Line(line)).is_err() {
+                break;
+            }
+        }
+    })
+}
+
+impl Editor {
+    /// `:make [args]` and `:lmake`: runs `makeprg` with `args` in the
+    /// background, putting the errors `errorformat` or cargo's JSON finds
+    /// in the quickfix list or the window's location list. Once it is done
+    /// the first error is jumped to, unless `jump` is off (`:make!`).
+    pub fn make(&mut self, args: &str, kind: ListKind, jump: bool) -> Result<(), EditorError> {
+        if self.make.is_some() {
+            return Err(EditorError::MakeRunning);
+        }
+
+        let command = match args.trim() {
+            "" => self.options.global.make_program.clone(),
+            args => format!("{} {}", self.options.global.make_program, args)
+        };
+
+        let output = spawn(&cargo_json_command(&command)).map_err(|err| EditorError::MakeFailed(command.clone(), err.to_string()))?;
+
+        self.set_list(kind, QuickfixList::new(&command, vec![]));
+        self.messages.show(format!(":!{}", command));
+
+        self.make = Some(MakeJob {
+            formats: compile_error_format(&self.options.global.error_format),
+            command,
+            kind,
+            window: self.current_window,
+            jump,
+            output
+        });
+
+        Ok(())
+    }
+
+    /// Takes in the output of a running `:make`, returning whether anything
+    /// came.
+    pub fn poll_make(&mut self) -> bool {
+        let Some(job) = self.make.as_mut() else {
+            return false;
+        };
+
+        let mut entries = vec![];
+        let mut done = None;
+
+        loop {
+            match job.output.try_recv() {
+                Ok(MakeOutput::Line(line)) => entries.extend(parse_output_line(&line, &job.formats)),
+                Ok(MakeOutput::Done(status)) => {
+                    done = Some(status);
+                    break;
+                },
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    done = Some(None);
+                    break;
+                }
+            }
+        }
+
+        let changed = !entries.is_empty() || done.is_some();
+        let (kind, window) = (job.kind, job.window);
+
+        if let Some(list) = self.list_mut(kind, window) {
+            list.entries.extend(entries);
+        }
+
+        if let Some(status) = done
+            && let Some(job) = self.make.take()
+        {
+            self.finish_make(job, status);
+        }
+
+        changed
+    }
+
+    fn finish_make(&mut self, job: MakeJob, status: Option<i32>) {
+        let entries = self.list_mut(job.kind, job.window).map(|list| list.entries.clone()).unwrap_or_default();
+        let errors = entries.iter().filter(|entry| entry.severity == Severity::Error).count();
+        let warnings = entries.iter().filter(|entry| entry.severity == Severity::Warning).count();
+
+        if job.jump && !entries.is_empty() && job.window == self.current_window {
+            let first = entries.iter().position(|entry| entry.severity == Severity::Error).unwrap_or(0);
+
+            if self.jump_to_entry(job.kind, first).is_ok() {
+                return;
+            }
+        }
+
+        let summary = format!("{}: {} errors, {} warnings", job.command, errors, warnings);
+
+        match status {
+            Some(0) => self.messages.info(summary),
+            Some(code) => self.messages.warn(format!("{} (exit code {})", summary, code)),
+            None => self.messages.warn(format!("{} (stopped)", summary))
+        }
+    }
+}
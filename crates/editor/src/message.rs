@@ -0,0 +1,132 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How many messages `:messages` keeps.
+pub const MESSAGE_HISTORY: usize = 200;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageLevel {
+    Info,
+    Warning,
+    Error
+}
+
+impl MessageLevel {
+    /// How long a one-line message of this level stays up. Errors stay
+    /// longest so a key pressed straight after doesn't hide them.
+    pub fn timeout(self) -> Duration {
+        match self {
+            MessageLevel::Info => Duration::from_secs(4),
+            MessageLevel::Warning => Duration::from_secs(6),
+            MessageLevel::Error => Duration::from_secs(10)
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Message {
+    pub level: MessageLevel,
+    pub text: String,
+    pub shown_at: Instant
+}
+
+impl Message {
+    pub fn new(level: MessageLevel, text: String) -> Self {
+        Self { level, text, shown_at: Instant::now() }
+    }
+
+    /// Whether the message needs more than the status line, as listings do.
+    pub fn is_multiline(&self) -> bool {
+        self.text.contains('\n')
+    }
+}
+
+/// The message in the status area and the ones shown before it.
+#[derive(Clone, Debug, Default)]
+pub struct Messages {
+    current: Option<Message>,
+    history: VecDeque<Message>
+}
+
+impl Messages {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn info(&mut self, text: impl ToString) {
+        self.push(MessageLevel::Info, text.to_string());
+    }
+
+    pub fn warn(&mut self, text: impl ToString) {
+        self.push(MessageLevel::Warning, text.to_string());
+    }
+
+    pub fn error(&mut self, text: impl ToString) {
+        self.push(MessageLevel::Error, text.to_string());
+    }
+
+    /// Shows `text` and records it for `:messages`.
+    pub fn push(&mut self, level: MessageLevel, text: String) {
+        let message = Message::new(level, text);
+
+        if self.history.len() == MESSAGE_HISTORY {
+            self.history.pop_front();
+        }
+
+        self.history.push_back(message.clone());
+        self.current = Some(message);
+    }
+
+    /// Shows `text` without recording it, for output such as option and
+    /// mapping listings.
+    pub fn show(&mut self, text: impl ToString) {
+        self.current = Some(Message::new(MessageLevel::Info, text.to_string()));
+    }
+
+    pub fn current(&self) -> Option<&Message> {
+        self.current.as_ref()
+    }
+
+    pub fn text(&self) -> Option<&str> {
+        self.current.as_ref().map(|message| message.text.as_str())
+    }
+
+    /// Takes the message off screen, returning its text.
+    pub fn take(&mut self) -> Option<String> {
+        self.current.take().map(|message| message.text)
+    }
+
+    pub fn clear(&mut self) {
+        self.current = None;
+    }
+
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    pub fn history(&self) -> impl Iterator<Item = &Message> {
+        self.history.iter()
+    }
+
+    /// Clears what a key press should: information and listings. Warnings
+    /// and errors stay until their timeout.
+    pub fn dismiss(&mut self) {
+        if self.current.as_ref().is_some_and(|message| message.level == MessageLevel::Info || message.is_multiline()) {
+            self.current = None;
+        }
+    }
+
+    /// How long until the message times out; `None` if there is none or it
+    /// waits for a key.
+    pub fn remaining(&self) -> Option<Duration> {
+        let message = self.current.as_ref().filter(|message| !message.is_multiline())?;
+        Some(message.level.timeout().saturating_sub(message.shown_at.elapsed()))
+    }
+
+    /// Clears the message if it has been up for its timeout.
+    pub fn expire(&mut self) {
+        if self.remaining().is_some_and(|remaining| remaining.is_zero()) {
+            self.current = None;
+        }
+    }
+}
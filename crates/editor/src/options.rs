@@ -0,0 +1,698 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use buffer::filetype;
+use toml::{Table, Value};
+use uuid::Uuid;
+
+use crate::editor::Editor;
+use crate::errors::EditorError;
+use crate::keymap::{Keymap, MapMode};
+use crate::theme::config_dir;
+
+/// The per-project configuration file, looked for in the working directory
+/// and its parents.
+pub const PROJECT_CONFIG: &str = ".paw.toml";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OptionValue {
+    Bool(bool),
+    Number(usize),
+    Text(String)
+}
+
+impl fmt::Display for OptionValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OptionValue::Bool(value) => write!(f, "{}", value),
+            OptionValue::Number(value) => write!(f, "{}", value),
+            OptionValue::Text(value) => write!(f, "{}", value)
+        }
+    }
+}
+
+/// Options that apply to the whole editor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GlobalOptions {
+    pub ignore_case: bool,
+    pub smart_case: bool,
+    pub color_scheme: String,
+    /// What `<leader>` stands for in mappings.
+    pub leader: String,
+    /// Whether keys that could start a longer mapping stop waiting for
+    /// the rest after `timeout_len` milliseconds.
+    pub timeout: bool,
+    pub timeout_len: usize,
+    /// What the bar at the top lists: `buffers` or `tabs` (tab pages).
+    pub top_bar: String,
+    /// The command `:make` runs.
+    pub make_program: String,
+    /// How `:make` finds errors in lines of output: patterns separated by
+    /// commas, with `%f` for the file, `%l` the line, `%c` the column, `%t`
+    /// the kind (`error`, `warning`...) and `%m` the message.
+    pub error_format: String,
+    /// How many columns the file explorer takes up.
+    pub explorer_width: usize
+}
+
+impl Default for GlobalOptions {
+    fn default() -> Self {
+        Self {
+            ignore_case: true,
+            smart_case: true,
+            color_scheme: "default".to_string(),
+            leader: "\\".to_string(),
+            timeout: true,
+            timeout_len: 1000,
+            top_bar: "buffers".to_string(),
+            make_program: "cargo build".to_string(),
+            error_format: "%f:%l:%c: %t: %m,%f:%l:%c: %m,%f:%l: %m".to_string(),
+            explorer_width: 30
+        }
+    }
+}
+
+/// Options each buffer has its own value of.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BufferOptions {
+    pub tab_stop: usize,
+    pub shift_width: usize,
+    pub expand_tab: bool,
+    pub auto_indent: bool,
+    pub auto_pairs: bool
+}
+
+impl Default for BufferOptions {
+    fn default() -> Self {
+        Self {
+            tab_stop: 8,
+            shift_width: 4,
+            expand_tab: true,
+            auto_indent: true,
+            auto_pairs: true
+        }
+    }
+}
+
+/// Options each window has its own value of.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowOptions {
+    pub scroll_off: usize,
+    pub side_scroll_off: usize,
+    pub wrap: bool,
+    pub line_break: bool,
+    pub show_break: String,
+    pub number: bool
+}
+
+impl Default for WindowOptions {
+    fn default() -> Self {
+        Self {
+            scroll_off: 5,
+            side_scroll_off: 0,
+            wrap: true,
+            line_break: false,
+            show_break: String::new(),
+            number: true
+        }
+    }
+}
+
+/// A mutable view of one option's value.
+pub enum OptionRef<'a> {
+    Bool(&'a mut bool),
+    Number(&'a mut usize),
+    Text(&'a mut String)
+}
+
+impl OptionRef<'_> {
+    fn get(&self) -> OptionValue {
+        match self {
+            OptionRef::Bool(value) => OptionValue::Bool(**value),
+            OptionRef::Number(value) => OptionValue::Number(**value),
+            OptionRef::Text(value) => OptionValue::Text(value.to_string())
+        }
+    }
+
+    fn set(&mut self, value: OptionValue) {
+        match (self, value) {
+            (OptionRef::Bool(field), OptionValue::Bool(value)) => **field = value,
+            (OptionRef::Number(field), OptionValue::Number(value)) => **field = value,
+            (OptionRef::Text(field), OptionValue::Text(value)) => **field = value,
+            _ => {}
+        }
+    }
+}
+
+/// Where an option's value lives.
+#[derive(Clone, Copy)]
+pub enum OptionField {
+    Global(for<'a> fn(&'a mut GlobalOptions) -> OptionRef<'a>),
+    Buffer(for<'a> fn(&'a mut BufferOptions) -> OptionRef<'a>),
+    Window(for<'a> fn(&'a mut WindowOptions) -> OptionRef<'a>),
+    /// The buffer's filetype, which the buffer itself keeps.
+    Filetype
+}
+
+#[derive(Clone, Copy)]
+pub struct OptionDef {
+    pub name: &'static str,
+    pub short: Option<&'static str>,
+    pub field: OptionField,
+    /// The range a number option must be in.
+    pub range: (usize, usize)
+}
+
+impl OptionDef {
+    const fn new(name: &'static str, short: Option<&'static str>, field: OptionField) -> Self {
+        Self { name, short, field, range: (0, usize::MAX) }
+    }
+
+    const fn range(self, min: usize, max: usize) -> Self {
+        Self { range: (min, max), ..self }
+    }
+
+    pub fn is_bool(&self) -> bool {
+        matches!(self.default_value(), OptionValue::Bool(_))
+    }
+
+    pub fn default_value(&self) -> OptionValue {
+        match self.field {
+            OptionField::Global(field) => field(&mut GlobalOptions::default()).get(),
+            OptionField::Buffer(field) => field(&mut BufferOptions::default()).get(),
+            OptionField::Window(field) => field(&mut WindowOptions::default()).get(),
+            OptionField::Filetype => OptionValue::Text(String::new())
+        }
+    }
+
+    /// Checks `value` has the option's type and is in range.
+    fn validate(&self, value: OptionValue) -> Result<OptionValue, EditorError> {
+        let invalid = |message: String| EditorError::InvalidOptionValue(self.name.to_string(), message);
+        let (min, max) = self.range;
+
+        match (self.default_value(), value) {
+            (OptionValue::Number(_), OptionValue::Number(value)) if value < min || value > max => match max {
+                usize::MAX => Err(invalid(format!("must be at least {}", min))),
+                _ => Err(invalid(format!("must be between {} and {}", min, max)))
+            },
+            (OptionValue::Bool(_), value @ OptionValue::Bool(_))
+            | (OptionValue::Number(_), value @ OptionValue::Number(_))
+            | (OptionValue::Text(_), value @ OptionValue::Text(_)) => Ok(value),
+            (OptionValue::Bool(_), _) => Err(invalid("expected true or false".to_string())),
+            (OptionValue::Number(_), _) => Err(invalid("number required".to_string())),
+            (OptionValue::Text(_), _) => Err(invalid("string required".to_string()))
+        }
+    }
+
+    /// Parses the text after `=` in `:set`.
+    fn parse(&self, text: &str) -> Result<OptionValue, EditorError> {
+        let value = match self.default_value() {
+            OptionValue::Bool(_) => return Err(EditorError::InvalidArgument(format!("{}={}", self.name, text))),
+            OptionValue::Number(_) => text
+                .parse()
+                .map(OptionValue::Number)
+                .map_err(|_| EditorError::InvalidOptionValue(self.name.to_string(), format!("number required: {}", text)))?,
+            OptionValue::Text(_) => OptionValue::Text(text.to_string())
+        };
+
+        self.validate(value)
+    }
+}
+
+impl fmt::Debug for OptionDef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OptionDef").field("name", &self.name).finish_non_exhaustive()
+    }
+}
+
+pub const OPTIONS: &[OptionDef] = &[
+    OptionDef::new("ignorecase", Some("ic"), OptionField::Global(|o| OptionRef::Bool(&mut o.ignore_case))),
+    OptionDef::new("smartcase", Some("scs"), OptionField::Global(|o| OptionRef::Bool(&mut o.smart_case))),
+    OptionDef::new("colorscheme", Some("colo"), OptionField::Global(|o| OptionRef::Text(&mut o.color_scheme))),
+    OptionDef::new("leader", None, OptionField::Global(|o| OptionRef::Text(&mut o.leader))),
+    OptionDef::new("timeout", Some("to"), OptionField::Global(|o| OptionRef::Bool(&mut o.timeout))),
+    OptionDef::new("timeoutlen", Some("tm"), OptionField::Global(|o| OptionRef::Number(&mut o.timeout_len))),
+    OptionDef::new("topbar", None, OptionField::Global(|o| OptionRef::Text(&mut o.top_bar))),
+    OptionDef::new("makeprg", Some("mp"), OptionField::Global(|o| OptionRef::Text(&mut o.make_program))),
+    OptionDef::new("errorformat", Some("efm"), OptionField::Global(|o| OptionRef::Text(&mut o.error_format))),
+    OptionDef::new("explorerwidth", None, OptionField::Global(|o| OptionRef::Number(&mut o.explorer_width))).range(10, 200),
+    OptionDef::new("tabstop", Some("ts"), OptionField::Buffer(|o| OptionRef::Number(&mut o.tab_stop))).range(1, 64),
+    OptionDef::new("shiftwidth", Some("sw"), OptionField::Buffer(|o| OptionRef::Number(&mut o.shift_width))).range(1, 64),
+    OptionDef::new("expandtab", Some("et"), OptionField::Buffer(|o| OptionRef::Bool(&mut o.expand_tab))),
+    OptionDef::new("autoindent", Some("ai"), OptionField::Buffer(|o| OptionRef::Bool(&mut o.auto_indent))),
+    OptionDef::new("autopairs", Some("ap"), OptionField::Buffer(|o| OptionRef::Bool(&mut o.auto_pairs))),
+    OptionDef::new("filetype", Some("ft"), OptionField::Filetype),
+    OptionDef::new("scrolloff", Some("so"), OptionField::Window(|o| OptionRef::Number(&mut o.scroll_off))),
+    OptionDef::new("sidescrolloff", Some("siso"), OptionField::Window(|o| OptionRef::Number(&mut o.side_scroll_off))),
+    OptionDef::new("wrap", None, OptionField::Window(|o| OptionRef::Bool(&mut o.wrap))),
+    OptionDef::new("linebreak", Some("lbr"), OptionField::Window(|o| OptionRef::Bool(&mut o.line_break))),
+    OptionDef::new("showbreak", Some("sbr"), OptionField::Window(|o| OptionRef::Text(&mut o.show_break))),
+    OptionDef::new("number", Some("nu"), OptionField::Window(|o| OptionRef::Bool(&mut o.number)))
+];
+
+pub fn find_option(name: &str) -> Option<&'static OptionDef> {
+    OPTIONS.iter().find(|def| def.name == name || def.short == Some(name))
+}
+
+/// A buffer's options, with the filetype they were set up for.
+#[derive(Clone, Debug)]
+struct LocalBufferOptions {
+    filetype: Option<String>,
+    options: BufferOptions
+}
+
+/// The values of every option. `:set` changes both the value in use and
+/// the default new buffers and windows start with; `:setlocal` only the
+/// value in use.
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    pub global: GlobalOptions,
+    pub buffer: BufferOptions,
+    pub window: WindowOptions,
+    /// Buffer option values from `[filetype.<name>]` tables in the config,
+    /// applied after the filetype's own conventions.
+    pub filetypes: HashMap<String, Vec<(&'static OptionDef, OptionValue)>>,
+    buffers: HashMap<Uuid, LocalBufferOptions>
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The options of buffer `id`, or the defaults if it has none yet.
+    pub fn buffer_options(&self, id: Option<Uuid>) -> &BufferOptions {
+        id.and_then(|id| self.buffers.get(&id)).map(|local| &local.options).unwrap_or(&self.buffer)
+    }
+
+    /// Gives buffer `id` fresh options for `filetype` if it has none or
+    /// they were set up for another filetype: the defaults, then the
+    /// filetype's indent convention, then the config's filetype table.
+    pub fn sync_buffer(&mut self, id: Uuid, filetype: Option<&str>) {
+        if self.buffers.get(&id).is_some_and(|local| local.filetype.as_deref() == filetype) {
+            return;
+        }
+
+        let mut options = self.buffer.clone();
+
+        if let Some(info) = filetype.and_then(filetype::info)
+            && let Some((shift_width, expand_tab)) = info.indent
+        {
+            options.shift_width = shift_width;
+            options.expand_tab = expand_tab;
+
+            if !expand_tab {
+                options.tab_stop = shift_width;
+            }
+        }
+
+        for (def, value) in filetype.and_then(|filetype| self.filetypes.get(filetype)).into_iter().flatten() {
+            if let OptionField::Buffer(field) = def.field {
+                field(&mut options).set(value.clone());
+            }
+        }
+
+        self.buffers.insert(id, LocalBufferOptions { filetype: filetype.map(String::from), options });
+    }
+
+    pub fn remove_buffer(&mut self, id: Uuid) {
+        self.buffers.remove(&id);
+    }
+
+    fn local_buffer_mut(&mut self, id: Option<Uuid>) -> Option<&mut BufferOptions> {
+        id.and_then(|id| self.buffers.get_mut(&id)).map(|local| &mut local.options)
+    }
+}
+
+/// What one `:set` argument asks for.
+enum Assignment {
+    Show,
+    Set(OptionValue),
+    Toggle,
+    Reset,
+    Add(String),
+    Subtract(String)
+}
+
+/// Splits `:set` arguments on whitespace, except where escaped with a
+/// backslash as in `:set showbreak=>\ `.
+fn split_args(args: &str) -> Vec<String> {
+    let mut parts = vec![];
+    let mut current = String::new();
+    let mut chars = args.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => current.extend(chars.next()),
+            c if c.is_whitespace() => {
+                if !current.is_empty() {
+                    parts.push(std::mem::take(&mut current));
+                }
+            },
+            c => current.push(c)
+        }
+    }
+
+    if !current.is_empty() {
+        parts.push(current);
+    }
+
+    parts
+}
+
+fn parse_arg(arg: &str) -> Result<(&'static OptionDef, Assignment), EditorError> {
+    let unknown = |name: &str| EditorError::UnknownOption(name.to_string());
+
+    if let Some(index) = arg.find(['=', ':']) {
+        let (name, value) = (&arg[.. index], &arg[index + 1 ..]);
+
+        let (name, assignment) = if let Some(name) = name.strip_suffix('+') {
+            (name, Assignment::Add(value.to_string()))
+        } else if let Some(name) = name.strip_suffix('-') {
+            (name, Assignment::Subtract(value.to_string()))
+        } else {
+            let def = find_option(name).ok_or_else(|| unknown(name))?;
+            return Ok((def, Assignment::Set(def.parse(value)?)));
+        };
+
+        return Ok((find_option(name).ok_or_else(|| unknown(name))?, assignment));
+    }
+
+    if let Some(name) = arg.strip_suffix('?') {
+        return Ok((find_option(name).ok_or_else(|| unknown(name))?, Assignment::Show));
+    }
+
+    if let Some(name) = arg.strip_suffix('&') {
+        return Ok((find_option(name).ok_or_else(|| unknown(name))?, Assignment::Reset));
+    }
+
+    if let Some(name) = arg.strip_suffix('!').or_else(|| arg.strip_prefix("inv").filter(|name| find_option(name).is_some())) {
+        let def = find_option(name).ok_or_else(|| unknown(name))?;
+
+        return match def.is_bool() {
+            true => Ok((def, Assignment::Toggle)),
+            false => Err(EditorError::InvalidArgument(arg.to_string()))
+        };
+    }
+
+    if let Some(def) = find_option(arg) {
+        return match def.is_bool() {
+            true => Ok((def, Assignment::Set(OptionValue::Bool(true)))),
+            false => Ok((def, Assignment::Show))
+        };
+    }
+
+    if let Some(name) = arg.strip_prefix("no")
+        && let Some(def) = find_option(name)
+    {
+        return match def.is_bool() {
+            true => Ok((def, Assignment::Set(OptionValue::Bool(false)))),
+            false => Err(EditorError::InvalidArgument(arg.to_string()))
+        };
+    }
+
+    Err(unknown(arg))
+}
+
+/// How `:set` shows an option: `wrap`/`nowrap` or `name=value`.
+fn show(def: &OptionDef, value: &OptionValue) -> String {
+    match value {
+        OptionValue::Bool(true) => def.name.to_string(),
+        OptionValue::Bool(false) => format!("no{}", def.name),
+        value => format!("{}={}", def.name, value)
+    }
+}
+
+/// The number after `+=` or `-=`.
+fn amount(def: &OptionDef, text: &str) -> Result<usize, EditorError> {
+    text.parse().map_err(|_| EditorError::InvalidOptionValue(def.name.to_string(), format!("number required: {}", text)))
+}
+
+impl Editor {
+    /// The options of the current buffer.
+    pub fn buffer_options(&self) -> &BufferOptions {
+        self.options.buffer_options(self.current_buffer)
+    }
+
+    /// Sets up the current buffer's options again if its filetype changed
+    /// since they were, so filetype conventions follow `:set ft` and
+    /// `:saveas` the way they follow opening a file.
+    pub fn sync_buffer_options(&mut self) {
+        if let Some(id) = self.current_buffer {
+            let filetype = self.buffers.get(&id).and_then(|buffer| buffer.filetype()).map(String::from);
+            self.options.sync_buffer(id, filetype.as_deref());
+        }
+    }
+
+    pub fn get_option(&self, def: &OptionDef) -> OptionValue {
+        match def.field {
+            OptionField::Global(field) => field(&mut self.options.global.clone()).get(),
+            OptionField::Buffer(field) => field(&mut self.buffer_options().clone()).get(),
+            OptionField::Window(field) => field(&mut self.window_options.clone()).get(),
+            OptionField::Filetype => OptionValue::Text(
+                self.get_current_buffer().and_then(|buffer| buffer.filetype()).unwrap_or("").to_string()
+            )
+        }
+    }
+
+    /// Sets an option's value in use and, unless `local`, the default new
+    /// buffers and windows get. Global options have only one value.
+    pub fn set_option(&mut self, def: &OptionDef, value: OptionValue, local: bool) -> Result<(), EditorError> {
+        let value = def.validate(value)?;
+
+        match def.field {
+            OptionField::Global(field) => {
+                if def.name == "colorscheme"
+                    && let OptionValue::Text(name) = &value
+                {
+                    self.set_theme(name)?;
+                }
+
+                if def.name == "topbar"
+                    && let OptionValue::Text(name) = &value
+                    && name != "buffers" && name != "tabs"
+                {
+                    return Err(EditorError::InvalidOptionValue(def.name.to_string(), "expected buffers or tabs".to_string()));
+                }
+
+                field(&mut self.options.global).set(value);
+
+                if def.name == "leader" {
+                    self.input.defaults = Keymap::defaults(&self.options.global.leader);
+                }
+            },
+            OptionField::Buffer(field) => {
+                self.sync_buffer_options();
+
+                if let Some(options) = self.options.local_buffer_mut(self.current_buffer) {
+                    field(options).set(value.clone());
+                }
+
+                if !local {
+                    field(&mut self.options.buffer).set(value);
+                }
+            },
+            OptionField::Window(field) => {
+                field(&mut self.window_options).set(value.clone());
+
+                if !local {
+                    field(&mut self.options.window).set(value);
+                }
+            },
+            OptionField::Filetype => {
+                let OptionValue::Text(filetype) = value else {
+                    return Ok(());
+                };
+
+                let buffer = self.get_current_buffer_mut().ok_or(EditorError::NoBuffer)?;
+                buffer.set_filetype((!filetype.is_empty()).then_some(filetype));
+                self.sync_buffer_options();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs `:set` (or `:setlocal` with `local`) arguments, showing the
+    /// values asked for in the message area. With no arguments it shows
+    /// every option not at its default.
+    pub fn set_options(&mut self, args: &str, local: bool) -> Result<(), EditorError> {
+        let args = split_args(args);
+        let mut shown = vec![];
+
+        if args.is_empty() {
+            for def in OPTIONS {
+                let value = self.get_option(def);
+
+                if value != def.default_value() {
+                    shown.push(show(def, &value));
+                }
+            }
+        }
+
+        for arg in &args {
+            let (def, assignment) = parse_arg(arg)?;
+            let current = self.get_option(def);
+
+            let value = match (assignment, &current) {
+                (Assignment::Show, value) => {
+                    shown.push(show(def, value));
+                    continue;
+                },
+                (Assignment::Set(value), _) => value,
+                (Assignment::Toggle, OptionValue::Bool(value)) => OptionValue::Bool(!value),
+                (Assignment::Reset, _) => def.default_value(),
+                (Assignment::Add(text), OptionValue::Text(value)) => OptionValue::Text(format!("{}{}", value, text)),
+                // The amount is any number; `set_option` checks the result
+                // is in range.
+                (Assignment::Add(text), OptionValue::Number(value)) => OptionValue::Number(value.saturating_add(amount(def, &text)?)),
+                (Assignment::Subtract(text), OptionValue::Number(value)) => OptionValue::Number(value.saturating_sub(amount(def, &text)?)),
+                _ => return Err(EditorError::InvalidArgument(arg.clone()))
+            };
+
+            self.set_option(def, value, local)?;
+        }
+
+        if !shown.is_empty() {
+            self.messages.show(shown.join("  "));
+        }
+
+        Ok(())
+    }
+
+    /// Applies a config file: top-level keys set options as `:set` does,
+    /// and `[filetype.<name>]` tables set buffer options for that filetype.
+    pub fn load_config(&mut self, path: &Path) -> Result<(), EditorError> {
+        let text = fs::read_to_string(path).map_err(|err| config_error(path, err.to_string()))?;
+        let table: Table = text.parse().map_err(|err: toml::de::Error| config_error(path, err.message().to_string()))?;
+
+        let mut keys = None;
+
+        for (key, value) in &table {
+            if key == "keys" {
+                keys = Some(value);
+                continue;
+            }
+
+            if key == "filetype" {
+                let Value::Table(filetypes) = value else {
+                    return Err(config_error(path, "filetype must be a table".to_string()));
+                };
+
+                for (filetype, options) in filetypes {
+                    let Value::Table(options) = options else {
+                        return Err(config_error(path, format!("filetype.{} must be a table", filetype)));
+                    };
+
+                    let values = options
+                        .iter()
+                        .map(|(key, value)| config_option(path, key, value))
+                        .collect::<Result<Vec<_>, _>>()?;
+
+                    if let Some((def, _)) = values.iter().find(|(def, _)| !matches!(def.field, OptionField::Buffer(_))) {
+                        return Err(config_error(path, format!("{} is not a buffer option", def.name)));
+                    }
+
+                    self.options.filetypes.entry(filetype.clone()).or_default().extend(values);
+                }
+
+                continue;
+            }
+
+            let (def, value) = config_option(path, key, value)?;
+            self.set_option(def, value, false).map_err(|err| config_error(path, err.to_string()))?;
+        }
+
+        // Mappings come after the options so `<leader>` means the leader
+        // the file sets.
+        if let Some(keys) = keys {
+            self.load_config_keys(path, keys)?;
+        }
+
+        self.options.buffers.clear();
+        self.sync_buffer_options();
+
+        Ok(())
+    }
+
+    /// Loads `config.toml` from the config directory and then the nearest
+    /// `.paw.toml` at or above the working directory, so project settings
+    /// win. A missing file is not an error; the first broken one is.
+    pub fn load_configs(&mut self) -> Result<(), EditorError> {
+        let project = std::env::current_dir()
+            .ok()
+            .and_then(|dir| dir.ancestors().map(|dir| dir.join(PROJECT_CONFIG)).find(|path| path.is_file()));
+
+        let paths: Vec<PathBuf> = config_dir()
+            .map(|dir| dir.join("config.toml"))
+            .filter(|path| path.is_file())
+            .into_iter()
+            .chain(project)
+            .collect();
+
+        for path in paths {
+            self.load_config(&path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads `[keys.normal]`, `[keys.insert]` and `[keys.visual]`, each
+    /// mapping keys to a command name or other keys. A string maps without
+    /// remapping; `{ keys = "...", remap = true }` lets other mappings apply.
+    fn load_config_keys(&mut self, path: &Path, keys: &Value) -> Result<(), EditorError> {
+        let Value::Table(modes) = keys else {
+            return Err(config_error(path, "keys must be a table".to_string()));
+        };
+
+        for (mode, mappings) in modes {
+            let map_mode = MapMode::from_name(mode).ok_or_else(|| config_error(path, format!("Unknown mode: keys.{}", mode)))?;
+
+            let Value::Table(mappings) = mappings else {
+                return Err(config_error(path, format!("keys.{} must be a table", mode)));
+            };
+
+            for (lhs, rhs) in mappings {
+                let (rhs, noremap) = match rhs {
+                    Value::String(rhs) => (rhs.as_str(), true),
+                    Value::Table(table) => match (table.get("keys"), table.get("remap")) {
+                        (Some(Value::String(rhs)), None | Some(Value::Boolean(_))) => {
+                            let remap = table.get("remap").and_then(Value::as_bool).unwrap_or(false);
+                            (rhs.as_str(), !remap)
+                        },
+                        _ => return Err(config_error(path, format!("Invalid mapping for {} in keys.{}", lhs, mode)))
+                    },
+                    _ => return Err(config_error(path, format!("Invalid mapping for {} in keys.{}", lhs, mode)))
+                };
+
+                self.map_keys(&[map_mode], lhs, rhs, noremap).map_err(|err| config_error(path, err.to_string()))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn config_option(path: &Path, key: &str, value: &Value) -> Result<(&'static OptionDef, OptionValue), EditorError> {
+    let def = find_option(key).ok_or_else(|| config_error(path, format!("Unknown option: {}", key)))?;
+
+    let value = match value {
+        Value::Boolean(value) => OptionValue::Bool(*value),
+        Value::Integer(value) => match usize::try_from(*value) {
+            Ok(value) => OptionValue::Number(value),
+            Err(_) => return Err(config_error(path, format!("Invalid value for {}: {}", def.name, value)))
+        },
+        Value::String(value) => OptionValue::Text(value.clone()),
+        value => return Err(config_error(path, format!("Invalid value for {}: {}", def.name, value)))
+    };
+
+    def.validate(value)
+        .map(|value| (def, value))
+        .map_err(|err| config_error(path, err.to_string()))
+}
+
+fn config_error(path: &Path, message: String) -> EditorError {
+    EditorError::InvalidConfig(path.display().to_string(), message)
+}
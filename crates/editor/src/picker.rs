@@ -0,0 +1,377 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::thread;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use uuid::Uuid;
+
+use utils::Position;
+
+use crate::editor::Editor;
+use crate::fuzzy::FuzzyMatcher;
+use crate::prompt::{Prompt, PromptEvent};
+use crate::quickfix::ListKind;
+use crate::substitute::Substitution;
+
+/// The fewest items worth giving a thread of their own when matching.
+const CHUNK: usize = 4096;
+
+/// What choosing a picker item opens.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PickerTarget {
+    Buffer(Uuid),
+    File(PathBuf),
+    /// A place in a file, such as a `:grep` match.
+    Location(PathBuf, Position),
+    /// An entry of the quickfix list or the location list, by index.
+    ListEntry(ListKind, usize)
+}
+
+#[derive(Clone, Debug)]
+pub struct PickerItem {
+    /// The text shown and matched against.
+    pub label: String,
+    pub target: PickerTarget
+}
+
+/// A popup list narrowed down by fuzzy matching what is typed.
+#[derive(Debug)]
+pub struct Picker {
+    pub title: String,
+    pub prompt: Prompt,
+    pub items: Vec<PickerItem>,
+    /// The items that match, best first, by index into `items` with their
+    /// scores.
+    pub matches: Vec<(usize, i64)>,
+    /// The highlighted entry of `matches`.
+    pub selected: usize,
+    /// The first lines of the highlighted file, if it is one.
+    pub preview: Option<Preview>,
+    /// The pattern `matches` was made with. Typing more can only narrow
+    /// them down, so then only they are matched again.
+    pattern: String,
+    /// Where more items come from while a background search runs.
+    source: Option<Receiver<Vec<PickerItem>>>,
+    /// The list the places in the items go into once they have all
+    /// arrived.
+    pub list: Option<ListKind>,
+    /// For `:greplace`, the replacement made at the places in the items
+    /// that are still ticked when `<CR>` is pressed.
+    pub replace: Option<Substitution>,
+    /// The items unticked with `<Tab>`, by index into `items`.
+    pub unchecked: HashSet<usize>
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Preview {
+    pub target: PickerTarget,
+    pub lines: Vec<String>,
+    /// The line of `lines` to pick out, the one a location is on.
+    pub line: Option<usize>,
+    /// What that line becomes if a replacement is made there.
+    pub replaced: Option<String>
+}
+
+/// What a key did to a picker.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PickerEvent {
+    Changed,
+    Cancel,
+    Submit(PickerTarget),
+    /// `<CR>` in a picker that acts on the ticked items all at once.
+    Apply
+}
+
+impl Picker {
+    pub fn new(title: &str, items: Vec<PickerItem>) -> Self {
+        let mut picker = Self {
+            title: title.to_string(),
+            prompt: Prompt::new(),
+            items,
+            matches: vec![],
+            selected: 0,
+            preview: None,
+            pattern: String::new(),
+            source: None,
+            list: None,
+            replace: None,
+            unchecked: HashSet::new()
+        };
+
+        picker.filter();
+        picker
+    }
+
+    /// A picker whose items arrive in batches from `source`, such as a
+    /// directory walk running on another thread.
+    pub fn with_source(title: &str, source: Receiver<Vec<PickerItem>>) -> Self {
+        let mut picker = Self::new(title, vec![]);
+        picker.source = Some(source);
+        picker
+    }
+
+    /// Whether items are still arriving.
+    pub fn is_loading(&self) -> bool {
+        self.source.is_some()
+    }
+
+    /// Takes the items that have arrived since the last call. Returns
+    /// whether there were any.
+    pub fn poll(&mut self) -> bool {
+        let Some(source) = &self.source else {
+            return false;
+        };
+
+        let mut items = vec![];
+
+        loop {
+            match source.try_recv() {
+                Ok(batch) => items.extend(batch),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.source = None;
+                    break;
+                }
+            }
+        }
+
+        let changed = !items.is_empty();
+        self.extend(items);
+
+        changed
+    }
+
+    /// Adds `items`, matching them against the prompt, and keeps the same
+    /// entry highlighted.
+    pub fn extend(&mut self, items: Vec<PickerItem>) {
+        if items.is_empty() {
+            return;
+        }
+
+        let selected = self.matches.get(self.selected).map(|(index, _)| *index);
+        let start = self.items.len();
+
+        self.items.extend(items);
+
+        let candidates: Vec<usize> = (start .. self.items.len()).collect();
+        let found = score_items(&self.items, &self.pattern, &candidates);
+
+        self.matches.extend(found);
+        self.sort();
+
+        self.selected = selected
+            .and_then(|selected| self.matches.iter().position(|(index, _)| *index == selected))
+            .unwrap_or(0);
+    }
+
+    /// Matches the items against the prompt, keeping the ones that match
+    /// best first; ties go to shorter labels, then to the original order.
+    /// With nothing typed the items stay in their original order.
+    pub fn filter(&mut self) {
+        let pattern = self.prompt.text.clone();
+
+        let candidates: Vec<usize> = match !self.pattern.is_empty() && pattern.starts_with(&self.pattern) {
+            true => self.matches.iter().map(|(index, _)| *index).collect(),
+            false => (0 .. self.items.len()).collect()
+        };
+
+        self.matches = score_items(&self.items, &pattern, &candidates);
+
+        self.pattern = pattern;
+        self.sort();
+        self.selected = 0;
+    }
+
+    fn sort(&mut self) {
+        let items = &self.items;
+
+        match self.pattern.is_empty() {
+            true => self.matches.sort_unstable_by_key(|(index, _)| *index),
+            false => self.matches.sort_unstable_by_key(|(index, score)| (std::cmp::Reverse(*score), items[*index].label.len(), *index))
+        }
+    }
+
+    /// The characters of item `index` the prompt matched, to highlight.
+    /// Only the items on screen need these, so they aren't kept.
+    pub fn positions(&self, index: usize) -> Vec<usize> {
+        FuzzyMatcher::new(&self.pattern)
+            .find(&self.items[index].label)
+            .map(|found| found.positions)
+            .unwrap_or_default()
+    }
+
+    pub fn selected_item(&self) -> Option<&PickerItem> {
+        self.matches.get(self.selected).map(|(index, _)| &self.items[*index])
+    }
+
+    /// Whether item `index` is ticked, in a picker with ticks.
+    pub fn is_checked(&self, index: usize) -> bool {
+        !self.unchecked.contains(&index)
+    }
+
+    /// The ticked items, in the order they arrived.
+    pub fn checked_items(&self) -> impl Iterator<Item = &PickerItem> {
+        self.items.iter().enumerate().filter(|(index, _)| self.is_checked(*index)).map(|(_, item)| item)
+    }
+
+    fn toggle_selected(&mut self) {
+        if let Some((index, _)) = self.matches.get(self.selected)
+            && !self.unchecked.remove(index)
+        {
+            self.unchecked.insert(*index);
+        }
+    }
+
+    /// Unticks the items that match if they are all ticked, otherwise
+    /// ticks them all.
+    fn toggle_matches(&mut self) {
+        let all = self.matches.iter().all(|(index, _)| self.is_checked(*index));
+
+        for (index, _) in &self.matches {
+            match all {
+                true => self.unchecked.insert(*index),
+                false => self.unchecked.remove(index)
+            };
+        }
+    }
+
+    /// Moves the highlight by `delta` entries, wrapping around.
+    pub fn move_selection(&mut self, delta: isize) {
+        let len = self.matches.len() as isize;
+
+        if len > 0 {
+            self.selected = (self.selected as isize + delta).rem_euclid(len) as usize;
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> PickerEvent {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        if key.code == KeyCode::Enter && self.replace.is_some() {
+            return PickerEvent::Apply;
+        }
+
+        if key.code == KeyCode::Enter {
+            return match self.selected_item() {
+                Some(item) => PickerEvent::Submit(item.target.clone()),
+                None => PickerEvent::Cancel
+            };
+        }
+
+        let checklist = self.replace.is_some();
+
+        match key.code {
+            // With ticks `<Tab>` ticks or unticks the highlighted item on its
+            // way past, as in fzf.
+            KeyCode::Tab | KeyCode::BackTab if checklist => {
+                let back = key.code == KeyCode::BackTab || key.modifiers.contains(KeyModifiers::SHIFT);
+
+                self.toggle_selected();
+                self.move_selection(if back { -1 } else { 1 });
+            },
+            KeyCode::Char('a') if control && checklist => self.toggle_matches(),
+            KeyCode::Up | KeyCode::BackTab => self.move_selection(-1),
+            KeyCode::Tab if key.modifiers.contains(KeyModifiers::SHIFT) => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Tab => self.move_selection(1),
+            KeyCode::Char('p' | 'k') if control => self.move_selection(-1),
+            KeyCode::Char('n' | 'j') if control => self.move_selection(1),
+            KeyCode::Char('c') if control && self.source.is_some() => self.source = None,
+            KeyCode::Backspace if self.prompt.text.is_empty() => {},
+            _ => match self.prompt.handle_key(key) {
+                PromptEvent::Cancel => return PickerEvent::Cancel,
+                PromptEvent::Changed => self.filter(),
+                _ => {}
+            }
+        }
+
+        PickerEvent::Changed
+    }
+}
+
+/// Matches the `candidates` among `items` against `pattern`, keeping the
+/// ones that match with their scores. Long lists are split between threads.
+fn score_items(items: &[PickerItem], pattern: &str, candidates: &[usize]) -> Vec<(usize, i64)> {
+    let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+    let size = candidates.len().div_ceil(threads).max(CHUNK);
+
+    thread::scope(|scope| {
+        let chunks: Vec<_> = candidates
+            .chunks(size)
+            .map(|chunk| scope.spawn(move || {
+                let mut matcher = FuzzyMatcher::new(pattern);
+
+                chunk
+                    .iter()
+                    .filter_map(|index| matcher.score(&items[*index].label).map(|score| (*index, score)))
+                    .collect::<Vec<_>>()
+            }))
+            .collect();
+
+        chunks.into_iter().flat_map(|chunk| chunk.join().unwrap_or_default()).collect()
+    })
+}
+
+impl Editor {
+    /// Opens the buffer picker, the buffer shown before this one first so
+    /// `<CR>` goes straight back to it.
+    pub fn open_buffer_picker(&mut self) {
+        let mut order = self.buffer_order.clone();
+
+        if let Some(alternate) = self.alternate_buffer
+            && let Some(index) = order.iter().position(|id| *id == alternate)
+        {
+            order.remove(index);
+            order.insert(0, alternate);
+        }
+
+        let items = order
+            .into_iter()
+            .map(|id| PickerItem { label: self.get_buffer_path_name(&id), target: PickerTarget::Buffer(id) })
+            .collect();
+
+        self.picker = Some(Picker::new("Buffers", items));
+    }
+
+    pub(crate) fn handle_picker_key(&mut self, key: KeyEvent) {
+        let Some(picker) = self.picker.as_mut() else {
+            return;
+        };
+
+        match picker.handle_key(key) {
+            PickerEvent::Changed => {
+                self.update_picker_preview();
+                self.fill_list_from_picker();
+            },
+            PickerEvent::Cancel => self.picker = None,
+            PickerEvent::Apply => {
+                if let Some(picker) = self.picker.take() {
+                    self.report(|editor| editor.apply_replace(&picker));
+                }
+            },
+            PickerEvent::Submit(target) => {
+                self.picker = None;
+
+                match target {
+                    PickerTarget::Buffer(id) if self.buffers.contains_key(&id) => self.switch_to_buffer(id),
+                    PickerTarget::Buffer(_) => {},
+                    PickerTarget::File(path) => {
+                        if let Err(err) = self.open_file(path) {
+                            self.messages.error(err);
+                        }
+                    },
+                    PickerTarget::Location(path, pos) => {
+                        if let Err(err) = self.open_location(path, pos) {
+                            self.messages.error(err);
+                        }
+                    },
+                    PickerTarget::ListEntry(kind, index) => {
+                        if let Err(err) = self.jump_to_entry(kind, index) {
+                            self.messages.error(err);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,451 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::command::parse_command;
+use crate::display::{next_boundary, prev_boundary};
+use crate::editor::Editor;
+use crate::options::OPTIONS;
+use crate::theme::Theme;
+
+/// How many entries a prompt's history keeps.
+pub const HISTORY_SIZE: usize = 200;
+
+/// What a key did to a prompt.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PromptEvent {
+    /// The text changed.
+    Changed,
+    /// The cursor moved, or the key did nothing.
+    Unchanged,
+    Submit(String),
+    Cancel,
+    /// `<Tab>` or `<S-Tab>` asked for the text before the cursor to be
+    /// completed, forwards or backwards.
+    Complete(bool)
+}
+
+/// Completions being cycled through with `<Tab>`.
+#[derive(Clone, Debug)]
+struct Completion {
+    /// Where the completed word starts.
+    start: usize,
+    original: String,
+    candidates: Vec<String>,
+    /// The candidate shown, or `None` for what was typed.
+    index: Option<usize>
+}
+
+/// A line of text typed at the bottom of the screen, such as the command
+/// line or a search pattern, with its own history.
+#[derive(Clone, Debug, Default)]
+pub struct Prompt {
+    pub text: String,
+    /// Byte offset of the cursor in `text`.
+    pub cursor: usize,
+    pub history: Vec<String>,
+    history_index: Option<usize>,
+    /// What was typed before browsing history; only entries starting with
+    /// it are shown.
+    history_prefix: String,
+    completion: Option<Completion>
+}
+
+impl Prompt {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the text, with the cursor at the end.
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.text.len();
+        self.completion = None;
+    }
+
+    pub fn clear(&mut self) {
+        self.set_text("");
+        self.history_index = None;
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> PromptEvent {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+
+        if !matches!(key.code, KeyCode::Tab | KeyCode::BackTab) {
+            self.completion = None;
+        }
+
+        match key.code {
+            KeyCode::Esc => PromptEvent::Cancel,
+            KeyCode::Char('c') if control => PromptEvent::Cancel,
+            KeyCode::Enter => PromptEvent::Submit(self.text.clone()),
+            KeyCode::Tab if key.modifiers.contains(KeyModifiers::SHIFT) => PromptEvent::Complete(false),
+            KeyCode::Tab => PromptEvent::Complete(true),
+            KeyCode::BackTab => PromptEvent::Complete(false),
+            KeyCode::Backspace if self.text.is_empty() => PromptEvent::Cancel,
+            KeyCode::Backspace => self.delete_to(prev_boundary(&self.text, self.cursor)),
+            KeyCode::Char('h') if control => self.delete_to(prev_boundary(&self.text, self.cursor)),
+            KeyCode::Delete => {
+                let end = next_boundary(&self.text, self.cursor);
+                self.text.replace_range(self.cursor .. end, "");
+                PromptEvent::Changed
+            },
+            KeyCode::Char('w') if control => self.delete_to(word_start(&self.text, self.cursor)),
+            KeyCode::Char('u') if control => self.delete_to(0),
+            KeyCode::Left if control => self.move_to(word_start(&self.text, self.cursor)),
+            KeyCode::Right if control => self.move_to(word_end(&self.text, self.cursor)),
+            KeyCode::Char('b') if alt => self.move_to(word_start(&self.text, self.cursor)),
+            KeyCode::Char('f') if alt => self.move_to(word_end(&self.text, self.cursor)),
+            KeyCode::Left => self.move_to(prev_boundary(&self.text, self.cursor)),
+            KeyCode::Right => self.move_to(next_boundary(&self.text, self.cursor)),
+            KeyCode::Home => self.move_to(0),
+            KeyCode::Char('b') if control => self.move_to(0),
+            KeyCode::End => self.move_to(self.text.len()),
+            KeyCode::Char('e') if control => self.move_to(self.text.len()),
+            KeyCode::Up => self.step_history(true),
+            KeyCode::Down => self.step_history(false),
+            KeyCode::Char('p') if control => self.step_history(true),
+            KeyCode::Char('n') if control => self.step_history(false),
+            KeyCode::Char(c) if !control && !alt => {
+                self.text.insert(self.cursor, c);
+                self.cursor += c.len_utf8();
+                PromptEvent::Changed
+            },
+            _ => PromptEvent::Unchanged
+        }
+    }
+
+    fn move_to(&mut self, cursor: usize) -> PromptEvent {
+        self.cursor = cursor;
+        PromptEvent::Unchanged
+    }
+
+    /// Deletes from `start` up to the cursor.
+    fn delete_to(&mut self, start: usize) -> PromptEvent {
+        self.text.replace_range(start .. self.cursor, "");
+        self.cursor = start;
+        PromptEvent::Changed
+    }
+
+    /// Adds `entry` as the newest history entry, dropping an older copy
+    /// and the oldest entries past `HISTORY_SIZE`.
+    pub fn push_history(&mut self, entry: &str) {
+        if entry.is_empty() {
+            return;
+        }
+
+        self.history.retain(|existing| existing != entry);
+        self.history.push(entry.to_string());
+
+        let excess = self.history.len().saturating_sub(HISTORY_SIZE);
+        self.history.drain(.. excess);
+        self.history_index = None;
+    }
+
+    /// Moves to the next older or newer history entry that starts with
+    /// what was typed before browsing; past the newest, that text returns.
+    fn step_history(&mut self, older: bool) -> PromptEvent {
+        if self.history_index.is_none() {
+            self.history_prefix = self.text.clone();
+        }
+
+        let prefix = &self.history_prefix;
+        let current = self.history_index.unwrap_or(self.history.len());
+
+        let found = if older {
+            self.history[.. current].iter().rposition(|entry| entry.starts_with(prefix.as_str()))
+        } else {
+            self.history.iter()
+                .enumerate()
+                .skip(current + 1)
+                .find(|(_, entry)| entry.starts_with(prefix.as_str()))
+                .map(|(index, _)| index)
+        };
+
+        match (found, older) {
+            (Some(index), _) => {
+                self.history_index = Some(index);
+                let entry = self.history[index].clone();
+                self.set_text(&entry);
+            },
+            (None, true) => return PromptEvent::Unchanged,
+            (None, false) => {
+                self.history_index = None;
+                let prefix = self.history_prefix.clone();
+                self.set_text(&prefix);
+            }
+        }
+
+        PromptEvent::Changed
+    }
+
+    /// Completes the word before the cursor. The first call asks `find`
+    /// for where the word starts and what it could become; later calls
+    /// cycle through those, coming back to what was typed. A single
+    /// candidate is taken straight away, so completing again goes on from
+    /// it, as into a directory.
+    pub fn complete(&mut self, forward: bool, find: impl FnOnce(&str) -> (usize, Vec<String>)) -> bool {
+        let Some(completion) = &mut self.completion else {
+            let (start, candidates) = find(&self.text[.. self.cursor]);
+
+            if candidates.is_empty() {
+                return false;
+            }
+
+            let original = self.text[start .. self.cursor].to_string();
+            let index = if forward { 0 } else { candidates.len() - 1 };
+            let single = candidates.len() == 1;
+
+            self.completion = Some(Completion { start, original, candidates, index: Some(index) });
+            self.show_completion();
+
+            if single {
+                self.completion = None;
+            }
+
+            return true;
+        };
+
+        let last = completion.candidates.len() - 1;
+
+        completion.index = match (completion.index, forward) {
+            (None, true) => Some(0),
+            (None, false) => Some(last),
+            (Some(index), true) if index < last => Some(index + 1),
+            (Some(index), false) if index > 0 => Some(index - 1),
+            (Some(_), _) => None
+        };
+
+        self.show_completion();
+        true
+    }
+
+    /// The candidates `<Tab>` is cycling through, and which is shown.
+    pub fn completions(&self) -> Option<(&[String], Option<usize>)> {
+        self.completion.as_ref().map(|completion| (completion.candidates.as_slice(), completion.index))
+    }
+
+    fn show_completion(&mut self) {
+        let Some(completion) = &self.completion else {
+            return;
+        };
+
+        let word = match completion.index {
+            Some(index) => &completion.candidates[index],
+            None => &completion.original
+        };
+
+        self.text.replace_range(completion.start .. self.cursor, word);
+        self.cursor = completion.start + word.len();
+    }
+
+    /// Reads history saved by `save_history`, one entry per line, oldest
+    /// first. A missing file leaves the history empty.
+    pub fn load_history(&mut self, path: &Path) -> io::Result<()> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err)
+        };
+
+        for line in text.lines() {
+            self.push_history(line);
+        }
+
+        Ok(())
+    }
+
+    pub fn save_history(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut text = self.history.join("\n");
+        text.push('\n');
+
+        fs::write(path, text)
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Where the word before `cursor` starts, as `<C-w>` sees it: spaces are
+/// skipped, then a run of word characters or of other characters.
+fn word_start(text: &str, cursor: usize) -> usize {
+    let before = text[.. cursor].trim_end();
+    let Some(last) = before.chars().next_back() else {
+        return 0;
+    };
+
+    before.char_indices()
+        .rev()
+        .take_while(|(_, c)| !c.is_whitespace() && is_word_char(*c) == is_word_char(last))
+        .last()
+        .map(|(index, _)| index)
+        .unwrap_or(before.len())
+}
+
+/// Where the word after `cursor` ends.
+fn word_end(text: &str, cursor: usize) -> usize {
+    let rest = &text[cursor ..];
+    let skipped = rest.len() - rest.trim_start().len();
+    let rest = &rest[skipped ..];
+
+    let Some(first) = rest.chars().next() else {
+        return text.len();
+    };
+
+    let len = rest.char_indices()
+        .find(|(_, c)| c.is_whitespace() || is_word_char(*c) != is_word_char(first))
+        .map(|(index, _)| index)
+        .unwrap_or(rest.len());
+
+    cursor + skipped + len
+}
+
+/// `$XDG_STATE_HOME/paw`, or `~/.local/state/paw` without it, where
+/// history is kept between sessions.
+pub fn state_dir() -> Option<PathBuf> {
+    match env::var_os("XDG_STATE_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => Some(PathBuf::from(dir).join("paw")),
+        None => env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state").join("paw"))
+    }
+}
+
+/// What the arguments of a command name.
+enum ArgumentKind {
+    Path,
+    Option,
+    Theme,
+    Buffer,
+    None
+}
+
+fn argument_kind(command: &str) -> ArgumentKind {
+    match command {
+        "edit" | "write" | "wq" | "xit" | "saveas" => ArgumentKind::Path,
+        "set" | "setlocal" => ArgumentKind::Option,
+        "colorscheme" => ArgumentKind::Theme,
+        "buffer" | "bdelete" => ArgumentKind::Buffer,
+        _ => ArgumentKind::None
+    }
+}
+
+impl Editor {
+    /// Reads the command line and search histories from `dir`.
+    pub fn load_history(&mut self, dir: &Path) -> io::Result<()> {
+        self.command_line.load_history(&dir.join("command_history"))?;
+        self.search.prompt.load_history(&dir.join("search_history"))
+    }
+
+    pub fn save_history(&self, dir: &Path) -> io::Result<()> {
+        self.command_line.save_history(&dir.join("command_history"))?;
+        self.search.prompt.save_history(&dir.join("search_history"))
+    }
+
+    /// Where the word before the end of `line` starts and what it could
+    /// complete to: a command name, or an argument of the kind the command
+    /// takes.
+    pub fn command_completions(&self, line: &str) -> (usize, Vec<String>) {
+        let Ok(command) = parse_command(line) else {
+            return (line.len(), vec![]);
+        };
+
+        if command.args.is_empty() && !command.bang && line.ends_with(&command.name) {
+            let start = line.len() - command.name.len();
+            let mut names: Vec<String> = self.commands.names()
+                .filter(|name| name.starts_with(&command.name) && name.len() > 1)
+                .map(String::from)
+                .collect();
+
+            names.sort();
+            names.dedup();
+            return (start, names);
+        }
+
+        let start = line.rfind(char::is_whitespace).map(|index| index + 1).unwrap_or(line.len());
+        let word = &line[start ..];
+
+        let Some(spec) = self.commands.lookup(&command.name) else {
+            return (start, vec![]);
+        };
+
+        let candidates = match argument_kind(spec.name) {
+            ArgumentKind::Path => complete_path(word),
+            ArgumentKind::Option => complete_option(word),
+            ArgumentKind::Theme => Theme::available().into_iter().filter(|name| name.starts_with(word)).collect(),
+            ArgumentKind::Buffer => self.complete_buffer(word),
+            ArgumentKind::None => vec![]
+        };
+
+        (start, candidates)
+    }
+
+    /// Names of open buffers containing `word`, those starting with it
+    /// first.
+    fn complete_buffer(&self, word: &str) -> Vec<String> {
+        let names: Vec<String> = self.buffer_order.iter().map(|id| self.get_buffer_display_name(id)).collect();
+        let (mut starts, contains): (Vec<String>, Vec<String>) = names.into_iter()
+            .filter(|name| name.contains(word))
+            .partition(|name| name.starts_with(word));
+
+        starts.extend(contains);
+        starts
+    }
+}
+
+/// Option names for `:set`, keeping a `no` or `inv` in front. After `=`,
+/// the option's current value is not known here, so nothing is offered.
+fn complete_option(word: &str) -> Vec<String> {
+    if word.contains(['=', ':', '!', '&', '?']) {
+        return vec![];
+    }
+
+    let (prefix, name) = ["no", "inv"].iter()
+        .find_map(|prefix| word.strip_prefix(prefix).map(|name| (*prefix, name)))
+        .filter(|(_, name)| OPTIONS.iter().any(|def| def.is_bool() && def.name.starts_with(name)))
+        .unwrap_or(("", word));
+
+    OPTIONS.iter()
+        .filter(|def| def.name.starts_with(name) && (prefix.is_empty() || def.is_bool()))
+        .map(|def| format!("{}{}", prefix, def.name))
+        .collect()
+}
+
+/// Files and directories starting with `word`, directories ending in `/`.
+/// Hidden entries are only offered when `word` names one.
+fn complete_path(word: &str) -> Vec<String> {
+    let (dir, prefix) = match word.rfind('/') {
+        Some(index) => (&word[.. index + 1], &word[index + 1 ..]),
+        None => ("", word)
+    };
+
+    let path = if dir.is_empty() { Path::new(".") } else { Path::new(dir) };
+
+    let Ok(entries) = fs::read_dir(path) else {
+        return vec![];
+    };
+
+    let mut candidates: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{}{}{}", dir, name, slash))
+        })
+        .collect();
+
+    candidates.sort();
+    candidates
+}
@@ -0,0 +1,208 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use utils::Position;
+
+use crate::editor::Editor;
+use crate::errors::EditorError;
+use crate::picker::{Picker, PickerItem, PickerTarget};
+use crate::window::WindowId;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+    Info
+}
+
+impl Severity {
+    /// The severity a compiler's word for it stands for, or its first
+    /// letter.
+    pub fn parse(text: &str) -> Self {
+        match text.chars().next().map(|c| c.to_ascii_lowercase()) {
+            Some('e') => Severity::Error,
+            Some('w') => Severity::Warning,
+            Some('n' | 'h') => Severity::Note,
+            _ => Severity::Info
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+            Severity::Info => write!(f, "info")
+        }
+    }
+}
+
+/// A place in a file and what was found there.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuickfixEntry {
+    pub path: PathBuf,
+    pub pos: Position,
+    pub message: String,
+    pub severity: Severity
+}
+
+impl QuickfixEntry {
+    /// `file:line:column: severity: message`, the message cut at its first
+    /// line.
+    pub fn label(&self) -> String {
+        let message = self.message.lines().next().unwrap_or_default();
+
+        format!("{}:{}:{}: {}: {}", self.path.display(), self.pos.line + 1, self.pos.column + 1, self.severity, message)
+    }
+}
+
+/// A list of places to go through one after another, such as the errors
+/// from `:make`: the quickfix list, or a window's location list.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QuickfixList {
+    /// What made the list, such as the `:make` command.
+    pub title: String,
+    pub entries: Vec<QuickfixEntry>,
+    /// The entry last jumped to.
+    pub current: usize
+}
+
+impl QuickfixList {
+    pub fn new(title: &str, entries: Vec<QuickfixEntry>) -> Self {
+        Self { title: title.to_string(), entries, current: 0 }
+    }
+}
+
+/// Which list a command works on: the quickfix list everything shares or
+/// the current window's location list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListKind {
+    Quickfix,
+    Location
+}
+
+impl Editor {
+    fn list(&self, kind: ListKind) -> Result<&QuickfixList, EditorError> {
+        let list = match kind {
+            ListKind::Quickfix => &self.quickfix,
+            ListKind::Location => self.location_lists.get(&self.current_window).ok_or(EditorError::NoLocationList)?
+        };
+
+        match list.entries.is_empty() {
+            true => Err(EditorError::NoErrors),
+            false => Ok(list)
+        }
+    }
+
+    /// The list of `kind` for window `window`, which has a location list
+    /// once something has been put in it.
+    pub(crate) fn list_mut(&mut self, kind: ListKind, window: WindowId) -> Option<&mut QuickfixList> {
+        match kind {
+            ListKind::Quickfix => Some(&mut self.quickfix),
+            ListKind::Location if window == self.current_window || self.windows.contains_key(&window) => {
+                Some(self.location_lists.entry(window).or_default())
+            },
+            ListKind::Location => None
+        }
+    }
+
+    /// Replaces the quickfix list or the current window's location list.
+    pub fn set_list(&mut self, kind: ListKind, list: QuickfixList) {
+        if let Some(current) = self.list_mut(kind, self.current_window) {
+            *current = list;
+        }
+    }
+
+    /// `:cc N` and `:ll N`: goes to entry `index` of the list, counting
+    /// from 0.
+    pub fn jump_to_entry(&mut self, kind: ListKind, index: usize) -> Result<(), EditorError> {
+        let list = self.list(kind)?;
+        let index = index.min(list.entries.len() - 1);
+        let total = list.entries.len();
+        let entry = list.entries[index].clone();
+
+        if let Some(list) = self.list_mut(kind, self.current_window) {
+            list.current = index;
+        }
+
+        self.open_location(entry.path, entry.pos)?;
+        self.messages.show(format!("({} of {}): {}", index + 1, total, entry.message.lines().next().unwrap_or_default()));
+
+        Ok(())
+    }
+
+    /// `:cnext`, `:cprev` and their location list forms: goes `count`
+    /// entries on, or back if negative.
+    pub fn jump_by_entries(&mut self, kind: ListKind, count: isize) -> Result<(), EditorError> {
+        let list = self.list(kind)?;
+        let index = list.current as isize + count;
+
+        if index < 0 || index >= list.entries.len() as isize {
+            return Err(EditorError::NoMoreItems);
+        }
+
+        self.jump_to_entry(kind, index as usize)
+    }
+
+    /// `:copen` and `:lopen`: the list in a picker, on the current entry.
+    pub fn open_list(&mut self, kind: ListKind) -> Result<(), EditorError> {
+        let list = self.list(kind)?;
+        let title = match (kind, list.title.is_empty()) {
+            (ListKind::Quickfix, true) => "Quickfix List".to_string(),
+            (ListKind::Location, true) => "Location List".to_string(),
+            (_, false) => list.title.clone()
+        };
+
+        let items = list.entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| PickerItem { label: entry.label(), target: PickerTarget::ListEntry(kind, index) })
+            .collect();
+
+        let mut picker = Picker::new(&title, items);
+        picker.selected = list.current;
+
+        self.picker = Some(picker);
+        self.update_picker_preview();
+
+        Ok(())
+    }
+
+    /// The entry a picker item for a list stands for.
+    pub(crate) fn list_entry(&self, kind: ListKind, index: usize) -> Option<&QuickfixEntry> {
+        self.list(kind).ok().and_then(|list| list.entries.get(index))
+    }
+
+    /// Once a picker that fills a list has all its items, as `:grep` does,
+    /// puts the places in them in the list.
+    pub(crate) fn fill_list_from_picker(&mut self) {
+        let Some(picker) = self.picker.as_mut().filter(|picker| !picker.is_loading()) else {
+            return;
+        };
+
+        let Some(kind) = picker.list.take() else {
+            return;
+        };
+
+        let mut entries: Vec<QuickfixEntry> = picker.items
+            .iter()
+            .filter_map(|item| match &item.target {
+                PickerTarget::Location(path, pos) => {
+                    let prefix = format!("{}:{}:{}: ", path.display(), pos.line + 1, pos.column + 1);
+                    let message = item.label.strip_prefix(&prefix).unwrap_or(&item.label).to_string();
+
+                    Some(QuickfixEntry { path: path.clone(), pos: *pos, message, severity: Severity::Info })
+                },
+                _ => None
+            })
+            .collect();
+
+        entries.sort_by(|a, b| (&a.path, a.pos.line, a.pos.column).cmp(&(&b.path, b.pos.line, b.pos.column)));
+
+        let list = QuickfixList::new(&picker.title, entries);
+        self.set_list(kind, list);
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use utils::Position;
+
+use crate::editor::Editor;
+use crate::errors::EditorError;
+use crate::grep::grep_matches;
+use crate::picker::{Picker, PickerTarget};
+use crate::substitute::{is_pattern_delimiter, split_delimited, Substitution};
+
+/// Splits `:greplace` arguments into the `/pattern/replacement/flags` part
+/// and the path to search.
+fn split_args(args: &str) -> Result<(&str, &str), EditorError> {
+    let args = args.trim();
+    let invalid = || EditorError::InvalidArgument(args.to_string());
+
+    let delimiter = match args.chars().next() {
+        Some(c) if is_pattern_delimiter(c) => c,
+        Some(_) => return Err(invalid()),
+        None => return Err(EditorError::ArgumentRequired)
+    };
+
+    let (_, rest) = split_delimited(&args[delimiter.len_utf8() ..], delimiter);
+    let (_, rest) = split_delimited(rest.ok_or_else(invalid)?, delimiter);
+    let rest = rest.unwrap_or("");
+    let (flags, path) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+
+    Ok((&args[.. args.len() - rest.len() + flags.len()], path.trim()))
+}
+
+/// `line` with the match of `substitution` that starts at byte `column`
+/// replaced, if there still is one there.
+pub fn replace_at(substitution: &Substitution, line: &str, column: usize) -> Option<String> {
+    if !line.is_char_boundary(column) {
+        return None;
+    }
+
+    let captures = substitution.regex.captures_at(line, column)?;
+    let whole = captures.get(0).filter(|whole| whole.start() == column)?;
+
+    Some(format!("{}{}{}", &line[.. column], substitution.expand(&captures), &line[whole.end() ..]))
+}
+
+/// What line `pos.line` of the file at `path` becomes with the match at
+/// `pos` replaced, with tabs shown the way the preview shows them.
+pub fn preview_replacement(substitution: &Substitution, path: &Path, pos: Position) -> Option<String> {
+    let file = File::open(path).ok()?;
+    let line = BufReader::new(file).split(b'\n').nth(pos.line)?.ok()?;
+    let line = String::from_utf8_lossy(&line);
+
+    replace_at(substitution, line.trim_end_matches('\r'), pos.column).map(|line| line.replace('\t', "    "))
+}
+
+fn plural(count: usize, word: &str) -> String {
+    match count {
+        1 => format!("{} {}", count, word),
+        _ => format!("{} {}s", count, word)
+    }
+}
+
+impl Editor {
+    /// `:greplace /pattern/replacement/[flags] [path]`: searches the files
+    /// under `path`, or the working directory, listing every match in a
+    /// picker that previews the change. `<Tab>` unticks a match and `<CR>`
+    /// makes the replacement at the ones still ticked.
+    pub fn project_replace(&mut self, args: &str) -> Result<(), EditorError> {
+        let (spec, path) = split_args(args)?;
+        let (mut substitution, _) = Substitution::parse(spec, None, self.last_pattern.as_deref())?;
+        let root = if path.is_empty() { "." } else { path };
+
+        if !Path::new(root).exists() {
+            return Err(EditorError::InvalidArgument(root.to_string()));
+        }
+
+        // Each match is its own item, so all of them are up for replacing.
+        substitution.global = true;
+        self.last_pattern = Some(substitution.regex.as_str().to_string());
+
+        let title = format!("Replace {} with {}", substitution.regex, substitution.replacement);
+        let mut picker = Picker::with_source(&title, grep_matches(substitution.regex.clone(), Path::new(root)));
+        picker.replace = Some(substitution);
+
+        self.picker = Some(picker);
+        self.update_picker_preview();
+
+        Ok(())
+    }
+
+    /// Makes the replacement of a `:greplace` picker at the places still
+    /// ticked, found or not by what has been typed. Each file goes through
+    /// its buffer, opened for it if need be, as one undo step. Buffers with
+    /// no changes of their own beforehand are written; the others are left
+    /// for the user to look over and write. A match no longer where it was
+    /// found, or in a file that can't be opened, is left alone.
+    pub fn apply_replace(&mut self, picker: &Picker) -> Result<(), EditorError> {
+        let Some(substitution) = &picker.replace else {
+            return Ok(());
+        };
+
+        let mut files: BTreeMap<PathBuf, Vec<Position>> = BTreeMap::new();
+
+        for item in picker.checked_items() {
+            if let PickerTarget::Location(path, pos) = &item.target {
+                files.entry(path.clone()).or_default().push(*pos);
+            }
+        }
+
+        if files.is_empty() {
+            return Err(EditorError::NothingSelected);
+        }
+
+        let (mut replaced, mut changed, mut skipped, mut unopened) = (0, 0, 0, 0);
+        let mut report = vec![];
+
+        for (path, mut places) in files {
+            let opened = match self.buffer_for_path(&path) {
+                Some(id) => Ok(id),
+                None if path.is_file() => self.create_buffer_from_file(path.clone()).map_err(|err| err.to_string()),
+                None => Err("no longer there".to_string())
+            };
+
+            // One file that can't be opened mustn't stop the rest, some of
+            // which may already be written.
+            let id = match opened {
+                Ok(id) => id,
+                Err(reason) => {
+                    unopened += places.len();
+                    report.push(format!("{}: {}", path.display(), reason));
+                    continue;
+                }
+            };
+
+            let Some(buffer) = self.buffers.get_mut(&id) else {
+                continue;
+            };
+
+            // From the end of the file back, so replacing a match doesn't
+            // move the ones still to do.
+            places.sort_by_key(|pos| std::cmp::Reverse((pos.line, pos.column)));
+
+            let modified = buffer.is_modified();
+            let mut count = 0;
+
+            buffer.begin_undo_group();
+
+            for pos in &places {
+                match buffer.lines.get(pos.line).and_then(|line| replace_at(substitution, line, pos.column)) {
+                    Some(text) => {
+                        let _ = buffer.set_line(pos.line, text);
+                        count += 1;
+                    },
+                    None => skipped += 1
+                }
+            }
+
+            buffer.end_undo_group();
+
+            if count == 0 {
+                continue;
+            }
+
+            replaced += count;
+            changed += 1;
+
+            let note = match modified {
+                true => " (not written)".to_string(),
+                false => match buffer.save_to_file() {
+                    Ok(_) => String::new(),
+                    Err(err) => format!(" ({})", EditorError::WriteFailed(path.display().to_string(), err))
+                }
+            };
+
+            report.push(format!("{}: {}{}", path.display(), plural(count, "replacement"), note));
+        }
+
+        self.clamp_cursor();
+
+        let mut summary = format!("{} in {}", plural(replaced, "replacement"), plural(changed, "file"));
+
+        if skipped > 0 {
+            summary.push_str(&format!(", {} no longer found", skipped));
+        }
+
+        if unopened > 0 {
+            summary.push_str(&format!(", {} in files that couldn't be opened", unopened));
+        }
+
+        report.insert(0, summary);
+        self.messages.info(report.join("\n"));
+
+        Ok(())
+    }
+}
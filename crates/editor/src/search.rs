@@ -0,0 +1,317 @@
+use buffer::buffer::Buffer;
+use crossterm::event::KeyEvent;
+use regex::Regex;
+use utils::Position;
+
+use crate::editor::{Editor, EditorMode};
+use crate::errors::EditorError;
+use crate::prompt::{Prompt, PromptEvent};
+
+/// Counting stops here so a search in a huge file stays cheap; the counter
+/// then shows `[>999]`.
+const MAX_MATCH_COUNT: usize = 999;
+
+#[derive(Debug)]
+pub struct SearchState {
+    /// The pattern being typed in `EditorMode::Search`, with the history
+    /// of patterns searched for.
+    pub prompt: Prompt,
+    pub forward: bool,
+    /// Where the cursor was when the search prompt opened, so incremental
+    /// matching starts from there and `<Esc>` can go back.
+    origin: Position,
+    /// Whether matches of the last pattern are highlighted; cleared by
+    /// `:nohlsearch` until the next search.
+    pub highlight: bool,
+    /// The regex last built to highlight matches, with the pattern and case
+    /// flag it was built from, so redrawing doesn't compile it again.
+    highlight_regex: Option<(String, Option<Regex>)>
+}
+
+impl Default for SearchState {
+    fn default() -> Self {
+        Self {
+            prompt: Prompt::new(),
+            forward: true,
+            origin: Position::new(0, 0),
+            highlight: true,
+            highlight_regex: None
+        }
+    }
+}
+
+/// A match found by `find_match`; `wrapped` is set when the search went past
+/// the end (or start) of the buffer to find it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub start: Position,
+    pub end: usize,
+    pub wrapped: bool
+}
+
+/// Finds the next match strictly after (or before) `from`, wrapping around
+/// the buffer.
+pub fn find_match(buffer: &Buffer, regex: &Regex, from: Position, forward: bool) -> Option<SearchMatch> {
+    let len = buffer.len();
+
+    if forward {
+        for step in 0 ..= len {
+            let line = (from.line + step) % len;
+            let text = &buffer.lines[line];
+            let wrapped = from.line + step >= len;
+
+            let found = if step == 0 {
+                regex.find_iter(text).find(|m| m.start() > from.column)
+            } else {
+                regex.find(text)
+            };
+
+            if let Some(m) = found {
+                return Some(SearchMatch {
+                    start: Position::new(line, m.start()),
+                    end: m.end(),
+                    wrapped
+                });
+            }
+        }
+    } else {
+        for step in 0 ..= len {
+            let line = (from.line + len * 2 - step) % len;
+            let text = &buffer.lines[line];
+            let wrapped = step > from.line;
+
+            let found = if step == 0 {
+                regex.find_iter(text).filter(|m| m.start() < from.column).last()
+            } else {
+                regex.find_iter(text).last()
+            };
+
+            if let Some(m) = found {
+                return Some(SearchMatch {
+                    start: Position::new(line, m.start()),
+                    end: m.end(),
+                    wrapped
+                });
+            }
+        }
+    }
+
+    None
+}
+
+/// Returns the 1-based index of the match at `at` and the total number of
+/// matches, or `None` for the total when there are more than
+/// `MAX_MATCH_COUNT`.
+pub fn count_matches(buffer: &Buffer, regex: &Regex, at: Position) -> (usize, Option<usize>) {
+    let mut index = 0;
+    let mut total = 0;
+
+    for (line, text) in buffer.lines.iter().enumerate() {
+        for m in regex.find_iter(text) {
+            total += 1;
+
+            if (line, m.start()) <= (at.line, at.column) {
+                index = total;
+            }
+
+            if total > MAX_MATCH_COUNT {
+                return (index, None);
+            }
+        }
+    }
+
+    (index, Some(total))
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// `flags` and `pattern` as a regex, or `pattern` taken literally if it
+/// isn't one.
+fn compile_regex(flags: &str, pattern: &str) -> Option<Regex> {
+    Regex::new(&format!("{}{}", flags, pattern))
+        .or_else(|_| Regex::new(&format!("{}{}", flags, regex::escape(pattern))))
+        .ok()
+}
+
+impl Editor {
+    /// Compiles a search pattern. `\c` and `\C` anywhere in the pattern force
+    /// case-insensitive or case-sensitive matching; otherwise `ignorecase`
+    /// applies unless `smartcase` is set and the pattern has an uppercase
+    /// letter. A pattern that isn't a valid regex is searched for literally.
+    /// Case folding is written into the pattern as `(?i)`, so the regex's
+    /// `as_str` tells two searches apart.
+    pub fn build_regex(&self, pattern: &str) -> Option<Regex> {
+        let (flags, pattern) = self.regex_parts(pattern)?;
+        compile_regex(flags, &pattern)
+    }
+
+    /// The case flag and pattern to compile for `pattern`, with `\c` and
+    /// `\C` and the case options turned into the flag.
+    fn regex_parts(&self, pattern: &str) -> Option<(&'static str, String)> {
+        if pattern.is_empty() {
+            return None;
+        }
+
+        let mut ignore_case = self.options.global.ignore_case && !(self.options.global.smart_case && pattern.chars().any(|c| c.is_uppercase()));
+
+        if pattern.contains("\\c") {
+            ignore_case = true;
+        } else if pattern.contains("\\C") {
+            ignore_case = false;
+        }
+
+        let pattern = pattern.replace("\\c", "").replace("\\C", "");
+        let flags = if ignore_case { "(?i)" } else { "" };
+
+        Some((flags, pattern))
+    }
+
+    pub fn start_search(&mut self, forward: bool) {
+        self.search.prompt.clear();
+        self.search.forward = forward;
+        self.search.origin = self.cursor.pos;
+        self.change_mode(EditorMode::Search);
+    }
+
+    pub fn handle_search_mode_input(&mut self, key: KeyEvent) {
+        match self.search.prompt.handle_key(key) {
+            PromptEvent::Cancel => {
+                self.cursor.pos = self.search.origin;
+                self.search.prompt.clear();
+                self.change_mode(EditorMode::Normal);
+            },
+            PromptEvent::Submit(input) => {
+                self.search.prompt.clear();
+                self.cursor.pos = self.search.origin;
+                self.change_mode(EditorMode::Normal);
+
+                let pattern = if input.is_empty() {
+                    match self.last_pattern.clone() {
+                        Some(pattern) => pattern,
+                        None => {
+                            self.messages.error(EditorError::NoPreviousPattern);
+                            return;
+                        }
+                    }
+                } else {
+                    input
+                };
+
+                self.search.prompt.push_history(&pattern);
+                self.last_pattern = Some(pattern);
+                self.search.highlight = true;
+
+                if let Err(err) = self.search_next(true) {
+                    self.messages.error(err);
+                }
+            },
+            PromptEvent::Changed => self.incremental_search(),
+            PromptEvent::Complete(_) | PromptEvent::Unchanged => {}
+        }
+    }
+
+    /// Moves the cursor to the first match of the pattern being typed, or
+    /// back to where the search started if there is none.
+    fn incremental_search(&mut self) {
+        self.cursor.pos = self.search.origin;
+
+        let Some(regex) = self.build_regex(&self.search.prompt.text) else {
+            return;
+        };
+
+        if let Some(buffer) = self.get_current_buffer()
+            && let Some(found) = find_match(buffer, &regex, self.search.origin, self.search.forward)
+        {
+            self.cursor.pos = found.start;
+        }
+    }
+
+    /// The regex whose matches the UI should highlight: the pattern being
+    /// typed while searching, otherwise the last search pattern. It is only
+    /// compiled again when the pattern changes.
+    pub fn search_highlight(&mut self) -> Option<Regex> {
+        let (flags, pattern) = match self.mode {
+            EditorMode::Search => self.regex_parts(&self.search.prompt.text)?,
+            _ if self.search.highlight => self.regex_parts(self.last_pattern.as_ref()?)?,
+            _ => return None
+        };
+
+        let key = format!("{}{}", flags, pattern);
+
+        if let Some((built_from, regex)) = &self.search.highlight_regex
+            && *built_from == key
+        {
+            return regex.clone();
+        }
+
+        let regex = compile_regex(flags, &pattern);
+        self.search.highlight_regex = Some((key, regex.clone()));
+        regex
+    }
+
+    /// Jumps to the next match of the last pattern, in the direction of the
+    /// last search when `same_direction` is set (`n`) or against it (`N`).
+    pub fn search_next(&mut self, same_direction: bool) -> Result<(), EditorError> {
+        let pattern = self.last_pattern.clone().ok_or(EditorError::NoPreviousPattern)?;
+        let regex = self.build_regex(&pattern).ok_or_else(|| EditorError::InvalidPattern(pattern.clone()))?;
+        let forward = self.search.forward == same_direction;
+
+        let buffer = self.get_current_buffer().ok_or(EditorError::NoBuffer)?;
+        let found = find_match(buffer, &regex, self.cursor.pos, forward)
+            .ok_or_else(|| EditorError::PatternNotFound(pattern.clone()))?;
+        let (index, total) = count_matches(buffer, &regex, found.start);
+
+        self.cursor.pos = found.start;
+        self.search.highlight = true;
+
+        let prefix = if self.search.forward { '/' } else { '?' };
+        let total = total.map(|total| total.to_string()).unwrap_or(format!(">{}", MAX_MATCH_COUNT));
+
+        if found.wrapped {
+            let wrap = if forward { "search hit BOTTOM, continuing at TOP" } else { "search hit TOP, continuing at BOTTOM" };
+            self.messages.warn(format!("{} [{}/{}]", wrap, index, total));
+        } else {
+            self.messages.show(format!("{}{} [{}/{}]", prefix, pattern, index, total));
+        }
+
+        Ok(())
+    }
+
+    /// `*` and `#`: searches for the whole word under (or after) the cursor.
+    pub fn search_word_under_cursor(&mut self, forward: bool) -> Result<(), EditorError> {
+        let buffer = self.get_current_buffer().ok_or(EditorError::NoBuffer)?;
+        let line = &buffer.lines[self.cursor.pos.line];
+        let column = self.cursor.pos.column.min(line.len());
+
+        let start = line[column ..]
+            .char_indices()
+            .find(|(_, c)| is_word_char(*c))
+            .map(|(i, _)| column + i)
+            .ok_or(EditorError::NoWordUnderCursor)?;
+
+        let start = line[.. start]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| is_word_char(*c))
+            .last()
+            .map(|(i, _)| i)
+            .unwrap_or(start);
+
+        let end = line[start ..]
+            .char_indices()
+            .find(|(_, c)| !is_word_char(*c))
+            .map(|(i, _)| start + i)
+            .unwrap_or(line.len());
+
+        let pattern = format!("\\b{}\\b", regex::escape(&line[start .. end]));
+
+        self.search.prompt.push_history(&pattern);
+        self.search.forward = forward;
+        self.last_pattern = Some(pattern);
+        self.cursor.pos.column = start;
+
+        self.search_next(true)
+    }
+}
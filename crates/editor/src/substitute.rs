@@ -0,0 +1,506 @@
+use crossterm::event::KeyCode;
+use regex::{Captures, Regex, RegexBuilder};
+use utils::Position;
+
+use crate::command::{parse_command, LineRange};
+use crate::editor::Editor;
+use crate::errors::EditorError;
+
+#[derive(Clone, Debug)]
+pub struct Substitution {
+    pub regex: Regex,
+    pub replacement: String,
+    pub global: bool,
+    pub confirm: bool
+}
+
+/// A piece of a previewed line; `true` marks text produced by the replacement.
+pub type PreviewSegment = (String, bool);
+
+/// What the preview's substitution was parsed from: the `:s` arguments, the
+/// last search pattern, and the pattern, replacement and flags of the last
+/// substitution.
+#[derive(Debug, PartialEq)]
+pub(crate) struct PreviewKey {
+    args: String,
+    last_pattern: Option<String>,
+    last_substitute: Option<(String, String, bool, bool)>
+}
+
+/// The substitution the preview last parsed, with what it was parsed from.
+pub(crate) type ParsedPreview = (PreviewKey, Option<(Substitution, Option<usize>)>);
+
+impl Substitution {
+    /// Parses the `/pattern/replacement/flags [count]` part of `:s`. Returns
+    /// the substitution and the optional trailing count. An empty pattern
+    /// reuses `last_pattern`, and no arguments at all repeats `last`.
+    pub fn parse(args: &str, last: Option<&Substitution>, last_pattern: Option<&str>) -> Result<(Self, Option<usize>), EditorError> {
+        let mut chars = args.chars();
+
+        let delimiter = match chars.next() {
+            None => {
+                let last = last.ok_or(EditorError::NoPreviousSubstitute)?;
+                return Ok((Substitution { global: false, confirm: false, ..last.clone() }, None));
+            },
+            Some(c) if !is_pattern_delimiter(c) => {
+                return Err(EditorError::TrailingCharacters(args.to_string()));
+            },
+            Some(c) => c
+        };
+
+        let rest = chars.as_str();
+        let (pattern, rest) = split_delimited(rest, delimiter);
+        let (replacement, flags) = split_delimited(rest.unwrap_or(""), delimiter);
+        let flags = flags.unwrap_or("");
+
+        let mut global = false;
+        let mut confirm = false;
+        let mut ignore_case = None;
+
+        let flag_end = flags.find(|c: char| !"gciI".contains(c)).unwrap_or(flags.len());
+
+        for flag in flags[.. flag_end].chars() {
+            match flag {
+                'g' => global = !global,
+                'c' => confirm = true,
+                'i' => ignore_case = Some(true),
+                'I' => ignore_case = Some(false),
+                _ => {}
+            }
+        }
+
+        let count = match flags[flag_end ..].trim() {
+            "" => None,
+            count => Some(count.parse::<usize>()
+                .ok()
+                .filter(|count| *count > 0)
+                .ok_or_else(|| EditorError::TrailingCharacters(count.to_string()))?)
+        };
+
+        let pattern = if pattern.is_empty() {
+            last_pattern.ok_or(EditorError::NoPreviousPattern)?.to_string()
+        } else {
+            pattern
+        };
+
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(ignore_case.unwrap_or(false))
+            .build()
+            .map_err(|_| EditorError::InvalidPattern(pattern.clone()))?;
+
+        Ok((Substitution {
+            regex,
+            replacement,
+            global,
+            confirm
+        }, count))
+    }
+
+    /// Replaces matches in `line`, returning the new text and the number of
+    /// replacements, or `None` if nothing matched.
+    pub fn replace_line(&self, line: &str) -> Option<(String, usize)> {
+        let segments = self.preview_line(line)?;
+        let count = segments.iter().filter(|(_, replaced)| *replaced).count();
+        let text = segments.into_iter().map(|(text, _)| text).collect();
+
+        Some((text, count))
+    }
+
+    pub fn preview_line(&self, line: &str) -> Option<Vec<PreviewSegment>> {
+        let mut segments = vec![];
+        let mut last = 0;
+
+        for caps in self.regex.captures_iter(line) {
+            let whole = caps.get(0).unwrap();
+
+            segments.push((line[last .. whole.start()].to_string(), false));
+            segments.push((self.expand(&caps), true));
+            last = whole.end();
+
+            if !self.global {
+                break;
+            }
+        }
+
+        if segments.is_empty() {
+            return None;
+        }
+
+        segments.push((line[last ..].to_string(), false));
+
+        Some(segments)
+    }
+
+    /// Finds the first match in `line` at or after byte `from`.
+    pub fn find_at(&self, line: &str, from: usize) -> Option<(usize, usize)> {
+        if from > line.len() {
+            return None;
+        }
+
+        self.regex.find_at(line, from).map(|m| (m.start(), m.end()))
+    }
+
+    /// Builds the replacement text for one match, handling `&`, `\1`-`\9`,
+    /// `$1`-`$9` and the `\u`, `\l`, `\U`, `\L` and `\E` case modifiers.
+    pub fn expand(&self, caps: &Captures) -> String {
+        let mut result = String::new();
+        let mut case = CaseState::default();
+        let mut chars = self.replacement.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some(d) if d.is_ascii_digit() => {
+                        case.push(&mut result, group(caps, d));
+                    },
+                    Some('u') => case.once = Some(Case::Upper),
+                    Some('l') => case.once = Some(Case::Lower),
+                    Some('U') => case.sticky = Some(Case::Upper),
+                    Some('L') => case.sticky = Some(Case::Lower),
+                    Some('E') | Some('e') => case.sticky = None,
+                    Some('t') => case.push(&mut result, "\t"),
+                    Some(other) => case.push(&mut result, &other.to_string()),
+                    None => case.push(&mut result, "\\")
+                },
+                '&' => case.push(&mut result, group(caps, '0')),
+                '$' => match chars.peek() {
+                    Some(d) if d.is_ascii_digit() => {
+                        let d = *d;
+                        chars.next();
+                        case.push(&mut result, group(caps, d));
+                    },
+                    _ => case.push(&mut result, "$")
+                },
+                _ => case.push(&mut result, &c.to_string())
+            }
+        }
+
+        result
+    }
+}
+
+fn group<'a>(caps: &Captures<'a>, digit: char) -> &'a str {
+    let index = digit.to_digit(10).unwrap_or(0) as usize;
+    caps.get(index).map(|m| m.as_str()).unwrap_or("")
+}
+
+/// Whether `c` may separate the pattern of `:s` and `:g` from the rest.
+pub(crate) fn is_pattern_delimiter(c: char) -> bool {
+    !(c.is_alphanumeric() || c == '\\' || c == '"' || c == '|' || c.is_whitespace())
+}
+
+/// Splits `input` at the first unescaped `delimiter`. An escaped delimiter is
+/// unescaped; other escapes are kept for the regex or replacement to handle.
+pub(crate) fn split_delimited(input: &str, delimiter: char) -> (String, Option<&str>) {
+    let mut result = String::new();
+    let mut chars = input.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        if c == delimiter {
+            return (result, Some(&input[i + c.len_utf8() ..]));
+        }
+
+        if c == '\\' {
+            match chars.next() {
+                Some((_, next)) if next == delimiter => result.push(next),
+                Some((_, next)) => {
+                    result.push('\\');
+                    result.push(next);
+                },
+                None => result.push('\\')
+            }
+        } else {
+            result.push(c);
+        }
+    }
+
+    (result, None)
+}
+
+#[derive(Clone, Copy)]
+enum Case {
+    Upper,
+    Lower
+}
+
+#[derive(Default)]
+struct CaseState {
+    once: Option<Case>,
+    sticky: Option<Case>
+}
+
+impl CaseState {
+    fn push(&mut self, result: &mut String, text: &str) {
+        for c in text.chars() {
+            let case = self.once.take().or(self.sticky);
+
+            match case {
+                Some(Case::Upper) => result.extend(c.to_uppercase()),
+                Some(Case::Lower) => result.extend(c.to_lowercase()),
+                None => result.push(c)
+            }
+        }
+    }
+}
+
+/// State of an interactive `:s///c`, waiting for the user to answer
+/// y/n/a/q/l for the match at `line`, `start .. end`.
+#[derive(Debug)]
+pub struct SubstituteConfirm {
+    pub substitution: Substitution,
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+    last_line: usize,
+    substitutions: usize,
+    lines_changed: usize,
+    line_changed: bool
+}
+
+impl Editor {
+    pub fn substitute(&mut self, range: LineRange, substitution: Substitution) -> Result<(), EditorError> {
+        self.last_substitute = Some(substitution.clone());
+        self.last_pattern = Some(substitution.regex.as_str().to_string());
+
+        if substitution.confirm {
+            // The whole interactive pass is one undo step; the group is
+            // closed again in `finish_confirm`.
+            if let Some(buffer) = self.get_current_buffer_mut() {
+                buffer.begin_undo_group();
+            }
+
+            let confirm = SubstituteConfirm {
+                substitution,
+                line: range.start,
+                start: 0,
+                end: 0,
+                last_line: range.end,
+                substitutions: 0,
+                lines_changed: 0,
+                line_changed: false
+            };
+
+            return self.advance_confirm(confirm, 0);
+        }
+
+        let buffer = self.get_current_buffer_mut().ok_or(EditorError::NoBuffer)?;
+        let mut substitutions = 0;
+        let mut lines_changed = 0;
+        let mut last_changed = None;
+
+        for line in range.start ..= range.end {
+            let Some((text, count)) = substitution.replace_line(&buffer.lines[line]) else {
+                continue;
+            };
+
+            let _ = buffer.set_line(line, text);
+            substitutions += count;
+            lines_changed += 1;
+            last_changed = Some(line);
+        }
+
+        let Some(last_changed) = last_changed else {
+            return Err(EditorError::PatternNotFound(substitution.regex.to_string()));
+        };
+
+        self.cursor.pos = Position::new(last_changed, 0);
+        self.clamp_cursor();
+        self.report_substitutions(substitutions, lines_changed);
+
+        Ok(())
+    }
+
+    /// Moves the confirmation to the next match at or after byte `from` on
+    /// its current line, finishing once the range is exhausted.
+    fn advance_confirm(&mut self, mut confirm: SubstituteConfirm, mut from: usize) -> Result<(), EditorError> {
+        let buffer = self.get_current_buffer().ok_or(EditorError::NoBuffer)?;
+
+        while confirm.line <= confirm.last_line.min(buffer.len() - 1) {
+            let text = &buffer.lines[confirm.line];
+            let allowed = from == 0 || confirm.substitution.global;
+
+            if allowed && let Some((start, end)) = confirm.substitution.find_at(text, from) {
+                confirm.start = start;
+                confirm.end = end;
+                self.cursor.pos = Position::new(confirm.line, start);
+                self.substitute_confirm = Some(confirm);
+
+                return Ok(());
+            }
+
+            if confirm.line_changed {
+                confirm.lines_changed += 1;
+                confirm.line_changed = false;
+            }
+
+            confirm.line += 1;
+            from = 0;
+        }
+
+        self.finish_confirm(confirm);
+
+        Ok(())
+    }
+
+    fn finish_confirm(&mut self, confirm: SubstituteConfirm) {
+        let lines_changed = confirm.lines_changed + usize::from(confirm.line_changed);
+
+        self.substitute_confirm = None;
+        self.clamp_cursor();
+
+        if let Some(buffer) = self.get_current_buffer_mut() {
+            buffer.end_undo_group();
+        }
+
+        if confirm.substitutions == 0 && lines_changed == 0 {
+            self.messages.error(EditorError::PatternNotFound(confirm.substitution.regex.to_string()));
+        } else {
+            self.report_substitutions(confirm.substitutions, lines_changed);
+        }
+    }
+
+    /// Replaces the current match and returns the byte just past the
+    /// inserted text, so the search continues after it.
+    fn replace_confirm_match(&mut self, confirm: &mut SubstituteConfirm) -> usize {
+        let Some(buffer) = self.get_current_buffer_mut() else {
+            return confirm.end;
+        };
+
+        let text = buffer.lines[confirm.line].clone();
+
+        let Some(caps) = confirm.substitution.regex.captures_at(&text, confirm.start) else {
+            return confirm.end;
+        };
+
+        let replacement = confirm.substitution.expand(&caps);
+        let new_text = format!("{}{}{}", &text[.. confirm.start], replacement, &text[confirm.end ..]);
+        let _ = buffer.set_line(confirm.line, new_text);
+
+        confirm.substitutions += 1;
+        confirm.line_changed = true;
+
+        confirm.start + replacement.len()
+    }
+
+    pub fn handle_confirm_input(&mut self, key: KeyCode) {
+        let Some(mut confirm) = self.substitute_confirm.take() else {
+            return;
+        };
+
+        // An empty match would be found again at the same spot, so step over
+        // a character after handling it.
+        let step = |text: &str, at: usize, end: usize| -> usize {
+            if at == end {
+                text[at ..].chars().next().map(|c| at + c.len_utf8()).unwrap_or(at + 1)
+            } else {
+                at
+            }
+        };
+
+        let result = match key {
+            KeyCode::Char('y') => {
+                let empty = confirm.start == confirm.end;
+                let next = self.replace_confirm_match(&mut confirm);
+                let text = self.get_current_buffer().map(|b| b.lines[confirm.line].clone()).unwrap_or_default();
+                let next = if empty { step(&text, next, next) } else { next };
+
+                self.advance_confirm(confirm, next)
+            },
+            KeyCode::Char('n') => {
+                let text = self.get_current_buffer().map(|b| b.lines[confirm.line].clone()).unwrap_or_default();
+                let next = step(&text, confirm.end, confirm.start);
+
+                self.advance_confirm(confirm, next)
+            },
+            KeyCode::Char('a') => {
+                loop {
+                    let empty = confirm.start == confirm.end;
+                    let next = self.replace_confirm_match(&mut confirm);
+                    let text = self.get_current_buffer().map(|b| b.lines[confirm.line].clone()).unwrap_or_default();
+                    let next = if empty { step(&text, next, next) } else { next };
+
+                    if let Err(err) = self.advance_confirm(confirm, next) {
+                        break Err(err);
+                    }
+
+                    match self.substitute_confirm.take() {
+                        Some(next) => confirm = next,
+                        None => break Ok(())
+                    }
+                }
+            },
+            KeyCode::Char('l') => {
+                self.replace_confirm_match(&mut confirm);
+                self.finish_confirm(confirm);
+                Ok(())
+            },
+            KeyCode::Char('q') | KeyCode::Esc => {
+                self.finish_confirm(confirm);
+                Ok(())
+            },
+            _ => {
+                self.substitute_confirm = Some(confirm);
+                Ok(())
+            }
+        };
+
+        if let Err(err) = result {
+            self.messages.error(err);
+        }
+    }
+
+    fn report_substitutions(&mut self, substitutions: usize, lines: usize) {
+        let plural = |n: usize, word: &str| if n == 1 { format!("{} {}", n, word) } else { format!("{} {}s", n, word) };
+
+        self.messages.info(format!("{} on {}", plural(substitutions, "substitution"), plural(lines, "line")));
+    }
+
+    /// Parses the command line being typed and, if it is a `:s`, returns the
+    /// substitution and the lines it applies to so the UI can preview it.
+    /// The substitution is only parsed again, compiling its pattern, when
+    /// its arguments change.
+    pub fn substitute_preview(&mut self) -> Option<(LineRange, Substitution)> {
+        let command = parse_command(&self.command_line.text).ok()?;
+        let spec = self.commands.lookup(&command.name)?;
+
+        if spec.name != "substitute" || command.args.is_empty() {
+            return None;
+        }
+
+        let key = PreviewKey {
+            args: command.args.clone(),
+            last_pattern: self.last_pattern.clone(),
+            last_substitute: self.last_substitute
+                .as_ref()
+                .map(|last| (last.regex.as_str().to_string(), last.replacement.clone(), last.global, last.confirm))
+        };
+
+        let parsed = match &self.parsed_preview {
+            Some((parsed_from, parsed)) if *parsed_from == key => parsed.clone(),
+            _ => {
+                let parsed = Substitution::parse(&command.args, self.last_substitute.as_ref(), self.last_pattern.as_deref()).ok();
+                self.parsed_preview = Some((key, parsed.clone()));
+                parsed
+            }
+        };
+
+        let (substitution, count) = parsed?;
+        let buffer = self.get_current_buffer()?;
+        let current = self.cursor.pos.line;
+
+        let range = match &command.range {
+            Some(range) => {
+                let (start, end) = range.resolve(buffer, current).ok()?;
+                LineRange::new(start.max(1) - 1, end.max(1) - 1)
+            },
+            None => LineRange::new(current, current)
+        };
+
+        let range = match count {
+            Some(count) => LineRange::new(range.end, (range.end + count - 1).min(buffer.len() - 1)),
+            None => range
+        };
+
+        Some((range, substitution))
+    }
+}
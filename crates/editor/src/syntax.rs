@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+
+use buffer::buffer::Buffer;
+use highlight::grammars::highlighter_for;
+use highlight::{HighlightSpan, Highlighter, Structure, TextObject};
+use utils::Position;
+
+use crate::display::{next_boundary, prev_boundary};
+use crate::editor::{Editor, EditorMode};
+use crate::errors::EditorError;
+
+/// A buffer's highlighter and the filetype it was made for.
+type Entry = (Option<String>, Option<Box<dyn Highlighter>>);
+
+/// Syntax state for the open buffers: a highlighter per buffer, keyed by
+/// `Buffer::id` so a buffer that is replaced (e.g. by `:edit`) starts over
+/// with a fresh one, and what was worked out for the lines on screen by the
+/// last `Editor::update_syntax`.
+#[derive(Default)]
+pub struct Syntax {
+    highlighters: HashMap<u64, Entry>,
+    top: usize,
+    spans: Vec<Vec<HighlightSpan>>,
+    context: Vec<usize>
+}
+
+impl fmt::Debug for Syntax {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Syntax")
+            .field("highlighters", &self.highlighters.len())
+            .field("top", &self.top)
+            .field("context", &self.context)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Syntax {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The highlighter for `buffer`'s filetype, made the first time it is
+    /// asked for and again when the filetype changes, or `None` if the
+    /// filetype has no grammar.
+    fn highlighter(&mut self, buffer: &Buffer) -> Option<&mut Box<dyn Highlighter>> {
+        let filetype = buffer.filetype();
+        let (made_for, highlighter) = self.highlighters.entry(buffer.id()).or_insert((None, None));
+
+        if made_for.as_deref() != filetype {
+            *made_for = filetype.map(|filetype| filetype.to_string());
+            *highlighter = filetype.and_then(|filetype| highlighter_for(filetype).ok());
+        }
+
+        highlighter.as_mut()
+    }
+
+    /// The parsed structure of `buffer`, if its highlighter has one.
+    pub fn structure(&mut self, buffer: &Buffer) -> Option<&mut dyn Structure> {
+        self.highlighter(buffer)?.structure()
+    }
+
+    /// The spans of `line` as of the last update, empty if it wasn't on
+    /// screen.
+    pub fn spans(&self, line: usize) -> &[HighlightSpan] {
+        line.checked_sub(self.top)
+            .and_then(|index| self.spans.get(index))
+            .map(|spans| spans.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// The first lines of the definitions enclosing the top line on screen,
+    /// outermost first, for the sticky context header.
+    pub fn context(&self) -> &[usize] {
+        &self.context
+    }
+}
+
+impl Editor {
+    /// Brings highlighting and the sticky context up to date for the lines
+    /// on screen. The UI calls this before drawing, so drawing itself only
+    /// needs to read the editor.
+    pub fn update_syntax(&mut self) {
+        let top = self.viewport.top;
+        let bottom = self.visible_bottom();
+        let syntax = &mut self.syntax;
+
+        syntax.highlighters.retain(|id, _| self.buffers.values().any(|buffer| buffer.id() == *id));
+        syntax.top = top;
+        syntax.spans.clear();
+        syntax.context.clear();
+
+        let Some(buffer) = self.current_buffer.and_then(|id| self.buffers.get(&id)) else {
+            return;
+        };
+
+        if let Some(highlighter) = syntax.highlighter(buffer) {
+            syntax.spans = highlighter.highlight_lines(buffer, top .. bottom + 1);
+        }
+
+        if let Some(structure) = syntax.structure(buffer) {
+            syntax.context = structure.context(buffer, top);
+        }
+    }
+
+    /// The visual selection, from its first character up to but not
+    /// including the one after its last.
+    pub fn selection(&self) -> Option<Range<Position>> {
+        let anchor = self.visual_anchor?;
+        let cursor = self.cursor.pos;
+
+        let (start, last) = match (anchor.line, anchor.column) <= (cursor.line, cursor.column) {
+            true => (anchor, cursor),
+            false => (cursor, anchor)
+        };
+
+        let end = self.get_current_buffer()
+            .and_then(|buffer| buffer.lines.get(last.line))
+            .map(|text| next_boundary(text, last.column))
+            .unwrap_or(last.column);
+
+        Some(start .. Position::new(last.line, end))
+    }
+
+    /// Makes `range` the visual selection, with the cursor on its last
+    /// character.
+    fn select(&mut self, range: Range<Position>) {
+        let Some(buffer) = self.get_current_buffer() else {
+            return;
+        };
+
+        let end = range.end;
+        let last = if end.column == 0 && end.line > range.start.line {
+            let text = &buffer.lines[end.line - 1];
+            Position::new(end.line - 1, prev_boundary(text, text.len()))
+        } else if end.line == range.start.line {
+            Position::new(end.line, prev_boundary(&buffer.lines[end.line], end.column).max(range.start.column))
+        } else {
+            Position::new(end.line, prev_boundary(&buffer.lines[end.line], end.column))
+        };
+
+        if !matches!(self.mode, EditorMode::Visual) {
+            self.change_mode(EditorMode::Visual);
+        }
+
+        self.visual_anchor = Some(range.start);
+        self.cursor.pos = last;
+        self.clamp_cursor();
+    }
+
+    /// Runs `query` against the current buffer's syntax tree.
+    fn query_structure<T>(&mut self, query: impl FnOnce(&mut dyn Structure, &Buffer) -> Option<T>) -> Result<T, EditorError> {
+        let buffer = self.current_buffer
+            .and_then(|id| self.buffers.get(&id))
+            .ok_or(EditorError::NoBuffer)?;
+
+        let structure = self.syntax.structure(buffer).ok_or(EditorError::NoSyntaxTree)?;
+        query(structure, buffer).ok_or(EditorError::NoTextObject)
+    }
+
+    /// `af`, `if` and the like in visual mode: selects the `object` around
+    /// the cursor.
+    pub fn select_text_object(&mut self, object: TextObject, inner: bool) -> Result<(), EditorError> {
+        let pos = self.cursor.pos;
+        let range = self.query_structure(|structure, buffer| structure.text_object(buffer, pos, object, inner))?;
+
+        if let Some(current) = self.selection() {
+            self.selection_history.push(current);
+        }
+
+        self.select(range);
+        Ok(())
+    }
+
+    /// Grows the selection to the smallest syntax node around it.
+    pub fn expand_selection(&mut self) -> Result<(), EditorError> {
+        let pos = self.cursor.pos;
+        let current = self.selection().unwrap_or(pos .. pos);
+
+        let query_range = current.clone();
+        let range = self.query_structure(|structure, buffer| structure.expand(buffer, query_range))?;
+
+        self.selection_history.push(current);
+        self.select(range);
+        Ok(())
+    }
+
+    /// Undoes the last expansion, going back to the selection before it.
+    pub fn shrink_selection(&mut self) {
+        if let Some(range) = self.selection_history.pop() {
+            self.select(range);
+        }
+    }
+}
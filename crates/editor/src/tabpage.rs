@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+
+use crate::editor::Editor;
+use crate::errors::EditorError;
+use crate::window::{Window, WindowId, WindowLayout};
+
+/// A tab page: a window layout of its own. The current tab page's layout
+/// lives in the editor's `window_layout`, `windows` and `current_window`;
+/// the copy here is brought up to date when another tab page is shown.
+#[derive(Clone, Debug)]
+pub struct TabPage {
+    pub layout: WindowLayout,
+    pub windows: HashMap<WindowId, Window>,
+    pub current_window: WindowId
+}
+
+impl Editor {
+    fn store_tab(&mut self) {
+        self.store_window();
+
+        self.tab_pages[self.current_tab] = TabPage {
+            layout: self.window_layout.clone(),
+            windows: self.windows.clone(),
+            current_window: self.current_window
+        };
+    }
+
+    fn load_tab(&mut self, index: usize) {
+        let tab = self.tab_pages[index].clone();
+
+        self.current_tab = index;
+        self.window_layout = tab.layout;
+        self.windows = tab.windows;
+        self.load_window(tab.current_window);
+    }
+
+    pub fn tab_count(&self) -> usize {
+        self.tab_pages.len()
+    }
+
+    /// What the top bar calls tab page `index`: the buffer in its current
+    /// window, after the number of windows if there are several.
+    pub fn tab_label(&self, index: usize) -> String {
+        let (buffer, windows) = match index == self.current_tab {
+            true => (self.current_buffer, self.windows.len()),
+            false => {
+                let Some(tab) = self.tab_pages.get(index) else {
+                    return String::new();
+                };
+
+                (tab.windows.get(&tab.current_window).and_then(|window| window.buffer), tab.windows.len())
+            }
+        };
+
+        let name = buffer.map(|id| self.get_buffer_display_name(&id)).unwrap_or_else(|| "Untitled".to_string());
+
+        match windows {
+            1 => name,
+            count => format!("{} {}", count, name)
+        }
+    }
+
+    /// Opens a tab page after the current one, with one window showing the
+    /// current buffer, and moves to it.
+    pub fn new_tab(&mut self) {
+        self.leave_visual_mode();
+        self.store_tab();
+
+        let id = self.next_window;
+        self.next_window += 1;
+
+        let tab = TabPage {
+            layout: WindowLayout::Window(id),
+            windows: HashMap::from([(id, self.current_window_state())]),
+            current_window: id
+        };
+
+        self.tab_pages.insert(self.current_tab + 1, tab);
+        self.load_tab(self.current_tab + 1);
+    }
+
+    pub fn switch_tab(&mut self, index: usize) {
+        if index == self.current_tab || index >= self.tab_pages.len() {
+            return;
+        }
+
+        self.leave_visual_mode();
+        self.store_tab();
+        self.load_tab(index);
+    }
+
+    /// `gt` and `gT`: moves `count` tab pages on or back, wrapping around.
+    pub fn cycle_tab(&mut self, forward: bool, count: usize) {
+        let len = self.tab_pages.len();
+        let count = count % len;
+
+        let index = match forward {
+            true => (self.current_tab + count) % len,
+            false => (self.current_tab + len - count) % len
+        };
+
+        self.switch_tab(index);
+    }
+
+    /// Closes tab page `index` and its windows; the buffers stay open. After
+    /// closing the current one the next one is shown.
+    pub fn close_tab(&mut self, index: usize) -> Result<(), EditorError> {
+        if self.tab_pages.len() == 1 {
+            return Err(EditorError::LastTabPage);
+        }
+
+        if index >= self.tab_pages.len() {
+            return Err(EditorError::InvalidArgument((index + 1).to_string()));
+        }
+
+        if index != self.current_tab {
+            self.store_tab();
+            let tab = self.tab_pages.remove(index);
+            self.location_lists.retain(|id, _| !tab.windows.contains_key(id));
+
+            if index < self.current_tab {
+                self.current_tab -= 1;
+            }
+
+            return Ok(());
+        }
+
+        self.leave_visual_mode();
+        self.location_lists.retain(|id, _| !self.windows.contains_key(id));
+        self.tab_pages.remove(index);
+        self.load_tab(index.min(self.tab_pages.len() - 1));
+
+        Ok(())
+    }
+
+    /// Moves the current tab page to position `index`, counting from 0.
+    pub fn move_tab(&mut self, index: usize) {
+        self.store_tab();
+
+        let tab = self.tab_pages.remove(self.current_tab);
+        let index = index.min(self.tab_pages.len());
+
+        self.tab_pages.insert(index, tab);
+        self.current_tab = index;
+    }
+}
@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use toml::{Table, Value};
+
+use crate::errors::EditorError;
+
+/// Themes compiled into the binary, found when no theme file of the same
+/// name exists in the user's theme directory.
+pub const BUILTIN_THEMES: &[(&str, &str)] = &[
+    ("default", include_str!("../themes/default.toml")),
+    ("gruvbox", include_str!("../themes/gruvbox.toml")),
+    ("solarized-light", include_str!("../themes/solarized-light.toml"))
+];
+
+/// The 16 ANSI colours by the names theme files use for them.
+const ANSI_NAMES: &[&str] = &[
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "gray",
+    "dark-gray", "light-red", "light-green", "light-yellow", "light-blue", "light-magenta", "light-cyan", "white"
+];
+
+/// What the 16 ANSI colours look like in xterm's default palette, for
+/// picking the closest one to a colour a terminal cannot show.
+const ANSI_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0), (205, 0, 0), (0, 205, 0), (205, 205, 0), (0, 0, 238), (205, 0, 205), (0, 205, 205), (229, 229, 229),
+    (127, 127, 127), (255, 0, 0), (0, 255, 0), (255, 255, 0), (92, 92, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255)
+];
+
+/// The channel levels of the 6x6x6 colour cube in the 256-colour palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    /// The terminal's own foreground or background.
+    Reset,
+    /// One of the 16 ANSI colours, which the terminal's palette decides.
+    Ansi(u8),
+    /// An entry of the 256-colour palette.
+    Indexed(u8),
+    Rgb(u8, u8, u8)
+}
+
+/// How many colours the terminal can show.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorDepth {
+    Ansi16,
+    Ansi256,
+    TrueColor
+}
+
+impl ColorDepth {
+    /// Guesses the depth from `$COLORTERM` and `$TERM`.
+    pub fn detect() -> Self {
+        Self::from_env(env::var("COLORTERM").ok().as_deref(), env::var("TERM").ok().as_deref())
+    }
+
+    pub fn from_env(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        let term = term.unwrap_or("");
+
+        if matches!(colorterm, Some("truecolor" | "24bit")) || term.ends_with("-direct") {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+}
+
+impl Color {
+    /// Parses `#rrggbb`, an ANSI colour name, `reset`, or a palette index.
+    fn parse(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Integer(index) => u8::try_from(*index)
+                .map(Color::Indexed)
+                .map_err(|_| format!("colour index out of range: {}", index)),
+            Value::String(text) => {
+                if let Some(hex) = text.strip_prefix('#') {
+                    let channel = |at: usize| hex.get(at .. at + 2).and_then(|digits| u8::from_str_radix(digits, 16).ok());
+
+                    match (hex.len(), channel(0), channel(2), channel(4)) {
+                        (6, Some(r), Some(g), Some(b)) => Ok(Color::Rgb(r, g, b)),
+                        _ => Err(format!("invalid colour: {}", text))
+                    }
+                } else if text == "reset" || text == "default" {
+                    Ok(Color::Reset)
+                } else {
+                    ANSI_NAMES
+                        .iter()
+                        .position(|name| name == text)
+                        .map(|index| Color::Ansi(index as u8))
+                        .ok_or_else(|| format!("unknown colour: {}", text))
+                }
+            },
+            _ => Err(format!("invalid colour: {}", value))
+        }
+    }
+
+    /// The closest colour a terminal of `depth` can show.
+    pub fn fit(self, depth: ColorDepth) -> Self {
+        match (self, depth) {
+            (_, ColorDepth::TrueColor) | (Color::Reset | Color::Ansi(_), _) => self,
+            (Color::Indexed(index), ColorDepth::Ansi256) => Color::Indexed(index),
+            (Color::Indexed(index), ColorDepth::Ansi16) if index < 16 => Color::Ansi(index),
+            (Color::Indexed(index), ColorDepth::Ansi16) => Color::Ansi(nearest_ansi(indexed_rgb(index))),
+            (Color::Rgb(r, g, b), ColorDepth::Ansi256) => Color::Indexed(nearest_indexed((r, g, b))),
+            (Color::Rgb(r, g, b), ColorDepth::Ansi16) => Color::Ansi(nearest_ansi((r, g, b)))
+        }
+    }
+}
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let channel = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    channel(r1, r2) + channel(g1, g2) + channel(b1, b2)
+}
+
+fn nearest_ansi(rgb: (u8, u8, u8)) -> u8 {
+    (0 .. 16).min_by_key(|&index| distance(ANSI_RGB[index as usize], rgb)).unwrap_or(0)
+}
+
+/// The nearest entry of the colour cube or the grey ramp; the first 16
+/// entries are left out as terminals redefine them.
+fn nearest_indexed(rgb: (u8, u8, u8)) -> u8 {
+    (16 ..= 255).min_by_key(|&index| distance(indexed_rgb(index), rgb)).unwrap_or(16)
+}
+
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0 .. 16 => ANSI_RGB[index as usize],
+        16 .. 232 => {
+            let index = index - 16;
+            (CUBE_LEVELS[(index / 36) as usize], CUBE_LEVELS[(index / 6 % 6) as usize], CUBE_LEVELS[(index % 6) as usize])
+        },
+        _ => {
+            let level = 8 + (index - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+
+/// Text attributes as a bit set.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    pub const BOLD: Modifiers = Modifiers(1);
+    pub const DIM: Modifiers = Modifiers(1 << 1);
+    pub const ITALIC: Modifiers = Modifiers(1 << 2);
+    pub const UNDERLINED: Modifiers = Modifiers(1 << 3);
+    pub const REVERSED: Modifiers = Modifiers(1 << 4);
+    pub const CROSSED_OUT: Modifiers = Modifiers(1 << 5);
+
+    const NAMES: &[(&str, Modifiers)] = &[
+        ("bold", Modifiers::BOLD),
+        ("dim", Modifiers::DIM),
+        ("italic", Modifiers::ITALIC),
+        ("underlined", Modifiers::UNDERLINED),
+        ("reversed", Modifiers::REVERSED),
+        ("crossed-out", Modifiers::CROSSED_OUT)
+    ];
+
+    pub fn contains(self, other: Modifiers) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: Modifiers) {
+        self.0 |= other.0;
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl std::ops::BitOr for Modifiers {
+    type Output = Modifiers;
+
+    fn bitor(self, other: Modifiers) -> Modifiers {
+        Modifiers(self.0 | other.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ThemeStyle {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub modifiers: Modifiers
+}
+
+/// Styles for named scopes such as `ui.linenr` or `syntax.keyword.control`.
+/// A scope without a style of its own takes the style of the longest
+/// prefix that has one, so `syntax.keyword` covers all keywords.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    styles: HashMap<String, ThemeStyle>
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::builtin("default").expect("the default theme parses")
+    }
+}
+
+impl Theme {
+    /// Parses a theme file. Top-level tables nest scopes, so `[ui]` with
+    /// `linenr = { fg = "gray" }` styles `ui.linenr`; a bare string is a
+    /// foreground colour. `[palette]` names colours for the rest of the
+    /// file and `inherits` names a theme whose styles this one extends.
+    pub fn parse(name: &str, text: &str) -> Result<Self, EditorError> {
+        Self::parse_from(name, text, theme_dir().as_deref(), &mut vec![name.to_string()])
+    }
+
+    /// Parses theme `name`, loading the theme it inherits from `dir` or the
+    /// built-in ones. `seen` holds the themes already on the way down, to
+    /// catch one that ends up inheriting itself. A theme in `dir` that
+    /// inherits its own name extends the built-in theme of that name.
+    fn parse_from(name: &str, text: &str, dir: Option<&Path>, seen: &mut Vec<String>) -> Result<Self, EditorError> {
+        let invalid = |message: String| EditorError::InvalidTheme(name.to_string(), message);
+
+        let mut table: Table = text.parse().map_err(|err: toml::de::Error| invalid(err.message().to_string()))?;
+
+        let mut theme = match table.remove("inherits") {
+            Some(Value::String(parent)) if parent == name => match (dir, builtin_text(name)) {
+                (Some(_), Some(text)) => Theme::parse_from(name, text, None, seen)?,
+                _ => return Err(invalid("inheritance cycle".to_string()))
+            },
+            Some(Value::String(parent)) => Theme::load_chain(&parent, dir, seen)?,
+            Some(_) => return Err(invalid("invalid inherits".to_string())),
+            None => Theme { name: String::new(), styles: HashMap::new() }
+        };
+
+        theme.name = name.to_string();
+
+        let palette = match table.remove("palette") {
+            Some(Value::Table(palette)) => palette
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), Color::parse(value)?)))
+                .collect::<Result<HashMap<_, _>, String>>()
+                .map_err(invalid)?,
+            Some(_) => return Err(invalid("palette must be a table".to_string())),
+            None => HashMap::new()
+        };
+
+        add_styles(&mut theme.styles, "", &table, &palette).map_err(invalid)?;
+
+        Ok(theme)
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        Theme::parse_from(name, builtin_text(name)?, None, &mut vec![name.to_string()]).ok()
+    }
+
+    /// Loads `name` from the user's theme directory, falling back to the
+    /// built-in themes.
+    pub fn load(name: &str) -> Result<Self, EditorError> {
+        Self::load_from(name, theme_dir().as_deref())
+    }
+
+    pub fn load_from(name: &str, dir: Option<&Path>) -> Result<Self, EditorError> {
+        Self::load_chain(name, dir, &mut vec![])
+    }
+
+    fn load_chain(name: &str, dir: Option<&Path>, seen: &mut Vec<String>) -> Result<Self, EditorError> {
+        if seen.iter().any(|seen| seen == name) {
+            return Err(EditorError::InvalidTheme(name.to_string(), "inheritance cycle".to_string()));
+        }
+
+        seen.push(name.to_string());
+
+        if let Some(dir) = dir
+            && let Ok(text) = fs::read_to_string(dir.join(format!("{}.toml", name)))
+        {
+            return Theme::parse_from(name, &text, Some(dir), seen);
+        }
+
+        match builtin_text(name) {
+            Some(text) => Theme::parse_from(name, text, None, seen),
+            None => Err(EditorError::UnknownTheme(name.to_string()))
+        }
+    }
+
+    /// The names of every theme that can be loaded, built-in or not.
+    pub fn available() -> Vec<String> {
+        let mut names: Vec<String> = BUILTIN_THEMES.iter().map(|(name, _)| name.to_string()).collect();
+
+        if let Some(entries) = theme_dir().and_then(|dir| fs::read_dir(dir).ok()) {
+            for path in entries.flatten().map(|entry| entry.path()) {
+                if path.extension().is_some_and(|ext| ext == "toml")
+                    && let Some(stem) = path.file_stem().and_then(|stem| stem.to_str())
+                    && !names.iter().any(|name| name == stem)
+                {
+                    names.push(stem.to_string());
+                }
+            }
+        }
+
+        names.sort();
+        names
+    }
+
+    pub fn style(&self, scope: &str) -> ThemeStyle {
+        let mut scope = scope;
+
+        loop {
+            if let Some(style) = self.styles.get(scope) {
+                return *style;
+            }
+
+            match scope.rfind('.') {
+                Some(dot) => scope = &scope[.. dot],
+                None => return ThemeStyle::default()
+            }
+        }
+    }
+
+    /// The style for a highlighter scope such as `keyword.control`.
+    pub fn syntax_style(&self, scope: &str) -> ThemeStyle {
+        self.style(&format!("syntax.{}", scope))
+    }
+
+    /// The theme with every colour replaced by the closest one a terminal
+    /// of `depth` can show.
+    pub fn fit(mut self, depth: ColorDepth) -> Self {
+        for style in self.styles.values_mut() {
+            style.fg = style.fg.map(|color| color.fit(depth));
+            style.bg = style.bg.map(|color| color.fit(depth));
+        }
+
+        self
+    }
+}
+
+fn add_styles(styles: &mut HashMap<String, ThemeStyle>, prefix: &str, table: &Table, palette: &HashMap<String, Color>) -> Result<(), String> {
+    for (key, value) in table {
+        let scope = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+
+        match value {
+            Value::Table(table) if !is_style(table) => add_styles(styles, &scope, table, palette)?,
+            value => {
+                styles.insert(scope.clone(), parse_style(value, palette).map_err(|err| format!("{}: {}", scope, err))?);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn is_style(table: &Table) -> bool {
+    ["fg", "bg", "modifiers"].iter().any(|key| table.contains_key(*key))
+}
+
+fn builtin_text(name: &str) -> Option<&'static str> {
+    BUILTIN_THEMES.iter().find(|(builtin, _)| *builtin == name).map(|(_, text)| *text)
+}
+
+fn parse_style(value: &Value, palette: &HashMap<String, Color>) -> Result<ThemeStyle, String> {
+    let color = |value: &Value| match value {
+        Value::String(name) if palette.contains_key(name) => Ok(palette[name]),
+        value => Color::parse(value)
+    };
+
+    let table = match value {
+        Value::Table(table) => table,
+        value => return Ok(ThemeStyle { fg: Some(color(value)?), ..ThemeStyle::default() })
+    };
+
+    let mut style = ThemeStyle::default();
+
+    for (key, value) in table {
+        match (key.as_str(), value) {
+            ("fg", value) => style.fg = Some(color(value)?),
+            ("bg", value) => style.bg = Some(color(value)?),
+            ("modifiers", Value::Array(names)) => {
+                for name in names {
+                    let modifier = Modifiers::NAMES
+                        .iter()
+                        .find(|(modifier, _)| Some(*modifier) == name.as_str())
+                        .ok_or_else(|| format!("unknown modifier: {}", name))?;
+
+                    style.modifiers.insert(modifier.1);
+                }
+            },
+            (key, _) => return Err(format!("unknown key: {}", key))
+        }
+    }
+
+    Ok(style)
+}
+
+/// `$XDG_CONFIG_HOME/paw`, or `~/.config/paw` without it.
+pub fn config_dir() -> Option<PathBuf> {
+    match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => Some(PathBuf::from(dir).join("paw")),
+        None => env::var_os("HOME").map(|home| PathBuf::from(home).join(".config").join("paw"))
+    }
+}
+
+fn theme_dir() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("themes"))
+}
@@ -0,0 +1,210 @@
+use utils::Position;
+
+use crate::editor::Editor;
+
+/// The part of the buffer shown in the text area: the first visible line and
+/// column, and how many lines and columns fit. The size is set by the UI
+/// before each draw; until then it is zero and no scrolling happens.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Viewport {
+    pub top: usize,
+    pub left: usize,
+    pub width: usize,
+    pub height: usize
+}
+
+/// Where `zt`, `zz` and `zb` put the cursor line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScrollAnchor {
+    Top,
+    Center,
+    Bottom
+}
+
+impl Viewport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The last line that fits on screen, whether or not the buffer has it.
+    pub fn bottom(&self) -> usize {
+        self.top + self.height.max(1) - 1
+    }
+
+    pub fn contains(&self, line: usize) -> bool {
+        line >= self.top && line <= self.bottom()
+    }
+
+    /// Scrolls just enough for `pos` to be visible with `scroll_off` lines
+    /// above and below it and `side_scroll_off` columns either side. The
+    /// margin below is never more than the lines left in the buffer, so the
+    /// view doesn't scroll past the end to honour it.
+    pub fn scroll_to(&mut self, pos: Position, line_count: usize, scroll_off: usize, side_scroll_off: usize) {
+        if self.height > 0 {
+            let above = scroll_off.min((self.height - 1) / 2);
+            let below = above.min(line_count.saturating_sub(pos.line + 1));
+
+            if pos.line < self.top + above {
+                self.top = pos.line.saturating_sub(above);
+            } else if pos.line + below > self.bottom() {
+                self.top = pos.line + below + 1 - self.height;
+            }
+
+            self.top = self.top.min(line_count.saturating_sub(1));
+        }
+
+        if self.width > 0 {
+            let margin = side_scroll_off.min((self.width - 1) / 2);
+
+            if pos.column < self.left + margin {
+                self.left = pos.column.saturating_sub(margin);
+            } else if pos.column + margin >= self.left + self.width {
+                self.left = pos.column + margin + 1 - self.width;
+            }
+        }
+    }
+}
+
+impl Editor {
+    pub fn resize_viewport(&mut self, width: usize, height: usize) {
+        self.viewport.width = width;
+        self.viewport.height = height;
+        self.scroll_to_cursor();
+    }
+
+    /// Scrolls so the cursor is on screen. Sideways scrolling works in
+    /// screen columns, and with `wrap` set the line-based scroll is followed
+    /// by one that accounts for wrapped rows.
+    pub fn scroll_to_cursor(&mut self) {
+        let line_count = self.line_count();
+        let pos = self.cursor.pos;
+
+        let column = self.get_current_buffer()
+            .and_then(|buffer| buffer.line_slice(pos.line, pos.line + 1).first())
+            .map(|text| self.text_layout().to_visual(text, pos.column).1)
+            .unwrap_or(pos.column);
+
+        let window = &self.window_options;
+        self.viewport.scroll_to(Position::new(pos.line, column), line_count, window.scroll_off, window.side_scroll_off);
+
+        if self.window_options.wrap {
+            let below = self.effective_scroll_off().min(line_count.saturating_sub(pos.line + 1));
+            self.scroll_wrapped_to_cursor(below);
+        }
+    }
+
+    fn line_count(&self) -> usize {
+        self.get_current_buffer().map(|buffer| buffer.len()).unwrap_or(1)
+    }
+
+    /// `scroll_off` as it applies to the current window height.
+    fn effective_scroll_off(&self) -> usize {
+        self.window_options.scroll_off.min(self.viewport.height.saturating_sub(1) / 2)
+    }
+
+    /// Moves the cursor line onto the screen after the view scrolled away
+    /// from it, keeping `scroll_off` lines between it and the edges except at
+    /// the start and end of the buffer.
+    fn keep_cursor_in_view(&mut self) {
+        let last = self.line_count() - 1;
+        let margin = self.effective_scroll_off();
+        let top = self.viewport.top;
+        let bottom = self.visible_bottom();
+
+        let min = if top == 0 { 0 } else { top + margin };
+        let max = if bottom >= last { last } else { bottom.saturating_sub(margin) };
+
+        self.cursor.pos.line = self.cursor.pos.line.clamp(min.min(last), max.max(min).min(last));
+        self.clamp_cursor();
+    }
+
+    /// `<C-e>` and `<C-y>`: scrolls the view by `delta` lines without moving
+    /// the cursor unless it would leave the screen.
+    pub fn scroll_lines(&mut self, delta: isize) {
+        let last = self.line_count() - 1;
+
+        self.viewport.top = self.viewport.top.saturating_add_signed(delta).min(last);
+        self.keep_cursor_in_view();
+    }
+
+    /// `<C-d>` and `<C-u>`: scrolls the view and the cursor by half a screen.
+    /// At the end of the buffer the cursor keeps moving after the view stops.
+    pub fn scroll_half_page(&mut self, down: bool) {
+        let amount = (self.viewport.height / 2).max(1);
+        let last = self.line_count() - 1;
+        let max_top = (last + 1).saturating_sub(self.viewport.height);
+
+        if down {
+            self.viewport.top = (self.viewport.top + amount).min(max_top.max(self.viewport.top));
+            self.cursor.pos.line = (self.cursor.pos.line + amount).min(last);
+        } else {
+            self.viewport.top = self.viewport.top.saturating_sub(amount);
+            self.cursor.pos.line = self.cursor.pos.line.saturating_sub(amount);
+        }
+
+        self.move_to_first_non_blank();
+        self.keep_cursor_in_view();
+    }
+
+    /// `<C-f>` and `<C-b>`: scrolls by a screen less two lines, so the lines
+    /// at the edge stay visible for context.
+    pub fn scroll_page(&mut self, down: bool) {
+        let amount = self.viewport.height.saturating_sub(2).max(1);
+        let last = self.line_count() - 1;
+
+        if down {
+            self.viewport.top = (self.viewport.top + amount).min(last);
+            self.cursor.pos.line = self.viewport.top;
+        } else {
+            self.viewport.top = self.viewport.top.saturating_sub(amount);
+            self.cursor.pos.line = self.visible_bottom().min(last);
+        }
+
+        self.move_to_first_non_blank();
+        self.keep_cursor_in_view();
+    }
+
+    /// `zt`, `zz` and `zb`: scrolls so the cursor line is at the top, middle
+    /// or bottom of the screen.
+    pub fn scroll_cursor_to(&mut self, anchor: ScrollAnchor) {
+        let line = self.cursor.pos.line;
+        let margin = self.effective_scroll_off();
+        let height = self.viewport.height.max(1);
+
+        self.viewport.top = match anchor {
+            ScrollAnchor::Top => line.saturating_sub(margin),
+            ScrollAnchor::Center => line.saturating_sub((height - 1) / 2),
+            ScrollAnchor::Bottom => {
+                let below = margin.min(self.line_count() - 1 - line);
+                (line + below + 1).saturating_sub(height)
+            }
+        };
+    }
+
+    /// `H`, `M` and `L`: moves the cursor to the top, middle or bottom line
+    /// on screen, inside `scroll_off`.
+    pub fn move_to_screen_line(&mut self, anchor: ScrollAnchor) {
+        let last = self.line_count() - 1;
+        let margin = self.effective_scroll_off();
+        let top = self.viewport.top.min(last);
+        let bottom = self.visible_bottom().min(last);
+
+        self.cursor.pos.line = match anchor {
+            ScrollAnchor::Top if top == 0 => 0,
+            ScrollAnchor::Top => (top + margin).min(bottom),
+            ScrollAnchor::Center => (top + bottom) / 2,
+            ScrollAnchor::Bottom if bottom == last => last,
+            ScrollAnchor::Bottom => bottom.saturating_sub(margin).max(top)
+        };
+
+        self.move_to_first_non_blank();
+    }
+
+    fn move_to_first_non_blank(&mut self) {
+        if let Some(buffer) = self.get_current_buffer()
+            && let Ok(line) = buffer.get_line(self.cursor.pos.line)
+        {
+            self.cursor.pos.column = line.len() - line.trim_start().len();
+        }
+    }
+}
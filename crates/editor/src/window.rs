@@ -0,0 +1,515 @@
+use uuid::Uuid;
+
+use crate::cursor::Cursor;
+use crate::editor::{Editor, EditorMode};
+use crate::errors::EditorError;
+use crate::options::WindowOptions;
+use crate::viewport::Viewport;
+
+pub type WindowId = usize;
+
+/// How a split arranges its windows: `Horizontal` above one another, as
+/// `:split` makes, `Vertical` side by side, as `:vsplit` makes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SplitDirection {
+    Horizontal,
+    Vertical
+}
+
+/// Which way `<C-w>h`, `j`, `k` and `l` move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowDirection {
+    Left,
+    Down,
+    Up,
+    Right
+}
+
+/// A view onto a buffer. The current window's state lives in the editor's
+/// own `current_buffer`, `cursor`, `viewport` and `window_options`; the
+/// copy here is brought up to date when another window gets the focus.
+#[derive(Clone, Debug)]
+pub struct Window {
+    pub buffer: Option<Uuid>,
+    pub cursor: Cursor,
+    pub viewport: Viewport,
+    pub options: WindowOptions
+}
+
+/// The part of the screen a window takes, its status line included.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WindowRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize
+}
+
+impl WindowRect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// The extent along `direction`: the height of a horizontal split's
+    /// windows, the width of a vertical one's.
+    fn size(&self, direction: SplitDirection) -> usize {
+        match direction {
+            SplitDirection::Horizontal => self.height,
+            SplitDirection::Vertical => self.width
+        }
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+}
+
+/// The split tree. Each child of a split has a weight, and the space is
+/// shared out in proportion to the weights.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WindowLayout {
+    Window(WindowId),
+    Split { direction: SplitDirection, children: Vec<(WindowLayout, usize)> }
+}
+
+impl WindowLayout {
+    /// The windows from top left to bottom right.
+    pub fn windows(&self) -> Vec<WindowId> {
+        match self {
+            WindowLayout::Window(id) => vec![*id],
+            WindowLayout::Split { children, .. } => children.iter().flat_map(|(child, _)| child.windows()).collect()
+        }
+    }
+
+    pub fn contains(&self, id: WindowId) -> bool {
+        match self {
+            WindowLayout::Window(window) => *window == id,
+            WindowLayout::Split { children, .. } => children.iter().any(|(child, _)| child.contains(id))
+        }
+    }
+
+    /// Puts `new` before `target`, sharing its space. A split in the same
+    /// direction gains a child rather than nesting another split.
+    pub fn split(&mut self, target: WindowId, new: WindowId, direction: SplitDirection) {
+        match self {
+            WindowLayout::Window(id) if *id == target => {
+                *self = WindowLayout::Split {
+                    direction,
+                    children: vec![(WindowLayout::Window(new), 1), (WindowLayout::Window(target), 1)]
+                };
+            },
+            WindowLayout::Window(_) => {},
+            WindowLayout::Split { direction: split_direction, children } => {
+                let index = children.iter().position(|(child, _)| *child == WindowLayout::Window(target));
+
+                match index {
+                    Some(index) if *split_direction == direction => {
+                        let weight = children[index].1;
+                        let half = weight / 2;
+
+                        children[index].1 = weight - half;
+                        children.insert(index, (WindowLayout::Window(new), half.max(1)));
+
+                        if half == 0 {
+                            for (_, weight) in children.iter_mut() {
+                                *weight *= 2;
+                            }
+                        }
+                    },
+                    _ => {
+                        for (child, _) in children.iter_mut() {
+                            child.split(target, new, direction);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Takes `target` out, giving its space to the rest of its split. A
+    /// split left with one child is replaced by it.
+    pub fn remove(&mut self, target: WindowId) {
+        let WindowLayout::Split { direction, children } = self else {
+            return;
+        };
+
+        children.retain(|(child, _)| *child != WindowLayout::Window(target));
+
+        for (child, _) in children.iter_mut() {
+            child.remove(target);
+        }
+
+        if children.len() == 1 {
+            let (child, _) = children.remove(0);
+            *self = child;
+            return;
+        }
+
+        // A split that collapsed into one in the same direction as this
+        // one joins it.
+        let direction = *direction;
+        let mut index = 0;
+
+        while index < children.len() {
+            if let (WindowLayout::Split { direction: inner, .. }, _) = &children[index]
+                && *inner == direction
+            {
+                let (WindowLayout::Split { children: inner, .. }, weight) = children.remove(index) else {
+                    unreachable!();
+                };
+
+                let total: usize = inner.iter().map(|(_, weight)| weight).sum();
+                let count = inner.len();
+
+                for (offset, (child, inner_weight)) in inner.into_iter().enumerate() {
+                    children.insert(index + offset, (child, (weight * inner_weight).div_ceil(total.max(1))));
+                }
+
+                index += count;
+            } else {
+                index += 1;
+            }
+        }
+    }
+
+    /// Gives every child of every split the same weight.
+    pub fn equalize(&mut self) {
+        if let WindowLayout::Split { children, .. } = self {
+            for (child, weight) in children.iter_mut() {
+                child.equalize();
+                *weight = 1;
+            }
+        }
+    }
+
+    /// Where each window goes in `area`.
+    pub fn rects(&self, area: WindowRect) -> Vec<(WindowId, WindowRect)> {
+        match self {
+            WindowLayout::Window(id) => vec![(*id, area)],
+            WindowLayout::Split { direction, children } => {
+                let sizes = share(area.size(*direction), children.iter().map(|(_, weight)| *weight));
+
+                children.iter()
+                    .enumerate()
+                    .flat_map(|(index, (child, _))| child.rects(part(area, *direction, &sizes, index)))
+                    .collect()
+            }
+        }
+    }
+
+    /// Makes `target` `size` cells high or wide in the innermost split in
+    /// `direction` it is part of, taking the space from the windows after
+    /// it and then the ones before. Returns whether there was such a split.
+    pub fn resize(&mut self, target: WindowId, direction: SplitDirection, size: usize, area: WindowRect) -> bool {
+        let WindowLayout::Split { direction: split_direction, children } = self else {
+            return false;
+        };
+
+        let split_direction = *split_direction;
+        let mut sizes = share(area.size(split_direction), children.iter().map(|(_, weight)| *weight));
+
+        let Some(index) = children.iter().position(|(child, _)| child.contains(target)) else {
+            return false;
+        };
+
+        if children[index].0.resize(target, direction, size, part(area, split_direction, &sizes, index)) {
+            return true;
+        }
+
+        if split_direction != direction {
+            return false;
+        }
+
+        let total: usize = sizes.iter().sum();
+        let size = size.clamp(1, total.saturating_sub(children.len() - 1).max(1));
+        let mut excess = size as isize - sizes[index] as isize;
+        sizes[index] = size;
+
+        for other in (index + 1 .. sizes.len()).chain((0 .. index).rev()) {
+            if excess > 0 {
+                let taken = (excess as usize).min(sizes[other].saturating_sub(1));
+                sizes[other] -= taken;
+                excess -= taken as isize;
+            } else if excess < 0 {
+                sizes[other] += excess.unsigned_abs();
+                excess = 0;
+            }
+        }
+
+        for ((_, weight), size) in children.iter_mut().zip(sizes) {
+            *weight = size;
+        }
+
+        true
+    }
+}
+
+/// The part of `area` the child at `index` of a split gets, given the
+/// sizes of all of them.
+fn part(area: WindowRect, direction: SplitDirection, sizes: &[usize], index: usize) -> WindowRect {
+    let offset: usize = sizes[.. index].iter().sum();
+
+    match direction {
+        SplitDirection::Horizontal => WindowRect::new(area.x, area.y + offset, area.width, sizes[index]),
+        SplitDirection::Vertical => WindowRect::new(area.x + offset, area.y, sizes[index], area.height)
+    }
+}
+
+/// Shares `total` cells out in proportion to `weights`, the rounding going
+/// to the last ones.
+fn share(total: usize, weights: impl Iterator<Item = usize>) -> Vec<usize> {
+    let weights: Vec<usize> = weights.collect();
+    let sum: usize = weights.iter().sum::<usize>().max(1);
+    let mut sizes: Vec<usize> = weights.iter().map(|weight| total * weight / sum).collect();
+    let mut left = total - sizes.iter().sum::<usize>();
+
+    for size in sizes.iter_mut().rev() {
+        if left == 0 {
+            break;
+        }
+
+        *size += 1;
+        left -= 1;
+    }
+
+    sizes
+}
+
+impl Editor {
+    /// The current window's state as the editor holds it.
+    pub(crate) fn current_window_state(&self) -> Window {
+        Window {
+            buffer: self.current_buffer,
+            cursor: self.cursor,
+            viewport: self.viewport,
+            options: self.window_options.clone()
+        }
+    }
+
+    pub(crate) fn store_window(&mut self) {
+        let window = self.current_window_state();
+        self.windows.insert(self.current_window, window);
+    }
+
+    /// Makes `id`'s state the editor's, with the cursor pulled back inside
+    /// its buffer in case another window changed it.
+    pub(crate) fn load_window(&mut self, id: WindowId) {
+        let Some(window) = self.windows.get(&id).cloned() else {
+            return;
+        };
+
+        self.current_window = id;
+        self.current_buffer = window.buffer;
+        self.cursor = window.cursor;
+        self.viewport = window.viewport;
+        self.window_options = window.options;
+        self.clamp_cursor();
+    }
+
+    /// Window `id`'s buffer, cursor, viewport and options.
+    pub fn window(&self, id: WindowId) -> Option<Window> {
+        match id == self.current_window {
+            true => Some(self.current_window_state()),
+            false => self.windows.get(&id).cloned()
+        }
+    }
+
+    pub fn window_count(&self) -> usize {
+        self.windows.len()
+    }
+
+    /// The windows and where they are on screen.
+    pub fn window_rects(&self) -> Vec<(WindowId, WindowRect)> {
+        self.window_layout.rects(self.window_area)
+    }
+
+    /// Sets the screen area the windows share.
+    pub fn resize_windows(&mut self, area: WindowRect) {
+        self.window_area = area;
+    }
+
+    /// Runs `action` with `id` as the current window, then gives the
+    /// focus back, as drawing each window needs.
+    pub fn with_window<T>(&mut self, id: WindowId, action: impl FnOnce(&mut Self) -> T) -> T {
+        let current = self.current_window;
+
+        if id == current || !self.windows.contains_key(&id) {
+            return action(self);
+        }
+
+        self.store_window();
+        self.load_window(id);
+        let result = action(self);
+        self.store_window();
+        self.load_window(current);
+
+        result
+    }
+
+    pub fn focus_window(&mut self, id: WindowId) {
+        if id == self.current_window || !self.windows.contains_key(&id) {
+            return;
+        }
+
+        self.leave_visual_mode();
+        self.store_window();
+        self.load_window(id);
+    }
+
+    /// Ends visual mode, as moving to another window or tab page does.
+    pub(crate) fn leave_visual_mode(&mut self) {
+        if matches!(self.mode, EditorMode::Visual) {
+            self.change_mode(EditorMode::Normal);
+        }
+    }
+
+    /// Splits the current window in two, both showing its buffer, and
+    /// moves to the new one.
+    pub fn split_window(&mut self, direction: SplitDirection) {
+        let id = self.next_window;
+        self.next_window += 1;
+
+        self.store_window();
+        self.windows.insert(id, self.current_window_state());
+
+        // The new window starts with a copy of the location list.
+        if let Some(list) = self.location_lists.get(&self.current_window).cloned() {
+            self.location_lists.insert(id, list);
+        }
+
+        self.window_layout.split(self.current_window, id, direction);
+        self.load_window(id);
+    }
+
+    /// Closes window `id`, moving to the one before it if it was current.
+    /// Closing the last window of a tab page closes the tab page.
+    pub fn close_window(&mut self, id: WindowId) -> Result<(), EditorError> {
+        if self.windows.len() == 1 {
+            return match self.tab_count() {
+                1 => Err(EditorError::LastWindow),
+                _ => self.close_tab(self.current_tab)
+            };
+        }
+
+        let order = self.window_layout.windows();
+        let index = order.iter().position(|window| *window == id).unwrap_or(0);
+
+        self.window_layout.remove(id);
+        self.windows.remove(&id);
+        self.location_lists.remove(&id);
+
+        if id == self.current_window {
+            let next = index.checked_sub(1).or(Some(index + 1)).and_then(|index| order.get(index)).copied().unwrap_or(0);
+            self.load_window(next);
+        }
+
+        Ok(())
+    }
+
+    /// `:only`: closes every window but the current one.
+    pub fn only_window(&mut self) {
+        let current = self.current_window;
+
+        self.location_lists.retain(|id, _| *id == current || !self.windows.contains_key(id));
+        self.windows.retain(|id, _| *id == current);
+        self.window_layout = WindowLayout::Window(current);
+    }
+
+    /// `<C-w>w` and `<C-w>W`: moves to the next or previous window,
+    /// wrapping around.
+    pub fn cycle_window(&mut self, forward: bool) {
+        let order = self.window_layout.windows();
+        let index = order.iter().position(|id| *id == self.current_window).unwrap_or(0);
+
+        let next = match forward {
+            true => (index + 1) % order.len(),
+            false => (index + order.len() - 1) % order.len()
+        };
+
+        self.focus_window(order[next]);
+    }
+
+    /// The window next to the current one in `direction`; of several, the
+    /// one level with the cursor.
+    pub fn window_in_direction(&self, direction: WindowDirection) -> Option<WindowId> {
+        let rects = self.window_rects();
+        let (_, current) = rects.iter().find(|(id, _)| *id == self.current_window)?;
+        let (row, column) = self.cursor_screen_position().unwrap_or((0, 0));
+        let (cursor_x, cursor_y) = (current.x + column.min(current.width.saturating_sub(1)), current.y + row.min(current.height.saturating_sub(1)));
+
+        let adjacent = |rect: &WindowRect| match direction {
+            WindowDirection::Left => rect.x + rect.width == current.x,
+            WindowDirection::Right => current.x + current.width == rect.x,
+            WindowDirection::Up => rect.y + rect.height == current.y,
+            WindowDirection::Down => current.y + current.height == rect.y
+        };
+
+        let overlaps = |rect: &WindowRect| match direction {
+            WindowDirection::Left | WindowDirection::Right => rect.y < current.y + current.height && current.y < rect.y + rect.height,
+            WindowDirection::Up | WindowDirection::Down => rect.x < current.x + current.width && current.x < rect.x + rect.width
+        };
+
+        let level = |rect: &WindowRect| match direction {
+            WindowDirection::Left | WindowDirection::Right => (rect.y .. rect.y + rect.height).contains(&cursor_y),
+            WindowDirection::Up | WindowDirection::Down => (rect.x .. rect.x + rect.width).contains(&cursor_x)
+        };
+
+        let candidates: Vec<&(WindowId, WindowRect)> = rects.iter()
+            .filter(|(_, rect)| adjacent(rect) && overlaps(rect))
+            .collect();
+
+        candidates.iter()
+            .find(|(_, rect)| level(rect))
+            .or(candidates.first())
+            .map(|(id, _)| *id)
+    }
+
+    /// `<C-w>h`, `j`, `k` and `l`.
+    pub fn move_to_window(&mut self, direction: WindowDirection) {
+        match self.window_in_direction(direction) {
+            Some(id) => self.focus_window(id),
+            // The explorer is to the left of every window.
+            None if direction == WindowDirection::Left => {
+                if let Some(explorer) = self.explorer.as_mut() {
+                    explorer.focused = true;
+                }
+            },
+            None => {}
+        }
+    }
+
+    /// The current window's height (text rows, not counting its status
+    /// line) or width.
+    pub fn window_size(&self, direction: SplitDirection) -> usize {
+        let rect = self.window_rects()
+            .into_iter()
+            .find(|(id, _)| *id == self.current_window)
+            .map(|(_, rect)| rect)
+            .unwrap_or_default();
+
+        match direction {
+            SplitDirection::Horizontal => rect.height.saturating_sub(1),
+            SplitDirection::Vertical => rect.width
+        }
+    }
+
+    /// `:resize` and `<C-w>+`: makes the current window `size` rows high,
+    /// or with `Vertical` columns wide, as far as the splits allow.
+    pub fn set_window_size(&mut self, direction: SplitDirection, size: usize) {
+        let size = match direction {
+            SplitDirection::Horizontal => size + 1,
+            SplitDirection::Vertical => size
+        };
+
+        self.window_layout.resize(self.current_window, direction, size, self.window_area);
+    }
+
+    pub fn resize_window(&mut self, direction: SplitDirection, delta: isize) {
+        let size = self.window_size(direction).saturating_add_signed(delta);
+        self.set_window_size(direction, size);
+    }
+
+    pub fn equalize_windows(&mut self) {
+        self.window_layout.equalize();
+    }
+}
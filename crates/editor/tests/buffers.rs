@@ -0,0 +1,185 @@
+pub mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use editor::editor::Editor;
+use editor::fuzzy::fuzzy_match;
+use utils::Position;
+
+use common::{temp_dir, type_keys};
+
+/// An editor with a file open for each of `names`, all in a fresh
+/// directory, showing the last one.
+fn editor_with_files(test: &str, names: &[&str]) -> (Editor, PathBuf) {
+    let dir = temp_dir(test);
+
+    let mut editor = Editor::new();
+
+    for name in names {
+        let path = dir.join(name);
+        fs::write(&path, format!("{}\nsecond\n", name)).unwrap();
+
+        let id = editor.create_buffer_from_file(path).unwrap();
+        editor.switch_to_buffer(id);
+    }
+
+    (editor, dir)
+}
+
+fn current_name(editor: &Editor) -> String {
+    editor.get_buffer_display_name(&editor.current_buffer.unwrap())
+}
+
+#[test]
+fn cycles_and_switches_buffers() {
+    let (mut editor, dir) = editor_with_files("cycle", &["main.rs", "lib.rs", "README.md"]);
+
+    editor.execute_command("bn").unwrap();
+    assert_eq!(current_name(&editor), "main.rs");
+
+    editor.execute_command("bp").unwrap();
+    assert_eq!(current_name(&editor), "README.md");
+
+    editor.execute_command("b 2").unwrap();
+    assert_eq!(current_name(&editor), "lib.rs");
+
+    editor.execute_command("b READ").unwrap();
+    assert_eq!(current_name(&editor), "README.md");
+
+    type_keys(&mut editor, "<C-^>");
+    assert_eq!(current_name(&editor), "lib.rs");
+
+    type_keys(&mut editor, "<C-^>");
+    assert_eq!(current_name(&editor), "README.md");
+
+    let err = editor.execute_command("b .rs").unwrap_err();
+    assert_eq!(err.to_string(), "More than one match for .rs");
+
+    let err = editor.execute_command("b nothing").unwrap_err();
+    assert_eq!(err.to_string(), "No matching buffer for nothing");
+
+    assert!(editor.execute_command("b 9").is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn alternate_buffer_needs_a_previous_buffer() {
+    let mut editor = Editor::new();
+    editor.create_empty_buffer();
+
+    let err = editor.switch_to_alternate_buffer().unwrap_err();
+    assert_eq!(err.to_string(), "No alternate file");
+}
+
+#[test]
+fn lists_buffers() {
+    let (mut editor, dir) = editor_with_files("ls", &["one.txt", "two.txt"]);
+
+    type_keys(&mut editor, "jix<Esc>");
+
+    let lines = editor.list_buffers();
+    assert_eq!(lines, vec![
+        format!("  1 #  \"{}\" line 1", dir.join("one.txt").display()),
+        format!("  2 %+ \"{}\" line 2", dir.join("two.txt").display())
+    ]);
+
+    editor.execute_command("ls").unwrap();
+    assert_eq!(editor.messages.current().unwrap().text, lines.join("\n"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn deletes_buffers() {
+    let (mut editor, dir) = editor_with_files("bdelete", &["a.txt", "b.txt", "c.txt"]);
+
+    type_keys(&mut editor, "ix<Esc>");
+
+    let err = editor.execute_command("bd").unwrap_err();
+    assert_eq!(err.to_string(), "No write since last change for buffer 3 (add ! to override)");
+    assert_eq!(editor.buffer_order.len(), 3);
+
+    // The alternate buffer takes the place of the one closed.
+    editor.execute_command("bd!").unwrap();
+    assert_eq!(current_name(&editor), "b.txt");
+    assert_eq!(editor.buffer_order.len(), 2);
+
+    editor.execute_command("bd a.txt").unwrap();
+    assert_eq!(current_name(&editor), "b.txt");
+
+    // Numbers stay with their buffers.
+    assert_eq!(editor.buffer_number(&editor.current_buffer.unwrap()), 2);
+
+    editor.execute_command("bd").unwrap();
+    assert_eq!(editor.buffer_order.len(), 1);
+    assert_eq!(editor.buffers.len(), 1);
+    assert_eq!(editor.buffer_order[0], editor.current_buffer.unwrap());
+    assert_eq!(current_name(&editor), "Untitled");
+    assert_eq!(editor.buffer_number(&editor.current_buffer.unwrap()), 4);
+    assert_eq!(editor.cursor.pos, Position::new(0, 0));
+    assert!(editor.buffer_cursor_pos.keys().all(|id| editor.buffers.contains_key(id)));
+    assert!(editor.switch_to_alternate_buffer().is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn deleting_a_buffer_updates_other_windows() {
+    let (mut editor, dir) = editor_with_files("bdelete-windows", &["a.txt", "b.txt"]);
+    let b = editor.current_buffer;
+
+    editor.execute_command("split").unwrap();
+    editor.execute_command("b a.txt").unwrap();
+    editor.execute_command("bd b.txt").unwrap();
+
+    type_keys(&mut editor, "<C-w>j");
+    assert_ne!(editor.current_buffer, b);
+    assert_eq!(current_name(&editor), "a.txt");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn picks_buffers_by_fuzzy_match() {
+    let (mut editor, dir) = editor_with_files("picker", &["editor.rs", "keymap.rs", "README.md"]);
+
+    type_keys(&mut editor, "\\b");
+    let picker = editor.picker.as_ref().unwrap();
+    assert_eq!(picker.matches.len(), 3);
+    assert!(picker.selected_item().unwrap().label.ends_with("keymap.rs"));
+
+    type_keys(&mut editor, "edrs");
+    let picker = editor.picker.as_ref().unwrap();
+    assert_eq!(picker.matches.len(), 1);
+
+    type_keys(&mut editor, "<CR>");
+    assert!(editor.picker.is_none());
+    assert_eq!(current_name(&editor), "editor.rs");
+
+    type_keys(&mut editor, "\\bxyz<Esc>");
+    assert!(editor.picker.is_none());
+    assert_eq!(current_name(&editor), "editor.rs");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn fuzzy_match_prefers_word_starts_and_runs() {
+    assert!(fuzzy_match("xyz", "src/main.rs").is_none());
+
+    let found = fuzzy_match("mr", "src/main.rs").unwrap();
+    assert_eq!(found.positions, vec![4, 9]);
+
+    let start = fuzzy_match("ed", "src/editor.rs").unwrap();
+    let middle = fuzzy_match("ed", "src/named.rs").unwrap();
+    assert!(start.score > middle.score);
+
+    let run = fuzzy_match("key", "keymap.rs").unwrap();
+    let scattered = fuzzy_match("key", "kind_of_every_y.rs").unwrap();
+    assert!(run.score > scattered.score);
+
+    assert!(fuzzy_match("Main", "src/main.rs").is_none());
+    assert!(fuzzy_match("main", "src/Main.rs").is_some());
+}
@@ -0,0 +1,149 @@
+pub mod common;
+
+use editor::command::{parse_command, Address, AddressKind, CommandRange};
+use editor::EditorError;
+use utils::Position;
+
+use common::{contents, editor_with};
+
+#[test]
+fn parse_ranges() {
+    let command = parse_command("1,10d").unwrap();
+    assert_eq!(command.name, "d");
+    assert_eq!(command.range, Some(CommandRange::Pair(
+        Address::new(AddressKind::Number(1)),
+        Address::new(AddressKind::Number(10))
+    )));
+
+    let command = parse_command(":.,$y a").unwrap();
+    assert_eq!(command.range, Some(CommandRange::Pair(
+        Address::new(AddressKind::Current),
+        Address::new(AddressKind::Last)
+    )));
+    assert_eq!(command.args, "a");
+
+    assert_eq!(parse_command("%>").unwrap().range, Some(CommandRange::Whole));
+
+    let command = parse_command("'a,'bm0").unwrap();
+    assert_eq!(command.name, "m");
+    assert_eq!(command.args, "0");
+}
+
+#[test]
+fn parse_search_and_offsets() {
+    let command = parse_command("/nya\\/mrrp/+2d").unwrap();
+    let Some(CommandRange::Single(address)) = command.range else {
+        panic!("expected a single address");
+    };
+
+    assert_eq!(address.kind, AddressKind::SearchForward("nya/mrrp".to_string()));
+    assert_eq!(address.offset, 2);
+}
+
+#[test]
+fn parse_bang_and_args() {
+    let command = parse_command("w! out.txt ").unwrap();
+    assert_eq!(command.name, "w");
+    assert!(command.bang);
+    assert_eq!(command.args, "out.txt ");
+}
+
+#[test]
+fn unknown_command() {
+    let mut editor = editor_with("hai");
+    let err = editor.execute_command("nyaa").unwrap_err();
+    assert!(matches!(err, EditorError::UnknownCommand(name) if name == "nyaa"));
+}
+
+#[test]
+fn abbreviations() {
+    let mut editor = editor_with("one\ntwo\nthree");
+    editor.execute_command("2del").unwrap();
+    assert_eq!(contents(&editor), "one\nthree");
+}
+
+#[test]
+fn goto_line() {
+    let mut editor = editor_with("one\ntwo\nthree");
+    editor.execute_command("3").unwrap();
+    assert_eq!(editor.cursor.pos, Position::new(2, 0));
+}
+
+#[test]
+fn delete_and_yank_registers() {
+    let mut editor = editor_with("one\ntwo\nthree\nfour");
+    editor.execute_command("2,3d a").unwrap();
+    assert_eq!(contents(&editor), "one\nfour");
+    assert_eq!(editor.registers[&'a'], vec!["two", "three"]);
+
+    editor.execute_command("%y").unwrap();
+    assert_eq!(editor.registers[&'"'], vec!["one", "four"]);
+}
+
+#[test]
+fn move_lines() {
+    let mut editor = editor_with("one\ntwo\nthree\nfour");
+    editor.execute_command("1,2m$").unwrap();
+    assert_eq!(contents(&editor), "three\nfour\none\ntwo");
+
+    editor.execute_command("4m0").unwrap();
+    assert_eq!(contents(&editor), "two\nthree\nfour\none");
+
+    assert!(editor.execute_command("1,3m2").is_err());
+}
+
+#[test]
+fn copy_lines() {
+    let mut editor = editor_with("one\ntwo");
+    editor.execute_command("1t.").unwrap();
+    assert_eq!(contents(&editor), "one\none\ntwo");
+
+    editor.execute_command("%co$").unwrap();
+    assert_eq!(contents(&editor), "one\none\ntwo\none\none\ntwo");
+}
+
+#[test]
+fn shift_lines() {
+    let mut editor = editor_with("one\n\ntwo");
+    editor.execute_command("%>>").unwrap();
+    assert_eq!(contents(&editor), "        one\n\n        two");
+
+    editor.execute_command("1<").unwrap();
+    assert_eq!(contents(&editor), "    one\n\n        two");
+}
+
+#[test]
+fn mark_and_search_addresses() {
+    let mut editor = editor_with("one\ntwo\nthree\nfour");
+    editor.execute_command("2mark a").unwrap();
+    editor.execute_command("'a,/four/-1d").unwrap();
+    assert_eq!(contents(&editor), "one\nfour");
+
+    assert!(matches!(editor.execute_command("/nope/d"), Err(EditorError::PatternNotFound(_))));
+
+    // `:k` takes its mark without a space.
+    let command = parse_command("3kb").unwrap();
+    assert_eq!((command.name.as_str(), command.args.as_str()), ("k", "b"));
+
+    let mut editor = editor_with("one\ntwo\nthree\nfour");
+    editor.execute_command("3kb").unwrap();
+    editor.execute_command("'b,$d").unwrap();
+    assert_eq!(contents(&editor), "one\ntwo");
+}
+
+#[test]
+fn backwards_range() {
+    let mut editor = editor_with("one\ntwo\nthree");
+    assert!(matches!(editor.execute_command("3,1d"), Err(EditorError::BackwardsRange)));
+}
+
+#[test]
+fn quit_with_unsaved_changes() {
+    let mut editor = editor_with("one");
+    editor.execute_command("d").unwrap();
+    assert!(matches!(editor.execute_command("q"), Err(EditorError::UnsavedChanges)));
+    assert!(!editor.should_quit);
+
+    editor.execute_command("q!").unwrap();
+    assert!(editor.should_quit);
+}
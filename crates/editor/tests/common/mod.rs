@@ -0,0 +1,59 @@
+//! Helpers shared by the editor's integration tests. Test files declare it
+//! as `pub mod common;`, so the helpers one of them has no use for aren't
+//! dead code there.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use buffer::buffer::Buffer;
+use editor::editor::Editor;
+use editor::keys::parse_keys;
+
+/// An editor with one buffer holding `text`.
+pub fn editor_with(text: &str) -> Editor {
+    let mut editor = Editor::new();
+    editor.create_empty_buffer();
+    *editor.get_current_buffer_mut().unwrap() = Buffer::from_text(text);
+    editor
+}
+
+/// Like `editor_with`, in a viewport 40 columns wide and 10 rows high.
+pub fn editor_in_viewport(text: &str) -> Editor {
+    let mut editor = editor_with(text);
+    editor.resize_viewport(40, 10);
+    editor
+}
+
+/// Like `editor_in_viewport`, with the buffer named `path` so its filetype
+/// is known.
+pub fn editor_with_path(text: &str, path: &str) -> Editor {
+    let mut editor = editor_in_viewport(text);
+    editor.get_current_buffer_mut().unwrap().set_path(PathBuf::from(path));
+    editor
+}
+
+pub fn type_keys(editor: &mut Editor, keys: &str) {
+    for key in parse_keys(keys) {
+        editor.handle_key(key);
+    }
+}
+
+/// The current buffer's text, its lines joined by newlines.
+pub fn contents(editor: &Editor) -> String {
+    editor.get_current_buffer().unwrap().get_buffer()
+}
+
+/// A directory of its own for `test` to put files in.
+pub fn temp_dir(test: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("paw-{}-{}", test, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Waits for the picker's items to finish arriving.
+pub fn load_picker(editor: &mut Editor) {
+    while editor.picker.as_ref().unwrap().is_loading() {
+        editor.poll_picker();
+    }
+}
@@ -0,0 +1,97 @@
+pub mod common;
+
+use editor::display::{cells, column_at, display_column, display_width};
+use editor::layout::TextLayout;
+use utils::Position;
+
+use common::{editor_with, type_keys};
+
+#[test]
+fn tabs_expand_to_tab_stops() {
+    assert_eq!(display_width("\tx", 8), 9);
+    assert_eq!(display_width("ab\tx", 4), 5);
+    assert_eq!(display_column("ab\tx", 3, 4), 4);
+    assert_eq!(column_at("ab\tx", 3, 4), 2);
+
+    let cell = cells("ab\tx", 4)[2];
+    assert_eq!(cell.display("ab\tx"), "  ");
+}
+
+#[test]
+fn wide_and_zero_width_characters() {
+    assert_eq!(display_width("日本", 8), 4);
+    assert_eq!(display_column("日本語", 6, 8), 4);
+
+    // A family emoji is one grapheme joined with zero-width joiners.
+    let family = "👨\u{200d}👩\u{200d}👧";
+    assert_eq!(cells(family, 8).len(), 1);
+    assert_eq!(display_width(family, 8), 2);
+
+    assert_eq!(display_width("e\u{301}", 8), 1);
+}
+
+#[test]
+fn control_characters_use_caret_notation() {
+    let text = "a\u{1}b\u{7f}";
+    let cells = cells(text, 8);
+    assert_eq!(cells[1].display(text), "^A");
+    assert_eq!(cells[3].display(text), "^?");
+    assert_eq!(display_width(text, 8), 6);
+}
+
+#[test]
+fn wrapping_counts_wide_characters() {
+    let layout = TextLayout {
+        width: 5,
+        tab_stop: 8,
+        wrap: true,
+        show_break: String::new(),
+        line_break: false
+    };
+
+    assert_eq!(layout.rows("日本語です"), vec![0 .. 6, 6 .. 12, 12 .. 15]);
+    assert_eq!(layout.to_visual("日本語です", 9), (1, 2));
+}
+
+#[test]
+fn cursor_moves_by_grapheme() {
+    let mut editor = editor_with("日本\ta\nxxxxxxxxxxx");
+    type_keys(&mut editor, "l");
+    assert_eq!(editor.cursor.pos, Position::new(0, 3));
+
+    type_keys(&mut editor, "ll");
+    assert_eq!(editor.cursor.pos, Position::new(0, 7));
+
+    type_keys(&mut editor, "j");
+    assert_eq!(editor.cursor.pos, Position::new(1, 8));
+
+    type_keys(&mut editor, "hk");
+    assert_eq!(editor.cursor.pos, Position::new(0, 6));
+
+    type_keys(&mut editor, "i<BS><Esc>");
+    assert_eq!(editor.get_current_buffer().unwrap().get_buffer(), "日\ta\nxxxxxxxxxxx");
+}
+
+#[test]
+fn indent_honours_expand_tab_and_shift_width() {
+    let mut editor = editor_with("ab");
+    editor.cursor.pos = Position::new(0, 1);
+    type_keys(&mut editor, "i<Tab><Esc>");
+    assert_eq!(editor.get_current_buffer().unwrap().get_buffer(), "a   b");
+
+    editor.execute_command("set noexpandtab shiftwidth=8").unwrap();
+    type_keys(&mut editor, "i<Tab><Esc>:><CR>");
+    assert_eq!(editor.get_current_buffer().unwrap().get_buffer(), "\ta   \tb");
+
+    // With tabs narrower or wider than a shift, `<Tab>` goes as far as `>`,
+    // turning the indentation before it into tabs where it can.
+    let mut editor = editor_with("x");
+    editor.execute_command("set noexpandtab tabstop=8 shiftwidth=4").unwrap();
+    type_keys(&mut editor, "i<Tab><Esc>");
+    assert_eq!(editor.get_current_buffer().unwrap().get_buffer(), "    x");
+    type_keys(&mut editor, "i<Tab><Tab><Esc>");
+    assert_eq!(editor.get_current_buffer().unwrap().get_buffer(), "\t    x");
+
+    editor.execute_command(">").unwrap();
+    assert_eq!(editor.get_current_buffer().unwrap().get_buffer(), "\t\tx");
+}
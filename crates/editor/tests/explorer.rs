@@ -0,0 +1,185 @@
+pub mod common;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use editor::editor::Editor;
+use editor::explorer::{parse_git_status, GitStatus};
+
+use common::{temp_dir, type_keys};
+
+fn project(test: &str) -> PathBuf {
+    let dir = temp_dir(test);
+    fs::create_dir_all(dir.join("src/nested")).unwrap();
+    fs::create_dir_all(dir.join("Docs")).unwrap();
+    fs::write(dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+    fs::write(dir.join("src/nested/deep.rs"), "fn deep() {}\n").unwrap();
+    fs::write(dir.join("build.rs"), "fn build() {}\n").unwrap();
+    fs::write(dir.join("Cargo.toml"), "[package]\n").unwrap();
+
+    dir.canonicalize().unwrap()
+}
+
+/// The explorer's entries as they would be drawn, indented by depth.
+fn tree(editor: &Editor) -> Vec<String> {
+    editor.explorer.as_ref().unwrap().entries
+        .iter()
+        .map(|entry| format!("{}{}{}", "  ".repeat(entry.depth), entry.name(), if entry.is_dir { "/" } else { "" }))
+        .collect()
+}
+
+fn selected(editor: &Editor) -> PathBuf {
+    editor.explorer.as_ref().unwrap().selected_entry().unwrap().path.clone()
+}
+
+#[test]
+fn git_status_marks_files_and_the_folders_they_are_in() {
+    let top = Path::new("/repo");
+    let output = "M  src/a.rs\0?? notes/\0R  src/new.rs\0src/old.rs\0UU src/deep/c.rs\0!! target/\0 D gone.txt\0";
+    let statuses = parse_git_status(output, top);
+
+    assert_eq!(statuses, HashMap::from([
+        (top.join("src/a.rs"), GitStatus::Modified),
+        (top.join("notes"), GitStatus::Untracked),
+        (top.join("src/new.rs"), GitStatus::Added),
+        (top.join("src/deep/c.rs"), GitStatus::Conflicted),
+        (top.join("src/deep"), GitStatus::Conflicted),
+        (top.join("src"), GitStatus::Conflicted),
+        (top.join("target"), GitStatus::Ignored),
+        (top.join("gone.txt"), GitStatus::Deleted)
+    ]));
+
+    assert_eq!(GitStatus::parse("AM"), Some(GitStatus::Modified));
+    assert_eq!(GitStatus::parse("A "), Some(GitStatus::Added));
+    assert_eq!(GitStatus::Untracked.marker(), '?');
+}
+
+#[test]
+fn folders_open_and_close() {
+    let dir = project("explorer-tree");
+
+    let mut editor = Editor::new();
+    editor.create_empty_buffer();
+    editor.open_file(dir.clone()).unwrap();
+
+    // Folders come first, then files, whatever the case of their names.
+    assert!(editor.explorer_focused());
+    assert_eq!(tree(&editor), vec!["Docs/", "src/", "build.rs", "Cargo.toml"]);
+
+    type_keys(&mut editor, "jl");
+    assert_eq!(tree(&editor), vec!["Docs/", "src/", "  nested/", "  main.rs", "build.rs", "Cargo.toml"]);
+
+    type_keys(&mut editor, "jl");
+    assert_eq!(tree(&editor)[3], "    deep.rs");
+
+    // `h` on a file goes up to its folder, and on an open folder closes it.
+    type_keys(&mut editor, "jh");
+    assert_eq!(selected(&editor), dir.join("src/nested"));
+    type_keys(&mut editor, "hh");
+    assert_eq!(selected(&editor), dir.join("src"));
+    type_keys(&mut editor, "h");
+    assert_eq!(tree(&editor), vec!["Docs/", "src/", "build.rs", "Cargo.toml"]);
+
+    // `q` closes the explorer and keys go back to the window.
+    type_keys(&mut editor, "q");
+    assert!(editor.explorer.is_none());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn opens_files_in_the_current_window_or_a_split() {
+    let dir = project("explorer-open");
+
+    let mut editor = Editor::new();
+    editor.create_empty_buffer();
+    editor.execute_command(&format!("explore {}", dir.display())).unwrap();
+    assert!(editor.execute_command(&format!("explore {}", dir.join("build.rs").display())).is_err());
+
+    type_keys(&mut editor, "G");
+    assert_eq!(selected(&editor), dir.join("Cargo.toml"));
+
+    type_keys(&mut editor, "s");
+    assert!(!editor.explorer_focused());
+    assert_eq!(editor.window_rects().len(), 2);
+    assert_eq!(editor.get_buffer_display_name(&editor.current_buffer.unwrap()), "Cargo.toml");
+
+    // `<C-w>h` from the leftmost window goes back to the explorer.
+    type_keys(&mut editor, "<C-w>h");
+    assert!(editor.explorer_focused());
+
+    type_keys(&mut editor, "kl");
+    assert_eq!(editor.window_rects().len(), 2);
+    assert_eq!(editor.get_buffer_display_name(&editor.current_buffer.unwrap()), "build.rs");
+
+    // Keys go to the window again once a file is open.
+    type_keys(&mut editor, "ix<Esc>");
+    assert_eq!(editor.get_current_buffer().unwrap().lines[0], "xfn build() {}");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn reveals_the_current_file() {
+    let dir = project("explorer-reveal");
+
+    let mut editor = Editor::new();
+    editor.open_file(dir.join("src/nested/deep.rs")).unwrap();
+    editor.open_explorer(&dir).unwrap();
+    type_keys(&mut editor, "<Esc>");
+
+    editor.execute_command("reveal").unwrap();
+    assert!(editor.explorer_focused());
+    assert_eq!(selected(&editor), dir.join("src/nested/deep.rs"));
+    assert_eq!(tree(&editor), vec!["Docs/", "src/", "  nested/", "    deep.rs", "  main.rs", "build.rs", "Cargo.toml"]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn creates_renames_and_deletes_files() {
+    let dir = project("explorer-files");
+
+    let mut editor = Editor::new();
+    editor.open_file(dir.join("src/main.rs")).unwrap();
+    editor.open_explorer(&dir).unwrap();
+
+    // New files go in the selected folder; a trailing `/` makes a folder.
+    type_keys(&mut editor, "ja");
+    assert_eq!(editor.explorer.as_ref().unwrap().prompt.as_ref().unwrap().prompt.text, "src/");
+    type_keys(&mut editor, "lib.rs<CR>");
+    assert!(dir.join("src/lib.rs").is_file());
+    assert_eq!(selected(&editor), dir.join("src/lib.rs"));
+    assert_eq!(editor.messages.text(), Some("Created src/lib.rs"));
+
+    type_keys(&mut editor, "a<C-u>tests/<CR>");
+    assert!(dir.join("tests").is_dir());
+
+    // Nothing is overwritten.
+    type_keys(&mut editor, "a<C-u>build.rs<CR>");
+    assert_eq!(editor.messages.text(), Some("Already exists: build.rs"));
+
+    // Renaming a file keeps its buffer pointing at it.
+    editor.reveal_file().unwrap();
+    type_keys(&mut editor, "r<C-u>app.rs<CR>");
+    assert!(dir.join("src/app.rs").is_file() && !dir.join("src/main.rs").exists());
+    assert_eq!(editor.messages.text(), Some("Moved src/main.rs to src/app.rs"));
+    assert_eq!(editor.get_current_buffer().unwrap().get_path().unwrap().canonicalize().unwrap(), dir.join("src/app.rs"));
+
+    // As does moving the folder it is in.
+    editor.explorer.as_mut().unwrap().select_path(&dir.join("src"));
+    type_keys(&mut editor, "m<C-u>lib/src<CR>");
+    assert!(dir.join("lib/src/app.rs").is_file());
+    assert_eq!(editor.get_current_buffer().unwrap().get_path().unwrap().canonicalize().unwrap(), dir.join("lib/src/app.rs"));
+
+    // Deleting asks first.
+    editor.explorer.as_mut().unwrap().select_path(&dir.join("build.rs"));
+    type_keys(&mut editor, "dn");
+    assert!(dir.join("build.rs").exists());
+    type_keys(&mut editor, "dy");
+    assert!(!dir.join("build.rs").exists());
+    assert_eq!(editor.messages.text(), Some("Deleted build.rs"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,64 @@
+pub mod common;
+
+use common::{contents, editor_with_path, type_keys};
+
+#[test]
+fn set_and_show_filetype() {
+    let mut editor = editor_with_path("x", "notes.txt");
+
+    editor.execute_command("set ft=python").unwrap();
+    assert_eq!(editor.get_current_buffer().unwrap().filetype(), Some("python"));
+
+    editor.execute_command("set ft?").unwrap();
+    assert_eq!(editor.messages.text(), Some("filetype=python"));
+
+    editor.execute_command("set filetype=").unwrap();
+    assert_eq!(editor.get_current_buffer().unwrap().filetype(), None);
+
+    assert!(editor.execute_command("set bogus").is_err());
+}
+
+#[test]
+fn toggle_line_comments() {
+    let mut editor = editor_with_path("fn main() {\n    let x = 1;\n\n    let y = 2;\n}", "main.rs");
+    type_keys(&mut editor, "jgcc");
+    assert_eq!(contents(&editor), "fn main() {\n    // let x = 1;\n\n    let y = 2;\n}");
+
+    type_keys(&mut editor, "vjjgc");
+    assert_eq!(contents(&editor), "fn main() {\n    // // let x = 1;\n\n    // let y = 2;\n}");
+
+    type_keys(&mut editor, "gcc");
+    assert_eq!(contents(&editor), "fn main() {\n    // let x = 1;\n\n    // let y = 2;\n}");
+
+    type_keys(&mut editor, "vjjgc");
+    assert_eq!(contents(&editor), "fn main() {\n    let x = 1;\n\n    let y = 2;\n}");
+}
+
+#[test]
+fn block_comments_and_missing_comment_strings() {
+    let mut editor = editor_with_path("a { color: red; }", "style.css");
+    type_keys(&mut editor, "gcc");
+    assert_eq!(contents(&editor), "/* a { color: red; } */");
+
+    type_keys(&mut editor, "gcc");
+    assert_eq!(contents(&editor), "a { color: red; }");
+
+    let mut editor = editor_with_path("{}", "data.json");
+    type_keys(&mut editor, "gcc");
+    assert_eq!(editor.messages.text(), Some("No comment string for this filetype"));
+}
+
+#[test]
+fn newline_follows_the_filetype_indent() {
+    let mut editor = editor_with_path("", "main.rs");
+    type_keys(&mut editor, "iimpl Point {<Enter>x");
+    assert_eq!(contents(&editor), "impl Point {\n    x\n}");
+
+    let mut editor = editor_with_path("", "main.py");
+    type_keys(&mut editor, "iif x:<Enter>y<Enter>z");
+    assert_eq!(contents(&editor), "if x:\n    y\n    z");
+
+    let mut editor = editor_with_path("", "notes.txt");
+    type_keys(&mut editor, "i  a {<Enter>b");
+    assert_eq!(contents(&editor), "  a {\nb}");
+}
@@ -0,0 +1,110 @@
+pub mod common;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use editor::editor::Editor;
+use editor::finder::{read_preview, walk_files};
+use editor::picker::{Picker, PickerItem, PickerTarget};
+
+use common::{load_picker, temp_dir, type_keys};
+
+/// A git work tree with a few files, some of them ignored or hidden.
+fn project(test: &str) -> PathBuf {
+    let dir = temp_dir(test);
+
+    for path in ["src/main.rs", "src/editor/keymap.rs", "README.md", "target/debug/paw", ".hidden/notes.md", "debug.log"] {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "fn main() {}\n").unwrap();
+    }
+
+    fs::create_dir_all(dir.join(".git")).unwrap();
+    fs::write(dir.join(".gitignore"), "target/\n*.log\n").unwrap();
+
+    dir
+}
+
+fn walk(root: &Path) -> Vec<String> {
+    let mut labels: Vec<String> = walk_files(root).into_iter().flatten().map(|item| item.label).collect();
+    labels.sort();
+    labels
+}
+
+#[test]
+fn walks_files_that_are_not_ignored() {
+    let dir = project("walk");
+
+    assert_eq!(walk(&dir), vec!["README.md", "src/editor/keymap.rs", "src/main.rs"]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn picker_takes_items_as_they_arrive() {
+    let item = |label: &str| PickerItem { label: label.to_string(), target: PickerTarget::File(label.into()) };
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let mut picker = Picker::with_source("Files", receiver);
+
+    picker.prompt.set_text("mai");
+    picker.filter();
+
+    sender.send(vec![item("src/main.rs"), item("README.md")]).unwrap();
+    assert!(picker.poll());
+    assert!(picker.is_loading());
+    assert_eq!(picker.matches.len(), 1);
+
+    picker.move_selection(1);
+    sender.send(vec![item("main.rs"), item("domain/mail.rs")]).unwrap();
+    drop(sender);
+
+    assert!(picker.poll());
+    assert!(!picker.is_loading());
+    assert_eq!(picker.matches.len(), 3);
+    assert_eq!(picker.selected_item().unwrap().label, "src/main.rs");
+
+    picker.prompt.set_text("mainr");
+    picker.filter();
+    assert_eq!(picker.matches.len(), 3);
+    assert_eq!(picker.selected_item().unwrap().label, "main.rs");
+
+    picker.prompt.set_text("read");
+    picker.filter();
+    assert_eq!(picker.selected_item().unwrap().label, "README.md");
+}
+
+#[test]
+fn finds_previews_and_opens_files() {
+    let dir = project("find");
+    env::set_current_dir(&dir).unwrap();
+
+    let mut editor = Editor::new();
+    editor.create_empty_buffer();
+
+    type_keys(&mut editor, "\\fkeym");
+
+    load_picker(&mut editor);
+
+    let picker = editor.picker.as_ref().unwrap();
+    assert_eq!(picker.matches.len(), 1);
+    assert_eq!(picker.preview.as_ref().unwrap().lines, vec!["fn main() {}"]);
+
+    type_keys(&mut editor, "<CR>");
+    assert!(editor.picker.is_none());
+    assert_eq!(editor.get_buffer_display_name(&editor.current_buffer.unwrap()), "keymap.rs");
+    assert_eq!(editor.buffer_order.len(), 2);
+
+    // Finding an open file again goes back to its buffer.
+    editor.execute_command("find src/editor/keymap.rs").unwrap();
+
+    load_picker(&mut editor);
+
+    type_keys(&mut editor, "<CR>");
+    assert_eq!(editor.buffer_order.len(), 2);
+
+    fs::write(dir.join("data.bin"), [0u8, 1, 2]).unwrap();
+    assert_eq!(read_preview(Path::new("data.bin"), 0), vec!["<binary file>"]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
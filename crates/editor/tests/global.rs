@@ -0,0 +1,65 @@
+pub mod common;
+
+use editor::EditorError;
+
+use common::{contents, editor_with};
+
+#[test]
+fn global_delete() {
+    let mut editor = editor_with("DEBUG a\nINFO b\nDEBUG c\nDEBUG d\nWARN e");
+    editor.execute_command("g/DEBUG/d").unwrap();
+    assert_eq!(contents(&editor), "INFO b\nWARN e");
+}
+
+#[test]
+fn vglobal_and_global_bang() {
+    let mut editor = editor_with("keep 1\ndrop\nkeep 2");
+    editor.execute_command("v/keep/d").unwrap();
+    assert_eq!(contents(&editor), "keep 1\nkeep 2");
+
+    let mut editor = editor_with("keep 1\ndrop\nkeep 2");
+    editor.execute_command("g!/keep/d").unwrap();
+    assert_eq!(contents(&editor), "keep 1\nkeep 2");
+}
+
+#[test]
+fn global_move_reverses() {
+    let mut editor = editor_with("1\n2\n3\n4");
+    editor.execute_command("g/^/m0").unwrap();
+    assert_eq!(contents(&editor), "4\n3\n2\n1");
+}
+
+#[test]
+fn global_substitute_reuses_pattern() {
+    let mut editor = editor_with("port = 80\nhost = x\nport = 81");
+    editor.execute_command("g/port/s//PORT/").unwrap();
+    assert_eq!(contents(&editor), "PORT = 80\nhost = x\nPORT = 81");
+}
+
+#[test]
+fn global_deleting_following_lines() {
+    let mut editor = editor_with("a\nx\nb\na\ny\nc");
+    editor.execute_command("g/a/.,+1d").unwrap();
+    assert_eq!(contents(&editor), "b\nc");
+}
+
+#[test]
+fn global_is_one_undo_step() {
+    let mut editor = editor_with("x 1\ny\nx 2\nx 3");
+    editor.execute_command("g/x/d").unwrap();
+    assert_eq!(contents(&editor), "y");
+
+    editor.execute_command("undo").unwrap();
+    assert_eq!(contents(&editor), "x 1\ny\nx 2\nx 3");
+    assert!(matches!(editor.execute_command("undo"), Err(EditorError::OldestChange)));
+
+    editor.execute_command("red").unwrap();
+    assert_eq!(contents(&editor), "y");
+}
+
+#[test]
+fn global_errors() {
+    let mut editor = editor_with("a");
+    assert!(matches!(editor.execute_command("g/zzz/d"), Err(EditorError::PatternNotFound(_))));
+    assert!(matches!(editor.execute_command("g/a/g/a/d"), Err(EditorError::GlobalRecursive)));
+}
@@ -0,0 +1,109 @@
+pub mod common;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use editor::editor::Editor;
+use editor::grep::grep;
+use editor::picker::PickerTarget;
+use regex::Regex;
+use utils::Position;
+
+use common::{load_picker, temp_dir, type_keys};
+
+fn project(test: &str) -> PathBuf {
+    let dir = temp_dir(test);
+    let files = [
+        ("src/main.rs", "fn main() {\n    let total = add(1, 2);\n}\n"),
+        ("src/math.rs", "// add two numbers\npub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n"),
+        ("target/out.rs", "fn add() {}\n"),
+        ("README.md", "Nothing to see.\r\n")
+    ];
+
+    for (path, text) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+
+    fs::write(dir.join("data.bin"), b"add\0add").unwrap();
+    fs::create_dir_all(dir.join(".git")).unwrap();
+    fs::write(dir.join(".gitignore"), "target/\n").unwrap();
+
+    dir
+}
+
+#[test]
+fn finds_matching_lines_in_files_not_ignored() {
+    let dir = project("grep");
+
+    let mut results: Vec<(String, PickerTarget)> = grep(Regex::new(r"add\(").unwrap(), &dir)
+        .into_iter()
+        .flatten()
+        .map(|item| (item.label, item.target))
+        .collect();
+
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let main = dir.join("src/main.rs");
+    let math = dir.join("src/math.rs");
+
+    assert_eq!(results, vec![
+        (format!("{}:2:17: let total = add(1, 2);", main.display()), PickerTarget::Location(main, Position::new(1, 16))),
+        (format!("{}:2:8: pub fn add(a: i32, b: i32) -> i32 {{", math.display()), PickerTarget::Location(math, Position::new(1, 7)))
+    ]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn grep_opens_the_match() {
+    let dir = project("grep-open");
+    env::set_current_dir(&dir).unwrap();
+
+    let mut editor = Editor::new();
+    editor.create_empty_buffer();
+
+    assert_eq!(editor.execute_command("grep").unwrap_err().to_string(), "Argument required");
+    assert!(editor.execute_command("grep add nowhere").is_err());
+
+    editor.execute_command("grep 'fn add' src").unwrap();
+
+    load_picker(&mut editor);
+
+    let picker = editor.picker.as_ref().unwrap();
+    assert_eq!(picker.title, "Grep fn add");
+    assert_eq!(picker.items.len(), 1);
+    assert_eq!(picker.items[0].label, "src/math.rs:2:5: pub fn add(a: i32, b: i32) -> i32 {");
+
+    // The preview shows the lines around the match with the match picked out.
+    let preview = picker.preview.as_ref().unwrap();
+    assert_eq!(preview.line, Some(1));
+    assert_eq!(preview.lines[0], "// add two numbers");
+
+    type_keys(&mut editor, "<CR>");
+    assert!(editor.picker.is_none());
+    assert_eq!(editor.get_buffer_display_name(&editor.current_buffer.unwrap()), "math.rs");
+    assert_eq!(editor.cursor.pos, Position::new(1, 4));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn a_running_search_can_be_stopped() {
+    let dir = project("grep-stop");
+    let mut editor = Editor::new();
+    editor.create_empty_buffer();
+
+    editor.execute_command(&format!("grep fn {}", dir.display())).unwrap();
+    type_keys(&mut editor, "<C-c>");
+
+    let picker = editor.picker.as_ref().unwrap();
+    assert!(!picker.is_loading());
+
+    type_keys(&mut editor, "<C-c>");
+    assert!(editor.picker.is_none());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,163 @@
+pub mod common;
+
+use std::fs;
+use std::time::Duration;
+
+use editor::editor::EditorMode;
+use editor::keys::{format_keys, parse_keys};
+use utils::Position;
+
+use common::{contents, editor_in_viewport, temp_dir, type_keys};
+
+#[test]
+fn control_keys_are_not_typed() {
+    let mut editor = editor_in_viewport("");
+
+    type_keys(&mut editor, "ia<C-x>b<A-c><Esc>");
+
+    assert_eq!(contents(&editor), "ab");
+    assert!(matches!(editor.mode, EditorMode::Normal));
+}
+
+#[test]
+fn insert_mapping_waits_for_the_rest() {
+    let mut editor = editor_in_viewport("");
+    editor.execute_command("inoremap jk <Esc>").unwrap();
+
+    type_keys(&mut editor, "iajk");
+    assert_eq!(contents(&editor), "a");
+    assert!(matches!(editor.mode, EditorMode::Normal));
+
+    editor.cursor.pos = Position::new(0, 1);
+    type_keys(&mut editor, "ij");
+    assert_eq!(contents(&editor), "a");
+    assert_eq!(editor.input.pending_keys(), parse_keys("j"));
+    assert_eq!(editor.key_timeout().map(|timeout| timeout <= Duration::from_secs(1)), Some(true));
+
+    editor.flush_keys();
+    assert_eq!(contents(&editor), "aj");
+    assert_eq!(editor.key_timeout(), None);
+
+    type_keys(&mut editor, "jx");
+    assert_eq!(contents(&editor), "ajjx");
+
+    editor.execute_command("set notimeout").unwrap();
+    type_keys(&mut editor, "j");
+    assert_eq!(editor.key_timeout(), None);
+}
+
+#[test]
+fn noremap_and_remap() {
+    let mut editor = editor_in_viewport("one\ntwo\nthree\nfour");
+
+    editor.execute_command("nnoremap j k").unwrap();
+    editor.execute_command("nmap J j").unwrap();
+    editor.execute_command("nnoremap K j").unwrap();
+
+    editor.cursor.pos = Position::new(2, 0);
+    type_keys(&mut editor, "J");
+    assert_eq!(editor.cursor.pos.line, 1);
+
+    type_keys(&mut editor, "K");
+    assert_eq!(editor.cursor.pos.line, 2);
+
+    editor.execute_command("nmap a b").unwrap();
+    editor.execute_command("nmap b a").unwrap();
+    type_keys(&mut editor, "a");
+    assert_eq!(editor.messages.take().as_deref(), Some("Recursive mapping"));
+}
+
+#[test]
+fn mappings_run_commands_and_keys() {
+    let mut editor = editor_in_viewport("one\ntwo\nthree");
+
+    editor.execute_command("nmap <C-n> move_down").unwrap();
+    editor.execute_command("nnoremap Q :s/o/0/<CR>").unwrap();
+
+    type_keys(&mut editor, "<C-n>Q");
+
+    assert_eq!(contents(&editor), "one\ntw0\nthree");
+    assert!(matches!(editor.mode, EditorMode::Normal));
+}
+
+#[test]
+fn leader_expands_when_mapped() {
+    let mut editor = editor_in_viewport("one\ntwo\nthree\nfour");
+
+    editor.execute_command("nnoremap <leader>j jj").unwrap();
+    editor.execute_command("set leader=<Space>").unwrap();
+    editor.execute_command("nnoremap <Leader>k jk").unwrap();
+
+    type_keys(&mut editor, "\\j");
+    assert_eq!(editor.cursor.pos.line, 2);
+
+    type_keys(&mut editor, "<Space>k");
+    assert_eq!(editor.cursor.pos.line, 2);
+}
+
+#[test]
+fn map_lists_and_unmap_removes() {
+    let mut editor = editor_in_viewport("x");
+
+    editor.execute_command("nnoremap <C-s> :w<CR>").unwrap();
+    editor.execute_command("imap jj <Esc>").unwrap();
+    editor.execute_command("map gx move_down").unwrap();
+
+    editor.execute_command("map").unwrap();
+    assert_eq!(editor.messages.take().as_deref(), Some("n  <C-s>  * :w<CR>\nn  gx  move_down\nv  gx  move_down"));
+
+    editor.execute_command("imap j").unwrap();
+    assert_eq!(editor.messages.take().as_deref(), Some("i  jj  <Esc>"));
+
+    editor.execute_command("vunmap gx").unwrap();
+    editor.execute_command("iunmap jj").unwrap();
+    editor.execute_command("map").unwrap();
+    assert_eq!(editor.messages.take().as_deref(), Some("n  <C-s>  * :w<CR>\nn  gx  move_down"));
+
+    assert_eq!(editor.execute_command("iunmap jj").unwrap_err().to_string(), "No such mapping");
+
+    editor.execute_command("imap").unwrap();
+    assert_eq!(editor.messages.take().as_deref(), Some("No mapping found"));
+}
+
+#[test]
+fn config_keys() {
+    let dir = temp_dir("keys");
+    let path = dir.join("config.toml");
+
+    fs::write(&path, concat!(
+        "leader = \",\"\n\n",
+        "[keys.normal]\n",
+        "\"<leader>d\" = \"half_page_down\"\n",
+        "J = { keys = \"<leader>d\", remap = true }\n",
+        "K = \"<leader>d\"\n\n",
+        "[keys.insert]\n",
+        "jk = \"<Esc>\"\n"
+    )).unwrap();
+
+    let mut editor = editor_in_viewport(&vec!["x"; 40].join("\n"));
+    editor.load_config(&path).unwrap();
+
+    type_keys(&mut editor, "J");
+    let line = editor.cursor.pos.line;
+    assert!(line >= 5);
+
+    type_keys(&mut editor, "K");
+    assert_eq!(editor.cursor.pos.line, line);
+
+    type_keys(&mut editor, "iyjk");
+    assert!(matches!(editor.mode, EditorMode::Normal));
+
+    fs::write(&path, "[keys.replace]\nx = \"y\"\n").unwrap();
+    let err = editor.load_config(&path).unwrap_err().to_string();
+    assert!(err.ends_with("Unknown mode: keys.replace"), "{}", err);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn format_keys_round_trips() {
+    for keys in ["jk", "<C-w>h", "<Space>f", "<lt>x<Bar>", "<CR><Esc><S-Tab>", "<A-x><F5>"] {
+        assert_eq!(format_keys(&parse_keys(keys)), keys);
+    }
+}
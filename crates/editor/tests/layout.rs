@@ -0,0 +1,102 @@
+pub mod common;
+
+use buffer::buffer::Buffer;
+use editor::editor::Editor;
+use editor::layout::TextLayout;
+use utils::Position;
+
+use common::type_keys;
+
+fn layout(width: usize) -> TextLayout {
+    TextLayout {
+        width,
+        tab_stop: 8,
+        wrap: true,
+        show_break: String::new(),
+        line_break: false
+    }
+}
+
+fn editor_with(text: &str, width: usize, height: usize) -> Editor {
+    let mut editor = Editor::new();
+    editor.create_empty_buffer();
+    *editor.get_current_buffer_mut().unwrap() = Buffer::from_text(text);
+    editor.window_options.scroll_off = 0;
+    editor.resize_viewport(width, height);
+    editor
+}
+
+#[test]
+fn wraps_at_width() {
+    assert_eq!(layout(4).rows("abcdefghij"), vec![0 .. 4, 4 .. 8, 8 .. 10]);
+    assert_eq!(layout(4).row_count(""), 1);
+
+    let nowrap = TextLayout { wrap: false, ..layout(4) };
+    assert_eq!(nowrap.row_count("abcdefghij"), 1);
+    assert_eq!(nowrap.to_visual("abcdefghij", 9), (0, 9));
+}
+
+#[test]
+fn show_break_and_line_break() {
+    let layout = TextLayout {
+        show_break: "> ".to_string(),
+        line_break: true,
+        ..layout(8)
+    };
+
+    assert_eq!(layout.rows("one two three four"), vec![0 .. 8, 8 .. 14, 14 .. 18]);
+    assert_eq!(layout.to_visual("one two three four", 9), (1, 3));
+    assert_eq!(layout.to_column("one two three four", 1, 3), 9);
+    assert_eq!(layout.to_column("one two three four", 1, 0), 8);
+}
+
+#[test]
+fn visual_position_round_trip() {
+    let layout = layout(4);
+    assert_eq!(layout.to_visual("abcdefghij", 4), (1, 0));
+    assert_eq!(layout.to_visual("abcdefghij", 10), (2, 2));
+    assert_eq!(layout.to_column("abcdefghij", 2, 1), 9);
+    assert_eq!(layout.to_column("abcdefghij", 0, 9), 3);
+}
+
+#[test]
+fn cursor_accounts_for_wrapped_lines() {
+    let mut editor = editor_with("abcdefghij\nxyz", 4, 10);
+    editor.cursor.pos = Position::new(1, 1);
+    assert_eq!(editor.cursor_screen_position(), Some((3, 1)));
+
+    editor.click(1, 2);
+    assert_eq!(editor.cursor.pos, Position::new(0, 6));
+
+    editor.click(8, 0);
+    assert_eq!(editor.cursor.pos, Position::new(1, 0));
+}
+
+#[test]
+fn display_line_motions() {
+    let mut editor = editor_with("abcdefghij\nxyz", 4, 10);
+    editor.cursor.pos = Position::new(0, 1);
+
+    type_keys(&mut editor, "gj");
+    assert_eq!(editor.cursor.pos, Position::new(0, 5));
+
+    type_keys(&mut editor, "gjgj");
+    assert_eq!(editor.cursor.pos, Position::new(1, 1));
+
+    type_keys(&mut editor, "gkg0");
+    assert_eq!(editor.cursor.pos, Position::new(0, 8));
+
+    type_keys(&mut editor, "gkg$");
+    assert_eq!(editor.cursor.pos, Position::new(0, 7));
+}
+
+#[test]
+fn scrolls_by_wrapped_rows() {
+    let mut editor = editor_with("abcdefghij\nabcdefghij\nabcdefghij", 4, 4);
+    type_keys(&mut editor, "j");
+    assert_eq!(editor.viewport.top, 0);
+
+    type_keys(&mut editor, "gjgj");
+    assert_eq!(editor.cursor.pos, Position::new(1, 8));
+    assert_eq!(editor.viewport.top, 1);
+}
@@ -0,0 +1,128 @@
+pub mod common;
+
+use std::error::Error;
+use std::fs;
+
+use editor::editor::Editor;
+use editor::errors::EditorError;
+use editor::message::{MessageLevel, Messages, MESSAGE_HISTORY};
+use utils::Position;
+
+use common::{editor_in_viewport, temp_dir, type_keys};
+
+fn level(editor: &Editor) -> Option<MessageLevel> {
+    editor.messages.current().map(|message| message.level)
+}
+
+#[test]
+fn write_reports_lines_and_bytes() {
+    let dir = temp_dir("write");
+    let path = dir.join("foo.rs");
+
+    let mut editor = editor_in_viewport("fn main() {\n}\n");
+    type_keys(&mut editor, &format!(":w {}<CR>", path.display()));
+
+    assert_eq!(editor.messages.text(), Some(format!("\"{}\" 2L, 13B written", path.display()).as_str()));
+    assert_eq!(level(&editor), Some(MessageLevel::Info));
+
+    type_keys(&mut editor, &format!(":1w! {}<CR>", dir.join("part.rs").display()));
+    assert_eq!(editor.messages.text(), Some(format!("\"{}\" 1L, 11B written", dir.join("part.rs").display()).as_str()));
+
+    // Another file that is already there is only overwritten with `!`.
+    let err = editor.execute_command(&format!("w {}", dir.join("part.rs").display())).unwrap_err();
+    assert!(matches!(err, EditorError::FileExists(..)), "{:?}", err);
+    assert_eq!(fs::read_to_string(dir.join("part.rs")).unwrap(), "fn main() {");
+
+    // As is the buffer's own file with part of the buffer, however it's named.
+    let own = dir.join(".").join("foo.rs");
+    let err = editor.execute_command(&format!("1w {}", own.display())).unwrap_err();
+    assert!(matches!(err, EditorError::PartialWrite), "{:?}", err);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "fn main() {\n}");
+
+    editor.execute_command(&format!("1w! {}", own.display())).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "fn main() {");
+    assert_eq!(editor.get_current_buffer().unwrap().get_path(), Some(path.clone()));
+
+    // Writing part of an unnamed buffer doesn't name it.
+    let mut editor = editor_in_viewport("a\nb\nc");
+    editor.execute_command(&format!("2,3w {}", dir.join("new.rs").display())).unwrap();
+    assert_eq!(fs::read_to_string(dir.join("new.rs")).unwrap(), "b\nc");
+    assert!(editor.get_current_buffer().unwrap().get_path().is_none());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn failures_carry_the_buffer_error() {
+    let dir = temp_dir("fail");
+
+    let mut editor = editor_in_viewport("x");
+    let target = dir.join("missing").join("x.txt");
+
+    let err = editor.execute_command(&format!("w {}", target.display())).unwrap_err();
+    assert!(matches!(err, EditorError::WriteFailed(..)), "{:?}", err);
+    assert!(err.to_string().starts_with(&format!("Can't open file for writing: {}: ", target.display())));
+    assert!(err.source().is_some());
+
+    let err = editor.create_buffer_from_file(dir.clone()).unwrap_err();
+    assert!(matches!(err, EditorError::ReadFailed(..)), "{:?}", err);
+    assert_eq!(err.source().unwrap().to_string(), "Is a directory");
+
+    // `:e` on a directory opens the explorer on it instead.
+    editor.execute_command(&format!("e {}", dir.display())).unwrap();
+    assert!(editor.explorer_focused());
+    type_keys(&mut editor, "q");
+
+    type_keys(&mut editor, ":bogus<CR>");
+    assert_eq!(editor.messages.text(), Some("Not an editor command: bogus"));
+    assert_eq!(level(&editor), Some(MessageLevel::Error));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn messages_lists_history() {
+    let mut editor = editor_in_viewport("a\nb\na");
+
+    type_keys(&mut editor, ":bogus<CR>:%s/a/c/<CR>");
+    editor.cursor.pos = Position::new(0, 0);
+    type_keys(&mut editor, "/b<CR>");
+    assert_eq!(editor.messages.text(), Some("/b [1/1]"));
+
+    editor.execute_command("messages").unwrap();
+    assert_eq!(editor.messages.text(), Some("Not an editor command: bogus\n2 substitutions on 2 lines"));
+
+    editor.execute_command("messages clear").unwrap();
+    editor.messages.clear();
+    editor.execute_command("mes").unwrap();
+    assert_eq!(editor.messages.text(), None);
+
+    assert_eq!(editor.execute_command("messages all").unwrap_err().to_string(), "Invalid argument: all");
+}
+
+#[test]
+fn keys_dismiss_only_information() {
+    let mut messages = Messages::new();
+
+    messages.info("written");
+    assert!(messages.remaining().is_some_and(|remaining| remaining <= MessageLevel::Info.timeout()));
+    messages.dismiss();
+    assert_eq!(messages.text(), None);
+
+    messages.error("broken");
+    messages.dismiss();
+    messages.expire();
+    assert_eq!(messages.text(), Some("broken"));
+
+    messages.show("one\ntwo");
+    assert_eq!(messages.remaining(), None);
+    messages.dismiss();
+    assert_eq!(messages.text(), None);
+
+    for index in 0 ..= MESSAGE_HISTORY {
+        messages.warn(index);
+    }
+
+    assert_eq!(messages.history().count(), MESSAGE_HISTORY);
+    assert_eq!(messages.history().next().map(|message| message.text.as_str()), Some("1"));
+}
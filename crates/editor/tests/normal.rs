@@ -0,0 +1,73 @@
+pub mod common;
+
+use crossterm::event::{KeyCode, KeyModifiers};
+use editor::editor::EditorMode;
+use editor::keys::parse_keys;
+
+use common::{contents, editor_with};
+
+#[test]
+fn parse_key_notation() {
+    let keys = parse_keys("ia<Esc><CR><C-w>j<lt><nope>");
+    let codes: Vec<KeyCode> = keys.iter().map(|key| key.code).collect();
+
+    assert_eq!(codes, vec![
+        KeyCode::Char('i'),
+        KeyCode::Char('a'),
+        KeyCode::Esc,
+        KeyCode::Enter,
+        KeyCode::Char('w'),
+        KeyCode::Char('j'),
+        KeyCode::Char('<'),
+        KeyCode::Char('<'),
+        KeyCode::Char('n'),
+        KeyCode::Char('o'),
+        KeyCode::Char('p'),
+        KeyCode::Char('e'),
+        KeyCode::Char('>'),
+    ]);
+    assert_eq!(keys[4].modifiers, KeyModifiers::CONTROL);
+}
+
+#[test]
+fn normal_over_range() {
+    let mut editor = editor_with("one\ntwo\nthree");
+    editor.execute_command("1,2normal i- ").unwrap();
+    assert_eq!(contents(&editor), "- one\n- two\nthree");
+    assert!(matches!(editor.mode, EditorMode::Normal));
+}
+
+#[test]
+fn normal_runs_ex_commands() {
+    let mut editor = editor_with("a\nb");
+    editor.execute_command("%norm :s/$/;/<CR>").unwrap();
+    assert_eq!(contents(&editor), "a;\nb;");
+}
+
+#[test]
+fn normal_with_global() {
+    let mut editor = editor_with("fn a\nlet b\nfn c");
+    editor.execute_command("g/^fn/normal ipub <Esc>").unwrap();
+    assert_eq!(contents(&editor), "pub fn a\nlet b\npub fn c");
+
+    editor.execute_command("undo").unwrap();
+    assert_eq!(contents(&editor), "fn a\nlet b\nfn c");
+}
+
+#[test]
+fn normal_survives_deleted_lines() {
+    let mut editor = editor_with("a\nb\nc\nd");
+    editor.execute_command("1,3normal :.,+1d<CR>").unwrap();
+    assert_eq!(contents(&editor), "");
+}
+
+#[test]
+fn drive_editor_without_terminal() {
+    let mut editor = editor_with("nya");
+
+    for key in parse_keys(":s/nya/mrrp/<CR>") {
+        editor.handle_key(key);
+    }
+
+    assert_eq!(contents(&editor), "mrrp");
+}
@@ -0,0 +1,143 @@
+pub mod common;
+
+use std::fs;
+
+use editor::editor::Editor;
+
+use common::{editor_with_path, temp_dir};
+
+fn show(editor: &mut Editor, args: &str) -> String {
+    editor.execute_command(&format!("set {}", args)).unwrap();
+    editor.messages.take().unwrap_or_default()
+}
+
+fn error(editor: &mut Editor, command: &str) -> String {
+    editor.execute_command(command).unwrap_err().to_string()
+}
+
+#[test]
+fn set_show_and_toggle() {
+    let mut editor = editor_with_path("x", "notes.txt");
+
+    assert_eq!(show(&mut editor, "ts?"), "tabstop=8");
+    assert_eq!(show(&mut editor, "ts=4 sw:2 ts sw"), "tabstop=4  shiftwidth=2");
+    assert_eq!(show(&mut editor, "sw+=3 ts-=1 sw? ts?"), "shiftwidth=5  tabstop=3");
+
+    // Only the result has to be in range, not the amount.
+    assert_eq!(show(&mut editor, "explorerwidth+=5 explorerwidth? explorerwidth&"), "explorerwidth=35");
+
+    assert_eq!(show(&mut editor, "noexpandtab et?"), "noexpandtab");
+    assert_eq!(show(&mut editor, "et! et?"), "expandtab");
+    assert_eq!(show(&mut editor, "invet et?"), "noexpandtab");
+    assert_eq!(show(&mut editor, "et& ts& et? ts?"), "expandtab  tabstop=8");
+
+    assert_eq!(show(&mut editor, "sbr=>\\  sbr?"), "showbreak=> ");
+    assert_eq!(editor.window_options.show_break, "> ");
+
+    assert_eq!(show(&mut editor, ""), "shiftwidth=5  showbreak=> ");
+}
+
+#[test]
+fn invalid_settings_are_reported() {
+    let mut editor = editor_with_path("x", "notes.txt");
+
+    assert_eq!(error(&mut editor, "set bogus"), "Unknown option: bogus");
+    assert_eq!(error(&mut editor, "set ts=abc"), "Invalid value for tabstop: number required: abc");
+    assert_eq!(error(&mut editor, "set ts=0"), "Invalid value for tabstop: must be between 1 and 64");
+    assert_eq!(error(&mut editor, "set ts-=8"), "Invalid value for tabstop: must be between 1 and 64");
+    assert_eq!(error(&mut editor, "set ts+=x"), "Invalid value for tabstop: number required: x");
+    assert_eq!(error(&mut editor, "set nots"), "Invalid argument: nots");
+    assert_eq!(error(&mut editor, "set wrap=yes"), "Invalid argument: wrap=yes");
+    assert_eq!(error(&mut editor, "set ts!"), "Invalid argument: ts!");
+    assert_eq!(error(&mut editor, "set colorscheme=nope"), "Cannot find color scheme: nope");
+
+    assert_eq!(editor.buffer_options().tab_stop, 8);
+}
+
+#[test]
+fn setlocal_leaves_the_defaults() {
+    let mut editor = editor_with_path("x", "notes.txt");
+
+    editor.execute_command("setlocal sw=2 nowrap").unwrap();
+    assert_eq!(editor.buffer_options().shift_width, 2);
+    assert!(!editor.window_options.wrap);
+    assert_eq!(editor.options.buffer.shift_width, 4);
+    assert!(editor.options.window.wrap);
+
+    editor.execute_command("set sw=3").unwrap();
+    assert_eq!(editor.buffer_options().shift_width, 3);
+
+    editor.create_empty_buffer();
+    let id = *editor.buffer_order.last().unwrap();
+    editor.switch_to_buffer(id);
+    assert_eq!(editor.buffer_options().shift_width, 3);
+
+    editor.execute_command("setlocal sw=6").unwrap();
+    editor.switch_to_buffer(editor.buffer_order[0]);
+    assert_eq!(editor.buffer_options().shift_width, 3);
+}
+
+#[test]
+fn filetypes_bring_their_indent() {
+    let mut editor = editor_with_path("x", "main.go");
+    editor.sync_buffer_options();
+
+    let options = editor.buffer_options();
+    assert_eq!((options.shift_width, options.tab_stop, options.expand_tab), (8, 8, false));
+
+    editor.execute_command("set ft=ruby").unwrap();
+    let options = editor.buffer_options();
+    assert_eq!((options.shift_width, options.expand_tab), (2, true));
+
+    assert_eq!(show(&mut editor, "ft?"), "filetype=ruby");
+    editor.execute_command("set ft=").unwrap();
+    assert_eq!(editor.get_current_buffer().unwrap().filetype(), None);
+}
+
+#[test]
+fn case_options_apply_to_search() {
+    let mut editor = editor_with_path("Word word", "notes.txt");
+
+    editor.execute_command("set noignorecase").unwrap();
+    assert!(!editor.build_regex("word").unwrap().is_match("WORD"));
+
+    editor.execute_command("set ic nosmartcase").unwrap();
+    assert!(editor.build_regex("Word").unwrap().is_match("WORD"));
+}
+
+#[test]
+fn config_files() {
+    let dir = temp_dir("config");
+    let path = dir.join("config.toml");
+
+    fs::write(&path, "tabstop = 4\nwrap = false\nsbr = \"+ \"\n\n[filetype.rust]\nshiftwidth = 2\n").unwrap();
+
+    let mut editor = editor_with_path("x", "main.rs");
+    editor.load_config(&path).unwrap();
+
+    assert_eq!(editor.buffer_options().tab_stop, 4);
+    assert_eq!(editor.buffer_options().shift_width, 2);
+    assert!(!editor.window_options.wrap);
+    assert_eq!(editor.window_options.show_break, "+ ");
+
+    editor.execute_command("set ft=python").unwrap();
+    assert_eq!(editor.buffer_options().shift_width, 4);
+
+    let broken = [
+        ("tabstop = \"wide\"", "Invalid value for tabstop: number required"),
+        ("tabstop = 0", "Invalid value for tabstop: must be between 1 and 64"),
+        ("nonsense = 1", "Unknown option: nonsense"),
+        ("[filetype.rust]\nwrap = true", "wrap is not a buffer option"),
+        ("tabstop = ", "")
+    ];
+
+    for (text, message) in broken {
+        fs::write(&path, text).unwrap();
+        let err = editor.load_config(&path).unwrap_err().to_string();
+
+        assert!(err.starts_with(&format!("Error in {}: ", path.display())), "{}", err);
+        assert!(err.ends_with(message), "{}", err);
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,153 @@
+pub mod common;
+
+use std::fs;
+
+use editor::editor::EditorMode;
+use editor::keys::parse_keys;
+use editor::prompt::{Prompt, PromptEvent, HISTORY_SIZE};
+use utils::Position;
+
+use common::{editor_in_viewport, temp_dir, type_keys};
+
+fn type_prompt(prompt: &mut Prompt, keys: &str) -> PromptEvent {
+    parse_keys(keys).into_iter().map(|key| prompt.handle_key(key)).last().unwrap()
+}
+
+#[test]
+fn editing_moves_the_cursor() {
+    let mut prompt = Prompt::new();
+
+    type_prompt(&mut prompt, "s/foo/bar/<Left><Left><BS>z");
+    assert_eq!((prompt.text.as_str(), prompt.cursor), ("s/foo/bzr/", 8));
+
+    type_prompt(&mut prompt, "<Home>%<End><C-w>");
+    assert_eq!(prompt.text, "%s/foo/bzr");
+
+    type_prompt(&mut prompt, "<C-w>");
+    assert_eq!(prompt.text, "%s/foo/");
+
+    type_prompt(&mut prompt, "<C-Left><C-Left>x<C-Right><Del>");
+    assert_eq!(prompt.text, "%s/xfoo");
+
+    assert_eq!(type_prompt(&mut prompt, "<C-u>"), PromptEvent::Changed);
+    assert_eq!(prompt.text, "");
+    assert_eq!(type_prompt(&mut prompt, "<BS>"), PromptEvent::Cancel);
+    assert_eq!(type_prompt(&mut prompt, "ab<CR>"), PromptEvent::Submit("ab".to_string()));
+}
+
+#[test]
+fn history_is_filtered_by_prefix() {
+    let mut prompt = Prompt::new();
+
+    for entry in ["set ts=4", "w", "set sw=2", "e foo", "set ts=4"] {
+        prompt.push_history(entry);
+    }
+
+    assert_eq!(prompt.history, vec!["w", "set sw=2", "e foo", "set ts=4"]);
+
+    type_prompt(&mut prompt, "se<Up>");
+    assert_eq!(prompt.text, "set ts=4");
+
+    type_prompt(&mut prompt, "<Up>");
+    assert_eq!(prompt.text, "set sw=2");
+
+    type_prompt(&mut prompt, "<Up>");
+    assert_eq!(prompt.text, "set sw=2");
+
+    type_prompt(&mut prompt, "<Down><Down>");
+    assert_eq!(prompt.text, "se");
+
+    prompt.clear();
+    type_prompt(&mut prompt, "<Up><Up>");
+    assert_eq!(prompt.text, "e foo");
+
+    for index in 0 .. HISTORY_SIZE {
+        prompt.push_history(&index.to_string());
+    }
+
+    assert_eq!(prompt.history.len(), HISTORY_SIZE);
+    assert_eq!(prompt.history[0], "0");
+}
+
+#[test]
+fn history_persists() {
+    let dir = temp_dir("history");
+
+    let mut editor = editor_in_viewport("one\ntwo");
+    type_keys(&mut editor, ":set ts=4<CR>/two<CR>:set sw=2<CR>");
+    editor.save_history(&dir).unwrap();
+
+    let mut editor = editor_in_viewport("one\ntwo");
+    editor.load_history(&dir).unwrap();
+
+    assert_eq!(editor.command_line.history, vec!["set ts=4", "set sw=2"]);
+    assert_eq!(editor.search.prompt.history, vec!["two"]);
+
+    type_keys(&mut editor, "/<Up><CR>");
+    assert_eq!(editor.cursor.pos, Position::new(1, 0));
+
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(editor.load_history(&dir).is_ok());
+}
+
+#[test]
+fn command_line_edits_before_running() {
+    let mut editor = editor_in_viewport("one\ntwo");
+
+    type_keys(&mut editor, ":s/x/O/<Left><Left><Left><BS>o<CR>");
+
+    assert_eq!(editor.get_current_buffer().unwrap().get_buffer(), "One\ntwo");
+    assert!(matches!(editor.mode, EditorMode::Normal));
+}
+
+#[test]
+fn completes_commands_options_and_themes() {
+    let mut editor = editor_in_viewport("x");
+
+    assert_eq!(editor.command_completions("colo").1, vec!["colorscheme"]);
+    assert_eq!(editor.command_completions("%subs"), (1, vec!["substitute".to_string()]));
+    assert_eq!(editor.command_completions("set noexp").1, vec!["noexpandtab"]);
+    assert_eq!(editor.command_completions("set scroll").1, vec!["scrolloff"]);
+    assert_eq!(editor.command_completions("set ts=").1, Vec::<String>::new());
+    assert_eq!(editor.command_completions("colorscheme gr").1, vec!["gruvbox"]);
+
+    type_keys(&mut editor, ":setl<Tab> ignorec<Tab><CR>");
+    assert_eq!(editor.command_line.history.last().map(String::as_str), Some("setlocal ignorecase"));
+
+    type_keys(&mut editor, ":no<Tab>");
+    assert_eq!(editor.command_line.text, "nohlsearch");
+
+    type_keys(&mut editor, "<Esc>:set sh<Tab>");
+    assert_eq!(editor.command_line.text, "set shiftwidth");
+    type_keys(&mut editor, "<Tab>");
+    assert_eq!(editor.command_line.text, "set showbreak");
+    type_keys(&mut editor, "<Tab>");
+    assert_eq!(editor.command_line.text, "set sh");
+    type_keys(&mut editor, "<S-Tab>");
+    assert_eq!(editor.command_line.text, "set showbreak");
+}
+
+#[test]
+fn completes_paths() {
+    let dir = temp_dir("complete");
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("main.rs"), "").unwrap();
+    fs::write(dir.join("make.toml"), "").unwrap();
+    fs::write(dir.join(".hidden"), "").unwrap();
+    fs::write(dir.join("src").join("lib.rs"), "").unwrap();
+
+    let mut editor = editor_in_viewport("x");
+    let root = format!("{}/", dir.display());
+
+    let names = |candidates: Vec<String>| -> Vec<String> {
+        candidates.into_iter().map(|path| path[root.len() ..].to_string()).collect()
+    };
+
+    assert_eq!(names(editor.command_completions(&format!("e {}", root)).1), vec!["main.rs", "make.toml", "src/"]);
+    assert_eq!(names(editor.command_completions(&format!("w {}.", root)).1), vec![".hidden"]);
+
+    type_keys(&mut editor, &format!(":e {}s<Tab><Tab>", root));
+    assert_eq!(editor.command_line.text, format!("e {}src/lib.rs", root));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,214 @@
+pub mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use editor::editor::Editor;
+use editor::make::{cargo_json_command, compile_error_format, parse_output_line};
+use editor::quickfix::{ListKind, QuickfixEntry, QuickfixList, Severity};
+use utils::Position;
+
+use common::{load_picker, temp_dir};
+
+fn project(test: &str) -> PathBuf {
+    let dir = temp_dir(test);
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("src/a.rs"), "fn a() {\n    let x = 1;\n}\n").unwrap();
+    fs::write(dir.join("src/b.rs"), "fn b() {}\nfn c() {}\n").unwrap();
+
+    dir
+}
+
+fn entry(path: PathBuf, line: usize, column: usize, message: &str) -> QuickfixEntry {
+    QuickfixEntry { path, pos: Position::new(line, column), message: message.to_string(), severity: Severity::Error }
+}
+
+#[test]
+fn errorformat_lines_become_entries() {
+    let formats = compile_error_format("%f:%l:%c: %t: %m,%f:%l: %m,%f(%l\\,%c): %m");
+
+    assert_eq!(parse_output_line("src/a.rs:3:7: warning: unused x", &formats), vec![QuickfixEntry {
+        path: PathBuf::from("src/a.rs"),
+        pos: Position::new(2, 6),
+        message: "unused x".to_string(),
+        severity: Severity::Warning
+    }]);
+
+    assert_eq!(parse_output_line("b.c:12: expected ';'", &formats), vec![entry(PathBuf::from("b.c"), 11, 0, "expected ';'")]);
+    assert_eq!(parse_output_line("c.cs(4,2): missing", &formats), vec![entry(PathBuf::from("c.cs"), 3, 1, "missing")]);
+    assert!(parse_output_line("   Compiling paw v0.1.0", &formats).is_empty());
+}
+
+#[test]
+fn cargo_json_messages_become_entries() {
+    let line = r#"{"reason":"compiler-message","message":{"message":"mismatched types","level":"error","spans":[
+        {"file_name":"src/lib.rs","line_start":2,"column_start":5,"is_primary":false},
+        {"file_name":"src/main.rs","line_start":10,"column_start":9,"is_primary":true}],
+        "children":[{"message":"expected due to this","level":"note","spans":[
+            {"file_name":"src/main.rs","line_start":9,"column_start":12,"is_primary":true}]},
+            {"message":"for more information","level":"help","spans":[]}]}}"#.replace('\n', "");
+
+    assert_eq!(parse_output_line(&line, &[]), vec![
+        entry(PathBuf::from("src/main.rs"), 9, 8, "mismatched types"),
+        QuickfixEntry {
+            path: PathBuf::from("src/main.rs"),
+            pos: Position::new(8, 11),
+            message: "expected due to this".to_string(),
+            severity: Severity::Note
+        }
+    ]);
+
+    assert!(parse_output_line(r#"{"reason":"build-finished","success":true}"#, &[]).is_empty());
+
+    assert_eq!(cargo_json_command("cargo build"), "cargo build --message-format=json");
+    assert_eq!(cargo_json_command("cargo clippy --all-targets"), "cargo clippy --message-format=json --all-targets");
+    assert_eq!(cargo_json_command("cargo build --message-format=short"), "cargo build --message-format=short");
+    assert_eq!(cargo_json_command("cargo fmt"), "cargo fmt");
+    assert_eq!(cargo_json_command("make all"), "make all");
+}
+
+#[test]
+fn moves_through_the_quickfix_list() {
+    let dir = project("quickfix");
+    let (a, b) = (dir.join("src/a.rs"), dir.join("src/b.rs"));
+
+    let mut editor = Editor::new();
+    editor.create_empty_buffer();
+
+    assert_eq!(editor.execute_command("cnext").unwrap_err().to_string(), "No Errors");
+
+    editor.set_list(ListKind::Quickfix, QuickfixList::new("test", vec![
+        entry(a.clone(), 1, 4, "first"),
+        entry(b.clone(), 1, 3, "second"),
+        entry(a.clone(), 0, 0, "third")
+    ]));
+
+    editor.execute_command("cc").unwrap();
+    assert_eq!(editor.get_buffer_display_name(&editor.current_buffer.unwrap()), "a.rs");
+    assert_eq!(editor.cursor.pos, Position::new(1, 4));
+
+    editor.execute_command("cnext").unwrap();
+    assert_eq!(editor.get_buffer_display_name(&editor.current_buffer.unwrap()), "b.rs");
+    assert_eq!(editor.cursor.pos, Position::new(1, 3));
+    assert_eq!(editor.quickfix.current, 1);
+
+    assert_eq!(editor.execute_command("cnext 2").unwrap_err().to_string(), "No more items");
+    editor.execute_command("cnext").unwrap();
+    assert_eq!(editor.cursor.pos, Position::new(0, 0));
+
+    editor.execute_command("cprev 2").unwrap();
+    assert_eq!(editor.quickfix.current, 0);
+    assert_eq!(editor.execute_command("cprev").unwrap_err().to_string(), "No more items");
+
+    editor.execute_command("cc 2").unwrap();
+    assert_eq!(editor.quickfix.current, 1);
+    assert!(editor.execute_command("cc 0").is_err());
+
+    editor.execute_command("copen").unwrap();
+    let picker = editor.picker.as_ref().unwrap();
+    assert_eq!(picker.title, "test");
+    assert_eq!(picker.selected, 1);
+    assert_eq!(picker.items[0].label, format!("{}:2:5: error: first", a.display()));
+    assert_eq!(picker.preview.as_ref().unwrap().line, Some(1));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn each_window_has_its_own_location_list() {
+    let dir = project("loclist");
+    let a = dir.join("src/a.rs");
+
+    let mut editor = Editor::new();
+    editor.create_empty_buffer();
+
+    assert_eq!(editor.execute_command("lnext").unwrap_err().to_string(), "No location list");
+
+    editor.set_list(ListKind::Location, QuickfixList::new("", vec![entry(a.clone(), 2, 0, "here")]));
+
+    // A new window starts with a copy of the list, as in Vim.
+    editor.execute_command("vsplit").unwrap();
+    editor.execute_command("ll").unwrap();
+    assert_eq!(editor.cursor.pos, Position::new(2, 0));
+
+    editor.set_list(ListKind::Location, QuickfixList::new("", vec![entry(a.clone(), 1, 4, "there")]));
+    editor.execute_command("ll").unwrap();
+    assert_eq!(editor.cursor.pos, Position::new(1, 4));
+
+    editor.execute_command("close").unwrap();
+    editor.execute_command("ll").unwrap();
+    assert_eq!(editor.cursor.pos, Position::new(2, 0));
+
+    // Closed windows take their lists with them.
+    assert_eq!(editor.location_lists.len(), 1);
+    editor.execute_command("tabnew").unwrap();
+    editor.set_list(ListKind::Location, QuickfixList::new("", vec![entry(a.clone(), 1, 0, "tab")]));
+    editor.execute_command("vsplit").unwrap();
+    editor.execute_command("only").unwrap();
+    assert_eq!(editor.location_lists.len(), 2);
+    editor.execute_command("tabclose").unwrap();
+    assert_eq!(editor.location_lists.len(), 1);
+
+    // The quickfix list is still empty.
+    assert!(editor.execute_command("cc").is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn grep_fills_the_quickfix_list() {
+    let dir = project("quickfix-grep");
+
+    let mut editor = Editor::new();
+    editor.create_empty_buffer();
+
+    editor.execute_command(&format!("grep fn {}", dir.display())).unwrap();
+
+    load_picker(&mut editor);
+
+    assert_eq!(editor.quickfix.title, "Grep fn");
+    assert_eq!(editor.quickfix.entries, vec![
+        QuickfixEntry { path: dir.join("src/a.rs"), pos: Position::new(0, 0), message: "fn a() {".to_string(), severity: Severity::Info },
+        QuickfixEntry { path: dir.join("src/b.rs"), pos: Position::new(0, 0), message: "fn b() {}".to_string(), severity: Severity::Info },
+        QuickfixEntry { path: dir.join("src/b.rs"), pos: Position::new(1, 0), message: "fn c() {}".to_string(), severity: Severity::Info }
+    ]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn make_runs_in_the_background_and_jumps_to_the_first_error() {
+    let dir = project("make");
+    let a = dir.join("src/a.rs");
+
+    let mut editor = Editor::new();
+    editor.create_empty_buffer();
+
+    editor.execute_command("set makeprg=printf").unwrap();
+    editor.execute_command(&format!("make '%s:1:1: warning: unused\\n%s:2:5: error: bad\\n' {} {}", a.display(), a.display())).unwrap();
+    assert!(editor.is_busy());
+    assert_eq!(editor.execute_command("make").unwrap_err().to_string(), "A make is already running");
+
+    while editor.make.is_some() {
+        editor.poll_background();
+    }
+
+    assert!(!editor.is_busy());
+    assert_eq!(editor.quickfix.entries.len(), 2);
+    assert_eq!(editor.quickfix.entries[0].severity, Severity::Warning);
+    assert_eq!(editor.get_buffer_display_name(&editor.current_buffer.unwrap()), "a.rs");
+    assert_eq!(editor.cursor.pos, Position::new(1, 4));
+    assert_eq!(editor.quickfix.current, 1);
+
+    // `:make!` leaves the cursor where it is.
+    editor.execute_command(&format!("make! '%s:1:1: error: again\\n' {}", a.display())).unwrap();
+
+    while editor.make.is_some() {
+        editor.poll_background();
+    }
+
+    assert_eq!(editor.cursor.pos, Position::new(1, 4));
+    assert_eq!(editor.quickfix.entries, vec![entry(a, 0, 0, "again")]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,168 @@
+pub mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use editor::editor::Editor;
+use editor::picker::{Picker, PickerItem, PickerTarget};
+use editor::replace::replace_at;
+use editor::substitute::Substitution;
+use utils::Position;
+
+use common::{load_picker, temp_dir, type_keys};
+
+fn project(test: &str) -> PathBuf {
+    let dir = temp_dir(test);
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("src/a.rs"), "fn old() {}\nfn main() { old(); old(); }\n").unwrap();
+    fs::write(dir.join("src/b.rs"), "use a::old;\nfn b() {}\n").unwrap();
+    fs::write(dir.join("src/c.rs"), "fn other() {}\n").unwrap();
+
+    dir
+}
+
+#[test]
+fn replaces_only_where_the_match_still_is() {
+    let (substitution, _) = Substitution::parse(r"/(\w+)\(\)/\U\1()/", None, None).unwrap();
+
+    assert_eq!(replace_at(&substitution, "a() + b()", 6).as_deref(), Some("a() + B()"));
+    assert_eq!(replace_at(&substitution, "a() + b()", 0).as_deref(), Some("A() + b()"));
+    assert_eq!(replace_at(&substitution, "a() + b()", 2), None);
+    assert_eq!(replace_at(&substitution, "a()", 10), None);
+}
+
+#[test]
+fn reviews_and_applies_a_replacement_across_files() {
+    let dir = project("replace");
+    let (a, b) = (dir.join("src/a.rs"), dir.join("src/b.rs"));
+
+    let mut editor = Editor::new();
+    editor.create_empty_buffer();
+
+    assert_eq!(editor.execute_command("greplace").unwrap_err().to_string(), "Argument required");
+
+    // b.rs is open with a change of its own, so it is updated but not written.
+    editor.open_file(b.clone()).unwrap();
+    type_keys(&mut editor, "jix<Esc>");
+
+    editor.execute_command(&format!("greplace /old/new/ {}", dir.display())).unwrap();
+    load_picker(&mut editor);
+
+    let picker = editor.picker.as_ref().unwrap();
+    assert_eq!(picker.title, "Replace old with new");
+    assert_eq!(picker.items.len(), 4);
+
+    // Every match is listed, with a preview of its line before and after.
+    type_keys(&mut editor, "a.rs:2:20");
+    let picker = editor.picker.as_ref().unwrap();
+    assert_eq!(picker.selected_item().unwrap().label, format!("{}:2:20: fn main() {{ old(); old(); }}", a.display()));
+
+    let preview = picker.preview.as_ref().unwrap();
+    assert_eq!(preview.lines[preview.line.unwrap()], "fn main() { old(); old(); }");
+    assert_eq!(preview.replaced.as_deref(), Some("fn main() { old(); new(); }"));
+
+    // Untick that one and apply the rest.
+    type_keys(&mut editor, "<Tab><C-u><CR>");
+    assert!(editor.picker.is_none());
+
+    assert_eq!(fs::read_to_string(&a).unwrap(), "fn new() {}\nfn main() { new(); old(); }");
+    assert_eq!(fs::read_to_string(&b).unwrap(), "use a::old;\nfn b() {}\n");
+    assert_eq!(editor.get_current_buffer().unwrap().lines, vec!["use a::new;", "xfn b() {}"]);
+
+    let summary = editor.messages.text().unwrap().to_string();
+    let mut lines = summary.lines();
+    assert_eq!(lines.next(), Some("3 replacements in 2 files"));
+    assert_eq!(lines.next(), Some(format!("{}: 2 replacements", a.display()).as_str()));
+    assert_eq!(lines.next(), Some(format!("{}: 1 replacement (not written)", b.display()).as_str()));
+
+    // Each buffer takes back the whole replacement in one step.
+    editor.execute_command("undo").unwrap();
+    assert_eq!(editor.get_current_buffer().unwrap().lines, vec!["use a::old;", "xfn b() {}"]);
+
+    let a_id = editor.buffer_for_path(&a).unwrap();
+    editor.switch_to_buffer(a_id);
+    editor.execute_command("undo").unwrap();
+    assert_eq!(editor.get_current_buffer().unwrap().lines, vec!["fn old() {}", "fn main() { old(); old(); }"]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn nothing_ticked_is_reported() {
+    let dir = project("replace-none");
+
+    let mut editor = Editor::new();
+    editor.create_empty_buffer();
+
+    editor.execute_command(&format!("greplace #fn#pub fn# {}", dir.display())).unwrap();
+    load_picker(&mut editor);
+    assert_eq!(editor.picker.as_ref().unwrap().items.len(), 4);
+
+    // `<C-a>` unticks everything that matches when it is all ticked.
+    type_keys(&mut editor, "<C-a>");
+    assert!((0 .. 4).all(|index| !editor.picker.as_ref().unwrap().is_checked(index)));
+
+    type_keys(&mut editor, "<CR>");
+    assert!(editor.picker.is_none());
+    assert_eq!(editor.messages.text(), Some("Nothing selected"));
+    assert_eq!(fs::read_to_string(dir.join("src/c.rs")).unwrap(), "fn other() {}\n");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn open_buffers_are_found_however_they_were_named() {
+    let dir = project("replace-paths");
+    let a = dir.join("src/a.rs");
+
+    let mut editor = Editor::new();
+    editor.open_file(dir.join("src/../src/a.rs")).unwrap();
+    let buffers = editor.buffers.len();
+
+    editor.execute_command(&format!("greplace /old/new/ {}", dir.display())).unwrap();
+    load_picker(&mut editor);
+    type_keys(&mut editor, "<CR>");
+
+    // The buffer already open is the one changed, with no second one made.
+    assert_eq!(editor.buffers.len(), buffers + 1);
+    assert_eq!(editor.get_current_buffer().unwrap().lines, vec!["fn new() {}", "fn main() { new(); new(); }"]);
+    assert_eq!(fs::read_to_string(&a).unwrap(), "fn new() {}\nfn main() { new(); new(); }");
+    assert_eq!(editor.buffer_for_path(&a), editor.current_buffer);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn files_that_cant_be_opened_are_skipped() {
+    let dir = project("replace-latin1");
+    let (a, latin) = (dir.join("src/a.rs"), dir.join("src/latin.rs"));
+    fs::write(&latin, b"fn old() { \"caf\xe9\" }\n").unwrap();
+
+    let mut editor = Editor::new();
+    editor.create_empty_buffer();
+
+    // Files that aren't UTF-8 aren't offered for replacing.
+    editor.execute_command(&format!("greplace /old/new/ {}", dir.display())).unwrap();
+    load_picker(&mut editor);
+    assert!(editor.picker.as_ref().unwrap().items.iter().all(|item| !item.label.contains("latin.rs")));
+    editor.picker = None;
+
+    // One that stopped being UTF-8 since is reported, and the rest still done.
+    let (mut substitution, _) = Substitution::parse("/old/new/", None, None).unwrap();
+    substitution.global = true;
+
+    let mut picker = Picker::new("", vec![
+        PickerItem { label: String::new(), target: PickerTarget::Location(a.clone(), Position::new(0, 3)) },
+        PickerItem { label: String::new(), target: PickerTarget::Location(latin.clone(), Position::new(0, 3)) }
+    ]);
+    picker.replace = Some(substitution);
+
+    editor.apply_replace(&picker).unwrap();
+    assert_eq!(fs::read_to_string(&a).unwrap(), "fn new() {}\nfn main() { old(); old(); }");
+
+    let summary = editor.messages.text().unwrap().to_string();
+    assert!(summary.starts_with("1 replacement in 1 file, 1 in files that couldn't be opened\n"), "{}", summary);
+    assert!(summary.contains(&format!("{}: Can't", latin.display())), "{}", summary);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
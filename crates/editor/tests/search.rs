@@ -0,0 +1,114 @@
+pub mod common;
+
+use editor::editor::{Editor, EditorMode};
+use utils::Position;
+
+use common::{editor_with, type_keys};
+
+#[test]
+fn search_forward_and_repeat() {
+    let mut editor = editor_with("foo\nbar foo\nfoo");
+    type_keys(&mut editor, "/foo<CR>");
+    assert_eq!(editor.cursor.pos, Position::new(1, 4));
+    assert_eq!(editor.messages.text(), Some("/foo [2/3]"));
+
+    type_keys(&mut editor, "n");
+    assert_eq!(editor.cursor.pos, Position::new(2, 0));
+
+    type_keys(&mut editor, "n");
+    assert_eq!(editor.cursor.pos, Position::new(0, 0));
+    assert_eq!(editor.messages.text(), Some("search hit BOTTOM, continuing at TOP [1/3]"));
+
+    type_keys(&mut editor, "N");
+    assert_eq!(editor.cursor.pos, Position::new(2, 0));
+}
+
+#[test]
+fn search_backward() {
+    let mut editor = editor_with("foo\nbar\nfoo bar");
+    editor.cursor.pos = Position::new(2, 4);
+    type_keys(&mut editor, "?bar<CR>");
+    assert_eq!(editor.cursor.pos, Position::new(1, 0));
+
+    type_keys(&mut editor, "n");
+    assert_eq!(editor.cursor.pos, Position::new(2, 4));
+}
+
+#[test]
+fn incremental_search_and_cancel() {
+    let mut editor = editor_with("alpha\nbeta\ngamma");
+    type_keys(&mut editor, "/gam");
+    assert!(matches!(editor.mode, EditorMode::Search));
+    assert_eq!(editor.cursor.pos, Position::new(2, 0));
+
+    type_keys(&mut editor, "<Esc>");
+    assert!(matches!(editor.mode, EditorMode::Normal));
+    assert_eq!(editor.cursor.pos, Position::new(0, 0));
+}
+
+#[test]
+fn smartcase() {
+    let mut editor = editor_with("start\nFoo\nfoo");
+    type_keys(&mut editor, "/foo<CR>");
+    assert_eq!(editor.cursor.pos, Position::new(1, 0));
+
+    editor.cursor.pos = Position::new(0, 0);
+    type_keys(&mut editor, "/Foo<CR>n");
+    assert_eq!(editor.cursor.pos, Position::new(1, 0));
+}
+
+#[test]
+fn literal_fallback() {
+    let mut editor = editor_with("x\ncall(a");
+    type_keys(&mut editor, "/call(<CR>");
+    assert_eq!(editor.cursor.pos, Position::new(1, 0));
+}
+
+#[test]
+fn star_and_hash() {
+    let mut editor = editor_with("let value = 1;\nvalues\nprint(value)");
+    editor.cursor.pos = Position::new(0, 6);
+    type_keys(&mut editor, "*");
+    assert_eq!(editor.cursor.pos, Position::new(2, 6));
+
+    type_keys(&mut editor, "#");
+    assert_eq!(editor.cursor.pos, Position::new(0, 4));
+}
+
+#[test]
+fn history_and_shared_pattern() {
+    let mut editor = editor_with("a\nb\na");
+    type_keys(&mut editor, "/a<CR>/b<CR>");
+    assert_eq!(editor.search.prompt.history, vec!["a", "b"]);
+
+    type_keys(&mut editor, "/<Up><Up><CR>");
+    assert_eq!(editor.cursor.pos, Position::new(2, 0));
+
+    editor.execute_command("%s//x/").unwrap();
+    assert_eq!(editor.get_current_buffer().unwrap().get_buffer(), "x\nb\nx");
+}
+
+#[test]
+fn not_found() {
+    let mut editor = editor_with("a");
+    type_keys(&mut editor, "/zzz<CR>");
+    assert_eq!(editor.messages.text(), Some("Pattern not found: zzz"));
+}
+
+#[test]
+fn highlight_follows_the_pattern_and_case_options() {
+    let mut editor = editor_with("Foo foo");
+    editor.execute_command("set noignorecase").unwrap();
+    let pattern = |editor: &mut Editor| editor.search_highlight().map(|regex| regex.as_str().to_string());
+
+    type_keys(&mut editor, "/fo");
+    assert_eq!(pattern(&mut editor).as_deref(), Some("fo"));
+    type_keys(&mut editor, "o<CR>");
+    assert_eq!(pattern(&mut editor).as_deref(), Some("foo"));
+
+    editor.execute_command("set ignorecase").unwrap();
+    assert_eq!(pattern(&mut editor).as_deref(), Some("(?i)foo"));
+
+    editor.execute_command("nohlsearch").unwrap();
+    assert_eq!(pattern(&mut editor), None);
+}
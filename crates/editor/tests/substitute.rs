@@ -0,0 +1,109 @@
+pub mod common;
+
+use crossterm::event::KeyCode;
+use editor::editor::EditorMode;
+use editor::EditorError;
+
+use common::{contents, editor_with};
+
+#[test]
+fn substitute_first_and_global() {
+    let mut editor = editor_with("nya nya\nnya");
+    editor.execute_command("s/nya/mrrp/").unwrap();
+    assert_eq!(contents(&editor), "mrrp nya\nnya");
+
+    editor.execute_command("%s/nya/woof/g").unwrap();
+    assert_eq!(contents(&editor), "mrrp woof\nwoof");
+    assert_eq!(editor.messages.text(), Some("2 substitutions on 2 lines"));
+}
+
+#[test]
+fn substitute_capture_groups() {
+    let mut editor = editor_with("hello world");
+    editor.execute_command(r"s/(\w+) (\w+)/\2 $1 [&]/").unwrap();
+    assert_eq!(contents(&editor), "world hello [hello world]");
+}
+
+#[test]
+fn substitute_case_modifiers() {
+    let mut editor = editor_with("hello world");
+    editor.execute_command(r"s/(\w+) (\w+)/\u\1 \U\2\E!/").unwrap();
+    assert_eq!(contents(&editor), "Hello WORLD!");
+}
+
+#[test]
+fn substitute_ignore_case_and_delimiters() {
+    let mut editor = editor_with("a/b A/B");
+    editor.execute_command("s#a/b#x#gi").unwrap();
+    assert_eq!(contents(&editor), "x x");
+}
+
+#[test]
+fn substitute_count() {
+    let mut editor = editor_with("a\na\na\na");
+    editor.execute_command("2s/a/b/ 2").unwrap();
+    assert_eq!(contents(&editor), "a\nb\nb\na");
+}
+
+#[test]
+fn substitute_repeat_last() {
+    let mut editor = editor_with("a a\na a");
+    editor.execute_command("s/a/b/g").unwrap();
+    editor.execute_command("2s").unwrap();
+    assert_eq!(contents(&editor), "b b\nb a");
+}
+
+#[test]
+fn substitute_not_found() {
+    let mut editor = editor_with("nya");
+    assert!(matches!(editor.execute_command("s/woof/x/"), Err(EditorError::PatternNotFound(_))));
+    assert!(matches!(editor.execute_command("s/(/x/"), Err(EditorError::InvalidPattern(_))));
+}
+
+#[test]
+fn substitute_confirm() {
+    let mut editor = editor_with("a a a\na");
+    editor.execute_command("%s/a/b/gc").unwrap();
+
+    let confirm = editor.substitute_confirm.as_ref().unwrap();
+    assert_eq!((confirm.line, confirm.start), (0, 0));
+
+    editor.handle_confirm_input(KeyCode::Char('y'));
+    editor.handle_confirm_input(KeyCode::Char('n'));
+
+    let confirm = editor.substitute_confirm.as_ref().unwrap();
+    assert_eq!((confirm.line, confirm.start), (0, 4));
+
+    editor.handle_confirm_input(KeyCode::Char('a'));
+    assert!(editor.substitute_confirm.is_none());
+    assert_eq!(contents(&editor), "b a b\nb");
+    assert_eq!(editor.messages.text(), Some("3 substitutions on 2 lines"));
+}
+
+#[test]
+fn substitute_confirm_quit() {
+    let mut editor = editor_with("a a");
+    editor.execute_command("s/a/b/gc").unwrap();
+    editor.handle_confirm_input(KeyCode::Char('q'));
+    assert!(editor.substitute_confirm.is_none());
+    assert_eq!(contents(&editor), "a a");
+}
+
+#[test]
+fn substitute_preview() {
+    let mut editor = editor_with("nya nya");
+    editor.change_mode(EditorMode::Command);
+    editor.command_line.set_text("s/nya/mrrp/g");
+
+    let (range, substitution) = editor.substitute_preview().unwrap();
+    assert_eq!((range.start, range.end), (0, 0));
+    assert_eq!(substitution.replace_line("nya nya").unwrap().0, "mrrp mrrp");
+
+    // An empty pattern previews whatever was searched for last.
+    editor.command_line.set_text("s//mrrp/");
+    editor.last_pattern = Some("a".to_string());
+    assert_eq!(editor.substitute_preview().unwrap().1.replace_line("nya").unwrap().0, "nymrrp");
+
+    editor.last_pattern = Some("n".to_string());
+    assert_eq!(editor.substitute_preview().unwrap().1.replace_line("nya").unwrap().0, "mrrpya");
+}
@@ -0,0 +1,74 @@
+pub mod common;
+
+use editor::editor::EditorMode;
+use utils::Position;
+
+use common::{editor_with_path, type_keys};
+
+#[test]
+fn visual_selection_follows_the_cursor() {
+    let mut editor = editor_with_path("one\ntwo", "notes.txt");
+    type_keys(&mut editor, "lvjl");
+
+    assert!(matches!(editor.mode, EditorMode::Visual));
+    assert_eq!(editor.selection(), Some(Position::new(0, 1) .. Position::new(1, 3)));
+
+    type_keys(&mut editor, "<Esc>");
+    assert_eq!(editor.selection(), None);
+}
+
+#[test]
+fn structural_keys_need_a_syntax_tree() {
+    let mut editor = editor_with_path("one two", "notes.txt");
+    type_keys(&mut editor, "vaf");
+
+    assert_eq!(editor.messages.text(), Some("No syntax tree for this buffer"));
+    assert_eq!(editor.selection(), Some(Position::new(0, 0) .. Position::new(0, 1)));
+}
+
+#[cfg(feature = "tree-sitter")]
+const SOURCE: &str = "impl Point {
+    fn add(&self, dx: i32) -> i32 {
+        let total = self.x + dx;
+        total
+    }
+}";
+
+#[cfg(feature = "tree-sitter")]
+#[test]
+fn text_objects_select_syntax_nodes() {
+    let mut editor = editor_with_path(SOURCE, "point.rs");
+    type_keys(&mut editor, "jjwwwwvaf");
+
+    assert_eq!(editor.selection(), Some(Position::new(1, 4) .. Position::new(4, 5)));
+
+    type_keys(&mut editor, "<Esc>vif");
+    assert_eq!(editor.selection(), Some(Position::new(2, 8) .. Position::new(3, 13)));
+}
+
+#[cfg(feature = "tree-sitter")]
+#[test]
+fn expand_and_shrink_selection() {
+    let mut editor = editor_with_path(SOURCE, "point.rs");
+    editor.move_cursor_to(Position::new(2, 12));
+    type_keys(&mut editor, "v+");
+
+    assert_eq!(editor.selection(), Some(Position::new(2, 12) .. Position::new(2, 17)));
+
+    type_keys(&mut editor, "+");
+    assert_eq!(editor.selection(), Some(Position::new(2, 8) .. Position::new(2, 32)));
+
+    type_keys(&mut editor, "--");
+    assert_eq!(editor.selection(), Some(Position::new(2, 12) .. Position::new(2, 13)));
+}
+
+#[cfg(feature = "tree-sitter")]
+#[test]
+fn sticky_context_for_the_top_line() {
+    let mut editor = editor_with_path(SOURCE, "point.rs");
+    editor.viewport.top = 2;
+    editor.update_syntax();
+
+    assert_eq!(editor.syntax.context(), &[0, 1]);
+    assert!(!editor.syntax.spans(2).is_empty());
+}
@@ -0,0 +1,142 @@
+pub mod common;
+
+use std::fs;
+
+use editor::editor::Editor;
+use editor::window::WindowRect;
+use utils::Position;
+
+use common::{temp_dir, type_keys};
+
+/// An editor with its windows laid out on an 80 by 24 screen.
+fn editor_with(text: &str) -> Editor {
+    let mut editor = common::editor_with(text);
+    editor.resize_windows(WindowRect::new(0, 0, 80, 24));
+    editor.resize_viewport(40, 10);
+    editor
+}
+
+fn labels(editor: &Editor) -> Vec<String> {
+    (0 .. editor.tab_count()).map(|index| editor.tab_label(index)).collect()
+}
+
+#[test]
+fn tab_pages_keep_their_own_layout() {
+    let mut editor = editor_with("one\ntwo");
+    let main = editor.current_buffer;
+
+    type_keys(&mut editor, "j:vsplit<CR>");
+    assert_eq!(editor.window_count(), 2);
+
+    editor.execute_command("tabnew").unwrap();
+    assert_eq!(editor.tab_count(), 2);
+    assert_eq!(editor.current_tab, 1);
+    assert_eq!(editor.window_count(), 1);
+    assert_ne!(editor.current_buffer, main);
+    assert_eq!(editor.buffer_order.len(), 2);
+
+    type_keys(&mut editor, "gt");
+    assert_eq!(editor.current_tab, 0);
+    assert_eq!(editor.window_count(), 2);
+    assert_eq!(editor.current_buffer, main);
+    assert_eq!(editor.cursor.pos, Position::new(1, 0));
+
+    type_keys(&mut editor, "gT");
+    assert_eq!(editor.current_tab, 1);
+
+    assert_eq!(labels(&editor), vec!["2 Untitled", "Untitled"]);
+}
+
+#[test]
+fn tabnew_opens_a_file() {
+    let dir = temp_dir("tabnew");
+    fs::write(dir.join("notes.md"), "# notes\n").unwrap();
+
+    let mut editor = editor_with("x");
+
+    editor.execute_command(&format!("tabe {}", dir.join("notes.md").display())).unwrap();
+    assert_eq!(labels(&editor), vec!["Untitled", "notes.md"]);
+
+    fs::write(dir.join("bad.txt"), [0xff, 0xfe]).unwrap();
+    assert!(editor.execute_command(&format!("tabnew {}", dir.join("bad.txt").display())).is_err());
+    assert_eq!(editor.tab_count(), 2);
+    assert_eq!(editor.current_tab, 1);
+
+    // A directory opens in the explorer.
+    editor.execute_command(&format!("tabnew {}", dir.display())).unwrap();
+    assert!(editor.explorer_focused());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn closes_and_moves_tab_pages() {
+    let mut editor = editor_with("x");
+
+    assert_eq!(editor.execute_command("tabclose").unwrap_err().to_string(), "Cannot close last tab page");
+
+    for _ in 0 .. 3 {
+        editor.execute_command("tabnew").unwrap();
+    }
+
+    let names: Vec<_> = (0 .. 4).map(|index| {
+        editor.switch_tab(index);
+        editor.current_buffer
+    }).collect();
+
+    let order = |editor: &mut Editor| -> Vec<usize> {
+        let current = editor.current_tab;
+        let order = (0 .. editor.tab_count())
+            .map(|index| {
+                editor.switch_tab(index);
+                names.iter().position(|name| *name == editor.current_buffer).unwrap()
+            })
+            .collect();
+
+        editor.switch_tab(current);
+        order
+    };
+
+    editor.switch_tab(0);
+    editor.execute_command("tabmove 2").unwrap();
+    assert_eq!(order(&mut editor), vec![1, 0, 2, 3]);
+    assert_eq!(editor.current_tab, 1);
+
+    editor.execute_command("tabmove").unwrap();
+    assert_eq!(order(&mut editor), vec![1, 2, 3, 0]);
+
+    editor.execute_command("tabmove 0").unwrap();
+    assert_eq!(order(&mut editor), vec![0, 1, 2, 3]);
+
+    editor.execute_command("tabm +2").unwrap();
+    assert_eq!(order(&mut editor), vec![1, 2, 0, 3]);
+
+    editor.execute_command("tabclose 1").unwrap();
+    assert_eq!(order(&mut editor), vec![2, 0, 3]);
+    assert_eq!(editor.current_tab, 1);
+
+    editor.execute_command("tabclose").unwrap();
+    assert_eq!(order(&mut editor), vec![2, 3]);
+    assert_eq!(editor.current_tab, 1);
+
+    editor.execute_command("tabn 1").unwrap();
+    assert_eq!(editor.current_tab, 0);
+    assert!(editor.execute_command("tabn 3").is_err());
+
+    editor.execute_command("q").unwrap();
+    assert!(!editor.should_quit);
+    assert_eq!(editor.tab_count(), 1);
+}
+
+#[test]
+fn top_bar_lists_buffers_or_tabs() {
+    let mut editor = editor_with("x");
+
+    assert_eq!(editor.options.global.top_bar, "buffers");
+
+    editor.execute_command("set topbar=tabs").unwrap();
+    assert_eq!(editor.options.global.top_bar, "tabs");
+
+    let err = editor.execute_command("set topbar=files").unwrap_err();
+    assert_eq!(err.to_string(), "Invalid value for topbar: expected buffers or tabs");
+}
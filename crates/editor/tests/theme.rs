@@ -0,0 +1,108 @@
+pub mod common;
+
+use std::fs;
+
+use editor::editor::Editor;
+use editor::theme::{Color, ColorDepth, Modifiers, Theme, BUILTIN_THEMES};
+
+use common::temp_dir;
+
+#[test]
+fn builtin_themes_parse() {
+    for (name, _) in BUILTIN_THEMES {
+        assert!(Theme::builtin(name).is_some(), "{} does not parse", name);
+    }
+}
+
+#[test]
+fn scopes_fall_back_to_their_prefix() {
+    let theme = Theme::parse("test", r##"
+        [palette]
+        accent = "#ff8000"
+
+        [ui]
+        linenr = "gray"
+        selection = { bg = 236 }
+
+        [syntax]
+        keyword = { fg = "accent", modifiers = ["bold", "italic"] }
+        "keyword.control" = "light-red"
+    "##).unwrap();
+
+    assert_eq!(theme.style("ui.linenr").fg, Some(Color::Ansi(7)));
+    assert_eq!(theme.style("ui.linenr.current").fg, Some(Color::Ansi(7)));
+    assert_eq!(theme.style("ui.selection").bg, Some(Color::Indexed(236)));
+
+    let keyword = theme.syntax_style("keyword.other");
+    assert_eq!(keyword.fg, Some(Color::Rgb(255, 128, 0)));
+    assert!(keyword.modifiers.contains(Modifiers::BOLD | Modifiers::ITALIC));
+
+    assert_eq!(theme.syntax_style("keyword.control.flow").fg, Some(Color::Ansi(9)));
+    assert_eq!(theme.syntax_style("string").fg, None);
+    assert_eq!(theme.style("ui").fg, None);
+}
+
+#[test]
+fn invalid_themes_are_rejected() {
+    for text in ["x = \"#12345\"", "x = \"mauve\"", "x = { fg = 300 }", "x = { fg = \"red\", size = 2 }", "x = { modifiers = [\"blink\"] }", "x ="] {
+        assert!(Theme::parse("bad", text).is_err(), "{} parsed", text);
+    }
+}
+
+#[test]
+fn themes_inherit_and_load_from_a_directory() {
+    let dir = temp_dir("themes");
+    fs::write(dir.join("mine.toml"), "inherits = \"default\"\n[ui]\nlinenr = \"#010203\"\n").unwrap();
+
+    let theme = Theme::load_from("mine", Some(&dir)).unwrap();
+    assert_eq!(theme.name, "mine");
+    assert_eq!(theme.style("ui.linenr").fg, Some(Color::Rgb(1, 2, 3)));
+    assert_eq!(theme.style("ui.text").fg, Some(Color::Ansi(15)));
+
+    assert!(Theme::load_from("gruvbox", Some(&dir)).is_ok());
+    assert!(Theme::load_from("missing", Some(&dir)).is_err());
+
+    // A theme named after a built-in one can extend it.
+    fs::write(dir.join("gruvbox.toml"), "inherits = \"gruvbox\"\n[ui]\nlinenr = \"#010203\"\n").unwrap();
+    let theme = Theme::load_from("gruvbox", Some(&dir)).unwrap();
+    assert_eq!(theme.style("ui.linenr").fg, Some(Color::Rgb(1, 2, 3)));
+    assert_eq!(theme.style("ui.text"), Theme::builtin("gruvbox").unwrap().style("ui.text"));
+
+    // Themes inheriting each other are an error, not a crash.
+    fs::write(dir.join("a.toml"), "inherits = \"b\"\n").unwrap();
+    fs::write(dir.join("b.toml"), "inherits = \"a\"\n").unwrap();
+    assert_eq!(Theme::load_from("a", Some(&dir)).unwrap_err().to_string(), "Invalid color scheme a: inheritance cycle");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn colors_fit_the_terminal() {
+    assert_eq!(ColorDepth::from_env(Some("truecolor"), Some("xterm")), ColorDepth::TrueColor);
+    assert_eq!(ColorDepth::from_env(None, Some("xterm-256color")), ColorDepth::Ansi256);
+    assert_eq!(ColorDepth::from_env(None, Some("linux")), ColorDepth::Ansi16);
+
+    let orange = Color::Rgb(255, 135, 0);
+    assert_eq!(orange.fit(ColorDepth::TrueColor), orange);
+    assert_eq!(orange.fit(ColorDepth::Ansi256), Color::Indexed(208));
+    assert_eq!(Color::Rgb(250, 10, 10).fit(ColorDepth::Ansi16), Color::Ansi(9));
+    assert_eq!(Color::Rgb(40, 40, 40).fit(ColorDepth::Ansi256), Color::Indexed(235));
+    assert_eq!(Color::Indexed(4).fit(ColorDepth::Ansi16), Color::Ansi(4));
+    assert_eq!(Color::Ansi(3).fit(ColorDepth::Ansi16), Color::Ansi(3));
+}
+
+#[test]
+fn colorscheme_command() {
+    let mut editor = Editor::new();
+    editor.color_depth = ColorDepth::Ansi256;
+
+    editor.execute_command("colorscheme gruvbox").unwrap();
+    assert_eq!(editor.theme.name, "gruvbox");
+    assert!(matches!(editor.theme.style("ui.text").bg, Some(Color::Indexed(_))));
+
+    assert_eq!(editor.execute_command("colo nope").unwrap_err().to_string(), "Cannot find color scheme: nope");
+    assert_eq!(editor.theme.name, "gruvbox");
+
+    editor.execute_command("colorscheme").unwrap();
+    assert_eq!(editor.messages.text(), Some("gruvbox"));
+}
@@ -0,0 +1,112 @@
+pub mod common;
+
+use buffer::buffer::Buffer;
+use editor::editor::Editor;
+use editor::viewport::Viewport;
+use utils::Position;
+
+use common::type_keys;
+
+fn editor_with_lines(count: usize, height: usize) -> Editor {
+    let text = (1 ..= count).map(|i| format!("line {}", i)).collect::<Vec<_>>().join("\n");
+
+    let mut editor = Editor::new();
+    editor.create_empty_buffer();
+    *editor.get_current_buffer_mut().unwrap() = Buffer::from_text(&text);
+    editor.window_options.scroll_off = 2;
+    editor.resize_viewport(40, height);
+    editor
+}
+
+#[test]
+fn scroll_to_keeps_scroll_off() {
+    let mut viewport = Viewport { top: 0, left: 0, width: 10, height: 10 };
+
+    viewport.scroll_to(Position::new(8, 0), 100, 2, 0);
+    assert_eq!(viewport.top, 1);
+
+    viewport.scroll_to(Position::new(2, 0), 100, 2, 0);
+    assert_eq!(viewport.top, 0);
+
+    viewport.scroll_to(Position::new(99, 0), 100, 2, 0);
+    assert_eq!(viewport.top, 90);
+
+    viewport.scroll_to(Position::new(0, 25), 100, 0, 3);
+    assert_eq!(viewport.left, 19);
+}
+
+#[test]
+fn moving_down_scrolls() {
+    let mut editor = editor_with_lines(50, 10);
+    type_keys(&mut editor, "jjjjjjjj");
+    assert_eq!(editor.cursor.pos.line, 8);
+    assert_eq!(editor.viewport.top, 1);
+}
+
+#[test]
+fn half_page_motions() {
+    let mut editor = editor_with_lines(50, 10);
+    type_keys(&mut editor, "<C-d>");
+    assert_eq!(editor.viewport.top, 5);
+    assert_eq!(editor.cursor.pos.line, 7);
+
+    type_keys(&mut editor, "<C-u><C-u>");
+    assert_eq!(editor.viewport.top, 0);
+    assert_eq!(editor.cursor.pos.line, 0);
+}
+
+#[test]
+fn page_motions() {
+    let mut editor = editor_with_lines(50, 10);
+    type_keys(&mut editor, "<C-f>");
+    assert_eq!(editor.viewport.top, 8);
+    assert_eq!(editor.cursor.pos.line, 10);
+
+    type_keys(&mut editor, "<C-b>");
+    assert_eq!(editor.viewport.top, 0);
+    assert_eq!(editor.cursor.pos.line, 7);
+}
+
+#[test]
+fn line_scrolling_drags_cursor() {
+    let mut editor = editor_with_lines(50, 10);
+    type_keys(&mut editor, "<C-e><C-e><C-e>");
+    assert_eq!(editor.viewport.top, 3);
+    assert_eq!(editor.cursor.pos.line, 5);
+
+    type_keys(&mut editor, "<C-y>");
+    assert_eq!(editor.viewport.top, 2);
+    assert_eq!(editor.cursor.pos.line, 5);
+}
+
+#[test]
+fn cursor_line_anchors() {
+    let mut editor = editor_with_lines(50, 10);
+    editor.cursor.pos = Position::new(20, 0);
+
+    type_keys(&mut editor, "zt");
+    assert_eq!(editor.viewport.top, 18);
+
+    type_keys(&mut editor, "zz");
+    assert_eq!(editor.viewport.top, 16);
+
+    type_keys(&mut editor, "zb");
+    assert_eq!(editor.viewport.top, 13);
+}
+
+#[test]
+fn screen_line_motions() {
+    let mut editor = editor_with_lines(50, 10);
+    editor.cursor.pos = Position::new(20, 0);
+    type_keys(&mut editor, "zz");
+
+    type_keys(&mut editor, "H");
+    assert_eq!(editor.cursor.pos.line, 18);
+
+    type_keys(&mut editor, "L");
+    assert_eq!(editor.cursor.pos.line, 23);
+
+    type_keys(&mut editor, "M");
+    assert_eq!(editor.cursor.pos.line, 20);
+    assert_eq!(editor.viewport.top, 16);
+}
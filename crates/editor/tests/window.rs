@@ -0,0 +1,163 @@
+pub mod common;
+
+use editor::editor::Editor;
+use editor::window::{SplitDirection, WindowLayout, WindowRect};
+use utils::Position;
+
+use common::{temp_dir, type_keys};
+
+/// An editor with its windows laid out on an 80 by 24 screen.
+fn editor_with(text: &str) -> Editor {
+    let mut editor = common::editor_with(text);
+    editor.resize_windows(WindowRect::new(0, 0, 80, 24));
+    editor.resize_viewport(40, 10);
+    editor
+}
+
+fn rect(editor: &Editor) -> WindowRect {
+    editor.window_rects()
+        .into_iter()
+        .find(|(id, _)| *id == editor.current_window)
+        .map(|(_, rect)| rect)
+        .unwrap()
+}
+
+#[test]
+fn windows_share_the_buffer_but_not_the_cursor() {
+    let mut editor = editor_with("one\ntwo\nthree");
+    let first = editor.current_window;
+
+    editor.execute_command("split").unwrap();
+    let second = editor.current_window;
+
+    assert_ne!(first, second);
+    assert_eq!(editor.window_layout.windows(), vec![second, first]);
+
+    type_keys(&mut editor, "jjiX<Esc>");
+    assert_eq!(editor.cursor.pos, Position::new(2, 1));
+
+    type_keys(&mut editor, "<C-w>j");
+    assert_eq!(editor.current_window, first);
+    assert_eq!(editor.cursor.pos, Position::new(0, 0));
+    assert_eq!(editor.get_current_buffer().unwrap().get_buffer(), "one\ntwo\nXthree");
+
+    type_keys(&mut editor, ":%d<CR>");
+    type_keys(&mut editor, "<C-w>k");
+    assert_eq!(editor.current_window, second);
+    assert_eq!(editor.cursor.pos, Position::new(0, 0));
+}
+
+#[test]
+fn moves_between_windows_by_position() {
+    let mut editor = editor_with("x");
+    let first = editor.current_window;
+
+    editor.execute_command("vsplit").unwrap();
+    let left = editor.current_window;
+    editor.execute_command("sp").unwrap();
+    let top_left = editor.current_window;
+
+    assert_eq!(editor.window_count(), 3);
+    assert_eq!(rect(&editor), WindowRect::new(0, 0, 40, 12));
+
+    type_keys(&mut editor, "<C-w>l");
+    assert_eq!(editor.current_window, first);
+    assert_eq!(rect(&editor), WindowRect::new(40, 0, 40, 24));
+
+    type_keys(&mut editor, "<C-w>l<C-w>h");
+    assert_eq!(editor.current_window, top_left);
+
+    type_keys(&mut editor, "<C-w>j");
+    assert_eq!(editor.current_window, left);
+
+    type_keys(&mut editor, "<C-w>w");
+    assert_eq!(editor.current_window, first);
+    type_keys(&mut editor, "<C-w>w");
+    assert_eq!(editor.current_window, top_left);
+    type_keys(&mut editor, "<C-w>W");
+    assert_eq!(editor.current_window, first);
+}
+
+#[test]
+fn resizes_and_equalizes() {
+    let mut editor = editor_with("x");
+
+    editor.execute_command("split").unwrap();
+    editor.execute_command("resize 5").unwrap();
+    assert_eq!(rect(&editor).height, 6);
+
+    editor.execute_command("resize +2").unwrap();
+    assert_eq!(editor.window_size(SplitDirection::Horizontal), 7);
+
+    type_keys(&mut editor, "<C-w>-<C-w>-");
+    assert_eq!(editor.window_size(SplitDirection::Horizontal), 5);
+
+    type_keys(&mut editor, "<C-w>j");
+    assert_eq!(rect(&editor), WindowRect::new(0, 6, 80, 18));
+
+    type_keys(&mut editor, "<C-w>=");
+    assert_eq!(rect(&editor).height, 12);
+
+    type_keys(&mut editor, "<C-w>_");
+    assert_eq!(rect(&editor).height, 23);
+
+    // Without a vertical split there is no width to change.
+    editor.execute_command("vertical resize 20").unwrap();
+    assert_eq!(rect(&editor).width, 80);
+
+    editor.execute_command("vert sp").unwrap();
+    editor.execute_command("vertical resize 20").unwrap();
+    assert_eq!(rect(&editor), WindowRect::new(0, 1, 20, 23));
+
+    assert_eq!(editor.execute_command("resize x").unwrap_err().to_string(), "Invalid argument: x");
+}
+
+#[test]
+fn closes_windows() {
+    let mut editor = editor_with("x");
+    let first = editor.current_window;
+
+    assert_eq!(editor.execute_command("close").unwrap_err().to_string(), "Cannot close last window");
+
+    editor.execute_command("split").unwrap();
+    editor.execute_command("vsplit").unwrap();
+    assert_eq!(editor.window_count(), 3);
+
+    type_keys(&mut editor, "<C-w>c");
+    assert_eq!(editor.window_count(), 2);
+
+    editor.execute_command("q").unwrap();
+    assert!(!editor.should_quit);
+    assert_eq!(editor.window_layout, WindowLayout::Window(first));
+    assert_eq!(editor.current_window, first);
+
+    editor.execute_command("vsplit").unwrap();
+    editor.execute_command("split").unwrap();
+    type_keys(&mut editor, "<C-w>o");
+    assert_eq!(editor.window_count(), 1);
+
+    editor.execute_command("q").unwrap();
+    assert!(editor.should_quit);
+}
+
+#[test]
+fn splits_open_a_file() {
+    let dir = temp_dir("split");
+    let path = dir.join("other.txt");
+    std::fs::write(&path, "other\n").unwrap();
+
+    let mut editor = editor_with("main");
+    let main = editor.current_buffer;
+
+    editor.execute_command(&format!("vsplit {}", path.display())).unwrap();
+    assert_eq!(editor.get_current_buffer().unwrap().get_buffer(), "other");
+
+    type_keys(&mut editor, "<C-w>l");
+    assert_eq!(editor.current_buffer, main);
+
+    std::fs::write(dir.join("bad.txt"), [0xff, 0xfe]).unwrap();
+    assert!(editor.execute_command(&format!("split {}", dir.join("bad.txt").display())).is_err());
+    assert_eq!(editor.window_count(), 2);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
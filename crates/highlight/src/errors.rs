@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum HighlightError {
+    #[error("Invalid pattern in grammar {grammar}: {pattern}")]
+    InvalidPattern { grammar: String, pattern: String },
+
+    #[error("No grammar for filetype: {0}")]
+    UnknownFiletype(String),
+
+    #[error("Invalid {language} query: {message}")]
+    InvalidQuery { language: String, message: String },
+}
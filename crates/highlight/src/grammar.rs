@@ -0,0 +1,132 @@
+use regex::Regex;
+
+use crate::errors::HighlightError;
+
+/// A rule in a grammar, modelled on TextMate grammars: either a single-line
+/// `Match`, or a `Region` that opens on `begin` and lasts, possibly over
+/// several lines, until `end`, with its own rules inside.
+///
+/// Scopes are dotted TextMate names such as `string.quoted.double` or
+/// `entity.name.function`; themes style them by longest matching prefix.
+#[derive(Clone, Debug)]
+pub enum Rule {
+    Match {
+        pattern: &'static str,
+        /// Scope of the whole match, or `None` to style only `captures`.
+        scope: Option<&'static str>,
+        captures: Vec<(usize, &'static str)>
+    },
+    Region {
+        scope: &'static str,
+        begin: &'static str,
+        end: &'static str,
+        rules: Vec<Rule>
+    }
+}
+
+impl Rule {
+    pub fn token(scope: &'static str, pattern: &'static str) -> Self {
+        Rule::Match { pattern, scope: Some(scope), captures: vec![] }
+    }
+
+    /// A match that styles only its capture groups.
+    pub fn captures(pattern: &'static str, captures: &[(usize, &'static str)]) -> Self {
+        Rule::Match { pattern, scope: None, captures: captures.to_vec() }
+    }
+
+    pub fn region(scope: &'static str, begin: &'static str, end: &'static str, rules: Vec<Rule>) -> Self {
+        Rule::Region { scope, begin, end, rules }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Grammar {
+    pub name: &'static str,
+    pub rules: Vec<Rule>
+}
+
+impl Grammar {
+    pub fn new(name: &'static str, rules: Vec<Rule>) -> Self {
+        Self {
+            name,
+            rules
+        }
+    }
+
+    /// Compiles every pattern, flattening regions into a table so a line's
+    /// state can be a stack of region indices.
+    pub fn compile(&self) -> Result<CompiledGrammar, HighlightError> {
+        let mut regions = vec![];
+        let rules = compile_rules(self.name, &self.rules, &mut regions)?;
+
+        Ok(CompiledGrammar {
+            name: self.name,
+            rules,
+            regions
+        })
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum RuleKind {
+    Match {
+        scope: Option<&'static str>,
+        captures: Vec<(usize, &'static str)>
+    },
+    Begin(usize)
+}
+
+#[derive(Debug)]
+pub(crate) struct CompiledRule {
+    pub regex: Regex,
+    pub kind: RuleKind
+}
+
+#[derive(Debug)]
+pub(crate) struct CompiledRegion {
+    pub scope: &'static str,
+    pub end: Regex,
+    pub rules: Vec<CompiledRule>
+}
+
+#[derive(Debug)]
+pub struct CompiledGrammar {
+    pub name: &'static str,
+    pub(crate) rules: Vec<CompiledRule>,
+    pub(crate) regions: Vec<CompiledRegion>
+}
+
+fn compile_pattern(grammar: &str, pattern: &str) -> Result<Regex, HighlightError> {
+    Regex::new(pattern).map_err(|_| HighlightError::InvalidPattern {
+        grammar: grammar.to_string(),
+        pattern: pattern.to_string()
+    })
+}
+
+fn compile_rules(grammar: &str, rules: &[Rule], regions: &mut Vec<CompiledRegion>) -> Result<Vec<CompiledRule>, HighlightError> {
+    rules
+        .iter()
+        .map(|rule| match rule {
+            Rule::Match { pattern, scope, captures } => Ok(CompiledRule {
+                regex: compile_pattern(grammar, pattern)?,
+                kind: RuleKind::Match { scope: *scope, captures: captures.clone() }
+            }),
+            Rule::Region { scope, begin, end, rules } => {
+                let regex = compile_pattern(grammar, begin)?;
+                let end = compile_pattern(grammar, end)?;
+                let inner = compile_rules(grammar, rules, regions)?;
+
+                regions.push(CompiledRegion {
+                    scope,
+                    end,
+                    rules: inner
+                });
+
+                Ok(CompiledRule {
+                    regex,
+                    kind: RuleKind::Begin(regions.len() - 1)
+                })
+            }
+        })
+        .collect()
+}
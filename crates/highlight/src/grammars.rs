@@ -0,0 +1,136 @@
+use crate::errors::HighlightError;
+use crate::grammar::{Grammar, Rule};
+use crate::highlighter::{Highlighter, RegexHighlighter};
+
+const NUMBER: &str = r"\b(?:0x[0-9a-fA-F_]+|0o[0-7_]+|0b[01_]+|\d[\d_]*(?:\.\d[\d_]*)?(?:[eE][+-]?\d+)?)";
+
+/// The filetypes with a built-in grammar.
+pub const FILETYPES: &[&str] = &["rust", "toml", "markdown", "json", "sh", "python"];
+
+pub fn grammar_for(filetype: &str) -> Option<Grammar> {
+    match filetype {
+        "rust" => Some(rust()),
+        "toml" => Some(toml()),
+        "markdown" => Some(markdown()),
+        "json" => Some(json()),
+        "sh" => Some(shell()),
+        "python" => Some(python()),
+        _ => None
+    }
+}
+
+/// The highlighter for `filetype`: a tree-sitter parser when built with the
+/// `tree-sitter` feature and one is vendored for it, otherwise its regex
+/// grammar.
+pub fn highlighter_for(filetype: &str) -> Result<Box<dyn Highlighter>, HighlightError> {
+    #[cfg(feature = "tree-sitter")]
+    if crate::syntax_tree::supports(filetype) {
+        return Ok(Box::new(crate::syntax_tree::SyntaxTree::new(filetype)?));
+    }
+
+    let grammar = grammar_for(filetype).ok_or_else(|| HighlightError::UnknownFiletype(filetype.to_string()))?;
+    Ok(Box::new(RegexHighlighter::new(grammar.compile()?)))
+}
+
+fn escape() -> Rule {
+    Rule::token("constant.character.escape", r"\\.")
+}
+
+pub fn rust() -> Grammar {
+    Grammar::new("rust", vec![
+        Rule::token("comment.line.documentation", r"//[/!].*$"),
+        Rule::token("comment.line", r"//.*$"),
+        Rule::region("comment.block", r"/\*", r"\*/", vec![]),
+        Rule::region("string.quoted.raw", r##"b?r#""##, r##""#"##, vec![]),
+        Rule::region("string.quoted.raw", r#"b?r""#, r#"""#, vec![]),
+        Rule::region("string.quoted.double", r#"b?""#, r#"""#, vec![escape()]),
+        Rule::token("string.quoted.single", r"b?'(?:\\.|[^\\'])'"),
+        Rule::token("storage.modifier.lifetime", r"'[a-zA-Z_][a-zA-Z0-9_]*"),
+        Rule::region("meta.attribute", r"#!?\[", r"\]", vec![]),
+        Rule::captures(r"\b(fn)\s+([a-zA-Z_][a-zA-Z0-9_]*)", &[(1, "keyword"), (2, "entity.name.function")]),
+        Rule::token("keyword.control", r"\b(?:if|else|match|loop|while|for|in|break|continue|return|yield|await)\b"),
+        Rule::token("keyword", r"\b(?:as|async|const|crate|dyn|enum|extern|fn|impl|let|mod|move|mut|pub|ref|static|struct|super|trait|type|union|unsafe|use|where)\b"),
+        Rule::token("variable.language", r"\b(?:self|Self)\b"),
+        Rule::token("constant.language", r"\b(?:true|false)\b"),
+        Rule::token("storage.type", r"\b(?:[iu](?:8|16|32|64|128|size)|f32|f64|bool|char|str)\b"),
+        Rule::token("entity.name.function.macro", r"\b[a-z_][a-zA-Z0-9_]*!"),
+        Rule::token("entity.name.type", r"\b[A-Z][a-zA-Z0-9_]*\b"),
+        Rule::token("constant.numeric", NUMBER),
+        Rule::captures(r"\b([a-z_][a-zA-Z0-9_]*)\s*(?:::<[^>]*>)?\(", &[(1, "entity.name.function")])
+    ])
+}
+
+pub fn toml() -> Grammar {
+    Grammar::new("toml", vec![
+        Rule::token("comment.line", r"#.*$"),
+        Rule::token("entity.name.section", r"^\s*\[\[?[^\]]*\]\]?"),
+        Rule::captures(r#"^\s*([A-Za-z0-9_.\-]+|"[^"]*"|'[^']*')\s*="#, &[(1, "variable.other.key")]),
+        Rule::region("string.quoted.triple", r#"""""#, r#"""""#, vec![escape()]),
+        Rule::region("string.quoted.triple", r"'''", r"'''", vec![]),
+        Rule::region("string.quoted.double", r#"""#, r#"""#, vec![escape()]),
+        Rule::token("string.quoted.single", r"'[^']*'"),
+        Rule::token("constant.other.date", r"\d{4}-\d{2}-\d{2}(?:[T ]\d{2}:\d{2}(?::\d{2}(?:\.\d+)?)?(?:Z|[+-]\d{2}:\d{2})?)?"),
+        Rule::token("constant.language", r"\b(?:true|false|inf|nan)\b"),
+        Rule::token("constant.numeric", r"[+-]?\b(?:0x[0-9a-fA-F_]+|0o[0-7_]+|0b[01_]+|\d[\d_]*(?:\.\d[\d_]*)?(?:[eE][+-]?\d+)?)")
+    ])
+}
+
+pub fn markdown() -> Grammar {
+    Grammar::new("markdown", vec![
+        Rule::region("markup.raw.block", r"^\s*(?:```|~~~).*$", r"^\s*(?:```|~~~)\s*$", vec![]),
+        Rule::region("comment.block", r"<!--", r"-->", vec![]),
+        Rule::token("markup.heading", r"^#{1,6}\s.*$"),
+        Rule::token("markup.quote", r"^\s*>.*$"),
+        Rule::token("meta.separator", r"^\s*(?:-{3,}|\*{3,}|_{3,})\s*$"),
+        Rule::token("markup.list", r"^\s*(?:[-*+]|\d+[.)])\s"),
+        Rule::token("markup.raw.inline", r"`[^`]+`"),
+        Rule::token("markup.bold", r"\*\*[^*]+\*\*|__[^_]+__"),
+        Rule::token("markup.italic", r"\*[^*\s][^*]*\*|\b_[^_\s][^_]*_\b"),
+        Rule::token("markup.underline.link", r"!?\[[^\]]*\]\([^)]*\)|<https?://[^>]+>")
+    ])
+}
+
+pub fn json() -> Grammar {
+    Grammar::new("json", vec![
+        Rule::captures(r#"("(?:\\.|[^"\\])*")\s*:"#, &[(1, "support.type.property-name")]),
+        Rule::region("string.quoted.double", r#"""#, r#"""#, vec![escape()]),
+        Rule::token("constant.language", r"\b(?:true|false|null)\b"),
+        Rule::token("constant.numeric", r"-?\b\d+(?:\.\d+)?(?:[eE][+-]?\d+)?\b")
+    ])
+}
+
+pub fn shell() -> Grammar {
+    let variable = || Rule::token("variable", r"\$\{[^}]*\}|\$[A-Za-z_][A-Za-z0-9_]*|\$[0-9@#?$!*-]");
+
+    Grammar::new("sh", vec![
+        Rule::token("comment.line.shebang", r"^#!.*$"),
+        Rule::captures(r"(?:^|\s)(#.*)$", &[(1, "comment.line")]),
+        Rule::region("string.quoted.double", r#"""#, r#"""#, vec![escape(), variable()]),
+        Rule::region("string.quoted.single", r"'", r"'", vec![]),
+        variable(),
+        Rule::captures(r"^\s*(?:function\s+)?([A-Za-z_][A-Za-z0-9_]*)\s*\(\)", &[(1, "entity.name.function")]),
+        Rule::token("keyword.control", r"\b(?:if|then|else|elif|fi|for|while|until|do|done|case|esac|in|select|return|break|continue|exit)\b"),
+        Rule::token("keyword", r"\b(?:function|local|export|readonly|declare|source|alias|unset)\b"),
+        Rule::token("constant.numeric", r"\b\d+\b")
+    ])
+}
+
+pub fn python() -> Grammar {
+    Grammar::new("python", vec![
+        Rule::token("comment.line", r"#.*$"),
+        Rule::region("string.quoted.triple", r#"(?:\b[rRbBuUfF]{1,2})?""""#, r#"""""#, vec![escape()]),
+        Rule::region("string.quoted.triple", r"(?:\b[rRbBuUfF]{1,2})?'''", r"'''", vec![escape()]),
+        Rule::token("string.quoted.double", r#"(?:\b[rRbBuUfF]{1,2})?"(?:\\.|[^"\\])*""#),
+        Rule::token("string.quoted.single", r"(?:\b[rRbBuUfF]{1,2})?'(?:\\.|[^'\\])*'"),
+        Rule::token("entity.name.function.decorator", r"^\s*@[\w.]+"),
+        Rule::captures(r"\b(def)\s+([A-Za-z_]\w*)", &[(1, "keyword"), (2, "entity.name.function")]),
+        Rule::captures(r"\b(class)\s+([A-Za-z_]\w*)", &[(1, "keyword"), (2, "entity.name.type")]),
+        Rule::token("keyword.control", r"\b(?:if|elif|else|for|while|break|continue|return|yield|try|except|finally|raise|with|match|case|pass|await)\b"),
+        Rule::token("keyword", r"\b(?:and|as|assert|async|def|class|del|from|global|import|in|is|lambda|nonlocal|not|or)\b"),
+        Rule::token("constant.language", r"\b(?:True|False|None)\b"),
+        Rule::token("variable.language", r"\b(?:self|cls)\b"),
+        Rule::token("support.function", r"\b(?:print|len|range|open|isinstance|super|enumerate|zip|map|filter|sorted|dict|list|set|tuple|int|str|float|bool)\b"),
+        Rule::token("constant.numeric", NUMBER),
+        Rule::captures(r"\b([A-Za-z_]\w*)\s*\(", &[(1, "entity.name.function")])
+    ])
+}
@@ -0,0 +1,233 @@
+use std::ops::Range;
+
+use buffer::buffer::Buffer;
+
+use crate::grammar::{CompiledGrammar, RuleKind};
+use crate::structure::Structure;
+
+/// A styled byte range of one line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HighlightSpan {
+    pub start: usize,
+    pub end: usize,
+    pub scope: &'static str
+}
+
+/// Produces styled spans for lines of a buffer. Implementations keep
+/// whatever per-line state they need between calls and bring it up to date
+/// with `Buffer::edits_since`, so asking for the lines on screen after an
+/// edit doesn't redo the whole file.
+pub trait Highlighter {
+    /// Spans for each line in `lines`, in order and not overlapping.
+    fn highlight_lines(&mut self, buffer: &Buffer, lines: Range<usize>) -> Vec<Vec<HighlightSpan>>;
+
+    fn name(&self) -> &str;
+
+    /// The buffer's syntactic structure, for highlighters that parse it.
+    fn structure(&mut self) -> Option<&mut dyn Structure> {
+        None
+    }
+}
+
+/// The regions open at the start of a line, innermost last.
+type LineState = Vec<usize>;
+
+#[derive(Debug)]
+struct CachedLine {
+    start: LineState,
+    end: LineState,
+    spans: Vec<HighlightSpan>
+}
+
+/// Highlights with a `CompiledGrammar`. Each line is tokenized from the
+/// state the previous one ended in; a cached line is reused as long as it
+/// starts in the same state, so an edit only retokenizes lines until the
+/// states line up again.
+#[derive(Debug)]
+pub struct RegexHighlighter {
+    grammar: CompiledGrammar,
+    lines: Vec<Option<Box<CachedLine>>>,
+    /// How many leading lines have a start state known to be current.
+    valid: usize,
+    buffer_id: Option<u64>,
+    version: u64
+}
+
+impl RegexHighlighter {
+    pub fn new(grammar: CompiledGrammar) -> Self {
+        Self {
+            grammar,
+            lines: vec![],
+            valid: 0,
+            buffer_id: None,
+            version: 0
+        }
+    }
+
+    fn sync(&mut self, buffer: &Buffer) {
+        let edits = match self.buffer_id {
+            Some(id) if id == buffer.id() => buffer.edits_since(self.version),
+            _ => None
+        };
+
+        match edits {
+            Some(edits) => {
+                for edit in edits {
+                    let end = (edit.line + edit.old_count).min(self.lines.len());
+                    let start = edit.line.min(end);
+
+                    self.lines.splice(start .. end, std::iter::repeat_with(|| None).take(edit.new_count));
+                    self.valid = self.valid.min(edit.line);
+                }
+            },
+            None => {
+                self.lines.clear();
+                self.valid = 0;
+            }
+        }
+
+        self.lines.resize_with(buffer.len(), || None);
+        self.valid = self.valid.min(buffer.len());
+        self.buffer_id = Some(buffer.id());
+        self.version = buffer.version();
+    }
+
+    /// Tokenizes `text` starting in `state`, leaving `state` as it is at the
+    /// end of the line.
+    fn tokenize(&self, text: &str, state: &mut LineState) -> Vec<HighlightSpan> {
+        let mut spans = vec![];
+        let mut pos = 0;
+
+        while pos <= text.len() {
+            let region = state.last().map(|&id| &self.grammar.regions[id]);
+            let rules = region.map(|region| &region.rules).unwrap_or(&self.grammar.rules);
+
+            let end = region.and_then(|region| region.end.find_at(text, pos));
+
+            let rule = rules
+                .iter()
+                .filter_map(|rule| rule.regex.captures_at(text, pos).map(|captures| (rule, captures)))
+                .min_by_key(|(_, captures)| captures.get(0).map(|m| m.start()).unwrap_or(usize::MAX));
+
+            let rule_start = rule.as_ref().and_then(|(_, captures)| captures.get(0)).map(|m| m.start());
+
+            // The end of the region wins ties with the rules inside it.
+            if let Some(end) = end
+                && rule_start.is_none_or(|start| end.start() <= start)
+            {
+                let scope = region.map(|region| region.scope).unwrap_or_default();
+                push_span(&mut spans, pos, end.end(), scope);
+                state.pop();
+                pos = advance(text, pos, end.end());
+                continue;
+            }
+
+            let Some((rule, captures)) = rule else {
+                if let Some(region) = region {
+                    push_span(&mut spans, pos, text.len(), region.scope);
+                }
+
+                break;
+            };
+
+            let whole = captures.get(0).expect("match has a group 0");
+
+            if let Some(region) = region {
+                push_span(&mut spans, pos, whole.start(), region.scope);
+            }
+
+            match &rule.kind {
+                RuleKind::Match { scope, captures: groups } => {
+                    let mut last = whole.start();
+
+                    for (group, group_scope) in groups {
+                        if let Some(m) = captures.get(*group)
+                            && m.start() >= last
+                        {
+                            if let Some(scope) = scope.or(region.map(|region| region.scope)) {
+                                push_span(&mut spans, last, m.start(), scope);
+                            }
+
+                            push_span(&mut spans, m.start(), m.end(), group_scope);
+                            last = m.end();
+                        }
+                    }
+
+                    if let Some(scope) = scope.or(region.map(|region| region.scope)) {
+                        push_span(&mut spans, last, whole.end(), scope);
+                    }
+                },
+                RuleKind::Begin(id) => {
+                    push_span(&mut spans, whole.start(), whole.end(), self.grammar.regions[*id].scope);
+                    state.push(*id);
+                }
+            }
+
+            pos = advance(text, pos, whole.end());
+        }
+
+        spans
+    }
+}
+
+/// Moves past a match, stepping one character when it was empty so the
+/// tokenizer can't loop in place.
+fn advance(text: &str, pos: usize, end: usize) -> usize {
+    if end > pos {
+        end
+    } else {
+        text[pos ..].chars().next().map(|c| pos + c.len_utf8()).unwrap_or(text.len() + 1)
+    }
+}
+
+pub(crate) fn push_span(spans: &mut Vec<HighlightSpan>, start: usize, end: usize, scope: &'static str) {
+    if start >= end {
+        return;
+    }
+
+    match spans.last_mut() {
+        Some(last) if last.end == start && last.scope == scope => last.end = end,
+        _ => spans.push(HighlightSpan { start, end, scope })
+    }
+}
+
+impl Highlighter for RegexHighlighter {
+    fn highlight_lines(&mut self, buffer: &Buffer, lines: Range<usize>) -> Vec<Vec<HighlightSpan>> {
+        self.sync(buffer);
+
+        let end = lines.end.min(buffer.len());
+        let mut state = match self.valid.checked_sub(1).and_then(|line| self.lines[line].as_ref()) {
+            Some(cached) => cached.end.clone(),
+            None => {
+                self.valid = 0;
+                vec![]
+            }
+        };
+
+        for line in self.valid .. end {
+            match &self.lines[line] {
+                Some(cached) if cached.start == state => state = cached.end.clone(),
+                _ => {
+                    let start = state.clone();
+                    let spans = self.tokenize(&buffer.lines[line], &mut state);
+
+                    self.lines[line] = Some(Box::new(CachedLine {
+                        start,
+                        end: state.clone(),
+                        spans
+                    }));
+                }
+            }
+
+            self.valid = line + 1;
+        }
+
+        (lines.start.min(end) .. end)
+            .map(|line| self.lines[line].as_ref().map(|cached| cached.spans.clone()).unwrap_or_default())
+            .collect()
+    }
+
+    fn name(&self) -> &str {
+        self.grammar.name
+    }
+}
@@ -0,0 +1,11 @@
+pub mod errors;
+pub mod grammar;
+pub mod grammars;
+pub mod highlighter;
+pub mod structure;
+#[cfg(feature = "tree-sitter")]
+pub mod syntax_tree;
+
+pub use errors::HighlightError;
+pub use highlighter::{HighlightSpan, Highlighter, RegexHighlighter};
+pub use structure::{Structure, TextObject};
@@ -0,0 +1,43 @@
+use std::ops::Range;
+
+use buffer::buffer::Buffer;
+use utils::Position;
+
+/// The kinds of syntax node that structural text objects select.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextObject {
+    Function,
+    Class,
+    Argument,
+    Comment
+}
+
+impl TextObject {
+    /// The object for the key typed after `a` or `i` in visual mode: `f`
+    /// function, `t` type/class, `a` argument and `c` comment.
+    pub fn from_key(key: char) -> Option<Self> {
+        match key {
+            'f' => Some(TextObject::Function),
+            't' => Some(TextObject::Class),
+            'a' => Some(TextObject::Argument),
+            'c' => Some(TextObject::Comment),
+            _ => None
+        }
+    }
+}
+
+/// Questions about the syntactic structure of a buffer, answered by
+/// highlighters that parse it. Ranges run from `start` up to but not
+/// including `end`.
+pub trait Structure {
+    /// The smallest `object` around `pos`. `inner` asks for its contents,
+    /// e.g. the statements of a function body, where the object has any.
+    fn text_object(&mut self, buffer: &Buffer, pos: Position, object: TextObject, inner: bool) -> Option<Range<Position>>;
+
+    /// The smallest node that covers more than `range`.
+    fn expand(&mut self, buffer: &Buffer, range: Range<Position>) -> Option<Range<Position>>;
+
+    /// The first lines of the functions, types and the like that enclose
+    /// `line` and start above it, outermost first.
+    fn context(&mut self, buffer: &Buffer, line: usize) -> Vec<usize>;
+}
@@ -0,0 +1,480 @@
+use std::ops::Range;
+
+use buffer::buffer::{Buffer, LineEdit, TextEdit};
+use tree_sitter::{InputEdit, Language, Node, Parser, Point, Query, QueryCursor, StreamingIterator, Tree};
+use utils::Position;
+
+use crate::errors::HighlightError;
+use crate::highlighter::{push_span, HighlightSpan, Highlighter};
+use crate::structure::{Structure, TextObject};
+
+/// Scopes for the capture names used by the grammars' highlight queries,
+/// so themes can style both backends alike. The longest name that is a
+/// prefix of a capture wins; captures with no entry are left plain.
+const CAPTURE_SCOPES: &[(&str, &str)] = &[
+    ("attribute", "meta.attribute"),
+    ("comment", "comment"),
+    ("comment.documentation", "comment.line.documentation"),
+    ("constant", "constant"),
+    ("constant.builtin", "constant.language"),
+    ("constructor", "entity.name.type"),
+    ("escape", "constant.character.escape"),
+    ("function", "entity.name.function"),
+    ("function.builtin", "support.function"),
+    ("function.macro", "entity.name.function.macro"),
+    ("keyword", "keyword"),
+    ("label", "entity.name.label"),
+    ("number", "constant.numeric"),
+    ("property", "variable.other.member"),
+    ("string", "string"),
+    ("string.special.key", "support.type.property-name"),
+    ("type", "entity.name.type"),
+    ("type.builtin", "storage.type"),
+    ("variable.builtin", "variable.language"),
+    ("variable.parameter", "variable.parameter")
+];
+
+struct LanguageConfig {
+    filetype: &'static str,
+    language: fn() -> Language,
+    highlights: &'static str,
+    /// Captures `@function`, `@class`, `@argument` and `@comment` nodes for
+    /// text objects.
+    objects: &'static str,
+    /// Node kinds whose first line is shown in the sticky context.
+    context: &'static [&'static str]
+}
+
+const LANGUAGES: &[LanguageConfig] = &[
+    LanguageConfig {
+        filetype: "rust",
+        language: || tree_sitter_rust::LANGUAGE.into(),
+        highlights: tree_sitter_rust::HIGHLIGHTS_QUERY,
+        objects: r#"
+            [(function_item) (closure_expression)] @function
+            [(struct_item) (enum_item) (union_item) (trait_item) (impl_item)] @class
+            (parameters (_) @argument)
+            (arguments (_) @argument)
+            (type_parameters (_) @argument)
+            (type_arguments (_) @argument)
+            [(line_comment) (block_comment)] @comment
+        "#,
+        context: &["function_item", "impl_item", "trait_item", "mod_item", "struct_item", "enum_item", "union_item"]
+    },
+    LanguageConfig {
+        filetype: "python",
+        language: || tree_sitter_python::LANGUAGE.into(),
+        highlights: tree_sitter_python::HIGHLIGHTS_QUERY,
+        objects: r#"
+            [(function_definition) (lambda)] @function
+            (class_definition) @class
+            (parameters (_) @argument)
+            (lambda_parameters (_) @argument)
+            (argument_list (_) @argument)
+            (comment) @comment
+        "#,
+        context: &["function_definition", "class_definition"]
+    },
+    LanguageConfig {
+        filetype: "json",
+        language: || tree_sitter_json::LANGUAGE.into(),
+        highlights: tree_sitter_json::HIGHLIGHTS_QUERY,
+        objects: r#"
+            (pair) @argument
+            (array (_) @argument)
+            (comment) @comment
+        "#,
+        context: &["pair"]
+    },
+    LanguageConfig {
+        filetype: "sh",
+        language: || tree_sitter_bash::LANGUAGE.into(),
+        highlights: tree_sitter_bash::HIGHLIGHT_QUERY,
+        objects: r#"
+            (function_definition) @function
+            (command argument: (_) @argument)
+            (comment) @comment
+        "#,
+        context: &["function_definition"]
+    }
+];
+
+/// Whether `filetype` has a tree-sitter grammar.
+pub fn supports(filetype: &str) -> bool {
+    LANGUAGES.iter().any(|config| config.filetype == filetype)
+}
+
+fn capture_scope(name: &str) -> Option<&'static str> {
+    CAPTURE_SCOPES
+        .iter()
+        .filter(|(capture, _)| name == *capture || name.strip_prefix(capture).is_some_and(|rest| rest.starts_with('.')))
+        .max_by_key(|(capture, _)| capture.len())
+        .map(|(_, scope)| *scope)
+}
+
+fn object_capture(object: TextObject) -> &'static str {
+    match object {
+        TextObject::Function => "function",
+        TextObject::Class => "class",
+        TextObject::Argument => "argument",
+        TextObject::Comment => "comment"
+    }
+}
+
+/// The lines touched by a run of edits: `start .. end` in the buffer as it
+/// is now, which were `start .. old_end` before them. Lines outside are
+/// unchanged apart from being shifted.
+#[derive(Clone, Copy, Debug)]
+struct DirtyLines {
+    start: usize,
+    end: usize,
+    old_end: usize
+}
+
+fn dirty_lines<'a>(edits: impl Iterator<Item = &'a LineEdit>) -> Option<DirtyLines> {
+    edits.fold(None, |dirty: Option<DirtyLines>, edit| {
+        let edit_end = edit.line + edit.old_count;
+        let DirtyLines { start, end, old_end } = dirty.unwrap_or(DirtyLines { start: edit.line, end: edit_end, old_end: edit_end });
+        let covered = end.max(edit_end);
+
+        Some(DirtyLines {
+            start: start.min(edit.line),
+            end: covered + edit.new_count - edit.old_count,
+            old_end: old_end + (covered - end)
+        })
+    })
+}
+
+fn line_starts(lines: &[String]) -> Vec<usize> {
+    lines
+        .iter()
+        .scan(0, |offset, line| {
+            let start = *offset;
+            *offset += line.len() + 1;
+            Some(start)
+        })
+        .collect()
+}
+
+fn point(line_starts: &[usize], offset: usize) -> Point {
+    let row = line_starts.partition_point(|&start| start <= offset).saturating_sub(1);
+    Point::new(row, offset - line_starts[row])
+}
+
+/// A highlighter backed by a tree-sitter parse of the buffer. The tree is
+/// kept across edits: the byte-level edits the buffer logged since the last
+/// parse are handed to tree-sitter as `InputEdit`s, so it only reparses the
+/// part of the file they touched, and only the lines they touched are copied
+/// into the cached text.
+pub struct SyntaxTree {
+    config: &'static LanguageConfig,
+    parser: Parser,
+    tree: Option<Tree>,
+    highlights: Query,
+    objects: Query,
+    /// The text the tree was parsed from, every line ending in a newline
+    /// that the parse leaves off the last, with the byte offset of the
+    /// start of each line.
+    text: String,
+    line_starts: Vec<usize>,
+    buffer_id: Option<u64>,
+    version: u64
+}
+
+impl SyntaxTree {
+    pub fn new(filetype: &str) -> Result<Self, HighlightError> {
+        let config = LANGUAGES
+            .iter()
+            .find(|config| config.filetype == filetype)
+            .ok_or_else(|| HighlightError::UnknownFiletype(filetype.to_string()))?;
+
+        let language = (config.language)();
+        let query = |source| Query::new(&language, source).map_err(|err| HighlightError::InvalidQuery {
+            language: config.filetype.to_string(),
+            message: err.to_string()
+        });
+
+        let highlights = query(config.highlights)?;
+        let objects = query(config.objects)?;
+
+        let mut parser = Parser::new();
+        parser.set_language(&language).map_err(|err| HighlightError::InvalidQuery {
+            language: config.filetype.to_string(),
+            message: err.to_string()
+        })?;
+
+        Ok(Self {
+            config,
+            parser,
+            tree: None,
+            highlights,
+            objects,
+            text: String::new(),
+            line_starts: vec![0],
+            buffer_id: None,
+            version: 0
+        })
+    }
+
+    fn sync(&mut self, buffer: &Buffer) {
+        let same_buffer = self.buffer_id == Some(buffer.id());
+
+        if same_buffer && self.version == buffer.version() && self.tree.is_some() {
+            return;
+        }
+
+        let edits = match same_buffer {
+            true => buffer.text_edits_since(self.version).zip(buffer.edits_since(self.version).and_then(dirty_lines)),
+            false => None
+        };
+
+        match (edits, self.tree.as_mut()) {
+            (Some((edits, dirty)), Some(tree)) => {
+                for edit in edits {
+                    tree.edit(&input_edit(edit));
+                }
+
+                self.patch(buffer, dirty);
+            },
+            _ => {
+                self.tree = None;
+                self.text = lines_text(buffer.lines.iter());
+                self.line_starts = line_starts(&buffer.lines);
+            }
+        }
+
+        let source = &self.text[.. self.text.len() - 1];
+        self.tree = self.parser.parse(source, self.tree.as_ref());
+        self.buffer_id = Some(buffer.id());
+        self.version = buffer.version();
+    }
+
+    /// Copies the lines `dirty` covers from `buffer` into the cached text in
+    /// place of the ones they replaced, and moves the starts of the lines
+    /// after them along.
+    fn patch(&mut self, buffer: &Buffer, dirty: DirtyLines) {
+        let start = self.line_starts.get(dirty.start).copied().unwrap_or(self.text.len());
+        let old_end = self.line_starts.get(dirty.old_end).copied().unwrap_or(self.text.len());
+        let lines = buffer.line_slice(dirty.start, dirty.end);
+        let text = lines_text(lines.iter());
+
+        let starts = line_starts(lines).into_iter().map(|offset| start + offset);
+        self.line_starts.splice(dirty.start .. dirty.old_end, starts);
+
+        for offset in &mut self.line_starts[dirty.end ..] {
+            *offset = *offset - (old_end - start) + text.len();
+        }
+
+        self.text.replace_range(start .. old_end, &text);
+    }
+
+    /// The text as the buffer has it, without the newline after the last
+    /// line.
+    fn source(&self) -> &str {
+        &self.text[.. self.text.len().saturating_sub(1)]
+    }
+
+    fn offset(&self, pos: Position) -> usize {
+        let len = self.source().len();
+
+        self.line_starts
+            .get(pos.line)
+            .map(|start| start + pos.column)
+            .unwrap_or(len)
+            .min(len)
+    }
+
+    fn position(&self, offset: usize) -> Position {
+        let point = point(&self.line_starts, offset);
+        Position::new(point.row, point.column)
+    }
+
+    fn range(&self, node: Node) -> Range<Position> {
+        self.position(node.start_byte()) .. self.position(node.end_byte())
+    }
+}
+
+fn lines_text<'a>(lines: impl Iterator<Item = &'a String>) -> String {
+    lines.flat_map(|line| [line.as_str(), "\n"]).collect()
+}
+
+fn input_edit(edit: &TextEdit) -> InputEdit {
+    let to_point = |pos: Position| Point::new(pos.line, pos.column);
+
+    InputEdit {
+        start_byte: edit.start_byte,
+        old_end_byte: edit.old_end_byte,
+        new_end_byte: edit.new_end_byte,
+        start_position: to_point(edit.start),
+        old_end_position: to_point(edit.old_end),
+        new_end_position: to_point(edit.new_end)
+    }
+}
+
+impl Highlighter for SyntaxTree {
+    fn highlight_lines(&mut self, buffer: &Buffer, lines: Range<usize>) -> Vec<Vec<HighlightSpan>> {
+        self.sync(buffer);
+
+        let end = lines.end.min(buffer.len());
+        let start = lines.start.min(end);
+
+        // The scope of each byte and the size of the node that set it: the
+        // smallest node wins, and the first pattern among equals, which is
+        // the precedence the grammars' queries are written for.
+        let mut painted: Vec<Vec<Option<(&'static str, usize)>>> = buffer.line_slice(start, end)
+            .iter()
+            .map(|line| vec![None; line.len()])
+            .collect();
+
+        if let Some(tree) = &self.tree {
+            let from = self.offset(Position::new(start, 0));
+            let to = self.offset(Position::new(end, 0));
+            let names = self.highlights.capture_names();
+
+            let mut cursor = QueryCursor::new();
+            cursor.set_byte_range(from .. to.max(from + 1));
+            let mut captures = cursor.captures(&self.highlights, tree.root_node(), self.source().as_bytes());
+
+            while let Some((found, index)) = captures.next() {
+                let capture = found.captures[*index];
+                let Some(scope) = capture_scope(names[capture.index as usize]) else {
+                    continue;
+                };
+
+                let range = capture.node.byte_range();
+                let first = point(&self.line_starts, range.start).row.max(start);
+
+                for line in first .. end {
+                    let line_start = self.line_starts[line];
+
+                    if line_start >= range.end {
+                        break;
+                    }
+
+                    let cells = &mut painted[line - start];
+                    let cell_start = range.start.saturating_sub(line_start).min(cells.len());
+                    let cell_end = (range.end - line_start).min(cells.len());
+
+                    for cell in &mut cells[cell_start .. cell_end] {
+                        if cell.is_none_or(|(_, size)| range.len() < size) {
+                            *cell = Some((scope, range.len()));
+                        }
+                    }
+                }
+            }
+        }
+
+        painted
+            .into_iter()
+            .map(|cells| {
+                let mut spans = vec![];
+
+                for (i, cell) in cells.into_iter().enumerate() {
+                    if let Some((scope, _)) = cell {
+                        push_span(&mut spans, i, i + 1, scope);
+                    }
+                }
+
+                spans
+            })
+            .collect()
+    }
+
+    fn name(&self) -> &str {
+        self.config.filetype
+    }
+
+    fn structure(&mut self) -> Option<&mut dyn Structure> {
+        Some(self)
+    }
+}
+
+impl Structure for SyntaxTree {
+    fn text_object(&mut self, buffer: &Buffer, pos: Position, object: TextObject, inner: bool) -> Option<Range<Position>> {
+        self.sync(buffer);
+
+        let tree = self.tree.as_ref()?;
+        let offset = self.offset(pos);
+        let names = self.objects.capture_names();
+        let wanted = object_capture(object);
+
+        let mut cursor = QueryCursor::new();
+        cursor.set_byte_range(offset .. offset + 1);
+        let mut matches = cursor.matches(&self.objects, tree.root_node(), self.source().as_bytes());
+        let mut best: Option<Node> = None;
+
+        while let Some(found) = matches.next() {
+            for capture in found.captures {
+                let node = capture.node;
+
+                if names[capture.index as usize] == wanted
+                    && node.start_byte() <= offset && offset < node.end_byte()
+                    && best.is_none_or(|best| node.byte_range().len() < best.byte_range().len())
+                {
+                    best = Some(node);
+                }
+            }
+        }
+
+        let node = best?;
+
+        if !inner {
+            return Some(self.range(node));
+        }
+
+        // The inside of a function or type is what its body holds, without
+        // the braces or the line ending the header.
+        let Some(body) = node.child_by_field_name("body") else {
+            return Some(self.range(node));
+        };
+
+        let mut walker = body.walk();
+        let children: Vec<Node> = body.named_children(&mut walker).collect();
+
+        match (children.first(), children.last()) {
+            (Some(first), Some(last)) => Some(self.position(first.start_byte()) .. self.position(last.end_byte())),
+            _ => Some(self.range(body))
+        }
+    }
+
+    fn expand(&mut self, buffer: &Buffer, range: Range<Position>) -> Option<Range<Position>> {
+        self.sync(buffer);
+
+        let tree = self.tree.as_ref()?;
+        let start = self.offset(range.start);
+        let end = self.offset(range.end);
+        let mut node = tree.root_node().named_descendant_for_byte_range(start, end)?;
+
+        while node.start_byte() == start && node.end_byte() == end {
+            node = node.parent()?;
+        }
+
+        Some(self.range(node))
+    }
+
+    fn context(&mut self, buffer: &Buffer, line: usize) -> Vec<usize> {
+        self.sync(buffer);
+
+        let (Some(tree), Some(text)) = (&self.tree, buffer.lines.get(line)) else {
+            return vec![];
+        };
+
+        let offset = self.line_starts[line] + text.len() - text.trim_start().len();
+        let mut node = tree.root_node().descendant_for_byte_range(offset, offset);
+        let mut lines = vec![];
+
+        while let Some(current) = node {
+            let row = current.start_position().row;
+
+            if row < line && self.config.context.contains(&current.kind()) {
+                lines.push(row);
+            }
+
+            node = current.parent();
+        }
+
+        lines.reverse();
+        lines.dedup();
+        lines
+    }
+}
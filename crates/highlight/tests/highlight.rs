@@ -0,0 +1,72 @@
+use buffer::buffer::Buffer;
+use highlight::grammars::{grammar_for, highlighter_for, FILETYPES};
+use highlight::{HighlightSpan, Highlighter, RegexHighlighter};
+use utils::Position;
+
+/// The regex backend, whether or not the tree-sitter one is built in.
+fn regex_highlighter(filetype: &str) -> RegexHighlighter {
+    RegexHighlighter::new(grammar_for(filetype).unwrap().compile().unwrap())
+}
+
+fn scopes(text: &str, spans: &[HighlightSpan]) -> Vec<(String, &'static str)> {
+    spans.iter().map(|span| (text[span.start .. span.end].to_string(), span.scope)).collect()
+}
+
+#[test]
+fn builtin_grammars_compile() {
+    for filetype in FILETYPES {
+        assert!(highlighter_for(filetype).is_ok(), "{}", filetype);
+    }
+
+    assert!(highlighter_for("cobol").is_err());
+}
+
+#[test]
+fn rust_tokens() {
+    let buffer = Buffer::from_text("pub fn main() { let x = \"a\\n\"; } // done");
+    let mut highlighter = regex_highlighter("rust");
+    let spans = highlighter.highlight_lines(&buffer, 0 .. 1);
+
+    assert_eq!(scopes(&buffer.lines[0], &spans[0]), vec![
+        ("pub".to_string(), "keyword"),
+        ("fn".to_string(), "keyword"),
+        ("main".to_string(), "entity.name.function"),
+        ("let".to_string(), "keyword"),
+        ("\"a".to_string(), "string.quoted.double"),
+        ("\\n".to_string(), "constant.character.escape"),
+        ("\"".to_string(), "string.quoted.double"),
+        ("// done".to_string(), "comment.line")
+    ]);
+}
+
+#[test]
+fn regions_span_lines() {
+    let buffer = Buffer::from_text("a = \"\"\"\nnot code\n\"\"\" # done");
+    let mut highlighter = regex_highlighter("python");
+    let spans = highlighter.highlight_lines(&buffer, 1 .. 3);
+
+    assert_eq!(scopes(&buffer.lines[1], &spans[0]), vec![("not code".to_string(), "string.quoted.triple")]);
+    assert_eq!(scopes(&buffer.lines[2], &spans[1]), vec![
+        ("\"\"\"".to_string(), "string.quoted.triple"),
+        ("# done".to_string(), "comment.line")
+    ]);
+}
+
+#[test]
+fn edits_update_following_lines() {
+    let mut buffer = Buffer::from_text("let a = 1;\nlet b = 2;\nlet c = 3;");
+    let mut highlighter = regex_highlighter("rust");
+    highlighter.highlight_lines(&buffer, 0 .. 3);
+
+    buffer.insert(Position::new(0, 0), "/* ").unwrap();
+    let spans = highlighter.highlight_lines(&buffer, 2 .. 3);
+    assert_eq!(scopes(&buffer.lines[2], &spans[0]), vec![("let c = 3;".to_string(), "comment.block")]);
+
+    buffer.insert(Position::new(1, 0), "*/").unwrap();
+    let spans = highlighter.highlight_lines(&buffer, 2 .. 3);
+    assert_eq!(spans[0][0].scope, "keyword");
+
+    buffer.delete_lines(0, 1).unwrap();
+    let spans = highlighter.highlight_lines(&buffer, 0 .. 1);
+    assert_eq!(scopes(&buffer.lines[0], &spans[0])[0], ("let".to_string(), "keyword"));
+}
@@ -0,0 +1,124 @@
+#![cfg(feature = "tree-sitter")]
+
+use std::ops::Range;
+
+use buffer::buffer::Buffer;
+use highlight::syntax_tree::SyntaxTree;
+use highlight::{Highlighter, Structure, TextObject};
+use utils::Position;
+
+const SOURCE: &str = "struct Point {
+    x: i32
+}
+
+impl Point {
+    fn add(&self, dx: i32, dy: i32) -> i32 {
+        // sum
+        let total = self.x + dx;
+        total + dy
+    }
+}";
+
+fn text(buffer: &Buffer, range: Range<Position>) -> String {
+    let text = buffer.lines.join("\n");
+    let offset = |pos: Position| buffer.lines[.. pos.line].iter().map(|line| line.len() + 1).sum::<usize>() + pos.column;
+
+    text[offset(range.start) .. offset(range.end)].to_string()
+}
+
+#[test]
+fn highlights_from_the_tree() {
+    let buffer = Buffer::from_text("fn main() { let s = \"x\"; } // c");
+    let mut tree = SyntaxTree::new("rust").unwrap();
+    let spans = tree.highlight_lines(&buffer, 0 .. 1);
+    let line = &buffer.lines[0];
+
+    let scope_of = |word: &str| {
+        let start = line.find(word).unwrap();
+        spans[0].iter().find(|span| span.start <= start && start < span.end).map(|span| span.scope)
+    };
+
+    assert_eq!(scope_of("fn"), Some("keyword"));
+    assert_eq!(scope_of("main"), Some("entity.name.function"));
+    assert_eq!(scope_of("\"x\""), Some("string"));
+    assert_eq!(scope_of("// c"), Some("comment"));
+}
+
+#[test]
+fn incremental_edits_match_a_fresh_parse() {
+    let mut buffer = Buffer::from_text(SOURCE);
+    let mut tree = SyntaxTree::new("rust").unwrap();
+    tree.highlight_lines(&buffer, 0 .. buffer.len());
+
+    let edits: Vec<fn(&mut Buffer)> = vec![
+        |buffer| buffer.insert(Position::new(0, 0), "/* ").unwrap(),
+        |buffer| buffer.insert(Position::new(2, 1), " */").unwrap(),
+        |buffer| buffer.insert(Position::new(7, 4), "let a = 1;\nlet b = \"").unwrap(),
+        |buffer| { buffer.delete_lines(3, 4).unwrap(); },
+        |buffer| { let end = buffer.len(); buffer.insert_lines(end, vec!["// tail".to_string()]).unwrap(); },
+        |buffer| { let last = buffer.len() - 1; buffer.delete_lines(last, last).unwrap(); },
+        |buffer| { buffer.undo(); },
+        |buffer| { buffer.undo(); buffer.undo(); },
+        |buffer| { buffer.delete(utils::Range::new(Position::new(5, 7), Position::new(5, 9))).unwrap(); },
+        |buffer| { buffer.delete(utils::Range::new(Position::new(6, 8), Position::new(8, 4))).unwrap(); },
+        |buffer| { buffer.set_line(0, "enum Point {".to_string()).unwrap(); },
+        |buffer| { buffer.undo(); buffer.redo(); }
+    ];
+
+    for edit in edits {
+        edit(&mut buffer);
+
+        let mut fresh = SyntaxTree::new("rust").unwrap();
+        let expected = fresh.highlight_lines(&buffer, 0 .. buffer.len());
+
+        assert_eq!(tree.highlight_lines(&buffer, 0 .. buffer.len()), expected, "{:?}", buffer.lines);
+    }
+}
+
+#[test]
+fn text_objects() {
+    let buffer = Buffer::from_text(SOURCE);
+    let mut tree = SyntaxTree::new("rust").unwrap();
+    let on_total = Position::new(7, 12);
+
+    let function = tree.text_object(&buffer, on_total, TextObject::Function, false).unwrap();
+    assert_eq!(function, Position::new(5, 4) .. Position::new(9, 5));
+
+    let inner = tree.text_object(&buffer, on_total, TextObject::Function, true).unwrap();
+    assert_eq!(text(&buffer, inner), "// sum\n        let total = self.x + dx;\n        total + dy");
+
+    let class = tree.text_object(&buffer, on_total, TextObject::Class, false).unwrap();
+    assert_eq!(class, Position::new(4, 0) .. Position::new(10, 1));
+
+    let argument = tree.text_object(&buffer, Position::new(5, 19), TextObject::Argument, false).unwrap();
+    assert_eq!(text(&buffer, argument), "dx: i32");
+
+    let comment = tree.text_object(&buffer, Position::new(6, 10), TextObject::Comment, false).unwrap();
+    assert_eq!(text(&buffer, comment), "// sum");
+
+    assert!(tree.text_object(&buffer, Position::new(1, 4), TextObject::Function, false).is_none());
+}
+
+#[test]
+fn expand_grows_to_enclosing_nodes() {
+    let buffer = Buffer::from_text(SOURCE);
+    let mut tree = SyntaxTree::new("rust").unwrap();
+
+    let word = Position::new(7, 12) .. Position::new(7, 13);
+    let identifier = tree.expand(&buffer, word).unwrap();
+    assert_eq!(text(&buffer, identifier.clone()), "total");
+
+    let declaration = tree.expand(&buffer, identifier).unwrap();
+    assert_eq!(text(&buffer, declaration), "let total = self.x + dx;");
+}
+
+#[test]
+fn context_lists_enclosing_headers() {
+    let buffer = Buffer::from_text(SOURCE);
+    let mut tree = SyntaxTree::new("rust").unwrap();
+
+    assert_eq!(tree.context(&buffer, 8), vec![4, 5]);
+    assert_eq!(tree.context(&buffer, 5), vec![4]);
+    assert_eq!(tree.context(&buffer, 1), vec![0]);
+    assert!(tree.context(&buffer, 3).is_empty());
+}
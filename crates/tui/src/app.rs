@@ -1,18 +1,26 @@
-use crossterm::{cursor::SetCursorStyle, event::{self, Event, KeyCode, KeyEventKind}, execute};
+use crossterm::{cursor::SetCursorStyle, event::{self, Event, KeyEventKind, MouseButton, MouseEventKind}, execute};
 use ratatui::prelude::Backend;
 use ratatui::Terminal;
 
 use std::io;
+use std::time::Duration;
 
 use editor::editor::{Editor, EditorMode};
+use editor::theme::ColorDepth;
 
-use crate::ui::ui;
+use crate::cache::LineCache;
+use crate::ui::{explorer_at, ui, window_at};
+
+const BACKGROUND_REFRESH: Duration = Duration::from_millis(50);
 
 pub fn run_editor<B: Backend>(terminal: &mut Terminal<B>, editor: &mut Editor) -> io::Result<bool> {
     let mut stdout = io::stdout();
+    let mut cache = LineCache::new();
+    editor.set_color_depth(ColorDepth::detect());
 
     loop {
-        terminal.draw(|f| ui(f, editor))?;
+        editor.poll_background();
+        terminal.draw(|f| ui(f, editor, &mut cache))?;
 
         match editor.mode {
             EditorMode::Normal => {
@@ -27,82 +35,62 @@ pub fn run_editor<B: Backend>(terminal: &mut Terminal<B>, editor: &mut Editor) -
                 execute!(stdout, SetCursorStyle::SteadyBlock)?;
             },
 
-            EditorMode::Command => {
+            EditorMode::Command | EditorMode::Search => {
                 execute!(stdout, SetCursorStyle::SteadyBlock)?;
             }
         }
 
-        if let Event::Key(key) = event::read()? {
-            if key.kind == KeyEventKind::Release {
-                continue;
+        // Keys that could start a longer mapping are taken as they are once
+        // `timeoutlen` passes without another key, messages go once they
+        // have been up for their timeout, and a picker still being filled
+        // in or a running `:make` is checked on now and then.
+        let busy = editor.is_busy().then_some(BACKGROUND_REFRESH);
+        let timeout = [editor.key_timeout(), editor.messages.remaining(), busy].into_iter().flatten().min();
+
+        if let Some(timeout) = timeout
+            && !event::poll(timeout)?
+        {
+            if editor.key_timeout().is_some_and(|timeout| timeout.is_zero()) {
+                editor.flush_keys();
             }
 
-            match editor.mode {
-                EditorMode::Normal => {
-                    editor.handle_normal_mode_input(key.code);
-                },
-
-                EditorMode::Insert => {
-                    editor.handle_insert_mode_input(key.code);
-                },
+            editor.messages.expire();
 
-                EditorMode::Visual => {
-                    editor.handle_visual_mode_input(key.code);
-                },
-
-                EditorMode::Command => {
-                    if key.code == KeyCode::Esc {
-                        editor.change_mode(EditorMode::Normal);
-                        continue;
-                    }
+            if editor.should_quit {
+                return Ok(true);
+            }
 
-                    let mut command: String = String::new();
+            continue;
+        }
 
-                    if let KeyCode::Char(c) = key.code {
-                        command.push(c);
-                    }
+        match event::read()? {
+            Event::Key(key) => {
+                if key.kind == KeyEventKind::Release {
+                    continue;
+                }
 
-                    loop {
-                        if let Event::Key(next_key) = event::read()? {
-                            if next_key.kind == KeyEventKind::Release {
-                                continue;
-                            }
-
-                            match next_key.code {
-                                KeyCode::Enter => break,
-                                KeyCode::Esc => {
-                                    editor.change_mode(EditorMode::Normal);
-                                    break;
-                                }
-                                KeyCode::Char(c) => {
-                                    command.push(c);
-                                }
-                                KeyCode::Backspace => {
-                                    command.pop();
-                                }
-                                _ => {}
-                            }
-                        }
-                    }
+                editor.messages.dismiss();
+                editor.handle_key(key);
 
-                    match command.as_str() {
-                        "q" => return Ok(true),
-                        "w" => match editor.save_buffer() {
-                            Ok(_) => {
-
-                            }
-                            Err(_) => {}
-                        },
-                        "wq" => match editor.save_buffer() {
-                            Ok(_) => return Ok(true),
-                            Err(_) => {}
-                        }
-                        _ => {}
+                if editor.should_quit {
+                    return Ok(true);
+                }
+            },
+            Event::Mouse(mouse) if mouse.kind == MouseEventKind::Down(MouseButton::Left) => {
+                if let Some(index) = explorer_at(editor, mouse.column, mouse.row) {
+                    editor.click_explorer(index);
+                    editor.scroll_to_cursor();
+                } else if let Some((id, area)) = window_at(editor, mouse.column, mouse.row) {
+                    if let Some(explorer) = editor.explorer.as_mut() {
+                        explorer.focused = false;
                     }
 
-                    editor.change_mode(EditorMode::Normal); 
+                    editor.focus_window(id);
+                    editor.click((mouse.row - area.y) as usize, (mouse.column - area.x) as usize);
+                    editor.scroll_to_cursor();
                 }
-            }
+            },
+            _ => {}
         }
     }
 }
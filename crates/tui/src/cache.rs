@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use buffer::buffer::Buffer;
+use editor::layout::TextLayout;
+use editor::theme::Theme;
+use ratatui::style::Style;
+use ratatui::text::Line;
+use regex::Regex;
+
+/// Byte ranges of a line to draw in a style.
+pub type Highlights = Vec<(usize, usize, Style)>;
+
+/// The lines on screen, kept between frames so a redraw only rebuilds the
+/// lines that changed. Lines are kept per buffer and follow it through
+/// `Buffer::edits_since`: an edit drops the lines it touched and moves the
+/// ones after it, so they still hit when it shifts them to another row. A
+/// different search pattern drops the matches found, and a different theme
+/// everything. Anything not drawn in a frame is dropped when the next one
+/// starts, which bounds the cache to about two screens of lines however
+/// large the buffers are.
+#[derive(Debug, Default)]
+pub struct LineCache {
+    pattern: Option<String>,
+    theme: Option<Theme>,
+    buffers: HashMap<u64, BufferLines>,
+    frame: u64
+}
+
+/// The cached lines of one buffer, as it was at `version`.
+#[derive(Debug)]
+struct BufferLines {
+    version: u64,
+    lines: HashMap<usize, CachedLine>
+}
+
+#[derive(Debug, Default)]
+struct CachedLine {
+    /// The frame the line was last drawn in.
+    frame: u64,
+    matches: Option<Vec<(usize, usize)>>,
+    rows: Option<CachedRows>
+}
+
+/// The rows a line is drawn in, with the layout and highlights they were
+/// built with.
+#[derive(Debug)]
+struct CachedRows {
+    layout: TextLayout,
+    highlights: Highlights,
+    lines: Vec<Line<'static>>
+}
+
+impl LineCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts a frame highlighting `regex` in the colours of `theme`.
+    pub fn begin_frame(&mut self, regex: Option<&Regex>, theme: &Theme) {
+        let pattern = regex.map(|regex| regex.as_str().to_string());
+
+        if self.theme.as_ref() != Some(theme) {
+            self.theme = Some(theme.clone());
+            self.buffers.clear();
+        }
+
+        if pattern != self.pattern {
+            self.pattern = pattern;
+
+            for line in self.buffers.values_mut().flat_map(|buffer| buffer.lines.values_mut()) {
+                line.matches = None;
+            }
+        }
+
+        let frame = self.frame;
+
+        for buffer in self.buffers.values_mut() {
+            buffer.lines.retain(|_, line| line.frame == frame);
+        }
+
+        self.buffers.retain(|_, buffer| !buffer.lines.is_empty());
+        self.frame += 1;
+    }
+
+    /// Byte ranges of the matches of `regex` in line `line` of `buffer`.
+    pub fn matches(&mut self, buffer: &Buffer, line: usize, regex: &Regex) -> &[(usize, usize)] {
+        let text = &buffer.lines[line];
+
+        self.line(buffer, line)
+            .matches
+            .get_or_insert_with(|| regex.find_iter(text).map(|m| (m.start(), m.end())).collect())
+    }
+
+    /// The rows line `line` of `buffer` is drawn in, built by `build` unless
+    /// they were last built with the same layout and highlights.
+    pub fn rows(
+        &mut self,
+        buffer: &Buffer,
+        line: usize,
+        layout: &TextLayout,
+        highlights: &Highlights,
+        build: impl FnOnce() -> Vec<Line<'static>>
+    ) -> &[Line<'static>] {
+        let cached = self.line(buffer, line);
+
+        let rows = match cached.rows.take() {
+            Some(rows) if rows.layout == *layout && rows.highlights == *highlights => rows,
+            _ => CachedRows {
+                layout: layout.clone(),
+                highlights: highlights.clone(),
+                lines: build()
+            }
+        };
+
+        &cached.rows.insert(rows).lines
+    }
+
+    /// How many lines have been drawn in this frame.
+    pub fn len(&self) -> usize {
+        self.buffers
+            .values()
+            .flat_map(|buffer| buffer.lines.values())
+            .filter(|line| line.frame == self.frame)
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The entry for line `line` of `buffer`, once the edits made to it since
+    /// it was last drawn have been followed.
+    fn line(&mut self, buffer: &Buffer, line: usize) -> &mut CachedLine {
+        let frame = self.frame;
+        let cached = self.buffers.entry(buffer.id()).or_insert_with(|| BufferLines {
+            version: buffer.version(),
+            lines: HashMap::new()
+        });
+
+        if cached.version != buffer.version() {
+            match buffer.edits_since(cached.version) {
+                Some(edits) => {
+                    for edit in edits {
+                        let end = edit.line + edit.old_count;
+
+                        cached.lines = std::mem::take(&mut cached.lines)
+                            .into_iter()
+                            .filter_map(|(line, entry)| match line {
+                                line if line < edit.line => Some((line, entry)),
+                                line if line < end => None,
+                                line => Some((line - edit.old_count + edit.new_count, entry))
+                            })
+                            .collect();
+                    }
+                },
+                None => cached.lines.clear()
+            }
+
+            cached.version = buffer.version();
+        }
+
+        let entry = cached.lines.entry(line).or_default();
+        entry.frame = frame;
+        entry
+    }
+}
@@ -0,0 +1,56 @@
+use ratatui::style::{Color, Modifier, Style};
+
+use editor::theme::{self, Modifiers, Theme, ThemeStyle};
+
+const MODIFIERS: &[(Modifiers, Modifier)] = &[
+    (Modifiers::BOLD, Modifier::BOLD),
+    (Modifiers::DIM, Modifier::DIM),
+    (Modifiers::ITALIC, Modifier::ITALIC),
+    (Modifiers::UNDERLINED, Modifier::UNDERLINED),
+    (Modifiers::REVERSED, Modifier::REVERSED),
+    (Modifiers::CROSSED_OUT, Modifier::CROSSED_OUT)
+];
+
+const ANSI: [Color; 16] = [
+    Color::Black, Color::Red, Color::Green, Color::Yellow, Color::Blue, Color::Magenta, Color::Cyan, Color::Gray,
+    Color::DarkGray, Color::LightRed, Color::LightGreen, Color::LightYellow, Color::LightBlue, Color::LightMagenta, Color::LightCyan, Color::White
+];
+
+fn color(color: theme::Color) -> Color {
+    match color {
+        theme::Color::Reset => Color::Reset,
+        theme::Color::Ansi(index) => ANSI[index as usize % 16],
+        theme::Color::Indexed(index) => Color::Indexed(index),
+        theme::Color::Rgb(r, g, b) => Color::Rgb(r, g, b)
+    }
+}
+
+pub fn to_style(style: ThemeStyle) -> Style {
+    let mut result = Style::default();
+
+    if let Some(fg) = style.fg {
+        result = result.fg(color(fg));
+    }
+
+    if let Some(bg) = style.bg {
+        result = result.bg(color(bg));
+    }
+
+    for (modifier, ratatui) in MODIFIERS {
+        if style.modifiers.contains(*modifier) {
+            result = result.add_modifier(*ratatui);
+        }
+    }
+
+    result
+}
+
+/// The style for a UI scope such as `ui.linenr`.
+pub fn ui_style(theme: &Theme, scope: &str) -> Style {
+    to_style(theme.style(scope))
+}
+
+/// The style for a highlighter scope such as `keyword.control`.
+pub fn scope_style(theme: &Theme, scope: &str) -> Style {
+    to_style(theme.syntax_style(scope))
+}
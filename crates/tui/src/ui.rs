@@ -1,118 +1,757 @@
+use std::borrow::Cow;
+use std::rc::Rc;
+
 use ratatui::{
     prelude::{Position},
-    layout::{self, Constraint, Direction, Layout, Alignment},
-    style::{Modifier, Color, Style},
-    text::{Line, Span, Text},
-    widgets::{Block, Borders, Padding, Clear, List, ListItem, Paragraph, Tabs, Wrap},
+    layout::{Constraint, Direction, Layout, Alignment, Rect},
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Padding, Paragraph, Tabs},
     Frame,
 };
 
-use editor::editor::Editor;
+use editor::display::{display_column, display_width, next_boundary, Cell};
+use editor::editor::{Editor, EditorMode};
+use editor::explorer::{Explorer, FileAction, GitStatus};
+use editor::keys::format_keys;
+use editor::message::{Message, MessageLevel};
+use editor::picker::Picker;
+use editor::prompt::Prompt;
+use editor::theme::Theme;
+use editor::window::{WindowId, WindowRect};
+use regex::Regex;
 
-pub fn ui(frame: &mut Frame, editor: &Editor) {
-    let chunks = Layout::default()
+use crate::cache::{Highlights, LineCache};
+use crate::theme::{scope_style, ui_style};
+
+fn screen_chunks(area: Rect) -> Rc<[Rect]> {
+    Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(3),
             Constraint::Min(1),
             Constraint::Length(3)
         ])
-        .split(frame.area());
+        .split(area)
+}
 
-    let tab_titles: Vec<String> = editor.buffer_order
-        .iter()
-        .map(|id| editor.get_buffer_display_name(id))
-        .collect();
+/// The explorer, if it is open, and the windows beside it in the middle
+/// of the screen. The explorer takes up to half the width.
+fn main_chunks(area: Rect, editor: &Editor) -> (Option<Rect>, Rect) {
+    let Some(_) = &editor.explorer else {
+        return (None, area);
+    };
 
-    let tabs = Tabs::new(tab_titles)
-        .block(Block::default().borders(Borders::ALL).title("Files"))
-        .select(editor.get_current_buffer_index())
-        .style(Style::default().fg(Color::Yellow))
-        .highlight_style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
+    let width = (editor.options.global.explorer_width as u16).min(area.width / 2);
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(width), Constraint::Min(1)])
+        .split(area);
 
-    frame.render_widget(tabs, chunks[0]);
+    (Some(chunks[0]), chunks[1])
+}
 
-    let editor_chunks = Layout::default()
+/// The line number gutter and the text, the gutter empty with `nonumber`.
+fn editor_chunks(area: Rect, number: bool) -> Rc<[Rect]> {
+    Layout::default()
         .direction(Direction::Horizontal)
         .constraints([
-            Constraint::Length(6),
+            Constraint::Length(if number { 6 } else { 0 }),
             Constraint::Min(1)
         ])
-        .split(chunks[1]);
+        .split(area)
+}
+
+fn text_block() -> Block<'static> {
+    Block::default().borders(Borders::NONE).padding(Padding::new(1, 1, 1, 1))
+}
+
+fn to_rect(rect: WindowRect) -> Rect {
+    Rect::new(rect.x as u16, rect.y as u16, rect.width as u16, rect.height as u16)
+}
+
+/// A window's line number gutter and text, and its status line below. A
+/// window with another to its left has a border on that side.
+fn window_chunks(rect: Rect, border: bool, number: bool) -> (Rc<[Rect]>, Rect) {
+    let borders = if border { Borders::LEFT } else { Borders::NONE };
+    let inner = Block::default().borders(borders).inner(rect);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(inner);
+
+    (editor_chunks(rows[0], number), rows[1])
+}
+
+/// Shares the screen out between the windows and sizes each one's
+/// viewport to its text area, as drawing and scrolling need.
+pub fn layout_windows(area: Rect, editor: &mut Editor) {
+    let (_, area) = main_chunks(screen_chunks(area)[1], editor);
+    editor.resize_windows(WindowRect::new(area.x as usize, area.y as usize, area.width as usize, area.height as usize));
+
+    for (id, rect) in editor.window_rects() {
+        editor.with_window(id, |editor| {
+            let (chunks, _) = window_chunks(to_rect(rect), rect.x > area.x as usize, editor.window_options.number);
+            let text = text_block().inner(chunks[1]);
+
+            editor.resize_viewport(text.width as usize, text.height as usize);
+        });
+    }
+}
+
+/// The window whose text is at `column`, `row` on screen, and where its
+/// text area is.
+pub fn window_at(editor: &Editor, column: u16, row: u16) -> Option<(WindowId, Rect)> {
+    let left = editor.window_area.x;
+
+    editor.window_rects().into_iter().find_map(|(id, rect)| {
+        let number = editor.window(id)?.options.number;
+        let (chunks, _) = window_chunks(to_rect(rect), rect.x > left, number);
+        let text = text_block().inner(chunks[1]);
+
+        text.contains(Position::new(column, row)).then_some((id, text))
+    })
+}
+
+/// The explorer entry at `column`, `row` on screen. The explorer fills the
+/// space to the left of the windows.
+pub fn explorer_at(editor: &Editor, column: u16, row: u16) -> Option<usize> {
+    let explorer = editor.explorer.as_ref()?;
+    let area = editor.window_area;
+
+    if column as usize >= area.x || (row as usize) <= area.y || row as usize >= area.y + area.height {
+        return None;
+    }
+
+    let rows = area.height.saturating_sub(1);
+    let skip = (explorer.selected + 1).saturating_sub(rows);
+    let index = skip + row as usize - area.y - 1;
+
+    (index < explorer.entries.len()).then_some(index)
+}
+
+pub fn ui(frame: &mut Frame, editor: &mut Editor, cache: &mut LineCache) {
+    let chunks = screen_chunks(frame.area());
+    let (explorer_area, windows_area) = main_chunks(chunks[1], editor);
+    layout_windows(frame.area(), editor);
+
+    frame.render_widget(top_bar(editor), chunks[0]);
+
+    let search = editor.search_highlight();
+    cache.begin_frame(search.as_ref(), &editor.theme);
+
+    let current = editor.current_window;
+
+    for (id, rect) in editor.window_rects() {
+        let border = rect.x > windows_area.x as usize;
+
+        editor.with_window(id, |editor| {
+            editor.update_syntax();
+            render_window(frame, editor, cache, search.as_ref(), to_rect(rect), border, id == current);
+        });
+    }
+
+    let editor = &*editor;
+    let theme = &editor.theme;
+
+    if let (Some(explorer), Some(area)) = (&editor.explorer, explorer_area) {
+        render_explorer(frame, area, explorer, theme);
+
+        if explorer.focused && editor.picker.is_none() {
+            let rows = area.height.saturating_sub(1) as usize;
+            let row = explorer.selected.min(rows.saturating_sub(1)) as u16;
+            frame.set_cursor_position(Position::new(area.x, area.y + 1 + row));
+        }
+    }
+    let mode_text = format!("-- {} --", editor.mode);
+    let pending = editor.input.pending_keys();
+
+    let status_text = match pending.is_empty() {
+        true => mode_text,
+        false => format!("{} | {}", mode_text, format_keys(&pending))
+    };
+
+    let message = editor.messages.current();
+    let status_style = ui_style(theme, "ui.statusline");
+
+    let status_line = if let Some(confirm) = &editor.substitute_confirm {
+        Line::from(format!("replace with {} (y/n/a/q/l)?", confirm.substitution.replacement))
+    } else if let EditorMode::Command | EditorMode::Search = editor.mode {
+        let (prefix, prompt) = match editor.mode {
+            EditorMode::Command => (":", &editor.command_line),
+            _ if editor.search.forward => ("/", &editor.search.prompt),
+            _ => ("?", &editor.search.prompt)
+        };
+
+        let (text, cursor) = prompt_line(prefix, prompt, chunks[2].width as usize);
+        frame.set_cursor_position(Position::new(chunks[2].x + cursor as u16, chunks[2].y));
+
+        Line::from(text)
+    } else if let Some(explorer) = &editor.explorer
+        && let Some(pending) = &explorer.prompt
+    {
+        let question = pending.question(&explorer.root);
+
+        match pending.action {
+            FileAction::Delete => Line::from(question),
+            _ => {
+                let (text, cursor) = prompt_line(&question, &pending.prompt, chunks[2].width as usize);
+                frame.set_cursor_position(Position::new(chunks[2].x + cursor as u16, chunks[2].y));
+
+                Line::from(text)
+            }
+        }
+    } else if let Some(message) = message.filter(|message| !message.is_multiline()) {
+        Line::from(vec![
+            Span::raw(format!("{} | ", status_text)),
+            Span::styled(message.text.as_str(), status_style.patch(message_style(theme, message.level)))
+        ])
+    } else {
+        Line::from(status_text)
+    };
 
-    let content = if let Some(buffer) = editor.get_current_buffer() {
-        let line_numbers: Vec<Line> = buffer.lines 
+    let status = Paragraph::new(status_line)
+        .block(Block::default().borders(Borders::NONE))
+        .style(status_style);
+
+    frame.render_widget(status, chunks[2]);
+
+    if let Some(message) = message.filter(|message| message.is_multiline()) {
+        render_message_lines(frame, chunks[1], message, theme);
+    }
+
+    if let Some(picker) = &editor.picker {
+        render_picker(frame, chunks[1], picker, theme);
+    }
+}
+
+/// The bar at the top, listing the buffers or with `topbar=tabs` the tab
+/// pages.
+fn top_bar(editor: &Editor) -> Tabs<'static> {
+    let (title, titles, selected) = match editor.options.global.top_bar.as_str() {
+        "tabs" => {
+            let titles: Vec<String> = (0 .. editor.tab_count())
+                .map(|index| format!("{}: {}", index + 1, editor.tab_label(index)))
+                .collect();
+
+            ("Tabs", titles, editor.current_tab)
+        },
+        _ => {
+            let titles: Vec<String> = editor.buffer_order
+                .iter()
+                .map(|id| editor.get_buffer_display_name(id))
+                .collect();
+
+            ("Buffers", titles, editor.get_current_buffer_index())
+        }
+    };
+
+    Tabs::new(titles)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .select(selected)
+        .style(ui_style(&editor.theme, "ui.tabline"))
+        .highlight_style(ui_style(&editor.theme, "ui.tabline.active"))
+}
+
+/// Draws the current window of `editor` in `rect`: its text, gutter and
+/// status line. Only the `active` window shows the selection, the
+/// substitute preview and the cursor; search matches show in all of them.
+fn render_window(frame: &mut Frame, editor: &mut Editor, cache: &mut LineCache, search: Option<&Regex>, rect: Rect, border: bool, active: bool) {
+    let preview = match editor.mode {
+        EditorMode::Command if active => editor.substitute_preview(),
+        _ => None
+    };
+
+    let editor = &*editor;
+    let theme = &editor.theme;
+    let (editor_chunks, status_area) = window_chunks(rect, border, editor.window_options.number);
+    let viewport = editor.viewport;
+
+    if border {
+        let separator = Block::default().borders(Borders::LEFT).border_style(ui_style(theme, "ui.window.border"));
+        frame.render_widget(separator, rect);
+    }
+
+    if let Some(buffer) = editor.get_current_buffer() {
+        let visible = viewport.top.min(buffer.len()) .. (viewport.top + viewport.height).min(buffer.len());
+
+        let match_style = ui_style(theme, "ui.search.match");
+        let current_match_style = ui_style(theme, "ui.search.current");
+
+        let selection_style = ui_style(theme, "ui.selection");
+        let selection = editor.selection().filter(|_| active);
+        let confirm = editor.substitute_confirm.as_ref().filter(|_| active);
+
+        let sources: Vec<(usize, Cow<str>, Highlights)> = buffer.line_slice(visible.start, visible.end)
             .iter()
-            .enumerate()
-            .map(|(i, _)| {
-                let line_num = i + 1;
-                
-                if i == editor.cursor.pos.line {
-                    Line::from(Span::styled(
-                        format!("{:>4}", line_num), 
-                        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
-                    ))
-                } else {
-                    Line::from(Span::styled(
-                        format!("{:>4}", line_num),
-                        Style::default().fg(Color::Rgb(150, 150, 150))
-                    ))
+            .zip(visible)
+            .map(|(line, i)| {
+                if let Some((range, substitution)) = &preview
+                    && i >= range.start && i <= range.end
+                    && let Some(segments) = substitution.preview_line(line)
+                {
+                    let mut text = String::new();
+                    let mut highlights = vec![];
+
+                    for (segment, replaced) in segments {
+                        if replaced {
+                            highlights.push((text.len(), text.len() + segment.len(), match_style));
+                        }
+
+                        text.push_str(&segment);
+                    }
+
+                    return (i, Cow::Owned(text), highlights);
+                }
+
+                let mut highlights: Highlights = vec![];
+
+                if let Some(confirm) = confirm
+                    && confirm.line == i
+                {
+                    highlights.push((confirm.start, confirm.end, match_style));
+                }
+
+                if let Some(regex) = search {
+                    for &(start, end) in cache.matches(buffer, i, regex) {
+                        let is_current = active
+                            && matches!(editor.mode, EditorMode::Search)
+                            && editor.cursor.pos.line == i
+                            && editor.cursor.pos.column == start;
+
+                        let style = if is_current { current_match_style } else { match_style };
+                        highlights.push((start, end, style));
+                    }
+                }
+
+                if let Some(range) = &selection
+                    && (range.start.line ..= range.end.line).contains(&i)
+                {
+                    let start = if i == range.start.line { range.start.column } else { 0 };
+                    let end = if i == range.end.line { range.end.column } else { line.len() };
+                    highlights.push((start, end, selection_style));
                 }
+
+                highlights.extend(editor.syntax.spans(i).iter().map(|span| (span.start, span.end, scope_style(theme, span.scope))));
+
+                (i, Cow::Borrowed(line.as_str()), highlights)
             })
             .collect();
 
+        let layout = editor.text_layout();
+        let show_break_style = ui_style(theme, "ui.showbreak");
+        let number_style = ui_style(theme, "ui.linenr");
+        let current_number_style = ui_style(theme, "ui.linenr.current");
+        let mut line_numbers: Vec<Line> = vec![];
+        let mut lines: Vec<Line> = vec![];
+
+        'rows: for (i, text, highlights) in &sources {
+            let build = || {
+                let cells = layout.cells(text);
+
+                layout.rows(text)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(row, range)| {
+                        let row_cells: Vec<Cell> = cells
+                            .iter()
+                            .filter(|cell| cell.start >= range.start && cell.end <= range.end)
+                            .copied()
+                            .collect();
+
+                        let mut line = owned(render_row(text, &row_cells, highlights));
+
+                        if row > 0 && !layout.show_break.is_empty() {
+                            line.spans.insert(0, Span::styled(layout.show_break.clone(), show_break_style));
+                        }
+
+                        line
+                    })
+                    .collect()
+            };
+
+            // A previewed substitution isn't the buffer's text, so it is
+            // drawn afresh.
+            let rows: Cow<[Line]> = match text {
+                Cow::Borrowed(_) => Cow::Borrowed(cache.rows(buffer, *i, &layout, highlights, build)),
+                Cow::Owned(_) => Cow::Owned(build())
+            };
+
+            for (row, line) in rows.iter().enumerate() {
+                if lines.len() >= viewport.height {
+                    break 'rows;
+                }
+
+                let number_style = if *i == editor.cursor.pos.line { current_number_style } else { number_style };
+
+                let number = if row == 0 { format!("{:>4}", i + 1) } else { String::new() };
+                line_numbers.push(Line::from(Span::styled(number, number_style)));
+                lines.push(line.clone());
+            }
+        }
+
         let line_numbers_column = Paragraph::new(line_numbers)
             .block(Block::default().borders(Borders::NONE).padding(Padding::new(0, 1, 1, 1)))
-            .alignment(Alignment::Right);
+            .alignment(Alignment::Right)
+            .style(number_style);
         
         frame.render_widget(line_numbers_column, editor_chunks[0]);
 
-        let lines: Vec<Line> = buffer.lines
+        let content = Paragraph::new(lines)
+            .block(text_block())
+            .style(ui_style(theme, "ui.text"))
+            .scroll((0, viewport.left as u16));
+
+        frame.render_widget(content, editor_chunks[1]);
+
+        // The sticky context: the headers of the definitions the top line
+        // is inside, innermost last, drawn over the first rows.
+        let context = editor.syntax.context();
+        let context = &context[context.len().saturating_sub(viewport.height / 3) ..];
+
+        if !context.is_empty() {
+            let context_style = ui_style(theme, "ui.context");
+            let rows = context.len() as u16;
+
+            let text_area = text_block().inner(editor_chunks[1]);
+            let text_area = Rect { height: rows, ..text_area };
+            let numbers_area = Block::default().padding(Padding::new(0, 1, 1, 1)).inner(editor_chunks[0]);
+            let numbers_area = Rect { height: rows, ..numbers_area };
+
+            let headers: Vec<Line> = context
+                .iter()
+                .map(|&line| {
+                    let text = buffer.lines[line].as_str();
+                    let first_row = layout.rows(text).into_iter().next().unwrap_or_default();
+                    let cells: Vec<Cell> = layout.cells(text)
+                        .into_iter()
+                        .filter(|cell| cell.end <= first_row.end)
+                        .collect();
+
+                    render_row(text, &cells, &Highlights::new())
+                })
+                .collect();
+
+            let numbers: Vec<Line> = context
+                .iter()
+                .map(|line| Line::from(Span::styled(format!("{:>4}", line + 1), number_style)))
+                .collect();
+
+            frame.render_widget(Clear, text_area);
+            frame.render_widget(Paragraph::new(headers).style(context_style).scroll((0, viewport.left as u16)), text_area);
+            frame.render_widget(Clear, numbers_area);
+            frame.render_widget(Paragraph::new(numbers).style(context_style).alignment(Alignment::Right), numbers_area);
+        }
+    } else {
+        let content = Paragraph::new("No buffer open")
+            .block(Block::default().borders(Borders::NONE).title("paw :3"))
+            .style(ui_style(theme, "ui.text"));
+
+        frame.render_widget(content, editor_chunks[1]);
+    }
+
+    if active && let Some((row, column)) = editor.cursor_screen_position() {
+        let area = text_block().inner(editor_chunks[1]);
+        frame.set_cursor_position(Position::new(area.x + column as u16, area.y + row as u16));
+    }
+
+    let status_style = match active {
+        true => ui_style(theme, "ui.statusline"),
+        false => ui_style(theme, "ui.statusline.inactive")
+    };
+
+    frame.render_widget(Paragraph::new(window_status(editor)).style(status_style), status_area);
+}
+
+/// A window's status line: its file, filetype and cursor position.
+fn window_status(editor: &Editor) -> String {
+    let Some(buffer) = editor.get_current_buffer() else {
+        return String::new();
+    };
+
+    let pos = editor.cursor.pos;
+    let column = buffer.line_slice(pos.line, pos.line + 1)
+        .first()
+        .map(|text| display_column(text, pos.column, editor.buffer_options().tab_stop))
+        .unwrap_or(pos.column);
+
+    let cursor_info = if column == pos.column {
+        format!("{}:{}", pos.line + 1, pos.column + 1)
+    } else {
+        format!("{}:{}-{}", pos.line + 1, pos.column + 1, column + 1)
+    };
+
+    let file_name = buffer.get_path()
+        .as_ref()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Untitled".to_string());
+
+    let modified = if buffer.is_modified() { " [+]" } else { "" };
+
+    match buffer.filetype() {
+        Some(filetype) => format!("{}{} | {} | {}", file_name, modified, filetype, cursor_info),
+        None => format!("{}{} | {}", file_name, modified, cursor_info)
+    }
+}
+
+fn message_style(theme: &Theme, level: MessageLevel) -> Style {
+    match level {
+        MessageLevel::Info => ui_style(theme, "ui.message.info"),
+        MessageLevel::Warning => ui_style(theme, "ui.message.warning"),
+        MessageLevel::Error => ui_style(theme, "ui.message.error")
+    }
+}
+
+/// Draws a message of several lines, such as `:messages`, over the bottom
+/// of `area`, keeping its last lines if there are more than fit.
+fn render_message_lines(frame: &mut Frame, area: Rect, message: &Message, theme: &Theme) {
+    let style = ui_style(theme, "ui.statusline");
+    let mut lines: Vec<Line> = message.text
+        .lines()
+        .map(|line| Line::from(line.to_string()).style(message_style(theme, message.level)))
+        .collect();
+
+    lines.push(Line::from("Press any key to continue"));
+
+    let height = (lines.len() as u16).min(area.height);
+    let skip = lines.len() - height as usize;
+    let rect = Rect::new(area.x, area.y + area.height - height, area.width, height);
+
+    frame.render_widget(Clear, rect);
+    frame.render_widget(Paragraph::new(lines.split_off(skip)).style(style), rect);
+}
+
+/// Draws the picker as a box in the middle of `area`: the prompt, then the
+/// matches with the matched characters picked out and the selected one
+/// highlighted, scrolled to keep it in view, and the preview beside them
+/// if there is one.
+fn render_picker(frame: &mut Frame, area: Rect, picker: &Picker, theme: &Theme) {
+    let share = if picker.preview.is_some() { 4 } else { 3 };
+    let width = (area.width * share / 5).max(area.width.min(40));
+    let height = (area.height * 3 / 5).max(area.height.min(8));
+    let rect = Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height);
+
+    let style = ui_style(theme, "ui.picker");
+    let selected_style = style.patch(ui_style(theme, "ui.picker.selected"));
+    let match_style = ui_style(theme, "ui.picker.match");
+
+    let loading = if picker.is_loading() { "…" } else { "" };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" {} {}/{}{} ", picker.title, picker.matches.len(), picker.items.len(), loading))
+        .style(style);
+
+    let mut inner = block.inner(rect);
+    frame.render_widget(Clear, rect);
+    frame.render_widget(block, rect);
+
+    if inner.height == 0 {
+        return;
+    }
+
+    if let Some(preview) = &picker.preview {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(45), Constraint::Percentage(55)])
+            .split(inner);
+
+        // A replacement shows as the line taken out and the one put in.
+        let lines: Vec<Line> = preview.lines
             .iter()
             .enumerate()
-            .map(|(_, line)| {
-                Line::from(line.clone())
+            .flat_map(|(index, line)| match (preview.line == Some(index), &preview.replaced) {
+                (true, Some(replaced)) => vec![
+                    Line::from(line.as_str()).style(selected_style.patch(ui_style(theme, "ui.diff.delete"))),
+                    Line::from(replaced.as_str()).style(selected_style.patch(ui_style(theme, "ui.diff.add")))
+                ],
+                (true, None) => vec![Line::from(line.as_str()).style(selected_style)],
+                (false, _) => vec![Line::from(line.as_str())]
             })
             .collect();
 
-        Paragraph::new(lines)
-            .block(Block::default().borders(Borders::NONE).padding(Padding::new(1, 1, 1, 1)))
-            .style(Style::default().fg(Color::White))
-            .wrap(Wrap { trim: false })
-    } else {
-        Paragraph::new("No buffer open")
-            .block(Block::default().borders(Borders::NONE).title("paw :3"))
-            .style(Style::default().fg(Color::White))
+        let pane = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::LEFT).border_style(ui_style(theme, "ui.window.border")).padding(Padding::left(1)));
+
+        frame.render_widget(pane, chunks[1]);
+        inner = chunks[0];
+    }
+
+    let (text, cursor) = prompt_line(">", &picker.prompt, inner.width as usize);
+    frame.render_widget(Paragraph::new(text), Rect::new(inner.x, inner.y, inner.width, 1));
+    frame.set_cursor_position(Position::new(inner.x + cursor as u16, inner.y));
+
+    let rows = inner.height as usize - 1;
+    let skip = (picker.selected + 1).saturating_sub(rows);
+
+    let lines: Vec<Line> = picker.matches
+        .iter()
+        .enumerate()
+        .skip(skip)
+        .take(rows)
+        .map(|(index, (item, _))| {
+            let row_style = if index == picker.selected { selected_style } else { style };
+            let positions = picker.positions(*item);
+            let tick = match (picker.replace.is_some(), picker.is_checked(*item)) {
+                (true, true) => Some(Span::styled("[x] ", row_style)),
+                (true, false) => Some(Span::styled("[ ] ", row_style)),
+                (false, _) => None
+            };
+
+            let spans: Vec<Span> = tick
+                .into_iter()
+                .chain(picker.items[*item].label
+                    .chars()
+                    .enumerate()
+                    .map(|(position, c)| match positions.contains(&position) {
+                        true => Span::styled(c.to_string(), row_style.patch(match_style)),
+                        false => Span::styled(c.to_string(), row_style)
+                    }))
+                .collect();
+
+            Line::from(spans).style(row_style)
+        })
+        .collect();
+
+    frame.render_widget(Paragraph::new(lines), Rect::new(inner.x, inner.y + 1, inner.width, inner.height - 1));
+}
+
+/// The theme scope a file's git status is drawn in.
+fn git_scope(status: GitStatus) -> &'static str {
+    match status {
+        GitStatus::Ignored => "ui.git.ignored",
+        GitStatus::Untracked => "ui.git.untracked",
+        GitStatus::Added => "ui.git.added",
+        GitStatus::Modified => "ui.git.modified",
+        GitStatus::Deleted => "ui.git.deleted",
+        GitStatus::Conflicted => "ui.git.conflict"
+    }
+}
+
+/// Draws the explorer: the root folder's name, then a row per entry,
+/// indented by depth, with the git status at the right edge.
+fn render_explorer(frame: &mut Frame, area: Rect, explorer: &Explorer, theme: &Theme) {
+    let style = ui_style(theme, "ui.explorer");
+    let selected_style = ui_style(theme, "ui.explorer.selected");
+    let directory_style = ui_style(theme, "ui.explorer.directory");
+
+    let block = Block::default()
+        .borders(Borders::RIGHT)
+        .border_style(ui_style(theme, "ui.window.border"))
+        .style(style);
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let width = inner.width as usize;
+    let rows = inner.height.saturating_sub(1) as usize;
+    let skip = (explorer.selected + 1).saturating_sub(rows);
+
+    let root = explorer.root.file_name().map(|name| name.to_string_lossy()).unwrap_or(Cow::Borrowed("/"));
+    let mut lines = vec![Line::styled(format!("{}/", root), directory_style)];
+
+    for (index, entry) in explorer.entries.iter().enumerate().skip(skip).take(rows) {
+        let status = explorer.status(&entry.path);
+        let name_style = match (status, entry.is_dir) {
+            (Some(status), _) => ui_style(theme, git_scope(status)),
+            (None, true) => directory_style,
+            (None, false) => Style::default()
+        };
+
+        let icon = match (entry.is_dir, explorer.expanded.contains(&entry.path)) {
+            (true, true) => "▾ ",
+            (true, false) => "▸ ",
+            (false, _) => "  "
+        };
+
+        let slash = if entry.is_dir { "/" } else { "" };
+        let mut name = format!("{}{}{}{}", "  ".repeat(entry.depth), icon, entry.name(), slash);
+
+        // Leave room for the status at the right edge.
+        let room = width.saturating_sub(2);
+
+        while display_width(&name, 1) > room && name.pop().is_some() {}
+
+        let marker = status.map(|status| status.marker().to_string()).unwrap_or_default();
+        let padding = " ".repeat(width.saturating_sub(display_width(&name, 1) + marker.len()));
+        let row_style = if index == explorer.selected { style.patch(selected_style) } else { style };
+
+        lines.push(Line::from(vec![
+            Span::styled(name, name_style),
+            Span::raw(padding),
+            Span::styled(marker, status.map(|status| ui_style(theme, git_scope(status))).unwrap_or_default())
+        ]).style(row_style));
+    }
+
+    frame.render_widget(Paragraph::new(lines), inner);
+}
+
+/// Builds the spans for one screen row from its cells, styling cells that
+/// fall in one of the `highlights` byte ranges (the first range containing
+/// a cell wins). Runs of plain cells borrow from `text`; tabs and control
+/// characters are expanded to what `Cell::display` draws for them.
+fn render_row<'a>(text: &'a str, cells: &[Cell], highlights: &Highlights) -> Line<'a> {
+    let style_at = |byte: usize| {
+        highlights
+            .iter()
+            .find(|(start, end, _)| *start <= byte && byte < *end)
+            .map(|(_, _, style)| *style)
+            .unwrap_or_default()
     };
 
-    frame.render_widget(content, editor_chunks[1]);
+    let mut spans = vec![];
+    let mut run: Option<(usize, usize, Style)> = None;
 
-    if let Some(buffer) = editor.get_current_buffer() {
-        let cursor_x = editor_chunks[1].x + 1 + editor.cursor.pos.column as u16;
-        let cursor_y = editor_chunks[1].y + 1 + editor.cursor.pos.line as u16;
-        
-        if cursor_x < editor_chunks[1].x + editor_chunks[1].width - 1 && cursor_y < editor_chunks[1].y + editor_chunks[1].height - 1 {
-            frame.set_cursor_position(Position::new(cursor_x, cursor_y));
+    for cell in cells {
+        let style = style_at(cell.start);
+
+        match cell.display(text) {
+            Cow::Borrowed(_) => match &mut run {
+                Some((_, end, run_style)) if *run_style == style && *end == cell.start => *end = cell.end,
+                _ => {
+                    if let Some((start, end, run_style)) = run.replace((cell.start, cell.end, style)) {
+                        spans.push(Span::styled(&text[start .. end], run_style));
+                    }
+                }
+            },
+            Cow::Owned(display) => {
+                if let Some((start, end, run_style)) = run.take() {
+                    spans.push(Span::styled(&text[start .. end], run_style));
+                }
+
+                spans.push(Span::styled(display, style));
+            }
         }
     }
 
-    let mode_text = format!("-- {} --", editor.mode);
-    
-    let status_text = if let Some(buffer) = editor.get_current_buffer() {
-        let cursor_info = format!("{}:{}", editor.cursor.pos.line + 1, editor.cursor.pos.column + 1);
-        let file_name = buffer.get_path()
-            .as_ref()
-            .map(|p| p.to_string_lossy().to_string())
-            .unwrap_or_else(|| "Untitled".to_string());
-        
-        format!("{} | {} | {}", mode_text, file_name, cursor_info)
-    } else {
-        mode_text
-    };
+    if let Some((start, end, style)) = run {
+        spans.push(Span::styled(&text[start .. end], style));
+    }
 
-    let status = Paragraph::new(status_text)
-        .block(Block::default().borders(Borders::NONE))
-        .style(Style::default().fg(Color::White));
+    Line::from(spans)
+}
 
-    frame.render_widget(status, chunks[2]);
+/// `line` with its text copied, so it can be kept after the text it was
+/// drawn from changes.
+fn owned(line: Line) -> Line<'static> {
+    let spans: Vec<Span<'static>> = line.spans
+        .into_iter()
+        .map(|span| Span::styled(span.content.into_owned(), span.style))
+        .collect();
+
+    Line::from(spans)
+}
+
+/// The prompt as it fits in `width` columns, scrolled so the cursor is on
+/// screen, and the cursor's column.
+fn prompt_line(prefix: &str, prompt: &Prompt, width: usize) -> (String, usize) {
+    let line = format!("{}{}", prefix, prompt.text);
+    let cursor = prefix.len() + prompt.cursor;
+    let mut start = 0;
+
+    while start < cursor && display_width(&line[start .. cursor], 1) >= width.max(1) {
+        start = next_boundary(&line, start);
+    }
+
+    (line[start ..].to_string(), display_width(&line[start .. cursor], 1))
 }
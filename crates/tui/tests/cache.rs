@@ -0,0 +1,104 @@
+use std::cell::Cell;
+
+use buffer::buffer::Buffer;
+use editor::layout::TextLayout;
+use editor::theme::Theme;
+use ratatui::style::Style;
+use ratatui::text::Line;
+use regex::Regex;
+use tui::cache::{Highlights, LineCache};
+
+fn layout() -> TextLayout {
+    TextLayout {
+        width: 80,
+        tab_stop: 8,
+        wrap: true,
+        show_break: String::new(),
+        line_break: false
+    }
+}
+
+/// Asks `cache` for the rows of `lines` of `buffer`, returning the lines it
+/// had to build again.
+fn draw(cache: &mut LineCache, buffer: &Buffer, lines: std::ops::Range<usize>, highlights: &Highlights) -> Vec<usize> {
+    let layout = layout();
+    let built = Cell::new(vec![]);
+
+    for line in lines {
+        cache.rows(buffer, line, &layout, highlights, || {
+            built.set([built.take(), vec![line]].concat());
+            vec![Line::from(buffer.lines[line].clone())]
+        });
+    }
+
+    built.take()
+}
+
+#[test]
+fn keeps_only_lines_from_the_last_frame() {
+    let regex = Regex::new("a").unwrap();
+    let theme = Theme::default();
+    let buffer = Buffer::from_text("banana\napple");
+    let mut cache = LineCache::new();
+
+    cache.begin_frame(Some(&regex), &theme);
+    assert_eq!(cache.matches(&buffer, 0, &regex), [(1, 2), (3, 4), (5, 6)]);
+    cache.matches(&buffer, 1, &regex);
+    assert_eq!(cache.len(), 2);
+
+    cache.begin_frame(Some(&regex), &theme);
+    cache.matches(&buffer, 1, &regex);
+    assert_eq!(cache.len(), 1);
+
+    cache.begin_frame(Some(&regex), &theme);
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn new_pattern_invalidates() {
+    let first = Regex::new("a").unwrap();
+    let second = Regex::new("n").unwrap();
+    let theme = Theme::default();
+    let buffer = Buffer::from_text("banana");
+    let mut cache = LineCache::new();
+
+    cache.begin_frame(Some(&first), &theme);
+    cache.matches(&buffer, 0, &first);
+
+    cache.begin_frame(Some(&second), &theme);
+    assert_eq!(cache.matches(&buffer, 0, &second), [(2, 3), (4, 5)]);
+}
+
+#[test]
+fn edits_only_rebuild_the_lines_they_touch() {
+    let theme = Theme::default();
+    let mut buffer = Buffer::from_text("one\ntwo\nthree");
+    let mut cache = LineCache::new();
+    let plain = Highlights::new();
+
+    cache.begin_frame(None, &theme);
+    assert_eq!(draw(&mut cache, &buffer, 0 .. 3, &plain), [0, 1, 2]);
+
+    cache.begin_frame(None, &theme);
+    assert!(draw(&mut cache, &buffer, 0 .. 3, &plain).is_empty());
+
+    // Lines pushed down by an edit above them are found where they went.
+    buffer.insert_lines(0, vec!["zero".to_string()]).unwrap();
+    buffer.set_line(2, "TWO".to_string()).unwrap();
+    cache.begin_frame(None, &theme);
+    assert_eq!(draw(&mut cache, &buffer, 0 .. 4, &plain), [0, 2]);
+
+    let rows = cache.rows(&buffer, 3, &layout(), &plain, || unreachable!());
+    assert_eq!(rows, [Line::from("three")]);
+
+    // As are lines from another buffer.
+    let other = Buffer::from_text("one");
+    assert_eq!(draw(&mut cache, &other, 0 .. 1, &plain), [0]);
+
+    // Different highlights or a different theme draw lines again.
+    cache.begin_frame(None, &theme);
+    assert_eq!(draw(&mut cache, &buffer, 1 .. 2, &vec![(0, 1, Style::default())]), [1]);
+
+    cache.begin_frame(None, &Theme::builtin("gruvbox").unwrap());
+    assert_eq!(draw(&mut cache, &buffer, 0 .. 2, &plain), [0, 1]);
+}
@@ -1,6 +1,6 @@
 use crate::Position;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Range {
     pub start: Position,
     pub end: Position
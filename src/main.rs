@@ -1,5 +1,6 @@
 use std::io;
 use std::env;
+use std::path::Path;
 
 use crossterm::cursor::DisableBlinking;
 use crossterm::cursor::EnableBlinking;
@@ -11,6 +12,7 @@ use crossterm::{event::EnableMouseCapture, execute, terminal::{enable_raw_mode,
 use anyhow::Result;
 
 use editor::editor::Editor;
+use editor::prompt::state_dir;
 use tui::app::run_editor;
 
 
@@ -25,9 +27,35 @@ fn main() -> Result<()>{
     let file = env::args().nth(1).expect("nope");
 
     let mut editor = Editor::new();
-    editor.create_buffer_from_file(file.into());
+    let config = editor.load_configs();
+    let state = state_dir();
+
+    if let Some(dir) = &state {
+        let _ = editor.load_history(dir);
+    }
+
+    // `paw some/dir` opens the explorer on it beside an empty buffer.
+    if Path::new(&file).is_dir() {
+        editor.create_empty_buffer();
+
+        if let Err(err) = editor.open_explorer(Path::new(&file)) {
+            editor.messages.error(err);
+        }
+    } else if let Err(err) = editor.create_buffer_from_file(file.into()) {
+        editor.create_empty_buffer();
+        editor.messages.error(err);
+    }
+
+    if let Err(err) = config {
+        editor.messages.error(err);
+    }
+
     let _res = run_editor(&mut terminal, &mut editor);
 
+    if let Some(dir) = &state {
+        let _ = editor.save_history(dir);
+    }
+
     disable_raw_mode()?;
     execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture, EnableBlinking)?;
     terminal.show_cursor()?;